@@ -1 +1,290 @@
 example-row = Example row
+add-pair = Add pair
+pair-placeholder = Enter exchange rate
+pairs-summary = { $count } pairs ▾
+tab-watchlist = Watchlist
+tab-debug = Debug
+next-fetch-in = next fetch in { $countdown }
+fetch-not-scheduled = not scheduled yet
+simulate = Simulate
+simulate-no-quote = Simulating needs a fetched quote of the panel pair
+tab-settings = Settings
+network = Network
+refresh-secs = Refresh interval (seconds)
+failure-digest-after-mins = Notify when every fetch failed for (minutes, 0 never)
+pause-on-unfocus = Slow down while the popup is in the background
+low-power = Low power mode
+low-power-below-pct = Turn on low power mode below this battery charge (%, 0 never)
+low-power-active = Low power mode active: refreshing less often, only the pairs in the panel
+cache-ttl-secs = Reuse quotes for (seconds)
+api-retry-count = Retries after a failed fetch
+api-retry-delay-secs = Seconds between retries
+retries-overlap-warning = Retries can take { $window }s, which runs into the next refresh in { $refresh }s.
+cache-ttl-capped = Quotes are reused for at most { $refresh }s / 2, not the { $ttl }s set.
+alerts-overlap = Two { $pair } alerts both fire between { $low } and { $high }.
+email-without-server = Alerts are emailed, but no SMTP server is set.
+forward-rates-without-url = Forward rates are shown, but no address to fetch them from is set.
+pinned-pair-missing = The pair the panel rotation rests on is no longer in the watchlist.
+invalid-fallback-provider = The fallback provider is the main one, or unknown.
+unsupported-pair-provider = {$pair} is to be fetched from {$provider}, which is unknown or doesn't quote it.
+fix = Fix
+tab-status = Status
+api-monthly-soft-limit = Monthly request limit per provider (0 = off)
+api-usage = API usage
+api-usage-counts = { $today } today, { $month } this month
+api-soft-limit-warning = { $provider } has used { $month } of { $limit } requests this month, consider raising the refresh interval.
+reset-api-usage = Reset counters
+network-unknown = Connecting…
+network-online = Online
+network-offline = Offline
+network-captive-portal = Log into the network to fetch rates
+network-tls = Secure connection failed
+network-clock-incorrect = Your system clock appears incorrect
+failure-digest-summary = Exchange rates haven't updated for {$duration}
+failure-digest-body = Click to see details
+rates-recovered = Exchange rates are updating again
+provider-maintenance = {$provider} maintenance until {$until}
+pause-frozen = Frozen
+pause-frozen-explanation = This pair is frozen: it keeps its last rate and isn't refreshed.
+pause-session-locked = Locked
+pause-session-locked-explanation = Rates aren't fetched while the session is locked, they update once you unlock it.
+pause-maintenance = Maintenance
+pause-maintenance-explanation = The provider is under maintenance, fetching resumes once it is over.
+pause-offline = Offline
+pause-offline-explanation = The provider can't be reached, rates update once the network is back.
+pause-circuit-breaker = Failing
+pause-circuit-breaker-explanation = Fetching this pair failed on every retry, it is tried again at the next refresh.
+pause-market-closed = Market closed
+pause-market-closed-explanation = The forex market is closed for the weekend, the rate holds Friday's close until it opens on Sunday.
+pause-low-power = Low power
+pause-low-power-explanation = Low power mode only refreshes the pairs shown in the panel.
+footer-active-pairs = { $count } active
+footer-fetches = { $count } fetches
+sort-by = Sort by
+sort-manual = Manual
+sort-alphabetical = Alphabetical
+sort-change-descending = Biggest change
+sort-rate-ascending = Rate
+panel = Panel
+sort-panel-rotation = Rotate the panel in the popup's sort order
+refresh-all = Refresh all
+calculator = Calculator
+convert-action = Convert…
+releases-action = See what's new
+updated-summary = Exchange Rate Applet updated to { $version }
+startup-notification = Announce updates
+about = About
+no-active-pairs = No active pairs
+no-pairs = No pairs
+get-started = Add a currency pair to get started
+refresh = Refresh
+keyboard-navigation = Keyboard navigation
+section-pair-list = Pair list
+section-pair-input = Pair input
+section-add-button = Add pair button
+section-refresh-button = Refresh button
+section-settings-panel = Settings
+shortcut-cycle-pair = Show the next pair in the panel
+shortcut-open-pair-input = Open the exchange rates to add a pair
+shortcuts = Keyboard shortcuts
+shortcuts-note = Assign keys to showing the next pair and adding a pair in COSMIC Settings
+data-quality-warning = ⚠ { $pair } is quoted at { $quoted }, but { $derived } going by { $from }: { $discrepancy } apart
+pair-removed = Removed { $pair }
+undo = Undo
+settings-not-saved = Settings can't be saved, changes only last this session
+dismiss = Dismiss
+app-name = Exchange Rate
+version = Version { $version }
+appearance = Appearance
+show-flags = Show currency flags in the popup
+show-change-heat = Show a bar of each pair's change
+use-monospace-for-rate = Fixed-width panel rate
+font-family = Panel font
+font-family-placeholder = Monospace
+font-preview = Preview
+pair-list-max-visible = Pairs shown before scrolling
+scroll-to-top = Back to top
+tab-alerts = Alerts
+new-alert = New alert
+unassigned-alerts = Alerts without a pair
+unassigned-alerts-notice = { $count } alerts lost their pair, it was removed from the watchlist outside the applet.
+review = Review
+alert-pair = Pair
+alert-condition = Condition
+alert-value = Value
+save-alert = Save alert
+alert-above = Rate above
+alert-below = Rate below
+alert-change-percent = Daily change beyond (%)
+alert-fired-summary = { $pair } alert
+alerts-while-locked = { $count } alerts fired while locked
+alerts-suppressed = { $count } alerts suppressed (rate limited)
+alerts-suppressed-summary = { $count } alerts were suppressed
+alerts-suppressed-body = More alerts fired this past hour than notifications are allowed, open the applet to see the rates.
+alert-fired-body = Rate is now { $rate } ({ $condition })
+conflict-overlap = Between { $low } and { $high } this rule and another one both fire, so one of them is always active.
+conflict-duplicate = The same rule already exists for this pair.
+conflict-always-true = This rule holds at any rate and will fire right away.
+conflict-never-true = This rule can never fire.
+conflict-may-overlap = This rule mixes a percentage and an absolute threshold, they may fire together depending on the previous close.
+first-panel-pair = Rotation rests on
+unpin = Unpin
+first-panel-dwell-secs = Rest on it for (seconds)
+panel-slot-label = Panel slot { $slot }
+clear-panel-slot = Clear
+panel-slots-note = Pairs in slots are shown side by side instead of one at a time, as long as they fit the panel.
+panel-slot = { $code } { $rate }
+visual-bell = Pulse the panel button until a fired alert is seen
+max-alert-notifications-per-hour = Alert notifications per hour at most (0 = no limit)
+popup-anchor = Open popups
+popup-anchor-auto = Depending on the panel
+popup-anchor-top = Below the button
+popup-anchor-bottom = Above the button
+quick-menu-click = Open the quick menu
+quick-menu-off = Never
+quick-menu-middle-click = With a middle click
+quick-menu-left-click = With a click, instead of the popup
+recent-fetches = Recent fetches
+next-requests = Next requests
+provider-group = {$provider}: {$pairs}
+forward-request = { $pair } forwards
+bundled-rate = ≈ { $rate }
+bundled-rate-note = Approximate, from { $date }, until rates can be fetched
+quote-stale = Quoted { $age } ago
+derived-from = = { $expression }
+expression-syntax = Not a valid expression from character { $at }
+expression-unknown-pair = { $pair } isn't a fetched pair of the watchlist
+expression-no-quote = { $pair } has no quote yet
+expression-division-by-zero = Division by zero
+expression-out-of-range = The result is out of range
+copy-diagnostic-report = Copy diagnostic report
+popup-capabilities = Popups on this system
+popup-focus = Get the keyboard focus
+popup-resize = Resize once open
+probe-untested = Not tried yet
+probe-works = Yes
+probe-failed = No, worked around
+footprint = Applet footprint
+footprint-memory = Memory in use
+footprint-history = Quotes in the history
+footprint-cache = Cached
+footprint-cache-counts = {$quotes} quotes, {$responses} raw responses
+footprint-disk = On disk
+footprint-disk-sizes = Settings {$settings}, state {$state}
+footprint-requests-today = Requests today
+flag-quote = Flag this quote as looking wrong, keeping it for the diagnostic report
+flagged-quotes = Flagged quotes
+flagged-quote = {$rate} from {$provider}
+flagged-quotes-none = No quotes flagged
+clear-flagged-quotes = Clear all
+trigger-startup = startup
+trigger-interval = interval
+trigger-manual = manual
+trigger-retry = retry
+trigger-pair-change = pair change
+trigger-unlock = unlock
+trigger-resumed = resumed after a pause
+webhooks = Webhooks
+test-webhook = Test webhook
+email-alerts = Email alerts
+alert-email = Send alerts to
+smtp-server = SMTP server
+smtp-port = SMTP port
+smtp-from = Sender address
+smtp-credential-key = Keyring credential key
+precision = Decimals
+precision-placeholder = As provided
+market-precision = Use market convention precision
+panel-trailing-zeros = Show trailing zeros in the panel
+decimal-groups = Number display
+decimal-groups-none = As is
+decimal-groups-every-3 = Group thousands
+decimal-groups-bankers-rounding = Round ties to even
+change-format = Show changes as
+change-format-percent = Percentage
+change-format-pips = Pips
+rate-change-animation = Animate changed rates
+animation-none = No animation
+animation-flash = Flash
+animation-slide = Slide
+change-pips = { $pips } pips
+change-undefined = —
+not-available = n/a
+effective-rate = ≈ { $rate } with { $markup }% markup (estimate)
+calculator-markup-estimate = Estimate including a { $markup }% markup
+markup = Fees and markup
+markup-pct = Markup (%)
+calculator-uses-markup = Convert at the effective rate
+panel-uses-markup = Show the effective rate in the panel
+markup-note = An estimate of what a bank or card charges on top of the quoted rate, such as IOF and spread
+pair-markup-placeholder = Markup % (default)
+export-history = Export
+export-format = Format
+export-format-csv = CSV
+export-format-json-lines = JSON Lines
+export-format-toml = TOML
+copy-history = Copy history
+history-exported = Copied { $count } quotes to the clipboard
+save-history = Save to file…
+save-history-title = Save exchange rate history
+history-saved = Exported to { $path }
+history-saved-fallback = No file chooser available, exported to { $path }
+history-save-failed = Couldn't export the history: { $error }
+forward-rates = Forward rates
+forward-rate = { $tenor }  { $rate }  { $carry }
+forward-rates-none = No forward rates fetched yet
+show-forward-rates = Show forward rates
+forward-rates-url = Forward rates endpoint
+allow-external-updates = Accept rates over D-Bus
+external-updates-note = For testing and scripts: any app of your session can then set rates, and alerts fire on them
+allow-external-watchlist = Accept watchlist changes over D-Bus
+external-watchlist-note = For setup scripts: any app of your session can then add, remove and pin pairs
+external-watchlist-disabled = Changing the watchlist over D-Bus isn't allowed, turn on "Accept watchlist changes over D-Bus" in settings
+external-updates-disabled = Setting rates over D-Bus isn't allowed, turn on "Accept rates over D-Bus" in settings
+set-alert = Set alert
+enable-pair = Enable
+disable-pair = Disable
+mute-pair = Mute changes
+unmute-pair = Unmute changes
+change-since = Change since
+change-window-previous-close = Previous close
+change-window-last-refresh = Last refresh
+change-window-rolling-24h = 24 hours ago
+change-window-since-time = A time of day
+change-window-previous-close-short = since close
+change-window-last-refresh-short = since last refresh
+change-window-rolling-24h-short = over 24h
+change-window-since-time-short = since { $time }
+delete-pair = Delete
+clipboard-invalid-pair = Clipboard did not contain a valid pair code
+pair-code-invalid = { $code } is not a valid pair code
+pair-already-watched = { $pair } is already in the watchlist
+pair-not-watched = { $pair } is not in the watchlist
+cadence-note-business-day = {$pair} updates once per business day, so it rarely changes between fetches
+cadence-note-every = {$pair} updates about every {$minutes} min, less often than it is fetched
+cadence-tag-business-day = Daily
+cadence-tag-every = Every {$minutes} min
+clipboard-unavailable = The clipboard could not be read
+tab-converter = Converter
+tab-history = History
+history-empty = No quotes received yet
+history-quotes = { $count } quotes
+show-more = Show more
+change-at-display-precision = Count changes at displayed precision only
+pair-timestamps = Last fetched and changed
+pair-timestamps-ago = fetched { $fetched } ago, changed { $changed } ago
+quoted-at = quoted at { $market }
+quoted-at-local = quoted at { $market } / { $local } your time
+quoted-by-fallback = Fetched from { $provider } while the main provider fails
+unchanged-for = Unchanged for { $duration }
+startup = Startup
+use-startup-list = Use startup list
+startup-list-note = Applies at the next start. While it is in use, changes to the watchlist last for the session only.
+alert-worth-above = Amount worth more than
+alert-worth-below = Amount worth less than
+alert-worth-above-rule = { $amount } { $base } worth more than { $target } { $quote }
+alert-worth-below-rule = { $amount } { $base } worth less than { $target } { $quote }
+alert-amount = Amount
+alert-target = Worth
+alert-equivalent-rate = Same as a rate { $condition }
+alert-fired-worth-body = { $amount } { $base } is now worth { $worth } { $quote }
@@ -1,34 +1,144 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
+use chrono::{DateTime, Local};
 use cosmic::app::{Command, Core};
+use cosmic::applet::cosmic_panel_config::PanelAnchor;
+use cosmic::cosmic_config::{self, CosmicConfigEntry};
+use cosmic::iced::font::{Family, Font};
 use cosmic::iced::wayland::popup::{destroy_popup, get_popup};
+use cosmic::iced::widget::scrollable::{self, AbsoluteOffset};
 use cosmic::iced::window::Id;
-use cosmic::iced::Limits;
+use cosmic::iced::{Alignment, Length, Limits, Size, Subscription};
 use cosmic::iced_style::application;
-use cosmic::widget::{self, settings};
-use cosmic::widget::{TextInput};
+use cosmic::widget::TextInput;
+use cosmic::widget::{self, segmented_button, settings};
 use cosmic::{Application, Element, Theme};
-use reqwest::Error;
 use serde_json::Value;
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
-use tokio::runtime::Runtime;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 
+use crate::capabilities::{self, Probe, PROBE_TIMEOUT};
+pub use crate::config::Flags;
+use crate::config::{
+    settings_dir, state_dir, validate_config, AlertCondition, AlertRule, AnimationStyle,
+    AppletConfig, AppletSection, AppletState, ChangeFormat, ChangeWindow, DecimalGroupStyle,
+    DisplayMode, ExportFormat, Pair, PairEntry, PopupAnchor, QuickMenuClick, SessionOverrides,
+    SortOrder, CUSTOM_STATE_DIR, MAX_PANEL_SLOTS,
+};
+use crate::dbus::{self, Refused, Reply};
+use crate::email::{send_rate_alert_email, AlertEvent};
+use crate::exact::{self, format_decimal, round_for_display, worth};
+use crate::expression::DERIVED;
 use crate::fl;
+use crate::footprint::{measure, Footprint, FOOTPRINT_REFRESH};
+use crate::format::{
+    clock_time, currency_flag, ellipsize, format_bytes, format_change_percent, format_countdown,
+    format_percent, format_rate, parse_amount, quote_time,
+};
+use crate::market::QuoteAge;
+use crate::portal::Saved;
+use crate::power::battery_changes;
+use crate::providers::{
+    describe_forward_request, fetch_forward_rates, provider, PairResponse, RateError,
+    FORWARD_TENORS, HTTP_CLIENT,
+};
+use crate::scheduler::{fetch_schedule, timezone_changes, FetchTrigger};
+use crate::session::lock_changes;
+use crate::shortcuts::shortcut_activations;
+pub use crate::state::YourApp;
+use crate::state::{
+    cadence_note, cadence_tag, degenerate_baseline, pair_change_summary, watchlist_of, AppView,
+    CalculatorSide, NetworkStatus, Observed, PauseReason, Persistence, PopupKind, RateSource,
+};
 
-/// This is the struct that represents your application.
-/// It is used to define the data that will be used by your application.
-#[derive(Default)]
-pub struct YourApp {
-    /// Application state which is managed by the COSMIC runtime.
-    core: Core,
-    /// The popup id.
-    popup: Option<Id>,
-    // Add a state for the text input
-    input_value: String,
-    // Add a state for the exchange rate
-    exchange_rate: Arc<Mutex<String>>,
+mod alerts;
+mod calculator;
+mod display;
+mod external;
+mod fetching;
+mod forward_rates;
+mod history;
+mod keyboard;
+mod notifications;
+mod pair_input;
+mod panel;
+mod pausing;
+mod popup;
+mod quick_menu;
+mod startup;
+mod status;
+mod watchlist;
+
+/// Font size used for the rates in the popup rows.
+const RATE_FONT_SIZE: f32 = 14.0;
+/// Conservative advance of a tabular figure, relative to the font size.
+const FIGURE_WIDTH_EM: f32 = 0.62;
+/// Where the applet's source and releases live.
+const REPOSITORY_URL: &str = "https://github.com/xfalcox/cosmic-applet-exchange-rate";
+/// The changelog, whose paragraph for the running version announces an update.
+const CHANGES: &str = include_str!("../CHANGES.md");
+/// Id of the notification action opening the releases page.
+const RELEASES_ACTION: &str = "releases";
+/// How long a webhook delivery may take before it is reported as failed.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Id of the notification action invoked by clicking the notification itself.
+const DEFAULT_ACTION: &str = "default";
+/// How long the visual bell stays lit, and then dark, while an alert is unacknowledged.
+const VISUAL_BELL_HALF_PERIOD: Duration = Duration::from_secs(1);
+/// How often the rate change animations move, 60 times a second.
+const ANIMATION_FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// The opacity of the `AnimationStyle::Flash` background when the rate just changed.
+const FLASH_ALPHA: f32 = 0.4;
+/// How many times less often pairs are fetched while `pause_on_unfocus` applies.
+const UNFOCUSED_REFRESH_FACTOR: u32 = 4;
+/// Characters of a flagged quote's response shown in the status tab, all are reported.
+const MAX_EXCERPT_CHARS: usize = 120;
+/// Up to how much later than announced fetching resumes after a maintenance window, so
+/// every client of the provider doesn't come back at the same second.
+const MAINTENANCE_JITTER_MS: u32 = 30_000;
+/// How far `AnimationStyle::Slide` moves the rate, in pixels.
+const SLIDE_DISTANCE: f32 = 8.0;
+/// Id of the notification action opening the calculator on the alerting pair.
+const CONVERT_ACTION: &str = "convert";
+/// Longest pair label shown in a popup row before it is ellipsized.
+const MAX_LABEL_CHARS: usize = 16;
+/// Width taken in a popup row by everything but the rate column and the heat bar.
+const ROW_CHROME_WIDTH: f32 = 260.0;
+/// Width of the change heat bar of a popup row, half of it on each side of no change.
+const HEAT_BAR_WIDTH: f32 = 48.0;
+/// Thickness of the change heat bar.
+const HEAT_BAR_HEIGHT: f32 = 6.0;
+/// Quotes added to an expanded pair's history by each "Show more".
+const HISTORY_PAGE_LEN: usize = 10;
+/// Share of a horizontal panel's length the panel slots may take before the panel rotates
+/// through the pairs instead.
+const PANEL_SLOTS_MAX_SHARE: f32 = 0.25;
+/// Padding of the panel button around its text, on each side.
+const PANEL_BUTTON_PADDING: f32 = 8.0;
+/// Height of a popup row, including the list's divider.
+const PAIR_ROW_HEIGHT: f32 = 44.0;
+/// How long handling a message may take before `UpdateWatchdog` logs it: the panel doesn't
+/// respond meanwhile.
+const UPDATE_BUDGET: Duration = Duration::from_millis(50);
+/// Space popups keep from the edges of the output and the panel.
+const POPUP_SCREEN_MARGIN: f32 = 8.0;
+/// Height the main popup's tab bar and footer take around the scrolled tab content.
+const POPUP_CHROME_HEIGHT: f32 = 120.0;
+
+/// The watchlist's pair input, focused by `Message::FocusPairInput`.
+static PAIR_INPUT_ID: LazyLock<widget::Id> = LazyLock::new(widget::Id::unique);
+/// The scrollable holding the watchlist rows, scrolled back up by `Message::ScrollPairsToTop`.
+static PAIR_LIST_ID: LazyLock<scrollable::Id> = LazyLock::new(scrollable::Id::unique);
+
+/// A setting of the email alerts, edited as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpField {
+    AlertEmail,
+    Server,
+    Port,
+    From,
+    CredentialKey,
 }
 
 /// This is the enum that contains all the possible variants that your application will need to transmit messages.
@@ -37,155 +147,4559 @@ pub struct YourApp {
 #[derive(Debug, Clone)]
 pub enum Message {
     TogglePopup,
+    /// Opens the popup scrolled to a pair of the panel slots, or closes it if open.
+    TogglePopupAt(Pair),
+    /// Scrolls the watchlist to a pair, see `PAIR_ROW_HEIGHT`.
+    ScrollToPair(Pair),
     PopupClosed(Id),
+    OpenContextMenu,
+    ContextMenuClosed(Id),
+    /// Opens the quick menu, or closes it when open.
+    ToggleQuickMenu,
+    /// Makes the pair with this index the panel's, from the quick menu, and closes it.
+    QuickMenuSelect(usize),
+    /// Down or Up in the quick menu: moves to the next or previous row.
+    QuickMenuMove(bool),
+    /// Enter in the quick menu: selects the row moved to.
+    QuickMenuActivate,
+    /// Opens the popup on its settings tab.
+    OpenSettings,
+    OpenCalculator,
+    /// Opens the calculator on the pair of an alert, from its notification.
+    ConvertAlerted(Pair),
+    OpenAbout,
+    OpenUrl(String),
+    RefreshAll,
+    CalculatorInputChanged(CalculatorSide, String),
     InputChanged(String),
+    PairInputFocused,
+    PairInputBlurred,
+    AddPair,
+    StartupInputChanged(String),
+    AddStartupPair,
+    RemoveStartupPair(usize),
+    ToggleStartupPairs(bool),
+    ToggleStartupNotification(bool),
+    /// Tab or Shift+Tab in the popup: moves to the next or previous section of
+    /// `AppletConfig::tab_key_order`.
+    FocusNext,
+    FocusPrev,
+    /// Enter outside of a text input: presses the button of the section moved to.
+    ActivateFocused,
+    MoveTabSection(usize, bool),
+    RemoveTabSection(usize),
+    AddTabSection(AppletSection),
+    /// Adds the pair whose code is in the clipboard.
+    PasteFromClipboard,
+    /// The clipboard was read, or could not be.
+    ClipboardRead(Result<String, String>),
+    TogglePair(usize, bool),
+    TogglePairQuiet(usize),
+    ChangeWindowChanged(usize, usize),
+    ChangeWindowTimeChanged(usize, String),
+    PairMarkupChanged(usize, String),
+    MarkupChanged(String),
+    ToggleCalculatorMarkup(bool),
+    TogglePanelMarkup(bool),
+    RemovePair(usize),
+    /// Puts the last removed pair back, see `RemovedPair`.
+    UndoRemovePair,
+    /// The removal made at this time can no longer be undone.
+    UndoExpired(Instant),
+    /// Opens the action menu of a watchlist row, or closes it if it is already open.
+    TogglePairMenu(usize),
+    /// Opens the alerts tab with the editor set to the pair at this index.
+    SetAlert(usize),
+    CyclePanel,
+    /// Pins the pair at this index to the start of the panel rotation, or unpins it.
+    PinPanelPair(Option<usize>),
+    FirstPanelDwellChanged(String),
+    /// Puts the pair at this index into a panel slot, or empties the slot.
+    PanelSlotChanged(usize, Option<usize>),
+    /// A navigation item of the popup was activated.
+    NavSelected(segmented_button::Entity),
+    /// The scheduler planned the next fetch of a pair.
+    FetchScheduled(Pair, Instant),
+    /// The scheduler asks for a pair to be fetched now.
+    FetchDue(Pair),
+    /// Evaluates the expression of a derived pair again, see `YourApp::derived_quote`.
+    DerivePair(Pair),
+    /// A fetch of a pair completed at the given time.
+    RateFetchedAt(Pair, Instant, Result<PairResponse, RateError>),
+    /// A fetch of a pair from the fallback provider completed, see `serving_provider`.
+    FallbackFetchedAt(Pair, Instant, Result<PairResponse, RateError>),
+    /// Snapshots the current quote of the pair with this index as looking wrong.
+    FlagQuote(usize),
+    DeleteFlaggedQuote(usize),
+    ClearFlaggedQuotes,
+    /// The maintenance window of the named provider was announced to end by now.
+    MaintenanceOver(String),
+    /// The outcomes of fetching several pairs together, see `YourApp::fetch_active`.
+    RatesFetched(Vec<(Pair, Instant, Result<PairResponse, RateError>)>),
+    /// Retries a failed fetch, carrying the attempt number.
+    FetchRetry(Pair, u32),
+    RefreshSecsChanged(String),
+    FailureDigestChanged(String),
+    /// Opens the popup on its status tab, from the failure digest.
+    OpenStatus,
+    CacheTtlChanged(String),
+    RetryCountChanged(String),
+    RetryDelayChanged(String),
+    SoftLimitChanged(String),
+    ResetApiUsage,
+    SortChanged(usize),
+    ToggleSortPanelRotation(bool),
+    ToggleShowFlags(bool),
+    ToggleChangeHeat(bool),
+    ToggleMonospaceForRate(bool),
+    ToggleForwardRates(bool),
+    ForwardRatesUrlChanged(String),
+    /// The forward rates of a pair were fetched, or failed to be.
+    ForwardRatesFetched(Pair, Result<HashMap<String, f64>, RateError>),
+    /// The font input was edited, the font is only applied on submit.
+    FontFamilyChanged(String),
+    FontFamilySubmitted,
+    PopupAnchorChanged(usize),
+    QuickMenuClickChanged(usize),
+    MaxVisibleChanged(String),
+    /// Whether the watchlist rows are scrolled away from the top.
+    PairListScrolled(bool),
+    ScrollPairsToTop,
+    /// Replaces the bid of the panel pair as if it had been fetched, in `--test-mode`.
+    SimulateRateChange(f64),
+    /// The session was locked or unlocked, see `lock_changes`.
+    SessionLocked(bool),
+    /// A window gained or lost focus.
+    WindowFocusChanged(Id, bool),
+    /// A window was configured to a new logical size, see `window_changes`.
+    WindowResized(Id, (f32, f32)),
+    /// The main popup had `PROBE_TIMEOUT` to get the keyboard focus.
+    FocusProbeExpired(Id),
+    /// A popup had `PROBE_TIMEOUT` to be configured to the size requested for it.
+    ResizeProbeExpired(Id),
+    /// Measures the footprint again for the open status page, see `crate::footprint`.
+    MeasureFootprint,
+    FootprintMeasured(Footprint),
+    /// An output was added or changed to the logical size given, see `output_changes`.
+    OutputResized(Option<String>, (f32, f32)),
+    TogglePauseOnUnfocus(bool),
+    ToggleLowPower(bool),
+    LowPowerBelowChanged(String),
+    /// The battery charge while it is discharging, `None` on AC, see `battery_changes`.
+    BatteryChanged(Option<f64>),
+    /// The fired alerts were seen, see `YourApp::acknowledge_alerts`.
+    AcknowledgeAlerts,
+    /// Moves the rate change animations, see `PairState::advance_animation`.
+    AnimationTick(Instant),
+    AnimationStyleChanged(usize),
+    /// Lights or darkens the visual bell.
+    VisualBellTick,
+    ToggleVisualBell(bool),
+    AlertBudgetChanged(String),
+    /// A rate set through the D-Bus interface, answered with why it was refused if it
+    /// was, see `crate::dbus`.
+    ExternalRateUpdate(Pair, f64, Reply<Result<(), Refused>>),
+    /// A `PauseReasons` call on the D-Bus interface, answered with the global reason and
+    /// that of every paused pair.
+    PauseReasons(Reply<(String, HashMap<String, String>)>),
+    /// A `RateSources` call on the D-Bus interface, answered with where the rate shown of
+    /// every pair with one comes from.
+    RateSources(Reply<HashMap<String, (String, String)>>),
+    /// The watchlist calls of the D-Bus interface, answered with the error the popup would
+    /// have shown, see `crate::dbus`.
+    ExternalAddPair(Pair, Reply<Result<(), Refused>>),
+    ExternalRemovePair(Pair, Reply<Result<(), Refused>>),
+    ExternalSetPrimary(Pair, Reply<Result<(), Refused>>),
+    ListPairs(Reply<Vec<(String, String, f64)>>),
+    /// The rate of a pair as the panel shows it, with the rate and timestamp of its quote,
+    /// see `GetFormattedRate`.
+    FormattedRate(Pair, Reply<Result<(String, f64, i64), String>>),
+    ToggleExternalUpdates(bool),
+    ToggleExternalWatchlist(bool),
+    /// Whether the global shortcuts could be bound, see `shortcut_activations`.
+    ShortcutsAvailable(bool),
+    /// Opens the popup on the watchlist with the pair input focused, whether or not it was
+    /// open.
+    OpenPairInput,
+    /// Queues a fired alert's notification while the session is locked: its pair,
+    /// summary and body.
+    QueueAlert(Pair, String, String),
+    /// Shows a fired alert's notification unless the notification budget is spent: its
+    /// pair, summary and body.
+    ShowAlert(Pair, String, String),
+    /// The notification budget may have room again to summarize the suppressed alerts, see
+    /// `NotificationBudget::reset`.
+    NotificationBudgetReset,
+    /// The system timezone changed, see `timezone_changes`.
+    TimezoneChanged,
+    /// Expands or collapses the history of a pair.
+    ToggleHistory(Pair),
+    ShowMoreHistory(Pair),
+    /// No pair is enabled any more, see `YourApp::unconfigured`.
+    UnconfiguredStateWarning,
+    DismissPersistenceNotice,
+    /// Shows the tab with the settings of the config error at this index.
+    FixConfigError(usize),
+    DismissConfigErrors,
+    FocusPairInput,
+    /// Empties the pair input and focuses it, from the clear button inside it.
+    ClearPairInput,
+    PrecisionChanged(String),
+    ToggleMarketPrecision(bool),
+    TogglePanelTrailingZeros(bool),
+    DecimalGroupsChanged(usize),
+    ChangeFormatChanged(usize),
+    ExportFormatChanged(usize),
+    /// Copies the history of every active pair to the clipboard, in the export format.
+    ExportHistory,
+    /// Saves the history of every active pair to a file, in the export format, through
+    /// `crate::portal::save_text`.
+    SaveHistory,
+    HistorySaved(Result<Saved, String>),
+    /// Copies the report of `crate::report` to the clipboard.
+    CopyDiagnosticReport,
+    ToggleChangeAtDisplayPrecision(bool),
+    AlertPairSelected(usize),
+    AlertConditionSelected(usize),
+    AlertValueChanged(String),
+    AlertAmountChanged(String),
+    SaveAlert,
+    RemoveAlert(usize),
+    /// Moves the unassigned alert with this index to the pair with the other index.
+    ReattachAlert(usize, usize),
+    DeleteUnassignedAlert(usize),
+    /// Opens the alerts tab on the unassigned alerts, dismissing the notice about them.
+    ReviewUnassignedAlerts,
+    WebhookUrlChanged(usize, String),
+    SmtpFieldChanged(SmtpField, String),
+    /// Posts a test payload to the webhook of the pair at this index.
+    WebhookTestFired(usize),
+    /// A test payload was delivered to the webhook of a pair, or failed to be.
+    WebhookTested(Pair, Result<reqwest::StatusCode, String>),
+    /// Re-renders time based labels such as the debug countdowns.
+    Tick,
 }
 
-/// Implement the `Application` trait for your application.
-/// This is where you define the behavior of your application.
-///
-/// The `Application` trait requires you to define the following types and constants:
-/// - `Executor` is the async executor that will be used to run your application's commands.
-/// - `Flags` is the data that your application needs to use before it starts.
-/// - `Message` is the enum that contains all the possible variants that your application will need to transmit messages.
-/// - `APP_ID` is the unique identifier of your application.
-impl Application for YourApp {
-    type Executor = cosmic::executor::Default;
+impl YourApp {
+    /// The formatted rate of `pair`, or a dash if it was not fetched yet.
+    fn rate_text(&self, pair: &Pair) -> String {
+        self.rate_text_with_zeros(pair, true)
+    }
 
-    type Flags = ();
+    /// `rate_text`, without the zeros ending the rate unless `trailing_zeros`.
+    fn rate_text_with_zeros(&self, pair: &Pair, trailing_zeros: bool) -> String {
+        match self.shown_rate(pair) {
+            Some(RateSource::Fetched(response)) => {
+                self.format_rate(pair, response.bid, trailing_zeros)
+            }
+            Some(RateSource::Bundled(rate)) => fl!(
+                "bundled-rate",
+                rate = self.format_rate(pair, rate.bid, trailing_zeros)
+            ),
+            // Without a quote yet, say why if it isn't coming.
+            None => self
+                .config
+                .pairs
+                .iter()
+                .find(|entry| &entry.pair == pair)
+                .and_then(|entry| self.pair_pause(entry))
+                .map_or_else(|| "–".to_string(), PauseReason::label),
+        }
+    }
 
-    type Message = Message;
+    /// `rate` of `pair` with the decimals it is displayed with, see `format::format_rate`.
+    fn format_rate(&self, pair: &Pair, rate: f64, trailing_zeros: bool) -> String {
+        format_rate(
+            rate,
+            self.display_precision(pair),
+            self.config.decimal_groups,
+            trailing_zeros,
+        )
+    }
 
-    const APP_ID: &'static str = "com.example.CosmicAppletTemplate";
+    /// The estimated rate of buying the base currency of `pair` with its markup, `None`
+    /// without a markup or a quote.
+    fn effective_rate_text(&self, pair: &Pair) -> Option<String> {
+        self.effective_rate_text_with_zeros(pair, true)
+    }
 
-    fn core(&self) -> &Core {
-        &self.core
+    /// `effective_rate_text`, without the zeros ending the rate unless `trailing_zeros`.
+    fn effective_rate_text_with_zeros(&self, pair: &Pair, trailing_zeros: bool) -> Option<String> {
+        let markup = self.config.markup_for(pair);
+        if markup <= 0.0 {
+            return None;
+        }
+        let rate = self
+            .exchange_rates
+            .get(pair)?
+            .effective_rate(CalculatorSide::Quote, markup)?;
+        // Rounded exactly before the float reaches `format_rate`, which then has no tie left
+        // to round.
+        let rate = match self.display_precision(pair) {
+            Some(precision) => {
+                round_for_display(rate, precision as u32, self.config.decimal_groups)
+            }
+            None => rate,
+        };
+        Some(self.format_rate(pair, exact::to_f64(rate), trailing_zeros))
     }
 
-    fn core_mut(&mut self) -> &mut Core {
-        &mut self.core
+    /// The rate of `pair` as shown in the panel: the quoted one, or the effective one if
+    /// the user opted in, without its trailing zeros unless `panel_trailing_zeros`.
+    pub(crate) fn panel_rate_text(&self, pair: &Pair) -> String {
+        let trailing_zeros = self.config.panel_trailing_zeros;
+        self.config
+            .panel_uses_markup
+            .then(|| self.effective_rate_text_with_zeros(pair, trailing_zeros))
+            .flatten()
+            .unwrap_or_else(|| self.rate_text_with_zeros(pair, trailing_zeros))
     }
 
-    /// This is the entry point of your application, it is where you initialize your application.
-    ///
-    /// Any work that needs to be done before the application starts should be done here.
-    ///
-    /// - `core` is used to passed on for you by libcosmic to use in the core of your own application.
-    /// - `flags` is used to pass in any data that your application needs to use before it starts.
-    /// - `Command` type is used to send messages to your application. `Command::none()` can be used to send no messages to your application.
-    fn init(core: Core, _flags: Self::Flags) -> (Self, Command<Self::Message>) {
-        let app = YourApp {
-            core,
-            input_value: "USDBRL".to_string(), // Set default value here
-            ..Default::default()
+    /// The change of `pair` over its window in the configured format, with the window
+    /// named unless it is the previous close.
+    fn change_text(&self, pair: &Pair) -> String {
+        let Some(change) = self.change_for(pair) else {
+            // Measured from zero, the change has no percentage rather than an infinite one.
+            return match self.change_baseline(pair) {
+                Some(from) if degenerate_baseline(from) => fl!("change-undefined"),
+                _ => String::new(),
+            };
         };
+        let text = match (self.config.change_format, pair.market_convention()) {
+            (ChangeFormat::Pips, Some(convention)) => fl!(
+                "change-pips",
+                pips = format!("{:+.0}", convention.pips(change.delta))
+            ),
+            _ => format_change_percent(change.percent, 2),
+        };
+        match self.change_window(pair) {
+            ChangeWindow::PreviousClose => text,
+            window => format!("{} {}", text, window.short_label()),
+        }
+    }
 
-        let exchange_rate = Arc::clone(&app.exchange_rate);
-        let input_value = app.input_value.clone();
-        thread::spawn(move || {
-            let rt = Runtime::new().unwrap();
-            loop {
-                rt.block_on(async {
-                    match fetch_exchange_rate(&input_value).await {
-                        Ok(rate) => {
-                            let mut exchange_rate = exchange_rate.lock().unwrap();
-                            *exchange_rate = rate.trim_matches('"').to_string();
-                        }
-                        Err(e) => eprintln!("Error fetching exchange rate: {:?}", e),
-                    }
-                });
-                thread::sleep(Duration::from_secs(600)); // 10 minutes
-            }
-        });
-
-        (app, Command::none())
+    /// The difference of a `forward` rate of `pair` from its `spot` rate, in the
+    /// configured change format.
+    fn carry_text(&self, pair: &Pair, forward: f64, spot: f64) -> String {
+        let carry = forward - spot;
+        match (self.config.change_format, pair.market_convention()) {
+            (ChangeFormat::Pips, Some(convention)) => fl!(
+                "change-pips",
+                pips = format!("{:+.0}", convention.pips(carry))
+            ),
+            _ if !degenerate_baseline(spot) => format_change_percent(carry / spot * 100.0, 2),
+            _ => fl!("change-undefined"),
+        }
     }
 
-    fn on_close_requested(&self, id: Id) -> Option<Message> {
-        Some(Message::PopupClosed(id))
+    /// The forward curve of every enabled pair whose forward rates were fetched, each tenor
+    /// with its carry.
+    fn view_forward_rates(&self) -> Element<Message> {
+        let mut section = settings::view_section(fl!("forward-rates"));
+        let mut empty = true;
+        for entry in self.config.pairs.iter().filter(|entry| entry.enabled) {
+            let (Some(spot), Some(rates)) = (
+                self.exchange_rates.get(&entry.pair),
+                self.pair_states
+                    .get(&entry.pair)
+                    .and_then(|state| state.forward_rates.as_ref()),
+            ) else {
+                continue;
+            };
+            let tenors = FORWARD_TENORS.iter().filter_map(|&tenor| {
+                let rate = *rates.get(tenor)?;
+                Some(
+                    widget::text::caption(fl!(
+                        "forward-rate",
+                        tenor = tenor,
+                        rate = self.format_rate(&entry.pair, rate, true),
+                        carry = self.carry_text(&entry.pair, rate, spot.bid)
+                    ))
+                    .into(),
+                )
+            });
+            section = section.add(settings::item(
+                entry.pair.to_string(),
+                widget::column::with_children(tenors.collect()),
+            ));
+            empty = false;
+        }
+        if empty {
+            section = section.add(widget::text::caption(fl!("forward-rates-none")));
+        }
+        section.into()
     }
 
-    /// This is the main view of your application, it is the root of your widget tree.
-    ///
-    /// The `Element` type is used to represent the visual elements of your application,
-    /// it has a `Message` associated with it, which dictates what type of message it can send.
-    ///
-    /// To get a better sense of which widgets are available, check out the `widget` module.
-    fn view(&self) -> Element<Self::Message> {
-        let exchange_rate = self.exchange_rate.lock().unwrap().clone();
-        cosmic::widget::button::text(exchange_rate)
-            .on_press(Message::TogglePopup)
-            .style(cosmic::theme::Button::AppletIcon)
-            .into()
+    /// Width of the popup rate column, estimated from the widest rate shown.
+    fn rate_column_width(&self) -> f32 {
+        let widest = self
+            .config
+            .pairs
+            .iter()
+            .filter(|e| e.enabled)
+            .map(|e| self.rate_text(&e.pair).chars().count())
+            .max()
+            .unwrap_or(1);
+        (widest as f32 * RATE_FONT_SIZE * FIGURE_WIDTH_EM).ceil()
     }
 
-    fn view_window(&self, _id: Id) -> Element<Self::Message> {
-        let content_list = widget::list_column()
+    /// The watchlist tab: one row per pair plus the input to add new ones.
+    fn view_watchlist(&self) -> Element<Message> {
+        let sort_index = SortOrder::ALL
+            .iter()
+            .position(|&order| order == self.config.popup_sort);
+        let mut content_list = widget::list_column()
             .padding(5)
             .spacing(0)
             .add(settings::item(
-                fl!("example-row"),
-                // Shows a text input that allows the user to enter a string for the exchange rate to show.
-                // For example USDEUR for USD to EUR exchange rate
-                TextInput::new("Enter exchange rate", &self.input_value)
-                    .on_input(Message::InputChanged)
-                    .padding(10)
-                    .size(20),
+                fl!("sort-by"),
+                widget::dropdown(&self.sort_labels, sort_index, Message::SortChanged),
+            ));
+        if self.persistence.show_notice() {
+            content_list = content_list.add(settings::item(
+                fl!("settings-not-saved"),
+                widget::button::text(fl!("dismiss")).on_press(Message::DismissPersistenceNotice),
+            ));
+        }
+        if self.unconfigured {
+            content_list = content_list.add(settings::item(
+                fl!("get-started"),
+                widget::button::text(fl!("add-pair")).on_press(Message::FocusPairInput),
+            ));
+        }
+        let rate_width = self.rate_column_width();
+
+        let mut rows = widget::list_column().padding([0, 5]).spacing(0);
+        let heat = self.watchlist_heat();
+        let indices = self.sorted_indices();
+        let overflows = indices.len() > usize::from(self.config.pair_list_max_visible);
+        for index in indices {
+            let entry = &self.config.pairs[index];
+            let (rate, change) = if entry.enabled {
+                (self.rate_text(&entry.pair), self.change_text(&entry.pair))
+            } else {
+                (String::new(), String::new())
+            };
+            let mut rate_cell =
+                vec![self.animated_rate(&entry.pair, widget::text(rate).size(RATE_FONT_SIZE))];
+            if let Some(effective) = self
+                .effective_rate_text(&entry.pair)
+                .filter(|_| entry.enabled)
+            {
+                rate_cell.push(
+                    widget::text::caption(fl!(
+                        "effective-rate",
+                        rate = effective,
+                        markup = self.config.markup_for(&entry.pair).to_string()
+                    ))
+                    .into(),
+                );
+            }
+            if let Some(RateSource::Bundled(rate)) =
+                self.shown_rate(&entry.pair).filter(|_| entry.enabled)
+            {
+                rate_cell.push(
+                    widget::text::caption(fl!(
+                        "bundled-rate-note",
+                        date = rate.as_of.format("%Y-%m-%d").to_string()
+                    ))
+                    .into(),
+                );
+            }
+            if let Some(expression) = entry.expression.as_deref().filter(|_| entry.enabled) {
+                rate_cell.push(
+                    widget::text::caption(fl!("derived-from", expression = expression)).into(),
+                );
+                let error = self
+                    .pair_states
+                    .get(&entry.pair)
+                    .and_then(|state| state.last_error.clone());
+                if let Some(error) = error {
+                    rate_cell.push(widget::text::caption(error).into());
+                }
+            }
+            // Old quotes past the close aren't a warning, they are the market's last: the
+            // market-closed pause reason below says so.
+            if let Some(QuoteAge::Stale) = self.quote_age(&entry.pair).filter(|_| entry.enabled) {
+                let quoted = self
+                    .exchange_rates
+                    .get(&entry.pair)
+                    .map_or(0, |quote| quote.timestamp);
+                let age = (self.clock.now_utc().timestamp() - quoted).max(0) as u64;
+                rate_cell.push(
+                    widget::text::caption(fl!(
+                        "quote-stale",
+                        age = format_countdown(Duration::from_secs(age))
+                    ))
+                    .into(),
+                );
+            }
+            if let Some(reason) = self.pair_pause(entry).filter(|_| entry.enabled) {
+                rate_cell.push(
+                    widget::tooltip(
+                        widget::row::with_children(vec![
+                            widget::icon::from_name(reason.icon()).size(12).into(),
+                            widget::text::caption(reason.label()).into(),
+                        ])
+                        .spacing(4)
+                        .align_items(Alignment::Center),
+                        reason.explanation(),
+                        widget::tooltip::Position::Top,
+                    )
+                    .into(),
+                );
+            }
+            if let Some(tag) = cadence_tag(self.pair_cadence(&entry.pair), self.config.refresh_secs)
+            {
+                rate_cell.push(widget::text::caption(tag).into());
+            }
+            let label = if self.config.show_flags {
+                format!("{} {}", currency_flag(entry.pair.base()), entry.pair)
+            } else {
+                entry.pair.to_string()
+            };
+            let mut cells = vec![widget::column::with_children(rate_cell)
+                .width(Length::Fixed(rate_width))
+                .into()];
+            if self.config.show_change_heat {
+                cells.push(heat_bar(heat[index]));
+            }
+            cells.extend([
+                widget::text::caption(change).into(),
+                widget::toggler(None, entry.enabled, move |enabled| {
+                    Message::TogglePair(index, enabled)
+                })
+                .into(),
+                widget::tooltip(
+                    widget::button::icon(widget::icon::from_name("emblem-important-symbolic"))
+                        .on_press(Message::FlagQuote(index)),
+                    fl!("flag-quote"),
+                    widget::tooltip::Position::Top,
+                )
+                .into(),
+                self.pair_context_menu(index),
+            ]);
+            rows = rows.add(settings::item(
+                ellipsize(&label, MAX_LABEL_CHARS),
+                widget::row::with_children(cells)
+                    .spacing(8)
+                    .align_items(Alignment::Center),
             ));
+        }
+
+        let height = if overflows {
+            Length::Fixed(f32::from(self.config.pair_list_max_visible) * PAIR_ROW_HEIGHT)
+        } else {
+            Length::Shrink
+        };
+        let mut pairs = vec![widget::scrollable(rows)
+            .id(PAIR_LIST_ID.clone())
+            .height(height)
+            .on_scroll(|viewport| Message::PairListScrolled(viewport.absolute_offset().y > 0.0))
+            .into()];
+        for inconsistency in self.quote_inconsistencies() {
+            let from: Vec<String> = inconsistency.from.iter().map(Pair::to_string).collect();
+            pairs.push(
+                widget::text::caption(fl!(
+                    "data-quality-warning",
+                    pair = inconsistency.pair.to_string(),
+                    quoted = inconsistency.quoted.to_string(),
+                    derived = format!("{:.6}", inconsistency.derived),
+                    from = from.join(" × "),
+                    discrepancy = format_percent(inconsistency.discrepancy_pct, 1)
+                ))
+                .into(),
+            );
+        }
+        if let Some(removed) = &self.removed_pair {
+            pairs.push(
+                widget::row::with_children(vec![
+                    widget::text(fl!("pair-removed", pair = removed.entry.pair.to_string())).into(),
+                    widget::button::text(fl!("undo"))
+                        .on_press(Message::UndoRemovePair)
+                        .into(),
+                ])
+                .spacing(8)
+                .align_items(Alignment::Center)
+                .into(),
+            );
+        }
+        if overflows && self.pair_list_scrolled {
+            pairs.push(
+                widget::button::text(fl!("scroll-to-top"))
+                    .leading_icon(widget::icon::from_name("go-top-symbolic"))
+                    .on_press(Message::ScrollPairsToTop)
+                    .into(),
+            );
+        }
+
+        let mut footer = widget::list_column()
+            .padding(5)
+            .spacing(0)
+            .add(settings::item(
+                fl!("add-pair"),
+                PairInput::new(&self.input_value, Message::InputChanged, Message::AddPair)
+                    .paste(Message::PasteFromClipboard)
+                    .clear(Message::ClearPairInput)
+                    .on_focus(Message::PairInputFocused, Message::PairInputBlurred)
+                    .id(PAIR_INPUT_ID.clone())
+                    .add_focused(self.focused_section == Some(AppletSection::AddButton)),
+            ))
+            .add(settings::item(fl!("refresh-all"), {
+                let refresh = widget::button::text(fl!("refresh")).on_press(Message::RefreshAll);
+                if self.focused_section == Some(AppletSection::RefreshButton) {
+                    refresh.style(cosmic::theme::Button::Suggested)
+                } else {
+                    refresh
+                }
+            }));
+
+        if let Some(error) = &self.paste_error {
+            footer = footer.add(widget::text::caption(error.clone()));
+        }
+        if let Some(note) = &self.cadence_note {
+            footer = footer.add(widget::text::caption(note.clone()));
+        }
+        if let Some(reason) = self.global_pause() {
+            footer = footer.add(widget::text::caption(reason.explanation()));
+        }
+        if self.unassigned_notice && !self.config.unassigned_alerts.is_empty() {
+            footer = footer.add(
+                widget::row::with_children(vec![
+                    widget::text::caption(fl!(
+                        "unassigned-alerts-notice",
+                        count = self.config.unassigned_alerts.len()
+                    ))
+                    .into(),
+                    widget::button::text(fl!("review"))
+                        .on_press(Message::ReviewUnassignedAlerts)
+                        .into(),
+                ])
+                .spacing(8)
+                .align_items(Alignment::Center),
+            );
+        }
+        if self.low_power() {
+            footer = footer.add(widget::text::caption(fl!("low-power-active")));
+        }
+        if self.notification_budget.suppressed > 0 {
+            footer = footer.add(widget::text::caption(fl!(
+                "alerts-suppressed",
+                count = self.notification_budget.suppressed
+            )));
+        }
 
-        self.core.applet.popup_container(content_list).into()
+        let mut sections = vec![
+            content_list.into(),
+            widget::column::with_children(pairs)
+                .spacing(4)
+                .align_items(Alignment::Center)
+                .into(),
+        ];
+        if self.config.show_forward_rates {
+            sections.push(self.view_forward_rates());
+        }
+        sections.push(footer.into());
+        widget::column::with_children(sections).into()
     }
 
-    /// Application messages are handled here. The application state can be modified based on
-    /// what message was received. Commands may be returned for asynchronous execution on a
-    /// background thread managed by the application's executor.
-    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
-        match message {
-            Message::TogglePopup => {
-                return if let Some(p) = self.popup.take() {
-                    destroy_popup(p)
+    /// Notifications for the alert rules of `pair` that start holding with `quote`. Rules
+    /// fire once when their condition becomes true, not on every fetch while it stays true.
+    fn fired_alerts(&self, pair: &Pair, quote: &PairResponse) -> Vec<Command<Message>> {
+        let previous = self.exchange_rates.get(pair);
+        let quiet = self
+            .config
+            .pairs
+            .iter()
+            .any(|entry| &entry.pair == pair && entry.quiet);
+        let rules: Vec<&AlertRule> = self
+            .config
+            .alert_rules
+            .iter()
+            .filter(|rule| &rule.pair == pair)
+            .filter(|rule| !(quiet && rule.condition.is_movement()))
+            .collect();
+        let conditions: Vec<AlertCondition> = rules.iter().map(|rule| rule.condition).collect();
+        let old = previous.map(|quote| Observed {
+            quote,
+            change: self.change_for(pair),
+        });
+        let new = Observed {
+            quote,
+            change: self.incoming_change(pair, quote),
+        };
+        let change = pair_change_summary(old, new, &conditions);
+        let window = self.change_window(pair);
+        rules
+            .into_iter()
+            .filter(|rule| change.fired(&rule.condition, old, new))
+            .flat_map(|rule| {
+                let body = match rule.condition {
+                    AlertCondition::WorthAbove { amount, .. }
+                    | AlertCondition::WorthBelow { amount, .. } => fl!(
+                        "alert-fired-worth-body",
+                        amount = amount.to_string(),
+                        base = pair.base().to_string(),
+                        worth = worth(amount, quote.bid)
+                            .map(|worth| format_decimal(worth, 2, self.config.decimal_groups))
+                            .unwrap_or_default(),
+                        quote = pair.quote().to_string()
+                    ),
+                    condition if condition.is_movement() => fl!(
+                        "alert-fired-body",
+                        rate = quote.bid.to_string(),
+                        condition = format!("{} {}", condition, window.short_label())
+                    ),
+                    _ => fl!(
+                        "alert-fired-body",
+                        rate = quote.bid.to_string(),
+                        condition = rule.condition.to_string()
+                    ),
+                };
+                let summary = fl!("alert-fired-summary", pair = pair.to_string());
+                let mut commands = Vec::new();
+                // Emails go out for the same firings as the notification, never more often.
+                if let Some(smtp) = self.config.smtp() {
+                    let alert = AlertEvent {
+                        summary: summary.clone(),
+                        body: body.clone(),
+                    };
+                    commands.push(Command::perform(
+                        async move { send_rate_alert_email(&smtp, &alert).await },
+                        |result| {
+                            if let Err(e) = result {
+                                tracing::warn!("Error sending alert email: {}", e);
+                            }
+                            cosmic::app::Message::None
+                        },
+                    ));
+                }
+                // Nobody sees notifications while the session is locked, they are summarized
+                // on unlock instead.
+                let shown = if self.session_locked {
+                    Message::QueueAlert(pair.clone(), summary, body)
                 } else {
-                    let new_id = Id::unique();
-                    self.popup.replace(new_id);
-                    let mut popup_settings =
-                        self.core
-                            .applet
-                            .get_popup_settings(Id::MAIN, new_id, None, None, None);
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .max_width(372.0)
-                        .min_width(300.0)
-                        .min_height(200.0)
-                        .max_height(1080.0);
-                    get_popup(popup_settings)
+                    Message::ShowAlert(pair.clone(), summary, body)
+                };
+                commands.push(Command::perform(
+                    std::future::ready(shown),
+                    cosmic::app::Message::App,
+                ));
+                if let Some(url) = self.webhook_url(pair) {
+                    let payload = serde_json::json!({
+                        "pair": pair.code(),
+                        "rate": quote.bid,
+                        "alert": rule.condition.to_string(),
+                        "change": {
+                            "delta": change.bid_delta,
+                            "percent": change.bid_pct,
+                            "direction": change.direction,
+                        },
+                    });
+                    commands.push(Command::perform(
+                        post_webhook(url.to_owned(), payload),
+                        |result| {
+                            if let Err(e) = result {
+                                tracing::warn!("Error posting to webhook: {}", e);
+                            }
+                            cosmic::app::Message::None
+                        },
+                    ));
+                }
+                commands
+            })
+            .collect()
+    }
+
+    /// The ⋮ button of a watchlist row, with the row's actions below it while open.
+    fn pair_context_menu(&self, index: usize) -> Element<Message> {
+        let button = widget::button::icon(widget::icon::from_name("view-more-symbolic"))
+            .on_press(Message::TogglePairMenu(index));
+        let Some(entry) = self
+            .config
+            .pairs
+            .get(index)
+            .filter(|_| self.pair_menu == Some(index))
+        else {
+            return button.into();
+        };
+
+        let toggle = if entry.enabled {
+            fl!("disable-pair")
+        } else {
+            fl!("enable-pair")
+        };
+        let quiet = if entry.quiet {
+            fl!("unmute-pair")
+        } else {
+            fl!("mute-pair")
+        };
+        let menu = widget::column::with_children(vec![
+            widget::button::text(fl!("set-alert"))
+                .on_press(Message::SetAlert(index))
+                .into(),
+            widget::button::text(toggle)
+                .on_press(Message::TogglePair(index, !entry.enabled))
+                .into(),
+            widget::button::text(quiet)
+                .on_press(Message::TogglePairQuiet(index))
+                .into(),
+            widget::button::text(fl!("delete-pair"))
+                .on_press(Message::RemovePair(index))
+                .into(),
+            widget::text(fl!("change-since")).size(12).into(),
+            widget::dropdown(
+                &self.change_window_labels,
+                Some(entry.change_window.index()),
+                move |kind| Message::ChangeWindowChanged(index, kind),
+            )
+            .into(),
+        ]);
+        let menu = menu.push(
+            TextInput::new(fl!("pair-markup-placeholder"), &self.pair_markup_input)
+                .on_input(move |text| Message::PairMarkupChanged(index, text)),
+        );
+        let menu = if let ChangeWindow::SinceTime(_) = entry.change_window {
+            menu.push(
+                TextInput::new("09:00", &self.change_window_time_input)
+                    .on_input(move |text| Message::ChangeWindowTimeChanged(index, text)),
+            )
+        } else {
+            menu
+        };
+        widget::popover(button)
+            .popup(widget::container(menu).style(cosmic::theme::Container::Dropdown))
+            .into()
+    }
+
+    /// The condition being entered in the alert editor, `None` while incomplete or invalid.
+    fn edited_alert_condition(&self) -> Option<AlertCondition> {
+        let value = parse_amount(&self.alert_value_input)?;
+        let amount = parse_amount(&self.alert_amount_input);
+        AlertCondition::from_index(self.alert_condition_index?, value, amount)
+    }
+
+    /// The alerts tab: the rules of every pair and an editor to add new ones.
+    fn view_alerts<'a>(&'a self) -> Element<'a, Message> {
+        let mut rules = widget::list_column().padding(5).spacing(0);
+        for (index, rule) in self.config.alert_rules.iter().enumerate() {
+            rules = rules.add(settings::item(
+                rule.pair.to_string(),
+                widget::row::with_children(vec![
+                    widget::text(rule.describe()).into(),
+                    widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                        .on_press(Message::RemoveAlert(index))
+                        .into(),
+                ])
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ));
+        }
+
+        // Parked by `reconcile_orphans`, for the user to decide on.
+        let mut unassigned = settings::view_section(fl!("unassigned-alerts"));
+        for (index, rule) in self.config.unassigned_alerts.iter().enumerate() {
+            unassigned = unassigned.add(settings::item(
+                rule.pair.to_string(),
+                widget::row::with_children(vec![
+                    widget::text(rule.describe()).into(),
+                    widget::dropdown(&self.pair_labels, None, move |pair_index| {
+                        Message::ReattachAlert(index, pair_index)
+                    })
+                    .into(),
+                    widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                        .on_press(Message::DeleteUnassignedAlert(index))
+                        .into(),
+                ])
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ));
+        }
+
+        let mut editor = settings::view_section(fl!("new-alert"))
+            .add(settings::item(
+                fl!("alert-pair"),
+                widget::dropdown(
+                    &self.pair_labels,
+                    self.alert_pair_index,
+                    Message::AlertPairSelected,
+                ),
+            ))
+            .add(settings::item(
+                fl!("alert-condition"),
+                widget::dropdown(
+                    &self.condition_labels,
+                    self.alert_condition_index,
+                    Message::AlertConditionSelected,
+                ),
+            ));
+        let for_amount = self
+            .alert_condition_index
+            .is_some_and(AlertCondition::needs_amount);
+        if for_amount {
+            editor = editor.add(settings::item(
+                fl!("alert-amount"),
+                TextInput::new("1000", &self.alert_amount_input)
+                    .on_input(Message::AlertAmountChanged)
+                    .on_submit(Message::SaveAlert),
+            ));
+        }
+        editor = editor.add(settings::item(
+            if for_amount {
+                fl!("alert-target")
+            } else {
+                fl!("alert-value")
+            },
+            TextInput::new(
+                if for_amount { "5600" } else { "5.40" },
+                &self.alert_value_input,
+            )
+            .on_input(Message::AlertValueChanged)
+            .on_submit(Message::SaveAlert),
+        ));
+        if let Some(condition) = self.edited_alert_condition().filter(|_| for_amount) {
+            editor = editor.add(widget::text::caption(fl!(
+                "alert-equivalent-rate",
+                condition = condition.rate_bound().to_string()
+            )));
+        }
+        editor = editor.add(widget::button::text(fl!("save-alert")).on_press(Message::SaveAlert));
+
+        // Saving is never blocked, these only point out rules that will misbehave.
+        for warning in &self.alert_warnings {
+            editor = editor.add(widget::text::caption(warning.message()));
+        }
+
+        let mut webhooks = settings::view_section(fl!("webhooks"));
+        for (index, entry) in self.config.pairs.iter().enumerate() {
+            let url = entry.webhook_url.as_deref().unwrap_or_default();
+            let mut controls = vec![TextInput::new("https://", url)
+                .on_input(move |url| Message::WebhookUrlChanged(index, url))
+                .width(Length::Fixed(160.0))
+                .into()];
+            if entry.webhook_url.is_some() {
+                controls.push(
+                    widget::button::text(fl!("test-webhook"))
+                        .on_press(Message::WebhookTestFired(index))
+                        .into(),
+                );
+            }
+            let last_test = self
+                .pair_states
+                .get(&entry.pair)
+                .and_then(|state| state.webhook_last_test.as_ref());
+            match last_test {
+                Some(Ok(status)) => controls.push(widget::text::caption(status.to_string()).into()),
+                Some(Err(e)) => controls.push(widget::text::caption(e.clone()).into()),
+                None => {}
+            }
+            webhooks = webhooks.add(settings::item(
+                entry.pair.to_string(),
+                widget::row::with_children(controls)
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+            ));
+        }
+
+        let smtp_input =
+            |label: String, placeholder: &'static str, value: &'a str, field: SmtpField| {
+                settings::item(
+                    label,
+                    TextInput::new(placeholder, value)
+                        .on_input(move |value| Message::SmtpFieldChanged(field, value)),
+                )
+            };
+        let email = settings::view_section(fl!("email-alerts"))
+            .add(smtp_input(
+                fl!("alert-email"),
+                "me@example.com",
+                self.config.alert_email.as_deref().unwrap_or_default(),
+                SmtpField::AlertEmail,
+            ))
+            .add(smtp_input(
+                fl!("smtp-server"),
+                "smtp.example.com",
+                &self.config.smtp_server,
+                SmtpField::Server,
+            ))
+            .add(smtp_input(
+                fl!("smtp-port"),
+                "587",
+                &self.smtp_port_input,
+                SmtpField::Port,
+            ))
+            .add(smtp_input(
+                fl!("smtp-from"),
+                "applet@example.com",
+                &self.config.smtp_from,
+                SmtpField::From,
+            ))
+            .add(smtp_input(
+                fl!("smtp-credential-key"),
+                "",
+                &self.config.smtp_credential_key,
+                SmtpField::CredentialKey,
+            ));
+
+        let mut sections: Vec<Element<Message>> = vec![rules.into()];
+        if !self.config.unassigned_alerts.is_empty() {
+            sections.push(unassigned.into());
+        }
+        sections.extend([editor.into(), webhooks.into(), email.into()]);
+        widget::column::with_children(sections)
+            .padding(8)
+            .spacing(12)
+            .into()
+    }
+
+    /// The settings tab.
+    fn view_settings(&self) -> Element<Message> {
+        let mut network = settings::view_section(fl!("network"))
+            .add(settings::item(
+                fl!("refresh-secs"),
+                TextInput::new("600", &self.refresh_input).on_input(Message::RefreshSecsChanged),
+            ))
+            .add(settings::item(
+                fl!("failure-digest-after-mins"),
+                TextInput::new("60", &self.failure_digest_input)
+                    .on_input(Message::FailureDigestChanged),
+            ))
+            .add(settings::item(
+                fl!("pause-on-unfocus"),
+                widget::toggler(
+                    None,
+                    self.config.pause_on_unfocus,
+                    Message::TogglePauseOnUnfocus,
+                ),
+            ))
+            .add(settings::item(
+                fl!("low-power"),
+                widget::toggler(None, self.config.low_power, Message::ToggleLowPower),
+            ))
+            .add(settings::item(
+                fl!("low-power-below-pct"),
+                TextInput::new("0", &self.low_power_below_input)
+                    .on_input(Message::LowPowerBelowChanged),
+            ))
+            .add(settings::item(
+                fl!("cache-ttl-secs"),
+                TextInput::new("300", &self.cache_ttl_input).on_input(Message::CacheTtlChanged),
+            ))
+            .add(settings::item(
+                fl!("api-retry-count"),
+                TextInput::new("3", &self.retry_count_input).on_input(Message::RetryCountChanged),
+            ))
+            .add(settings::item(
+                fl!("api-retry-delay-secs"),
+                TextInput::new("30", &self.retry_delay_input).on_input(Message::RetryDelayChanged),
+            ))
+            .add(settings::item(
+                fl!("api-monthly-soft-limit"),
+                TextInput::new("0", &self.soft_limit_input).on_input(Message::SoftLimitChanged),
+            ))
+            .add(settings::item(
+                fl!("forward-rates-url"),
+                TextInput::new(
+                    "https://example.com/forward/{pair}",
+                    &self.config.forward_rates_url,
+                )
+                .on_input(Message::ForwardRatesUrlChanged),
+            ));
+
+        if self.config.retries_overlap_refresh() {
+            network = network.add(widget::text(fl!(
+                "retries-overlap-warning",
+                window = u64::from(self.config.api_retry_count) * self.config.api_retry_delay_secs,
+                refresh = self.config.refresh_secs
+            )));
+        }
+
+        network = network
+            .add(settings::item(
+                fl!("allow-external-updates"),
+                widget::toggler(
+                    None,
+                    self.config.allow_external_updates,
+                    Message::ToggleExternalUpdates,
+                ),
+            ))
+            .add(widget::text::caption(fl!("external-updates-note")))
+            .add(settings::item(
+                fl!("allow-external-watchlist"),
+                widget::toggler(
+                    None,
+                    self.config.allow_external_watchlist,
+                    Message::ToggleExternalWatchlist,
+                ),
+            ))
+            .add(widget::text::caption(fl!("external-watchlist-note")));
+
+        let anchor_index = PopupAnchor::ALL
+            .iter()
+            .position(|&anchor| anchor == self.config.popup_anchor);
+        let quick_menu_click_index = QuickMenuClick::ALL
+            .iter()
+            .position(|&click| click == self.config.quick_menu_click);
+        let mut panel = settings::view_section(fl!("panel"))
+            .add(settings::item(
+                fl!("sort-panel-rotation"),
+                widget::toggler(
+                    None,
+                    self.config.sort_panel_rotation,
+                    Message::ToggleSortPanelRotation,
+                ),
+            ))
+            .add(settings::item(
+                fl!("first-panel-pair"),
+                widget::row::with_children(vec![
+                    widget::dropdown(&self.pair_labels, self.config.first_panel_pair, |index| {
+                        Message::PinPanelPair(Some(index))
+                    })
+                    .into(),
+                    widget::button::text(fl!("unpin"))
+                        .on_press_maybe(
+                            self.config
+                                .first_panel_pair
+                                .map(|_| Message::PinPanelPair(None)),
+                        )
+                        .into(),
+                ])
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ))
+            .add(settings::item(
+                fl!("first-panel-dwell-secs"),
+                TextInput::new("60", &self.first_panel_dwell_input)
+                    .on_input(Message::FirstPanelDwellChanged),
+            ))
+            .add(settings::item(
+                fl!("visual-bell"),
+                widget::toggler(None, self.config.visual_bell, Message::ToggleVisualBell),
+            ))
+            .add(settings::item(
+                fl!("max-alert-notifications-per-hour"),
+                TextInput::new("20", &self.alert_budget_input)
+                    .on_input(Message::AlertBudgetChanged),
+            ))
+            .add(settings::item(
+                fl!("popup-anchor"),
+                widget::dropdown(
+                    &self.anchor_labels,
+                    anchor_index,
+                    Message::PopupAnchorChanged,
+                ),
+            ))
+            .add(settings::item(
+                fl!("quick-menu-click"),
+                widget::dropdown(
+                    &self.quick_menu_click_labels,
+                    quick_menu_click_index,
+                    Message::QuickMenuClickChanged,
+                ),
+            ));
+
+        // The filled slots and one more to fill, up to `MAX_PANEL_SLOTS`.
+        let slots = &self.config.panel_slots;
+        for slot in 0..(slots.len() + 1).min(MAX_PANEL_SLOTS) {
+            let selected = slots.get(slot).and_then(|pair| self.pair_index(pair));
+            panel = panel.add(settings::item(
+                fl!("panel-slot-label", slot = slot + 1),
+                widget::row::with_children(vec![
+                    widget::dropdown(&self.pair_labels, selected, move |index| {
+                        Message::PanelSlotChanged(slot, Some(index))
+                    })
+                    .into(),
+                    widget::button::text(fl!("clear-panel-slot"))
+                        .on_press_maybe(
+                            (slot < slots.len()).then_some(Message::PanelSlotChanged(slot, None)),
+                        )
+                        .into(),
+                ])
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ));
+        }
+        panel = panel.add(widget::text::caption(fl!("panel-slots-note")));
+
+        let change_format_index = ChangeFormat::ALL
+            .iter()
+            .position(|&format| format == self.config.change_format);
+        let decimal_groups_index = DecimalGroupStyle::ALL
+            .iter()
+            .position(|&style| style == self.config.decimal_groups);
+        let animation_index = AnimationStyle::ALL
+            .iter()
+            .position(|&style| style == self.config.rate_change_animation);
+        let appearance = settings::view_section(fl!("appearance"))
+            .add(settings::item(
+                fl!("show-flags"),
+                widget::toggler(None, self.config.show_flags, Message::ToggleShowFlags),
+            ))
+            .add(settings::item(
+                fl!("show-change-heat"),
+                widget::toggler(
+                    None,
+                    self.config.show_change_heat,
+                    Message::ToggleChangeHeat,
+                ),
+            ))
+            .add(settings::item(
+                fl!("use-monospace-for-rate"),
+                widget::toggler(
+                    None,
+                    self.config.use_monospace_for_rate,
+                    Message::ToggleMonospaceForRate,
+                ),
+            ))
+            .add(settings::item(
+                fl!("font-family"),
+                TextInput::new(fl!("font-family-placeholder"), &self.font_family_input)
+                    .on_input(Message::FontFamilyChanged)
+                    .on_submit(Message::FontFamilySubmitted),
+            ))
+            .add(settings::item(fl!("font-preview"), {
+                let sample = match self.panel_pair() {
+                    Some(pair) => self.rate_text(pair),
+                    None => "1234.5678".to_string(),
+                };
+                match self.panel_font() {
+                    Some(font) => widget::text(sample).font(font),
+                    None => widget::text(sample),
                 }
+            }))
+            .add(settings::item(
+                fl!("show-forward-rates"),
+                widget::toggler(
+                    None,
+                    self.config.show_forward_rates,
+                    Message::ToggleForwardRates,
+                ),
+            ))
+            .add(settings::item(
+                fl!("pair-list-max-visible"),
+                TextInput::new("8", &self.max_visible_input).on_input(Message::MaxVisibleChanged),
+            ))
+            .add(settings::item(
+                fl!("precision"),
+                TextInput::new(fl!("precision-placeholder"), &self.precision_input)
+                    .on_input(Message::PrecisionChanged),
+            ))
+            .add(settings::item(
+                fl!("market-precision"),
+                widget::toggler(
+                    None,
+                    self.config.market_precision,
+                    Message::ToggleMarketPrecision,
+                ),
+            ))
+            .add(settings::item(
+                fl!("panel-trailing-zeros"),
+                widget::toggler(
+                    None,
+                    self.config.panel_trailing_zeros,
+                    Message::TogglePanelTrailingZeros,
+                ),
+            ))
+            .add(settings::item(
+                fl!("decimal-groups"),
+                widget::dropdown(
+                    &self.decimal_group_labels,
+                    decimal_groups_index,
+                    Message::DecimalGroupsChanged,
+                ),
+            ))
+            .add(settings::item(
+                fl!("change-at-display-precision"),
+                widget::toggler(
+                    None,
+                    self.config.change_at_display_precision,
+                    Message::ToggleChangeAtDisplayPrecision,
+                ),
+            ))
+            .add(settings::item(
+                fl!("change-format"),
+                widget::dropdown(
+                    &self.change_format_labels,
+                    change_format_index,
+                    Message::ChangeFormatChanged,
+                ),
+            ))
+            .add(settings::item(
+                fl!("rate-change-animation"),
+                widget::dropdown(
+                    &self.animation_labels,
+                    animation_index,
+                    Message::AnimationStyleChanged,
+                ),
+            ));
+
+        let markup = settings::view_section(fl!("markup"))
+            .add(settings::item(
+                fl!("markup-pct"),
+                TextInput::new("0", &self.markup_input).on_input(Message::MarkupChanged),
+            ))
+            .add(settings::item(
+                fl!("calculator-uses-markup"),
+                widget::toggler(
+                    None,
+                    self.config.calculator_uses_markup,
+                    Message::ToggleCalculatorMarkup,
+                ),
+            ))
+            .add(settings::item(
+                fl!("panel-uses-markup"),
+                widget::toggler(
+                    None,
+                    self.config.panel_uses_markup,
+                    Message::TogglePanelMarkup,
+                ),
+            ))
+            .add(widget::text::caption(fl!("markup-note")));
+
+        let mut navigation = settings::view_section(fl!("keyboard-navigation"));
+        let order = &self.config.tab_key_order;
+        for (index, section) in order.iter().enumerate() {
+            let mut up = widget::button::icon(widget::icon::from_name("go-up-symbolic"));
+            if index > 0 {
+                up = up.on_press(Message::MoveTabSection(index, true));
+            }
+            let mut down = widget::button::icon(widget::icon::from_name("go-down-symbolic"));
+            if index + 1 < order.len() {
+                down = down.on_press(Message::MoveTabSection(index, false));
+            }
+            navigation = navigation.add(settings::item(
+                section.label(),
+                widget::row::with_children(vec![
+                    up.into(),
+                    down.into(),
+                    widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                        .on_press(Message::RemoveTabSection(index))
+                        .into(),
+                ])
+                .spacing(4),
+            ));
+        }
+        for section in AppletSection::ALL
+            .into_iter()
+            .filter(|s| !order.contains(s))
+        {
+            navigation = navigation.add(settings::item(
+                section.label(),
+                widget::button::icon(widget::icon::from_name("list-add-symbolic"))
+                    .on_press(Message::AddTabSection(section)),
+            ));
+        }
+
+        let mut startup = settings::view_section(fl!("startup"))
+            .add(settings::item(
+                fl!("startup-notification"),
+                widget::toggler(
+                    None,
+                    self.config.startup_notification,
+                    Message::ToggleStartupNotification,
+                ),
+            ))
+            .add(settings::item(
+                fl!("use-startup-list"),
+                widget::toggler(
+                    None,
+                    self.config.startup_pairs_enabled,
+                    Message::ToggleStartupPairs,
+                ),
+            ));
+        for (index, pair) in self.config.startup_pairs.iter().enumerate() {
+            startup = startup.add(settings::item(
+                pair.to_string(),
+                widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                    .on_press(Message::RemoveStartupPair(index)),
+            ));
+        }
+        startup = startup
+            .add(settings::item(
+                fl!("add-pair"),
+                PairInput::new(
+                    &self.startup_input,
+                    Message::StartupInputChanged,
+                    Message::AddStartupPair,
+                ),
+            ))
+            .add(widget::text::caption(fl!("startup-list-note")));
+
+        let mut sections = vec![
+            network.into(),
+            panel.into(),
+            appearance.into(),
+            markup.into(),
+            navigation.into(),
+            startup.into(),
+        ];
+        if self.shortcuts_available {
+            sections.push(
+                settings::view_section(fl!("shortcuts"))
+                    .add(widget::text::caption(fl!("shortcuts-note")))
+                    .into(),
+            );
+        }
+        widget::column::with_children(sections)
+            .padding(8)
+            .spacing(12)
+            .into()
+    }
+
+    /// The status tab: requests made to each provider.
+    fn view_status(&self) -> Element<Message> {
+        let today = self.clock.now_local().date_naive();
+        let limit = self.config.api_monthly_soft_limit;
+        let mut usage = settings::view_section(fl!("api-usage"));
+
+        for (provider, counters) in &self.state.api_usage.providers {
+            let counters = counters.as_of(today);
+            usage = usage.add(settings::item(
+                provider.clone(),
+                widget::text(fl!(
+                    "api-usage-counts",
+                    today = counters.today,
+                    month = counters.this_month
+                )),
+            ));
+            if counters.near_limit(limit) {
+                usage = usage.add(widget::text(fl!(
+                    "api-soft-limit-warning",
+                    provider = provider.clone(),
+                    month = counters.this_month,
+                    limit = limit
+                )));
+            }
+        }
+
+        let now = self.clock.now_local();
+        for (provider, until) in &self.maintenance {
+            if self.under_maintenance(provider, now) {
+                usage = usage.add(widget::text(fl!(
+                    "provider-maintenance",
+                    provider = provider.clone(),
+                    until = until.format("%H:%M").to_string()
+                )));
             }
-            Message::PopupClosed(id) => {
-                if self.popup.as_ref() == Some(&id) {
-                    self.popup = None;
+        }
+
+        usage = usage
+            .add(widget::button::text(fl!("reset-api-usage")).on_press(Message::ResetApiUsage));
+
+        let mut fetches = settings::view_section(fl!("recent-fetches"));
+        let in_use = self.providers_in_use();
+        for name in &in_use {
+            let Some(stats) = self.provider_stats.get(*name) else {
+                continue;
+            };
+            for record in &stats.recent_fetches {
+                let mut label = format!("{} {}", clock_time(&record.at), record.pair);
+                // Only told apart once pairs are fetched from more than one provider.
+                if in_use.len() > 1 {
+                    label.push_str(&format!(" ({name})"));
                 }
+                fetches = fetches.add(settings::item(label, widget::text(record.trigger.label())));
+            }
+        }
+
+        let now = self.clock.now_instant();
+        let ago = |at: Option<&Instant>| match at {
+            Some(at) => format_countdown(now.saturating_duration_since(*at)),
+            None => "–".to_string(),
+        };
+        let mut pairs = settings::view_section(fl!("pair-timestamps"));
+        for entry in self.config.pairs.iter().filter(|e| e.enabled) {
+            let source = provider(self.config.provider_of(&entry.pair));
+            let market = source.timezone();
+            let changed_at = self
+                .pair_states
+                .get(&entry.pair)
+                .and_then(|state| state.changed_at.as_ref());
+            let mut lines = vec![widget::text(fl!(
+                "pair-timestamps-ago",
+                fetched = ago(self.fetched_at.get(&entry.pair)),
+                changed = ago(changed_at)
+            ))
+            .into()];
+            let quoted = self
+                .exchange_rates
+                .get(&entry.pair)
+                .and_then(|rate| quote_time(rate.timestamp, market));
+            if let Some(quoted) = quoted {
+                let text = match quoted.local {
+                    Some(local) => fl!("quoted-at-local", market = quoted.market, local = local),
+                    None => fl!("quoted-at", market = quoted.market),
+                };
+                lines.push(widget::text(text).into());
             }
-            Message::InputChanged(new_value) => {
-                self.input_value = new_value;
+            let quoted_by = self
+                .pair_states
+                .get(&entry.pair)
+                .and_then(|state| state.quoted_by)
+                .filter(|&name| name != source.name() && name != DERIVED);
+            if let Some(name) = quoted_by {
+                lines.push(widget::text(fl!("quoted-by-fallback", provider = name)).into());
             }
+            pairs = pairs.add(settings::item(
+                entry.pair.to_string(),
+                widget::column::with_children(lines),
+            ));
         }
-        Command::none()
+
+        // Built from the current settings and watchlist, so they are the next requests.
+        let groups = self.pairs_by_provider();
+        let active: Vec<Pair> = groups
+            .iter()
+            .flat_map(|(_, pairs)| pairs.iter().cloned())
+            .collect();
+        let mut requests = settings::view_section(fl!("next-requests"));
+        for (source, group) in &groups {
+            let codes: Vec<String> = group.iter().map(Pair::to_string).collect();
+            requests = requests.add(settings::item(
+                fl!(
+                    "provider-group",
+                    provider = source.name(),
+                    pairs = codes.join(", ")
+                ),
+                widget::text::caption(source.describe_request(group)),
+            ));
+        }
+        if self.config.show_forward_rates && !self.config.forward_rates_url.is_empty() {
+            for pair in &active {
+                requests = requests.add(settings::item(
+                    fl!("forward-request", pair = pair.to_string()),
+                    widget::text::caption(describe_forward_request(
+                        &self.config.forward_rates_url,
+                        pair,
+                    )),
+                ));
+            }
+        }
+
+        let flagged_quotes = &self.state.flagged_quotes;
+        let mut flagged = settings::view_section(fl!("flagged-quotes"));
+        for (index, flag) in flagged_quotes.iter().enumerate().rev() {
+            let mut lines = vec![widget::text(fl!(
+                "flagged-quote",
+                rate = flag
+                    .quote
+                    .as_ref()
+                    .map_or_else(|| "–".to_string(), |quote| quote.bid.to_string()),
+                provider = flag
+                    .quoted_by
+                    .clone()
+                    .unwrap_or_else(|| flag.provider.clone())
+            ))
+            .into()];
+            if let Some(excerpt) = &flag.response_excerpt {
+                lines.push(widget::text::caption(ellipsize(excerpt, MAX_EXCERPT_CHARS)).into());
+            }
+            flagged = flagged.add(settings::item(
+                format!(
+                    "{} {}",
+                    flag.flagged_at.format("%Y-%m-%d %H:%M:%S"),
+                    flag.pair
+                ),
+                widget::row::with_children(vec![
+                    widget::column::with_children(lines).into(),
+                    widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                        .on_press(Message::DeleteFlaggedQuote(index))
+                        .into(),
+                ])
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ));
+        }
+        flagged = if flagged_quotes.is_empty() {
+            flagged.add(widget::text::caption(fl!("flagged-quotes-none")))
+        } else {
+            flagged.add(
+                widget::button::text(fl!("clear-flagged-quotes"))
+                    .on_press(Message::ClearFlaggedQuotes),
+            )
+        };
+
+        let probes = self.popup_capabilities;
+        let popups = settings::view_section(fl!("popup-capabilities"))
+            .add(settings::item(
+                fl!("popup-focus"),
+                widget::text(probes.focus.label()),
+            ))
+            .add(settings::item(
+                fl!("popup-resize"),
+                widget::text(probes.resize.label()),
+            ));
+
+        let held = self.held_counts();
+        let measured = |bytes: Option<u64>| bytes.map_or_else(|| "–".to_string(), format_bytes);
+        let footprint = self.footprint.unwrap_or_default();
+        let own = settings::view_section(fl!("footprint"))
+            .add(settings::item(
+                fl!("footprint-memory"),
+                widget::text(measured(footprint.resident_bytes)),
+            ))
+            .add(settings::item(
+                fl!("footprint-history"),
+                widget::text(held.history_points.to_string()),
+            ))
+            .add(settings::item(
+                fl!("footprint-cache"),
+                widget::text(fl!(
+                    "footprint-cache-counts",
+                    quotes = held.cached_quotes,
+                    responses = held.raw_responses
+                )),
+            ))
+            .add(settings::item(
+                fl!("footprint-disk"),
+                widget::text(fl!(
+                    "footprint-disk-sizes",
+                    settings = measured(footprint.settings_bytes),
+                    state = measured(footprint.state_bytes)
+                )),
+            ))
+            .add(settings::item(
+                fl!("footprint-requests-today"),
+                widget::text(held.requests_today.to_string()),
+            ));
+
+        let report = widget::button::text(fl!("copy-diagnostic-report"))
+            .on_press(Message::CopyDiagnosticReport);
+
+        widget::column::with_children(vec![
+            usage.into(),
+            fetches.into(),
+            pairs.into(),
+            requests.into(),
+            flagged.into(),
+            popups.into(),
+            own.into(),
+            report.into(),
+        ])
+        .padding(8)
+        .into()
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
+    /// Measures the footprint off the UI thread, answering with `Message::FootprintMeasured`.
+    fn measure_footprint(&self) -> Command<Message> {
+        let (settings_dir, state_dir) = (self.persistence.dir.clone(), self.state_dir.clone());
+        Command::perform(
+            tokio::task::spawn_blocking(move || measure(settings_dir, state_dir)),
+            |result| match result {
+                Ok(footprint) => cosmic::app::Message::App(Message::FootprintMeasured(footprint)),
+                // The measurement panicked, the previous one is kept.
+                Err(_) => cosmic::app::Message::None,
+            },
+        )
     }
-}
 
-async fn fetch_exchange_rate(input_value: &str) -> Result<String, Error> {
-    // Get the first 3 letter from the input_value
-    let from_currency = &input_value[..3];
-    // Get the last 3 letter from the input_value
-    let to_currency = &input_value[3..];
-    let response = reqwest::get(format!(
-        "https://economia.awesomeapi.com.br/last/{from_currency}-{to_currency}",
-    ))
-    .await?
-    .json::<Value>()
-    .await?;
-    Ok(response[input_value]["bid"].to_string())
+    /// The state field holding the id of the popup of `kind`.
+    fn popup_slot(&mut self, kind: PopupKind) -> &mut Option<Id> {
+        match kind {
+            PopupKind::Main => &mut self.popup,
+            PopupKind::ContextMenu => &mut self.context_menu,
+            PopupKind::QuickMenu => &mut self.quick_menu,
+            PopupKind::Calculator => &mut self.calculator_popup,
+            PopupKind::About => &mut self.about_popup,
+        }
+    }
+
+    /// Destroys every open popup and clears their state.
+    fn close_popups(&mut self) -> Vec<Command<Message>> {
+        let ids: Vec<(Id, PopupKind)> = self.popup_kind.drain().collect();
+        ids.into_iter()
+            .map(|(id, kind)| {
+                if kind == PopupKind::Main {
+                    self.removed_pair = None;
+                }
+                *self.popup_slot(kind) = None;
+                self.popup_size = None;
+                destroy_popup(id)
+            })
+            .collect()
+    }
+
+    /// Shows an alert notification if the notification budget allows it, counting it as
+    /// suppressed otherwise. The first suppressed alert schedules the summary of them all
+    /// for when the budget has room again.
+    fn notify_within_budget(
+        &mut self,
+        summary: String,
+        body: String,
+        pair: Option<Pair>,
+    ) -> Command<Message> {
+        let limit = self.config.max_alert_notifications_per_hour;
+        if self
+            .notification_budget
+            .take(self.clock.now_instant(), limit)
+        {
+            return notify_alert(summary, body, pair);
+        }
+        tracing::debug!("alert notification suppressed: {summary}");
+        if self.notification_budget.suppressed == 1 {
+            return self.schedule_budget_reset();
+        }
+        Command::none()
+    }
+
+    /// Wakes the applet up when the notification budget has room again.
+    fn schedule_budget_reset(&self) -> Command<Message> {
+        let wait = self
+            .notification_budget
+            .next_room()
+            .map_or(Duration::ZERO, |at| {
+                at.saturating_duration_since(self.clock.now_instant())
+            });
+        Command::perform(tokio::time::sleep(wait), |_| {
+            cosmic::app::Message::App(Message::NotificationBudgetReset)
+        })
+    }
+
+    /// The font of the panel label, `None` for COSMIC's default.
+    fn panel_font(&self) -> Option<Font> {
+        if !self.config.use_monospace_for_rate {
+            return None;
+        }
+        let family = match self.config.font_family.as_str() {
+            "" => Family::Monospace,
+            name => Family::Name(static_font_name(name)),
+        };
+        Some(Font {
+            family,
+            ..Font::DEFAULT
+        })
+    }
+
+    /// Fetches the forward rates of `pair` in the background while the forward curve is
+    /// shown, answering with `Message::ForwardRatesFetched`.
+    fn fetch_forward(&self, pair: Pair) -> Command<Message> {
+        if !self.config.show_forward_rates || self.config.forward_rates_url.is_empty() {
+            return Command::none();
+        }
+        let endpoint = self.config.forward_rates_url.clone();
+        let usage = self.usage.clone();
+        let clock = self.clock.clone();
+        Command::perform(
+            async move {
+                let result =
+                    fetch_forward_rates(&endpoint, &pair, FORWARD_TENORS, &usage, clock.as_ref())
+                        .await;
+                (pair, result)
+            },
+            |(pair, result)| cosmic::app::Message::App(Message::ForwardRatesFetched(pair, result)),
+        )
+    }
+
+    /// Stops fetching from `provider` until the end of the maintenance window it announced,
+    /// then sends `Message::MaintenanceOver` up to `MAINTENANCE_JITTER_MS` later.
+    fn pause_for_maintenance(
+        &mut self,
+        provider: &'static str,
+        until: DateTime<Local>,
+    ) -> Command<Message> {
+        let provider = provider.to_string();
+        // Every pair of a batch announces the same window, it is only scheduled once.
+        if self.maintenance.get(&provider) == Some(&until) {
+            return Command::none();
+        }
+        tracing::info!("{provider} is under maintenance until {until}");
+        self.maintenance.insert(provider.clone(), until);
+        let wait = maintenance_resumption(until, self.clock.now_local());
+        Command::perform(tokio::time::sleep(wait), move |_| {
+            cosmic::app::Message::App(Message::MaintenanceOver(provider))
+        })
+    }
+
+    /// Takes in a new `quote` of `pair`, firing the alerts it triggers.
+    fn apply_quote(&mut self, pair: Pair, quote: PairResponse, at: Instant) -> Command<Message> {
+        let notifications = self.fired_alerts(&pair, &quote);
+        if !notifications.is_empty() {
+            self.alerts_unacknowledged = true;
+        }
+        self.store_quote(pair, quote, at);
+        self.recompute_calculator();
+        Command::batch(notifications)
+    }
+
+    /// Takes the quote of the derived pair `pair` from its expression evaluated now, or
+    /// notes on its row why it can't be.
+    fn derive(&mut self, pair: Pair) -> Command<Message> {
+        let Some(expression) = self.expression_of(&pair) else {
+            return Command::none();
+        };
+        let derived = self.derived_quote(expression);
+        let now = self.clock.now_instant();
+        let state = self.pair_states.entry(pair.clone()).or_default();
+        match derived {
+            Ok(quote) => {
+                state.last_error = None;
+                state.quoted_by = Some(DERIVED);
+                self.fetched_at.insert(pair.clone(), now);
+                self.apply_quote(pair, quote, now)
+            }
+            Err(e) => {
+                state.last_error = Some(e.message());
+                Command::none()
+            }
+        }
+    }
+
+    /// Adds `pair` to the watchlist and fetches it, `None` if it is already there. With an
+    /// `expression`, it is a derived pair evaluated by it instead.
+    fn add_pair(&mut self, pair: Pair, expression: Option<String>) -> Option<Command<Message>> {
+        if self.pair_index(&pair).is_some() {
+            return None;
+        }
+        self.cadence_note = cadence_note(&pair, self.pair_cadence(&pair), self.config.refresh_secs);
+        self.config.pairs.push(PairEntry {
+            expression,
+            ..PairEntry::new(pair.clone())
+        });
+        self.save_config();
+        let warning = self.check_configured();
+        Some(Command::batch([
+            warning,
+            self.fetch(pair, FetchTrigger::PairChange),
+        ]))
+    }
+
+    /// Warns with `Message::UnconfiguredStateWarning` once no pair is enabled any more,
+    /// and dismisses the warning as soon as one is again.
+    fn check_configured(&mut self) -> Command<Message> {
+        if self.config.pairs.iter().any(|e| e.enabled) {
+            self.unconfigured = false;
+        } else if !self.unconfigured {
+            return Command::perform(
+                std::future::ready(Message::UnconfiguredStateWarning),
+                cosmic::app::Message::App,
+            );
+        }
+        Command::none()
+    }
+
+    /// Announces an update when the applet runs for the first time since it changed version,
+    /// not on its very first run, then remembers the running version.
+    fn check_updated(&mut self) -> Command<Message> {
+        let version = env!("CARGO_PKG_VERSION");
+        if self.state.last_seen_version == version {
+            return Command::none();
+        }
+        let updated = !self.state.last_seen_version.is_empty();
+        self.state.last_seen_version = version.to_string();
+        self.save_state();
+        if !updated || !self.config.startup_notification {
+            return Command::none();
+        }
+        notify_update(
+            fl!("updated-summary", version = version),
+            changelog_summary(CHANGES, version).unwrap_or_default(),
+        )
+    }
+
+    /// Opens the calculator on `pair`, or on the panel pair if `None`.
+    fn open_calculator(&mut self, pair: Option<Pair>) -> Command<Message> {
+        self.calculator_pair = pair;
+        if self.calculator_base_input.is_empty() && self.calculator_quote_input.is_empty() {
+            self.calculator_base_input = "1".to_string();
+            self.calculator_last_edited = CalculatorSide::Base;
+        }
+        self.recompute_calculator();
+        self.open_popup(PopupKind::Calculator)
+    }
+
+    /// Opens the popup of `kind`, closing whichever popup was open before.
+    fn open_popup(&mut self, kind: PopupKind) -> Command<Message> {
+        if kind == PopupKind::Main {
+            self.acknowledge_alerts();
+            self.popup_unfocused = false;
+        }
+        let mut commands = self.close_popups();
+        let new_id = Id::unique();
+        self.popup_kind.insert(new_id, kind);
+        *self.popup_slot(kind) = Some(new_id);
+
+        let mut popup_settings =
+            self.core
+                .applet
+                .get_popup_settings(Id::MAIN, new_id, None, None, None);
+        if let Some((anchor, gravity)) =
+            self.config.popup_anchor.positioner(self.core.applet.anchor)
+        {
+            popup_settings.positioner.anchor = anchor;
+            popup_settings.positioner.gravity = gravity;
+        }
+        popup_settings.positioner.size_limits = self.popup_limits(kind);
+        commands.push(get_popup(popup_settings));
+        if kind == PopupKind::Main && self.popup_capabilities.focus == Probe::Untested {
+            commands.push(Command::perform(
+                tokio::time::sleep(PROBE_TIMEOUT),
+                move |_| cosmic::app::Message::App(Message::FocusProbeExpired(new_id)),
+            ));
+        }
+        Command::batch(commands)
+    }
+
+    /// The size limits of the popup of `kind`, capped to `work_area` so it never extends
+    /// under the panel or off the output. Its content scrolls instead.
+    fn popup_limits(&self, kind: PopupKind) -> Limits {
+        let limits = match kind {
+            PopupKind::Main => {
+                // Leave room for the widest rate so values never wrap or get cut.
+                let heat_width = if self.config.show_change_heat {
+                    HEAT_BAR_WIDTH
+                } else {
+                    0.0
+                };
+                let min_width =
+                    (ROW_CHROME_WIDTH + heat_width + self.rate_column_width()).max(300.0);
+                Limits::NONE
+                    .max_width(min_width.max(372.0))
+                    .min_width(min_width)
+                    .min_height(200.0)
+                    .max_height(1080.0)
+            }
+            PopupKind::ContextMenu => Limits::NONE
+                .min_width(160.0)
+                .max_width(240.0)
+                .min_height(40.0)
+                .max_height(400.0),
+            // Narrow, the rows only hold the pair, its rate and its change.
+            PopupKind::QuickMenu => Limits::NONE
+                .min_width(200.0)
+                .max_width(300.0)
+                .min_height(40.0)
+                .max_height(600.0),
+            PopupKind::Calculator | PopupKind::About => Limits::NONE
+                .min_width(280.0)
+                .max_width(372.0)
+                .min_height(100.0)
+                .max_height(600.0),
+        };
+        let Some((width, height)) = self.work_area() else {
+            return limits;
+        };
+        let (min, max) = (limits.min(), limits.max());
+        Limits::new(
+            Size::new(min.width.min(width), min.height.min(height)),
+            Size::new(max.width.min(width), max.height.min(height)),
+        )
+    }
+
+    /// The room popups have on the panel's output: its size less the panel on its edge and
+    /// `POPUP_SCREEN_MARGIN` all around. `None` until the output size is known.
+    fn work_area(&self) -> Option<(f32, f32)> {
+        let (width, height) = self.output_size?;
+        let (size, _) = self.core.applet.suggested_size(false);
+        let padding = self.core.applet.suggested_padding(false);
+        let panel = f32::from(size) + 2.0 * f32::from(padding);
+        let (width, height) = match self.core.applet.anchor {
+            PanelAnchor::Top | PanelAnchor::Bottom => (width, height - panel),
+            PanelAnchor::Left | PanelAnchor::Right => (width - panel, height),
+        };
+        let margins = 2.0 * POPUP_SCREEN_MARGIN;
+        Some(((width - margins).max(0.0), (height - margins).max(0.0)))
+    }
+
+    /// Clears the state of the popup `id` after it was closed.
+    fn forget_popup(&mut self, id: Id) {
+        if let Some(kind) = self.popup_kind.remove(&id) {
+            if kind == PopupKind::Main {
+                self.pair_menu = None;
+                self.removed_pair = None;
+                // Shown once, the alerts tab keeps listing them.
+                self.unassigned_notice = false;
+            }
+            let slot = self.popup_slot(kind);
+            if *slot == Some(id) {
+                *slot = None;
+                self.popup_size = None;
+            }
+        }
+    }
+
+    /// The rate of a popup row, played with the `rate_change_animation` after it changed.
+    fn animated_rate<'a>(
+        &self,
+        pair: &Pair,
+        rate: impl Into<Element<'a, Message>>,
+    ) -> Element<'a, Message> {
+        let (progress, rising) = self.pair_states.get(pair).map_or((0.0, false), |state| {
+            (state.animation_progress, state.animation_rising)
+        });
+        match self.animation_style() {
+            AnimationStyle::None => rate.into(),
+            AnimationStyle::Flash => widget::container(rate)
+                .style(cosmic::theme::Container::custom(move |theme| {
+                    let cosmic = theme.cosmic();
+                    let mut color = cosmic::iced::Color::from(if rising {
+                        cosmic.success_color()
+                    } else {
+                        cosmic.destructive_color()
+                    });
+                    color.a = FLASH_ALPHA * progress;
+                    widget::container::Appearance {
+                        background: Some(cosmic::iced::Background::Color(color)),
+                        ..Default::default()
+                    }
+                }))
+                .into(),
+            // The padding around the rate always adds up to `SLIDE_DISTANCE`, so the row
+            // keeps its height while the rate moves inside it.
+            AnimationStyle::Slide => {
+                let half = SLIDE_DISTANCE / 2.0;
+                let shift = if rising { half } else { -half } * progress;
+                widget::container(rate)
+                    .padding([half + shift, 0.0, half - shift, 0.0])
+                    .into()
+            }
+        }
+    }
+
+    /// Whether the popup lost focus with `pause_on_unfocus` set: animations then hold still
+    /// and pairs are fetched `UNFOCUSED_REFRESH_FACTOR` times less often.
+    fn paused_for_focus(&self) -> bool {
+        self.config.pause_on_unfocus
+            && self.popup.is_some()
+            && self.popup_unfocused
+            && capabilities::focus_tracking(self.popup_capabilities)
+    }
+
+    /// The style of the panel button, highlighted while the visual bell is lit.
+    fn panel_button_style(&self) -> cosmic::theme::Button {
+        if self.bell_lit {
+            cosmic::theme::Button::Suggested
+        } else {
+            cosmic::theme::Button::AppletIcon
+        }
+    }
+
+    /// Marks the fired alerts as seen, stopping the visual bell. Every way of acknowledging
+    /// them ends up here: opening the popup, clicking a notification or its convert action,
+    /// and the D-Bus interface.
+    fn acknowledge_alerts(&mut self) {
+        self.alerts_unacknowledged = false;
+        self.bell_lit = false;
+    }
+
+    /// The panel button in `DisplayMode::Icon`: the applet icon badged with the number of
+    /// active pairs, sized for the dock, with the rates in a tooltip.
+    fn view_icon_button(&self) -> Element<Message> {
+        let (icon_size, _) = self.core.applet.suggested_size(false);
+        let badge = widget::row::with_children(vec![
+            widget::icon::from_name(YourApp::APP_ID)
+                .size(icon_size)
+                .into(),
+            widget::text::caption(self.active_pairs_count().to_string()).into(),
+        ])
+        .spacing(2)
+        .align_items(Alignment::End);
+        let padding = if self.in_dock() { 8 } else { 4 };
+        let button = widget::button(badge)
+            .padding(padding)
+            .on_press(self.left_click())
+            .style(self.panel_button_style());
+
+        let rates: Vec<String> = self
+            .config
+            .pairs
+            .iter()
+            .filter(|e| e.is_active())
+            .map(|e| format!("{} {}", e.pair, self.rate_text(&e.pair)))
+            .collect();
+        let tooltip_position = match self.core.applet.anchor {
+            PanelAnchor::Top => widget::tooltip::Position::Bottom,
+            PanelAnchor::Bottom => widget::tooltip::Position::Top,
+            PanelAnchor::Left => widget::tooltip::Position::Right,
+            PanelAnchor::Right => widget::tooltip::Position::Left,
+        };
+        self.panel_clicks(widget::tooltip(button, rates.join("\n"), tooltip_position))
+    }
+
+    /// The panel slots side by side, each opening the popup on its pair, separated by
+    /// dividers. `None` without slots, or if they don't fit the panel.
+    fn view_panel_slots(&self) -> Option<Element<Message>> {
+        let pairs = self.panel_slot_pairs();
+        if pairs.is_empty() {
+            return None;
+        }
+        // Slots of pairs quoted in one currency are told apart by their base alone.
+        let one_quote = pairs.iter().all(|pair| pair.quote() == pairs[0].quote());
+        let labels: Vec<String> = pairs
+            .iter()
+            .map(|pair| {
+                let code = if one_quote {
+                    pair.base().to_string()
+                } else {
+                    pair.to_string()
+                };
+                fl!("panel-slot", code = code, rate = self.panel_rate_text(pair))
+            })
+            .collect();
+        if !self.panel_slots_fit(&labels) {
+            return None;
+        }
+
+        let (icon_size, _) = self.core.applet.suggested_size(false);
+        let mut slots = Vec::new();
+        for (pair, label) in pairs.into_iter().zip(labels) {
+            if !slots.is_empty() {
+                slots.push(
+                    widget::container(widget::divider::vertical::default())
+                        .height(Length::Fixed(f32::from(icon_size)))
+                        .into(),
+                );
+            }
+            let mut text = match self.panel_font() {
+                Some(font) => widget::text(label).font(font),
+                None => widget::text(label),
+            };
+            if self.panel_dims(pair) {
+                text = text.style(cosmic::theme::Text::Custom(dimmed_text));
+            }
+            let click = match self.config.quick_menu_click {
+                QuickMenuClick::Left => Message::ToggleQuickMenu,
+                QuickMenuClick::Off | QuickMenuClick::Middle => {
+                    Message::TogglePopupAt(pair.clone())
+                }
+            };
+            slots.push(
+                widget::button(text)
+                    .padding(PANEL_BUTTON_PADDING as u16)
+                    .on_press(click)
+                    .style(self.panel_button_style())
+                    .into(),
+            );
+        }
+        let row = widget::row::with_children(slots)
+            .spacing(2)
+            .align_items(Alignment::Center);
+        Some(self.panel_clicks(row))
+    }
+
+    /// Whether the panel slots showing `labels` fit the panel: its thickness on a vertical
+    /// panel, `PANEL_SLOTS_MAX_SHARE` of the output's width on a horizontal one. They are
+    /// taken to fit until the output's size is known.
+    fn panel_slots_fit(&self, labels: &[String]) -> bool {
+        let text: usize = labels.iter().map(|label| label.chars().count()).sum();
+        let chrome = labels.len() as f32 * 2.0 * PANEL_BUTTON_PADDING;
+        let width = text as f32 * RATE_FONT_SIZE * FIGURE_WIDTH_EM + chrome;
+        let available = match self.core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => {
+                let (size, _) = self.core.applet.suggested_size(false);
+                let padding = self.core.applet.suggested_padding(false);
+                f32::from(size) + 2.0 * f32::from(padding)
+            }
+            PanelAnchor::Top | PanelAnchor::Bottom => match self.output_size {
+                Some((output_width, _)) => output_width * PANEL_SLOTS_MAX_SHARE,
+                None => return true,
+            },
+        };
+        width <= available
+    }
+
+    /// What left-clicking the panel button does: opens the popup, or the quick menu if
+    /// it has taken over the left click.
+    fn left_click(&self) -> Message {
+        match self.config.quick_menu_click {
+            QuickMenuClick::Left => Message::ToggleQuickMenu,
+            QuickMenuClick::Off | QuickMenuClick::Middle => Message::TogglePopup,
+        }
+    }
+
+    /// `button` with the other clicks of the panel button: right for the context menu,
+    /// middle for the quick menu if it is opened that way.
+    fn panel_clicks<'a>(&self, button: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+        let area = widget::mouse_area(button).on_right_press(Message::OpenContextMenu);
+        if self.config.quick_menu_click == QuickMenuClick::Middle {
+            area.on_middle_press(Message::ToggleQuickMenu).into()
+        } else {
+            area.into()
+        }
+    }
+
+    /// The main popup: the tab bar, the selected tab and the footer.
+    fn view_main_popup(&self) -> Element<Message> {
+        // Applets have no nav bar of their own, so the model is shown as tabs instead.
+        let tabs = widget::container(
+            widget::tab_bar::horizontal(&self.nav_model).on_activate(Message::NavSelected),
+        )
+        .padding([8, 8, 0, 8]);
+
+        let started = Instant::now();
+        // The tab content scrolls rather than growing the popup past the work area.
+        let content_height = self.work_area().map_or(f32::INFINITY, |(_, height)| {
+            (height - POPUP_CHROME_HEIGHT).max(PAIR_ROW_HEIGHT)
+        });
+        let content = widget::container(widget::scrollable(self.view_for(self.active_view)))
+            .max_height(content_height)
+            .into();
+        tracing::debug!(
+            "built the {:?} view in {:?}",
+            self.active_view,
+            started.elapsed()
+        );
+
+        // The banner keeps its slot when empty, so the content stays at the same place in
+        // the widget tree and keeps its scroll and focus as errors get fixed.
+        let banner = if self.config_errors.is_empty() {
+            cosmic::iced::widget::Space::new(0, 0).into()
+        } else {
+            self.view_config_errors()
+        };
+        self.core
+            .applet
+            .popup_container(widget::column::with_children(vec![
+                widget::column::with_children(vec![tabs.into(), banner, content]).into(),
+                widget::divider::horizontal::default().into(),
+                self.view_footer(),
+            ]))
+            .into()
+    }
+
+    /// The banner listing the `validate_config` errors found at startup, each with a button
+    /// to the tab its settings are on.
+    fn view_config_errors(&self) -> Element<Message> {
+        let mut banner = widget::list_column().padding(5).spacing(0);
+        for (index, error) in self.config_errors.iter().enumerate() {
+            banner = banner.add(settings::item(
+                error.message(),
+                widget::button::text(fl!("fix")).on_press(Message::FixConfigError(index)),
+            ));
+        }
+        banner =
+            banner.add(widget::button::text(fl!("dismiss")).on_press(Message::DismissConfigErrors));
+        widget::container(banner).padding([8, 8, 0, 8]).into()
+    }
+
+    /// The content of `view` in the main popup.
+    fn view_for(&self, view: AppView) -> Element<Message> {
+        match view {
+            AppView::Watchlist => self.view_watchlist(),
+            AppView::Converter => self.view_calculator(),
+            AppView::Alerts => self.view_alerts(),
+            AppView::History => self.view_history(),
+            AppView::Settings => self.view_settings(),
+            AppView::Status => self.view_status(),
+            AppView::Debug => self.view_debug(),
+        }
+    }
+
+    /// The history view: the quotes received this session for each active pair.
+    fn view_history(&self) -> Element<Message> {
+        let export_index = ExportFormat::ALL
+            .iter()
+            .position(|&format| format == self.config.history_export_format);
+        let mut export = settings::view_section(fl!("export-history")).add(settings::item(
+            fl!("export-format"),
+            widget::row::with_children(vec![
+                widget::dropdown(
+                    &self.export_format_labels,
+                    export_index,
+                    Message::ExportFormatChanged,
+                )
+                .into(),
+                widget::button::text(fl!("copy-history"))
+                    .on_press(Message::ExportHistory)
+                    .into(),
+                widget::button::text(fl!("save-history"))
+                    .on_press(Message::SaveHistory)
+                    .into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center),
+        ));
+        if let Some(status) = &self.export_status {
+            export = export.add(widget::text::caption(status.clone()));
+        }
+        let mut sections = vec![export.into()];
+        for entry in self.config.pairs.iter().filter(|e| e.is_active()) {
+            let mut section = settings::view_section(entry.pair.to_string());
+            let state = self.pair_states.get(&entry.pair);
+            if let Some(changed_at) = state.and_then(|state| state.changed_at) {
+                section = section.add(widget::text::caption(fl!(
+                    "unchanged-for",
+                    duration = format_countdown(
+                        self.clock
+                            .now_instant()
+                            .saturating_duration_since(changed_at)
+                    )
+                )));
+            }
+            let history = state.map(|state| &state.history);
+            match history {
+                Some(history) if !history.is_empty() => {
+                    // The rows are only built while the pair is expanded, a page at a time.
+                    let pages = self.history_pages.get(&entry.pair).copied();
+                    let icon = if pages.is_some() {
+                        "go-up-symbolic"
+                    } else {
+                        "go-down-symbolic"
+                    };
+                    section = section.add(settings::item(
+                        fl!("history-quotes", count = history.len()),
+                        widget::button::icon(widget::icon::from_name(icon))
+                            .on_press(Message::ToggleHistory(entry.pair.clone())),
+                    ));
+                    let shown = pages.unwrap_or(0) * HISTORY_PAGE_LEN;
+                    for (at, bid) in history.iter().take(shown) {
+                        section = section.add(settings::item(
+                            clock_time(at),
+                            widget::text(bid.to_string()),
+                        ));
+                    }
+                    if pages.is_some() && history.len() > shown {
+                        section = section.add(
+                            widget::button::text(fl!("show-more"))
+                                .on_press(Message::ShowMoreHistory(entry.pair.clone())),
+                        );
+                    }
+                }
+                _ => section = section.add(widget::text::caption(fl!("history-empty"))),
+            }
+            sections.push(section.into());
+        }
+
+        widget::column::with_children(sections)
+            .padding(8)
+            .spacing(12)
+            .into()
+    }
+
+    /// The right-click menu of the panel button.
+    fn view_context_menu(&self) -> Element<Message> {
+        let item = |label: String, message: Message| -> Element<Message> {
+            widget::button::text(label)
+                .on_press(message)
+                .style(cosmic::theme::Button::Text)
+                .width(Length::Fill)
+                .into()
+        };
+        widget::column::with_children(vec![
+            item(fl!("refresh-all"), Message::RefreshAll),
+            item(fl!("calculator"), Message::OpenCalculator),
+            item(fl!("about"), Message::OpenAbout),
+        ])
+        .padding(4)
+        .into()
+    }
+
+    /// The quick menu: a row per active pair with its rate and change, selecting one shows
+    /// it on the panel, and a footer to refresh or open the settings.
+    fn view_quick_menu(&self) -> Element<Message> {
+        let mut rows: Vec<Element<Message>> = self
+            .quick_menu_pairs()
+            .into_iter()
+            .enumerate()
+            .map(|(row, index)| {
+                let pair = &self.config.pairs[index].pair;
+                let button = widget::button(
+                    widget::row::with_children(vec![
+                        widget::text(pair.to_string()).width(Length::Fill).into(),
+                        widget::text(self.rate_text(pair)).into(),
+                        widget::text::caption(self.change_text(pair)).into(),
+                    ])
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Message::QuickMenuSelect(index))
+                .width(Length::Fill);
+                if row == self.quick_menu_selected {
+                    button.style(cosmic::theme::Button::Suggested).into()
+                } else {
+                    button.style(cosmic::theme::Button::Text).into()
+                }
+            })
+            .collect();
+        if rows.is_empty() {
+            rows.push(widget::text(fl!("no-active-pairs")).into());
+        }
+        rows.push(widget::divider::horizontal::default().into());
+        rows.push(
+            widget::row::with_children(vec![
+                widget::button::text(fl!("refresh"))
+                    .on_press(Message::RefreshAll)
+                    .into(),
+                widget::button::text(fl!("tab-settings"))
+                    .on_press(Message::OpenSettings)
+                    .into(),
+            ])
+            .spacing(8)
+            .into(),
+        );
+        widget::column::with_children(rows)
+            .spacing(2)
+            .padding(4)
+            .into()
+    }
+
+    /// Converts amounts both ways with the pair of `converted_pair`.
+    fn view_calculator(&self) -> Element<Message> {
+        let Some(pair) = self.converted_pair() else {
+            return widget::text(fl!("no-active-pairs")).into();
+        };
+        let side = |currency: &str, value: &str, side: CalculatorSide| -> Element<Message> {
+            widget::column::with_children(vec![
+                widget::text::caption(currency.to_string()).into(),
+                TextInput::new("1", value)
+                    .on_input(move |text| Message::CalculatorInputChanged(side, text))
+                    .into(),
+            ])
+            .spacing(4)
+            .width(Length::Fill)
+            .into()
+        };
+        let sides = widget::row::with_children(vec![
+            side(
+                pair.base(),
+                &self.calculator_base_input,
+                CalculatorSide::Base,
+            ),
+            widget::text("⇄").into(),
+            side(
+                pair.quote(),
+                &self.calculator_quote_input,
+                CalculatorSide::Quote,
+            ),
+        ])
+        .spacing(8)
+        .align_items(Alignment::End);
+        let markup = self.config.markup_for(pair);
+        let mut calculator = vec![sides.into()];
+        if self.config.calculator_uses_markup && markup > 0.0 {
+            calculator.push(
+                widget::text::caption(fl!(
+                    "calculator-markup-estimate",
+                    markup = markup.to_string()
+                ))
+                .into(),
+            );
+        }
+        widget::column::with_children(calculator)
+            .spacing(8)
+            .padding(12)
+            .into()
+    }
+
+    fn view_about(&self) -> Element<Message> {
+        widget::column::with_children(vec![
+            widget::text::title4(fl!("app-name")).into(),
+            widget::text(fl!("version", version = env!("CARGO_PKG_VERSION"))).into(),
+            widget::button::link(REPOSITORY_URL.to_string())
+                .on_press(Message::OpenUrl(REPOSITORY_URL.to_string()))
+                .into(),
+        ])
+        .spacing(8)
+        .padding(12)
+        .into()
+    }
+
+    /// The footer shown below every tab: active pairs, fetches this session, network status
+    /// and the API backend.
+    fn view_footer(&self) -> Element<Message> {
+        let network = match self.network_status {
+            NetworkStatus::Unknown => fl!("network-unknown"),
+            NetworkStatus::Online => fl!("network-online"),
+            NetworkStatus::Offline => fl!("network-offline"),
+            NetworkStatus::CaptivePortal => fl!("network-captive-portal"),
+            NetworkStatus::Tls => fl!("network-tls"),
+            NetworkStatus::ClockIncorrect => fl!("network-clock-incorrect"),
+        };
+        let in_use = self.providers_in_use();
+        let now = self.clock.now_local();
+        let maintenance = in_use.iter().find_map(|&name| {
+            self.maintenance
+                .get(name)
+                .filter(|_| self.under_maintenance(name, now))
+                .map(|until| (name, until))
+        });
+        let network = match maintenance {
+            Some((name, until)) => fl!(
+                "provider-maintenance",
+                provider = name,
+                until = until.format("%H:%M").to_string()
+            ),
+            None => network,
+        };
+        widget::row::with_children(vec![
+            widget::text::caption(fl!(
+                "footer-active-pairs",
+                count = self.active_pairs_count()
+            ))
+            .into(),
+            widget::divider::vertical::default().into(),
+            widget::text::caption(fl!("footer-fetches", count = self.fetch_count)).into(),
+            widget::divider::vertical::default().into(),
+            widget::text::caption(network).into(),
+            widget::divider::vertical::default().into(),
+            widget::text::caption(in_use.join(", ")).into(),
+        ])
+        .spacing(8)
+        .padding([4, 8])
+        .height(Length::Fixed(24.0))
+        .align_items(Alignment::Center)
+        .into()
+    }
+
+    /// The debug tab: when each pair is going to be fetched next.
+    fn view_debug(&self) -> Element<Message> {
+        let now = self.clock.now_instant();
+        let mut content_list = widget::list_column().padding(5).spacing(0);
+
+        for entry in self.config.pairs.iter().filter(|e| e.is_active()) {
+            let countdown = match self.next_fetch_at.get(&entry.pair) {
+                Some(at) => fl!(
+                    "next-fetch-in",
+                    countdown = format_countdown(at.saturating_duration_since(now))
+                ),
+                None => fl!("fetch-not-scheduled"),
+            };
+            content_list = content_list.add(settings::item(
+                entry.pair.to_string(),
+                widget::text(countdown),
+            ));
+        }
+
+        if !self.test_mode {
+            return content_list.into();
+        }
+        // Around the last fetched bid rather than the simulated one, so the range stays put
+        // while dragging.
+        let simulated = self.panel_pair().and_then(|pair| {
+            let anchor = *self.simulation_anchor.get(pair)?;
+            let bid = self.exchange_rates.get(pair)?.bid;
+            // A zero anchor leaves the slider no range to move in, nor a step.
+            Some((pair, anchor, bid)).filter(|_| anchor.is_normal())
+        });
+        let mut simulate = settings::view_section(fl!("simulate"));
+        simulate = match simulated {
+            Some((pair, anchor, bid)) => simulate.add(settings::item(
+                format!("{pair} {}", self.rate_text(pair)),
+                widget::slider(
+                    anchor * 0.5..=anchor * 1.5,
+                    bid,
+                    Message::SimulateRateChange,
+                )
+                .step(anchor / 1000.0),
+            )),
+            None => simulate.add(widget::text::caption(fl!("simulate-no-quote"))),
+        };
+
+        widget::column::with_children(vec![content_list.into(), simulate.into()]).into()
+    }
+}
+
+/// Implement the `Application` trait for your application.
+/// This is where you define the behavior of your application.
+///
+/// The `Application` trait requires you to define the following types and constants:
+/// - `Executor` is the async executor that will be used to run your application's commands.
+/// - `Flags` is the data that your application needs to use before it starts.
+/// - `Message` is the enum that contains all the possible variants that your application will need to transmit messages.
+/// - `APP_ID` is the unique identifier of your application.
+impl Application for YourApp {
+    type Executor = cosmic::executor::Default;
+
+    type Flags = Flags;
+
+    type Message = Message;
+
+    const APP_ID: &'static str = "com.example.CosmicAppletTemplate";
+
+    fn core(&self) -> &Core {
+        &self.core
+    }
+
+    fn core_mut(&mut self) -> &mut Core {
+        &mut self.core
+    }
+
+    /// This is the entry point of your application, it is where you initialize your application.
+    ///
+    /// Any work that needs to be done before the application starts should be done here.
+    ///
+    /// - `core` is used to passed on for you by libcosmic to use in the core of your own application.
+    /// - `flags` is used to pass in any data that your application needs to use before it starts.
+    /// - `Command` type is used to send messages to your application. `Command::none()` can be used to send no messages to your application.
+    fn init(core: Core, flags: Self::Flags) -> (Self, Command<Self::Message>) {
+        let config_handler = match &flags.config_path {
+            Some(path) => cosmic_config::Config::with_custom_path(
+                Self::APP_ID,
+                AppletConfig::VERSION,
+                path.clone(),
+            ),
+            None => cosmic_config::Config::new(Self::APP_ID, AppletConfig::VERSION),
+        }
+        .ok();
+        let mut config = config_handler
+            .as_ref()
+            .map(|handler| {
+                AppletConfig::get_entry(handler).unwrap_or_else(|(errors, config)| {
+                    tracing::warn!("Error loading config: {:?}", errors);
+                    config
+                })
+            })
+            .unwrap_or_default();
+        if config.dedup_pairs() {
+            tracing::info!("Merged watchlist entries and alerts of pairs repeated in the config");
+        }
+        // Before the startup list or `--pairs` replace the watchlist, which this session only.
+        let parked = config.reconcile_orphans();
+        if parked > 0 {
+            tracing::info!("Parked {parked} alerts of pairs no longer in the watchlist");
+        }
+        // The startup list and the flags only replace settings for this session.
+        let mut session_overrides = SessionOverrides::default();
+        if config.startup_pairs_enabled && !config.startup_pairs.is_empty() {
+            let startup = config.startup_pairs.iter().cloned().map(PairEntry::new);
+            session_overrides.replace_pairs(&mut config, startup.collect());
+        }
+        flags.apply(&mut config, &mut session_overrides);
+
+        // With `--config-path`, the state is kept apart from the real one as well.
+        let state_handler = match &flags.config_path {
+            Some(path) => cosmic_config::Config::with_custom_path(
+                Self::APP_ID,
+                AppletState::VERSION,
+                path.join(CUSTOM_STATE_DIR),
+            ),
+            None => cosmic_config::Config::new_state(Self::APP_ID, AppletState::VERSION),
+        }
+        .ok();
+        let state = state_handler
+            .as_ref()
+            .map(|handler| {
+                AppletState::get_entry(handler).unwrap_or_else(|(errors, state)| {
+                    tracing::warn!("Error loading state: {:?}", errors);
+                    state
+                })
+            })
+            .unwrap_or_default();
+
+        let mut nav_model = segmented_button::SingleSelectModel::default();
+        for view in AppView::ALL {
+            let item = nav_model.insert().text(view.label()).data(view);
+            if view == AppView::default() {
+                item.activate();
+            }
+        }
+
+        let settings_dir = settings_dir(flags.config_path.as_deref());
+        let mut app = YourApp {
+            core,
+            clock: flags.clock(),
+            usage: Arc::new(Mutex::new(state.api_usage.clone())),
+            session_overrides,
+            persistence: Persistence::new(settings_dir.filter(|_| config_handler.is_some())),
+            state_dir: state_dir(flags.config_path.as_deref()),
+            nav_model,
+            config_handler,
+            state_handler,
+            state,
+            soft_limit_input: config.api_monthly_soft_limit.to_string(),
+            smtp_port_input: config.smtp_port.to_string(),
+            sort_labels: SortOrder::ALL.iter().map(|order| order.label()).collect(),
+            change_format_labels: ChangeFormat::ALL
+                .iter()
+                .map(|format| format.label())
+                .collect(),
+            decimal_group_labels: DecimalGroupStyle::ALL
+                .iter()
+                .map(|style| style.label())
+                .collect(),
+            animation_labels: AnimationStyle::ALL
+                .iter()
+                .map(|style| style.label())
+                .collect(),
+            export_format_labels: ExportFormat::ALL
+                .iter()
+                .map(|format| format.label())
+                .collect(),
+            max_visible_input: config.pair_list_max_visible.to_string(),
+            announced_watchlist: watchlist_of(&config),
+            markup_input: if config.markup_pct > 0.0 {
+                config.markup_pct.to_string()
+            } else {
+                String::new()
+            },
+            font_family_input: config.font_family.clone(),
+            precision_input: config
+                .precision
+                .map(|precision| precision.to_string())
+                .unwrap_or_default(),
+            anchor_labels: PopupAnchor::ALL
+                .iter()
+                .map(|anchor| anchor.label())
+                .collect(),
+            quick_menu_click_labels: QuickMenuClick::ALL
+                .iter()
+                .map(|click| click.label())
+                .collect(),
+            pair_labels: config.pairs.iter().map(|e| e.pair.to_string()).collect(),
+            condition_labels: AlertCondition::labels(),
+            change_window_labels: ChangeWindow::labels(),
+            refresh_input: config.refresh_secs.to_string(),
+            failure_digest_input: config.failure_digest_after_mins.to_string(),
+            low_power_below_input: config.low_power_below_pct.to_string(),
+            config_errors: validate_config(&config),
+            cache_ttl_input: config.cache_ttl_secs.to_string(),
+            first_panel_dwell_input: config.first_panel_dwell_secs.to_string(),
+            alert_budget_input: config.max_alert_notifications_per_hour.to_string(),
+            retry_count_input: config.api_retry_count.to_string(),
+            retry_delay_input: config.api_retry_delay_secs.to_string(),
+            unassigned_notice: parked > 0,
+            test_mode: flags.test_mode,
+            config,
+            ..Default::default()
+        };
+
+        // Fetch every pair right away and concurrently, rather than one after the other.
+        let startup_fetches = app.fetch_active(FetchTrigger::Startup);
+        let warning = app.check_configured();
+        let updated = app.check_updated();
+
+        (app, Command::batch([startup_fetches, warning, updated]))
+    }
+
+    /// Switches the popup to the view of the activated navigation item.
+    fn on_nav_select(&mut self, entity: segmented_button::Entity) -> Command<Message> {
+        if let Some(&view) = self.nav_model.data::<AppView>(entity) {
+            self.select_view(view);
+            if view == AppView::Status {
+                return self.measure_footprint();
+            }
+        }
+        Command::none()
+    }
+
+    fn on_close_requested(&self, id: Id) -> Option<Message> {
+        match self.popup_kind.get(&id) {
+            Some(PopupKind::ContextMenu) => Some(Message::ContextMenuClosed(id)),
+            _ => Some(Message::PopupClosed(id)),
+        }
+    }
+
+    /// Schedules the periodic fetches of every active pair, rotates the panel through the
+    /// active pairs when cycling is enabled and keeps the debug countdowns ticking.
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let mut refresh = self.refresh_interval();
+        if self.paused_for_focus() {
+            refresh *= UNFOCUSED_REFRESH_FACTOR;
+        }
+        // Fetching is paused while the session is locked, `SessionLocked` catches up, and
+        // for the pairs low power mode holds back until it is turned off.
+        let mut subscriptions: Vec<Subscription<Message>> = self
+            .config
+            .pairs
+            .iter()
+            .filter(|e| e.is_active() && !self.session_locked)
+            .filter(|e| !self.paused_for_low_power(&e.pair))
+            .map(|e| fetch_schedule(e.pair.clone(), refresh, self.clock.clone()))
+            .collect();
+        subscriptions.push(lock_changes());
+        if self.config.low_power_below_pct > 0 {
+            subscriptions.push(battery_changes());
+        }
+        subscriptions.push(cosmic::iced::event::listen_with(output_changes));
+        subscriptions.push(shortcut_activations());
+        subscriptions.push(dbus::interface());
+
+        // In low power mode the panel holds its pair, the only one still fetched.
+        if self.config.cycle_interval_secs > 0 && self.active_pairs_count() > 1 && !self.low_power()
+        {
+            subscriptions
+                .push(cosmic::iced::time::every(self.panel_dwell()).map(|_| Message::CyclePanel));
+        }
+
+        // Timestamps are only shown in the popup, and drawn in the current zone when it opens.
+        if self.popup.is_some() {
+            subscriptions.push(timezone_changes(self.clock.clone()));
+        }
+
+        if self.popup.is_some() && capabilities::keyboard_navigation(self.popup_capabilities) {
+            subscriptions.push(cosmic::iced::event::listen_with(tab_navigation));
+        }
+        if !self.popup_kind.is_empty() {
+            subscriptions.push(cosmic::iced::event::listen_with(window_changes));
+        }
+        if self.quick_menu.is_some() {
+            subscriptions.push(cosmic::iced::event::listen_with(quick_menu_navigation));
+        }
+
+        if self.config.visual_bell && self.alerts_unacknowledged {
+            subscriptions.push(
+                cosmic::iced::time::every(VISUAL_BELL_HALF_PERIOD).map(|_| Message::VisualBellTick),
+            );
+        }
+
+        if !self.paused_for_focus()
+            && self
+                .pair_states
+                .values()
+                .any(|state| state.animation_progress > 0.0)
+        {
+            subscriptions
+                .push(cosmic::iced::time::every(ANIMATION_FRAME).map(Message::AnimationTick));
+        }
+
+        if self.popup.is_some() && self.active_view == AppView::Debug {
+            subscriptions
+                .push(cosmic::iced::time::every(Duration::from_secs(1)).map(|_| Message::Tick));
+        }
+
+        if self.popup.is_some() && self.active_view == AppView::Status {
+            subscriptions.push(
+                cosmic::iced::time::every(FOOTPRINT_REFRESH).map(|_| Message::MeasureFootprint),
+            );
+        }
+
+        Subscription::batch(subscriptions)
+    }
+
+    /// This is the main view of your application, it is the root of your widget tree.
+    ///
+    /// The `Element` type is used to represent the visual elements of your application,
+    /// it has a `Message` associated with it, which dictates what type of message it can send.
+    ///
+    /// To get a better sense of which widgets are available, check out the `widget` module.
+    fn view(&self) -> Element<Self::Message> {
+        let mode = self.effective_display_mode();
+        if mode != DisplayMode::Icon {
+            if let Some(slots) = self.view_panel_slots() {
+                return slots;
+            }
+        }
+        let rotating = |pair: Option<&Pair>| match pair {
+            Some(pair) => (self.panel_rate_text(pair), self.panel_dims(pair)),
+            None => (String::new(), false),
+        };
+        let (label, dimmed) = match mode {
+            DisplayMode::Icon => return self.view_icon_button(),
+            // Slots that don't fit rotate, whatever the mode.
+            _ if !self.panel_slot_pairs().is_empty() => rotating(self.panel_pair()),
+            // A count badge with a dropdown arrow, the rates themselves live in the popup.
+            DisplayMode::Summary => (
+                fl!("pairs-summary", count = self.active_pairs_count()),
+                false,
+            ),
+            _ => rotating(self.panel_pair()),
+        };
+        let text = if self.unconfigured {
+            widget::text(fl!("no-pairs")).style(cosmic::theme::Text::Custom(dimmed_text))
+        } else {
+            let text = match self.panel_font() {
+                Some(font) => widget::text(label).font(font),
+                None => widget::text(label),
+            };
+            // Bundled and stale rates are dimmed, neither is the current one.
+            if dimmed {
+                text.style(cosmic::theme::Text::Custom(dimmed_text))
+            } else {
+                text
+            }
+        };
+        let button = widget::button(text);
+        self.panel_clicks(
+            button
+                .on_press(self.left_click())
+                .style(self.panel_button_style()),
+        )
+    }
+
+    fn view_window(&self, id: Id) -> Element<Self::Message> {
+        let content = match self.popup_kind.get(&id) {
+            Some(PopupKind::ContextMenu) => self.view_context_menu(),
+            Some(PopupKind::QuickMenu) => self.view_quick_menu(),
+            Some(PopupKind::Calculator) => self.view_calculator(),
+            Some(PopupKind::About) => self.view_about(),
+            Some(PopupKind::Main) | None => return self.view_main_popup(),
+        };
+        self.core.applet.popup_container(content).into()
+    }
+
+    /// Application messages are handled here. The application state can be modified based on
+    /// what message was received. Commands may be returned for asynchronous execution on a
+    /// background thread managed by the application's executor.
+    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+        let _watchdog = UpdateWatchdog::start(&message);
+        // Any action taken from a row menu closes it.
+        if matches!(
+            message,
+            Message::TogglePair(..)
+                | Message::TogglePairQuiet(_)
+                | Message::RemovePair(_)
+                | Message::SetAlert(_)
+        ) {
+            self.pair_menu = None;
+        }
+
+        match message {
+            Message::TogglePopup => {
+                return if self.popup.is_some() {
+                    Command::batch(self.close_popups())
+                } else {
+                    self.open_popup(PopupKind::Main)
+                };
+            }
+            Message::TogglePopupAt(pair) => {
+                if self.popup.is_some() {
+                    return Command::batch(self.close_popups());
+                }
+                self.select_view(AppView::Watchlist);
+                // Scrolled once the popup exists, after the command creating it ran.
+                return Command::batch([
+                    self.open_popup(PopupKind::Main),
+                    Command::perform(std::future::ready(Message::ScrollToPair(pair)), |message| {
+                        cosmic::app::Message::App(message)
+                    }),
+                ]);
+            }
+            Message::ScrollToPair(pair) => {
+                let Some(row) = self
+                    .sorted_indices()
+                    .iter()
+                    .position(|&index| self.config.pairs[index].pair == pair)
+                else {
+                    return Command::none();
+                };
+                let y = row as f32 * PAIR_ROW_HEIGHT;
+                self.pair_list_scrolled = y > 0.0;
+                return scrollable::scroll_to(PAIR_LIST_ID.clone(), AbsoluteOffset { x: 0.0, y });
+            }
+            Message::PopupClosed(id) | Message::ContextMenuClosed(id) => {
+                self.forget_popup(id);
+            }
+            Message::OpenContextMenu => {
+                return self.open_popup(PopupKind::ContextMenu);
+            }
+            Message::OpenCalculator => {
+                return self.open_calculator(None);
+            }
+            Message::OpenAbout => {
+                return self.open_popup(PopupKind::About);
+            }
+            Message::OpenUrl(url) => {
+                if let Err(e) = open::that_detached(&url) {
+                    tracing::warn!("Error opening {url}: {:?}", e);
+                }
+            }
+            // Timestamps keep the offset they were taken with, and are converted to the
+            // current zone when drawn. Receiving the message is enough to redraw them.
+            Message::TimezoneChanged => {}
+            Message::UnconfiguredStateWarning => {
+                self.unconfigured = true;
+            }
+            Message::NavSelected(entity) => {
+                return self.on_nav_select(entity);
+            }
+            // The messages of each feature are handled in its module.
+            message @ (Message::AddPair
+            | Message::TogglePair(..)
+            | Message::TogglePairQuiet(..)
+            | Message::ChangeWindowChanged(..)
+            | Message::ChangeWindowTimeChanged(..)
+            | Message::RemovePair(..)
+            | Message::UndoRemovePair
+            | Message::UndoExpired(..)
+            | Message::TogglePairMenu(..)
+            | Message::SortChanged(..)
+            | Message::ToggleSortPanelRotation(..)
+            | Message::MaxVisibleChanged(..)
+            | Message::PairListScrolled(..)
+            | Message::ScrollPairsToTop) => return self.update_watchlist(message),
+            message @ (Message::InputChanged(..)
+            | Message::PairInputFocused
+            | Message::ClearPairInput
+            | Message::PairInputBlurred
+            | Message::PasteFromClipboard
+            | Message::ClipboardRead(..)
+            | Message::ShortcutsAvailable(..)
+            | Message::OpenPairInput
+            | Message::FocusPairInput) => return self.update_pair_input(message),
+            message @ (Message::PinPanelPair(..)
+            | Message::FirstPanelDwellChanged(..)
+            | Message::PanelSlotChanged(..)
+            | Message::CyclePanel) => return self.update_panel(message),
+            message @ (Message::StartupInputChanged(..)
+            | Message::AddStartupPair
+            | Message::RemoveStartupPair(..)
+            | Message::ToggleStartupPairs(..)
+            | Message::ToggleStartupNotification(..)) => return self.update_startup(message),
+            message @ (Message::RefreshAll
+            | Message::FetchScheduled(..)
+            | Message::FetchDue(..)
+            | Message::FetchRetry(..)
+            | Message::DerivePair(..)
+            | Message::RateFetchedAt(..)
+            | Message::FallbackFetchedAt(..)
+            | Message::MaintenanceOver(..)
+            | Message::RatesFetched(..)
+            | Message::SimulateRateChange(..)
+            | Message::RefreshSecsChanged(..)
+            | Message::CacheTtlChanged(..)
+            | Message::RetryCountChanged(..)
+            | Message::RetryDelayChanged(..)) => return self.update_fetching(message),
+            message @ (Message::ToggleForwardRates(..)
+            | Message::ForwardRatesUrlChanged(..)
+            | Message::ForwardRatesFetched(..)) => return self.update_forward_rates(message),
+            message @ (Message::ToggleHistory(..)
+            | Message::ShowMoreHistory(..)
+            | Message::ExportFormatChanged(..)
+            | Message::ExportHistory
+            | Message::SaveHistory
+            | Message::HistorySaved(..)) => return self.update_history(message),
+            message @ (Message::SetAlert(..)
+            | Message::AlertPairSelected(..)
+            | Message::AlertConditionSelected(..)
+            | Message::AlertValueChanged(..)
+            | Message::AlertAmountChanged(..)
+            | Message::SaveAlert
+            | Message::RemoveAlert(..)
+            | Message::ReattachAlert(..)
+            | Message::DeleteUnassignedAlert(..)
+            | Message::ReviewUnassignedAlerts) => return self.update_alerts(message),
+            message @ (Message::AcknowledgeAlerts
+            | Message::VisualBellTick
+            | Message::ToggleVisualBell(..)
+            | Message::AlertBudgetChanged(..)
+            | Message::QueueAlert(..)
+            | Message::ShowAlert(..)
+            | Message::NotificationBudgetReset
+            | Message::FailureDigestChanged(..)
+            | Message::SmtpFieldChanged(..)
+            | Message::WebhookUrlChanged(..)
+            | Message::WebhookTestFired(..)
+            | Message::WebhookTested(..)) => return self.update_notifications(message),
+            message @ (Message::ConvertAlerted(..)
+            | Message::CalculatorInputChanged(..)
+            | Message::PairMarkupChanged(..)
+            | Message::MarkupChanged(..)
+            | Message::ToggleCalculatorMarkup(..)
+            | Message::TogglePanelMarkup(..)) => return self.update_calculator(message),
+            message @ (Message::AnimationTick(..)
+            | Message::DecimalGroupsChanged(..)
+            | Message::AnimationStyleChanged(..)
+            | Message::ToggleShowFlags(..)
+            | Message::ToggleChangeHeat(..)
+            | Message::ToggleMonospaceForRate(..)
+            | Message::FontFamilyChanged(..)
+            | Message::FontFamilySubmitted
+            | Message::PrecisionChanged(..)
+            | Message::ToggleMarketPrecision(..)
+            | Message::TogglePanelTrailingZeros(..)
+            | Message::ToggleChangeAtDisplayPrecision(..)
+            | Message::ChangeFormatChanged(..)
+            | Message::PopupAnchorChanged(..)) => return self.update_display(message),
+            message @ (Message::FocusNext
+            | Message::FocusPrev
+            | Message::ActivateFocused
+            | Message::MoveTabSection(..)
+            | Message::RemoveTabSection(..)
+            | Message::AddTabSection(..)) => return self.update_keyboard(message),
+            message @ (Message::ToggleQuickMenu
+            | Message::QuickMenuSelect(..)
+            | Message::QuickMenuMove(..)
+            | Message::QuickMenuActivate
+            | Message::OpenSettings
+            | Message::QuickMenuClickChanged(..)) => return self.update_quick_menu(message),
+            message @ (Message::OutputResized(..)
+            | Message::WindowResized(..)
+            | Message::FocusProbeExpired(..)
+            | Message::ResizeProbeExpired(..)) => return self.update_popup(message),
+            message @ (Message::WindowFocusChanged(..)
+            | Message::TogglePauseOnUnfocus(..)
+            | Message::ToggleLowPower(..)
+            | Message::LowPowerBelowChanged(..)
+            | Message::BatteryChanged(..)
+            | Message::SessionLocked(..)) => return self.update_pausing(message),
+            message @ (Message::ExternalAddPair(..)
+            | Message::ExternalRemovePair(..)
+            | Message::ExternalSetPrimary(..)
+            | Message::ListPairs(..)
+            | Message::FormattedRate(..)
+            | Message::PauseReasons(..)
+            | Message::RateSources(..)
+            | Message::ExternalRateUpdate(..)
+            | Message::ToggleExternalUpdates(..)
+            | Message::ToggleExternalWatchlist(..)) => return self.update_external(message),
+            message @ (Message::MeasureFootprint
+            | Message::FootprintMeasured(..)
+            | Message::FixConfigError(..)
+            | Message::DismissConfigErrors
+            | Message::DismissPersistenceNotice
+            | Message::OpenStatus
+            | Message::SoftLimitChanged(..)
+            | Message::FlagQuote(..)
+            | Message::DeleteFlaggedQuote(..)
+            | Message::ClearFlaggedQuotes
+            | Message::ResetApiUsage
+            | Message::CopyDiagnosticReport) => return self.update_status(message),
+            Message::Tick => {}
+        }
+        Command::none()
+    }
+
+    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
+        Some(cosmic::applet::style())
+    }
+}
+
+/// A text input for a pair code with a button adding it, built like the widgets it is made
+/// of: the input and the add button are always there, the rest is opted into.
+struct PairInput<'a> {
+    value: &'a str,
+    on_input: fn(String) -> Message,
+    on_submit: Message,
+    paste: Option<Message>,
+    clear: Option<Message>,
+    focus: Option<(Message, Message)>,
+    id: Option<widget::Id>,
+    add_focused: bool,
+}
+
+impl<'a> PairInput<'a> {
+    fn new(value: &'a str, on_input: fn(String) -> Message, on_submit: Message) -> Self {
+        PairInput {
+            value,
+            on_input,
+            on_submit,
+            paste: None,
+            clear: None,
+            focus: None,
+            id: None,
+            add_focused: false,
+        }
+    }
+
+    /// Adds a paste button, `paste` being the message reading the clipboard.
+    fn paste(mut self, paste: Message) -> Self {
+        self.paste = Some(paste);
+        self
+    }
+
+    /// Adds a button clearing the input while it isn't empty.
+    fn clear(mut self, clear: Message) -> Self {
+        self.clear = Some(clear);
+        self
+    }
+
+    fn on_focus(mut self, on_focus: Message, on_blur: Message) -> Self {
+        self.focus = Some((on_focus, on_blur));
+        self
+    }
+
+    fn id(mut self, id: widget::Id) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Highlights the add button, as keyboard navigation does for the focused section.
+    fn add_focused(mut self, focused: bool) -> Self {
+        self.add_focused = focused;
+        self
+    }
+}
+
+impl<'a> From<PairInput<'a>> for Element<'a, Message> {
+    fn from(pair_input: PairInput<'a>) -> Self {
+        let PairInput {
+            value,
+            on_input,
+            on_submit,
+            paste,
+            clear,
+            focus,
+            id,
+            add_focused,
+        } = pair_input;
+        // Shows a text input that allows the user to enter a string for the exchange rate to show.
+        // For example USDEUR for USD to EUR exchange rate
+        let mut input = TextInput::new(fl!("pair-placeholder"), value)
+            .on_input(on_input)
+            .on_submit(on_submit.clone())
+            .padding(10)
+            .size(20);
+        if let Some((on_focus, on_blur)) = focus {
+            input = input.on_focus(on_focus).on_unfocus(on_blur);
+        }
+        if let Some(id) = id {
+            input = input.id(id);
+        }
+        if let Some(clear) = clear.filter(|_| !value.is_empty()) {
+            input = input.trailing_icon(
+                widget::button::icon(widget::icon::from_name("edit-clear-symbolic"))
+                    .on_press(clear)
+                    .into(),
+            );
+        }
+        let mut row = vec![input.into()];
+        if let Some(paste) = paste {
+            row.push(
+                widget::button::icon(widget::icon::from_name("edit-paste-symbolic"))
+                    .on_press(paste)
+                    .into(),
+            );
+        }
+        let mut add =
+            widget::button::icon(widget::icon::from_name("list-add-symbolic")).on_press(on_submit);
+        if add_focused {
+            add = add.style(cosmic::theme::Button::Suggested);
+        }
+        row.push(add.into());
+        widget::row::with_children(row)
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into()
+    }
+}
+
+/// How long after `now` fetching resumes from a provider under maintenance until `until`:
+/// once it is over, and up to `MAINTENANCE_JITTER_MS` later, going by the microseconds of
+/// `now` so clients pausing at different instants spread out.
+fn maintenance_resumption(until: DateTime<Local>, now: DateTime<Local>) -> Duration {
+    let jitter = now.timestamp_subsec_micros() % MAINTENANCE_JITTER_MS;
+    (until - now).to_std().unwrap_or_default() + Duration::from_millis(u64::from(jitter))
+}
+
+/// `name` with a static lifetime, as fonts require. Each name is leaked once, and only
+/// committed names get here, not every keystroke of the font input.
+fn static_font_name(name: &str) -> &'static str {
+    static NAMES: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+    let mut names = NAMES.lock().unwrap();
+    match names.get(name) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+/// Text drawn at half the opacity of regular text.
+fn dimmed_text(theme: &Theme) -> cosmic::iced::widget::text::Appearance {
+    let mut color: cosmic::iced::Color = theme.cosmic().on_bg_color().into();
+    color.a *= 0.5;
+    cosmic::iced::widget::text::Appearance { color: Some(color) }
+}
+
+/// Where the filled part of a change heat bar lies within its `HEAT_BAR_WIDTH`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HeatSpan {
+    /// The empty width before the filled part.
+    before: f32,
+    length: f32,
+    /// Drawn in the theme's success color when rising, in its destructive color otherwise.
+    rising: bool,
+}
+
+/// The filled part of the heat bar of `heat`: from the bar's middle to the right for a
+/// rise and to the left for a fall, in proportion to `heat`. `None` without a heat or for
+/// no change.
+fn heat_span(heat: Option<f64>) -> Option<HeatSpan> {
+    let half = HEAT_BAR_WIDTH / 2.0;
+    let heat = heat.filter(|heat| *heat != 0.0)?;
+    // At least a pixel, so the smallest movers still show which way they went.
+    let length = (heat.abs() as f32 * half).clamp(1.0, half);
+    let rising = heat > 0.0;
+    Some(HeatSpan {
+        before: if rising { half } else { half - length },
+        length,
+        rising,
+    })
+}
+
+/// The change heat bar of a popup row, `HEAT_BAR_WIDTH` wide and filled as `heat_span`
+/// says. Empty without a heat or for no change.
+fn heat_bar<'a>(heat: Option<f64>) -> Element<'a, Message> {
+    let Some(span) = heat_span(heat) else {
+        return cosmic::iced::widget::Space::new(HEAT_BAR_WIDTH, 0).into();
+    };
+    let bar = widget::container(cosmic::iced::widget::Space::new(
+        span.length,
+        HEAT_BAR_HEIGHT,
+    ))
+    .style(cosmic::theme::Container::custom(move |theme| {
+        let cosmic = theme.cosmic();
+        let color = if span.rising {
+            cosmic.success_color()
+        } else {
+            cosmic.destructive_color()
+        };
+        widget::container::Appearance {
+            background: Some(cosmic::iced::Background::Color(color.into())),
+            ..Default::default()
+        }
+    }));
+    widget::row::with_children(vec![
+        cosmic::iced::widget::Space::new(span.before, 0).into(),
+        bar.into(),
+        cosmic::iced::widget::Space::new(HEAT_BAR_WIDTH - span.before - span.length, 0).into(),
+    ])
+    .width(Length::Fixed(HEAT_BAR_WIDTH))
+    .into()
+}
+
+/// Logs the message it was started for when dropped more than `UPDATE_BUDGET` later, so a
+/// blocking call that slipped into `update` shows up.
+struct UpdateWatchdog {
+    message: String,
+    started: Instant,
+}
+
+impl UpdateWatchdog {
+    fn start(message: &Message) -> Self {
+        UpdateWatchdog {
+            message: variant_name(message),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for UpdateWatchdog {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        if elapsed > UPDATE_BUDGET {
+            tracing::warn!(
+                "Handling {} took {:?}, the panel was unresponsive meanwhile",
+                self.message,
+                elapsed
+            );
+        }
+    }
+}
+
+/// The name of the variant of `message`, its fields are not formatted at all.
+fn variant_name(message: &Message) -> String {
+    /// Keeps what is written up to the first character that can't be in a name, then
+    /// stops the formatting.
+    struct Name(String);
+    impl std::fmt::Write for Name {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            let end = s.find(|c: char| !c.is_alphanumeric() && c != '_');
+            self.0.push_str(&s[..end.unwrap_or(s.len())]);
+            end.map_or(Ok(()), |_| Err(std::fmt::Error))
+        }
+    }
+    let mut name = Name(String::new());
+    let _ = std::fmt::Write::write_fmt(&mut name, format_args!("{message:?}"));
+    name.0
+}
+
+/// `Message::OutputResized` for every output added or changed; only the panel's is acted
+/// upon.
+fn output_changes(event: cosmic::iced::Event, _: cosmic::iced::event::Status) -> Option<Message> {
+    use cosmic::iced::event::wayland::{Event as WaylandEvent, OutputEvent};
+    use cosmic::iced::event::PlatformSpecific;
+    let cosmic::iced::Event::PlatformSpecific(PlatformSpecific::Wayland(WaylandEvent::Output(
+        event,
+        _,
+    ))) = event
+    else {
+        return None;
+    };
+    let info = match event {
+        OutputEvent::Created(info) => info?,
+        OutputEvent::InfoUpdate(info) => info,
+        _ => return None,
+    };
+    let (width, height) = info.logical_size?;
+    Some(Message::OutputResized(
+        info.name,
+        (width as f32, height as f32),
+    ))
+}
+
+/// `Message::WindowFocusChanged` and `Message::WindowResized` for the focus and size
+/// changes of every window; only the popups' are acted upon.
+fn window_changes(event: cosmic::iced::Event, _: cosmic::iced::event::Status) -> Option<Message> {
+    use cosmic::iced::window::Event as WindowEvent;
+    match event {
+        cosmic::iced::Event::Window(id, WindowEvent::Focused) => {
+            Some(Message::WindowFocusChanged(id, true))
+        }
+        cosmic::iced::Event::Window(id, WindowEvent::Unfocused) => {
+            Some(Message::WindowFocusChanged(id, false))
+        }
+        cosmic::iced::Event::Window(id, WindowEvent::Resized { width, height }) => {
+            Some(Message::WindowResized(id, (width as f32, height as f32)))
+        }
+        _ => None,
+    }
+}
+
+/// The messages of the popup's keyboard navigation: Tab and Shift+Tab, and Enter unless a
+/// widget such as a text input took it.
+fn tab_navigation(
+    event: cosmic::iced::Event,
+    status: cosmic::iced::event::Status,
+) -> Option<Message> {
+    use cosmic::iced::keyboard::{key::Named, Event as KeyEvent, Key};
+    let cosmic::iced::Event::Keyboard(KeyEvent::KeyPressed { key, modifiers, .. }) = event else {
+        return None;
+    };
+    match key {
+        Key::Named(Named::Tab) if modifiers.shift() => Some(Message::FocusPrev),
+        Key::Named(Named::Tab) => Some(Message::FocusNext),
+        Key::Named(Named::Enter) if status == cosmic::iced::event::Status::Ignored => {
+            Some(Message::ActivateFocused)
+        }
+        _ => None,
+    }
+}
+
+/// The messages of the quick menu's keyboard navigation: Up and Down between the rows,
+/// Enter to select one and Escape to close it.
+fn quick_menu_navigation(
+    event: cosmic::iced::Event,
+    _status: cosmic::iced::event::Status,
+) -> Option<Message> {
+    use cosmic::iced::keyboard::{key::Named, Event as KeyEvent, Key};
+    let cosmic::iced::Event::Keyboard(KeyEvent::KeyPressed { key, .. }) = event else {
+        return None;
+    };
+    match key {
+        Key::Named(Named::ArrowDown) => Some(Message::QuickMenuMove(true)),
+        Key::Named(Named::ArrowUp) => Some(Message::QuickMenuMove(false)),
+        Key::Named(Named::Enter) => Some(Message::QuickMenuActivate),
+        Key::Named(Named::Escape) => Some(Message::ToggleQuickMenu),
+        _ => None,
+    }
+}
+
+/// Shows the notification announcing an update, offering to open the releases page.
+fn notify_update(summary: String, body: String) -> Command<Message> {
+    Command::perform(
+        tokio::task::spawn_blocking(move || -> notify_rust::error::Result<()> {
+            let actions = notify_rust::get_capabilities()
+                .is_ok_and(|capabilities| capabilities.iter().any(|c| c == "actions"));
+            let mut notification = notify_rust::Notification::new();
+            notification
+                .appname(&fl!("app-name"))
+                .summary(&summary)
+                .body(&body);
+            if actions {
+                notification.action(RELEASES_ACTION, &fl!("releases-action"));
+            }
+            let handle = notification.show()?;
+            if actions {
+                handle.wait_for_action(|action| {
+                    if action == RELEASES_ACTION {
+                        let url = format!("{REPOSITORY_URL}/releases");
+                        if let Err(e) = open::that_detached(url) {
+                            tracing::warn!("Error opening the releases page: {}", e);
+                        }
+                    }
+                });
+            }
+            Ok(())
+        }),
+        |result| {
+            if let Ok(Err(e)) = result {
+                tracing::warn!("Error showing notification: {:?}", e);
+            }
+            cosmic::app::Message::None
+        },
+    )
+}
+
+/// The paragraph under the `## <version>` heading of `changes`, joined into one line.
+fn changelog_summary(changes: &str, version: &str) -> Option<String> {
+    let heading = format!("## {version}");
+    let mut lines = changes.lines().skip_while(|line| line.trim() != heading);
+    lines.next()?;
+    let paragraph: Vec<&str> = lines
+        .map(str::trim)
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    (!paragraph.is_empty()).then(|| paragraph.join(" "))
+}
+
+/// The summary, body and pair of the notification of the alerts `queued` while the
+/// session was locked: the alert itself if there was one, a summary of them all without
+/// a pair otherwise. `None` if none was.
+fn locked_alerts_notification(
+    mut queued: Vec<(Pair, String, String)>,
+) -> Option<(String, String, Option<Pair>)> {
+    match queued.len() {
+        0 => None,
+        1 => {
+            let (pair, summary, body) = queued.remove(0);
+            Some((summary, body, Some(pair)))
+        }
+        count => {
+            let body = queued
+                .into_iter()
+                .map(|(_, summary, body)| format!("{summary}: {body}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Some((fl!("alerts-while-locked", count = count), body, None))
+        }
+    }
+}
+
+/// Shows the notification of a fired alert of `pair`. Where the notification server
+/// supports actions, it offers to open the calculator on `pair` and waits for the
+/// notification to close, answering with `Message::ConvertAlerted` if that was chosen, or
+/// `Message::AcknowledgeAlerts` if the notification was clicked. Without a `pair`, as when
+/// summarizing several alerts, only clicking is offered.
+fn notify_alert(summary: String, body: String, pair: Option<Pair>) -> Command<Message> {
+    let can_convert = pair.is_some();
+    Command::perform(
+        tokio::task::spawn_blocking(move || -> notify_rust::error::Result<Option<String>> {
+            let actions = notify_rust::get_capabilities()
+                .is_ok_and(|capabilities| capabilities.iter().any(|c| c == "actions"));
+            let mut notification = notify_rust::Notification::new();
+            notification
+                .appname(&fl!("app-name"))
+                .summary(&summary)
+                .body(&body);
+            if actions {
+                // Clicking the notification itself acknowledges the alert.
+                notification.action(DEFAULT_ACTION, "");
+                if can_convert {
+                    notification.action(CONVERT_ACTION, &fl!("convert-action"));
+                }
+            }
+            let handle = notification.show()?;
+            let mut invoked = None;
+            if actions {
+                handle.wait_for_action(|action| invoked = Some(action.to_string()));
+            }
+            Ok(invoked)
+        }),
+        move |result| match result {
+            Ok(Ok(Some(action))) => match pair {
+                Some(pair) if action == CONVERT_ACTION => {
+                    cosmic::app::Message::App(Message::ConvertAlerted(pair))
+                }
+                _ if action == DEFAULT_ACTION => {
+                    cosmic::app::Message::App(Message::AcknowledgeAlerts)
+                }
+                _ => cosmic::app::Message::None,
+            },
+            Ok(Err(e)) => {
+                tracing::warn!("Error showing notification: {:?}", e);
+                cosmic::app::Message::None
+            }
+            _ => cosmic::app::Message::None,
+        },
+    )
+}
+
+/// Posts `payload` to a webhook, answering with the status code it replied with.
+async fn post_webhook(url: String, payload: Value) -> Result<reqwest::StatusCode, String> {
+    HTTP_CLIENT
+        .post(url)
+        .json(&payload)
+        .timeout(WEBHOOK_TIMEOUT)
+        .send()
+        .await
+        .map(|response| response.status())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundled::bundled_rate;
+    use crate::capabilities::Refit;
+    use crate::providers::{AWESOMEAPI, HTTP_CLIENT};
+    use crate::state::{Change, DiskStore, SettingsStore, WriteJob};
+    use chrono::NaiveTime;
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    /// An app converting with USDBRL, selling dollars at 5 and buying them at 5.5.
+    fn calculator() -> YourApp {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let mut app = YourApp::default();
+        app.exchange_rates.insert(
+            pair.clone(),
+            PairResponse {
+                bid: 5.0,
+                ask: 5.5,
+                ..PairResponse::default()
+            },
+        );
+        app.config.pairs = vec![PairEntry::new(pair)];
+        app
+    }
+
+    fn type_in(app: &mut YourApp, side: CalculatorSide, text: &str) {
+        let _ = app.update(Message::CalculatorInputChanged(side, text.to_string()));
+    }
+
+    #[test]
+    fn each_side_converts_into_the_other() {
+        let mut app = calculator();
+        type_in(&mut app, CalculatorSide::Base, "10");
+        assert_eq!(app.calculator_quote_input, "50.00");
+        type_in(&mut app, CalculatorSide::Quote, "11");
+        assert_eq!(app.calculator_base_input, "2.00");
+    }
+
+    #[test]
+    fn the_side_being_typed_in_keeps_its_text() {
+        let mut app = calculator();
+        for text in ["1", "1.", "1.5", "1.50", "1,5"] {
+            type_in(&mut app, CalculatorSide::Base, text);
+            assert_eq!(app.calculator_base_input, text);
+        }
+        assert_eq!(app.calculator_quote_input, "7.50");
+
+        // A new quote recomputes the other side only.
+        for quote in app.exchange_rates.values_mut() {
+            quote.bid = 6.0;
+        }
+        app.recompute_calculator();
+        assert_eq!(app.calculator_base_input, "1,5");
+        assert_eq!(app.calculator_quote_input, "9.00");
+    }
+
+    #[test]
+    fn switching_sides_does_not_bounce_back() {
+        let mut app = calculator();
+        type_in(&mut app, CalculatorSide::Base, "10");
+        type_in(&mut app, CalculatorSide::Quote, "55");
+        assert_eq!(app.calculator_base_input, "10.00");
+        // The quote keeps what was typed rather than what the recomputed base gives.
+        assert_eq!(app.calculator_quote_input, "55");
+        assert_eq!(app.calculator_last_edited, CalculatorSide::Quote);
+
+        type_in(&mut app, CalculatorSide::Quote, "");
+        assert_eq!(app.calculator_base_input, "");
+        type_in(&mut app, CalculatorSide::Base, "2");
+        assert_eq!(app.calculator_quote_input, "10.00");
+        assert_eq!(app.calculator_last_edited, CalculatorSide::Base);
+    }
+
+    #[test]
+    fn quiet_pairs_only_mute_movement_alerts() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let mut app = YourApp::default();
+        let mut entry = PairEntry::new(pair.clone());
+        entry.quiet = true;
+        app.config.pairs = vec![entry];
+        let rule = |condition| AlertRule {
+            pair: pair.clone(),
+            condition,
+        };
+        let moved = PairResponse {
+            bid: 5.67,
+            var_bid: 0.27,
+            pct_change: 5.0,
+            ..PairResponse::default()
+        };
+        app.config.alert_rules = vec![rule(AlertCondition::ChangePercent(1.0))];
+        assert!(app.fired_alerts(&pair, &moved).is_empty());
+        app.config.alert_rules = vec![rule(AlertCondition::Above(5.5))];
+        assert_eq!(app.fired_alerts(&pair, &moved).len(), 1);
+        app.config.pairs[0].quiet = false;
+        app.config.alert_rules = vec![rule(AlertCondition::ChangePercent(1.0))];
+        assert_eq!(app.fired_alerts(&pair, &moved).len(), 1);
+    }
+
+    /// An app watching 50 pairs, each with 200 quotes of history and 4 alert rules.
+    fn large_watchlist() -> YourApp {
+        let mut app = YourApp::default();
+        let now = Local::now();
+        for i in 0..50u8 {
+            let quote = format!(
+                "X{}{}",
+                char::from(b'A' + i / 26),
+                char::from(b'A' + i % 26)
+            );
+            let pair = Pair::new("USD", &quote).unwrap();
+            let state = app.pair_states.entry(pair.clone()).or_default();
+            for minute in 0..200 {
+                let at = now - chrono::Duration::minutes(200 - minute);
+                state.record_quote(5.0 + minute as f64 / 1000.0, at);
+            }
+            app.exchange_rates.insert(
+                pair.clone(),
+                PairResponse {
+                    bid: 5.2,
+                    ..PairResponse::default()
+                },
+            );
+            for bound in 4..8 {
+                app.config.alert_rules.push(AlertRule {
+                    pair: pair.clone(),
+                    condition: AlertCondition::Above(bound as f64),
+                });
+            }
+            app.config.pairs.push(PairEntry::new(pair));
+        }
+        app
+    }
+
+    #[test]
+    fn a_large_watchlist_builds_its_popup_quickly() {
+        let mut app = large_watchlist();
+        for view in [AppView::Watchlist, AppView::History, AppView::Alerts] {
+            app.active_view = view;
+            let started = Instant::now();
+            let _ = app.view_main_popup();
+            // Loose enough for a debug build on a busy machine.
+            let took = started.elapsed();
+            assert!(took < Duration::from_secs(2), "{view:?} took {took:?}");
+        }
+    }
+
+    #[test]
+    fn history_opens_a_page_at_a_time() {
+        let mut app = large_watchlist();
+        let pair = app.config.pairs[0].pair.clone();
+        assert_eq!(app.history_pages.get(&pair), None);
+        let _ = app.update(Message::ToggleHistory(pair.clone()));
+        assert_eq!(app.history_pages.get(&pair), Some(&1));
+        let _ = app.update(Message::ShowMoreHistory(pair.clone()));
+        assert_eq!(app.history_pages.get(&pair), Some(&2));
+        let _ = app.update(Message::ToggleHistory(pair.clone()));
+        assert_eq!(app.history_pages.get(&pair), None);
+        // Other pairs stay collapsed.
+        assert!(app.history_pages.is_empty());
+    }
+
+    fn alert(code: &str, body: &str) -> (Pair, String, String) {
+        let pair = Pair::parse(code).unwrap();
+        (pair.clone(), format!("{pair} alert"), body.to_string())
+    }
+
+    #[test]
+    fn alerts_held_while_locked_are_summarized() {
+        assert_eq!(locked_alerts_notification(Vec::new()), None);
+        let (pair, summary, body) = alert("USDBRL", "now 5.6");
+        assert_eq!(
+            locked_alerts_notification(vec![(pair.clone(), summary.clone(), body.clone())]),
+            Some((summary, body, Some(pair)))
+        );
+        let queued = vec![
+            alert("USDBRL", "now 5.6"),
+            alert("EURBRL", "now 6.1"),
+            alert("USDBRL", "now 5.7"),
+        ];
+        let (summary, body, pair) = locked_alerts_notification(queued).unwrap();
+        assert_eq!(summary, fl!("alerts-while-locked", count = 3));
+        assert_eq!(pair, None);
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(
+            lines[1].contains("EUR") && lines[1].ends_with("now 6.1"),
+            "{body}"
+        );
+    }
+
+    /// The pairs fetched since `app` was created, with why.
+    fn fetches(app: &YourApp) -> Vec<(String, FetchTrigger)> {
+        app.provider_stats
+            .values()
+            .flat_map(|stats| &stats.recent_fetches)
+            .map(|record| (record.pair.code(), record.trigger))
+            .collect()
+    }
+
+    #[test]
+    fn locking_holds_fetches_and_alerts_until_unlocked() {
+        let mut app = YourApp::default();
+        let (fresh, stale) = (
+            Pair::parse("USDBRL").unwrap(),
+            Pair::parse("EURBRL").unwrap(),
+        );
+        app.config.pairs = vec![PairEntry::new(fresh.clone()), PairEntry::new(stale.clone())];
+        app.fetched_at
+            .insert(fresh.clone(), app.clock.now_instant());
+        app.next_fetch_at
+            .insert(fresh.clone(), app.clock.now_instant());
+
+        let _ = app.update(Message::SessionLocked(true));
+        assert!(app.session_locked);
+        assert!(app.next_fetch_at.is_empty());
+        let (pair, summary, body) = alert("USDBRL", "now 5.6");
+        let _ = app.update(Message::QueueAlert(pair, summary, body));
+        let _ = app.update(Message::QueueAlert(stale.clone(), "b".into(), "c".into()));
+        assert_eq!(app.queued_alerts.len(), 2);
+        assert!(fetches(&app).is_empty());
+
+        // The same state again changes nothing.
+        let _ = app.update(Message::SessionLocked(true));
+        assert_eq!(app.queued_alerts.len(), 2);
+
+        let _ = app.update(Message::SessionLocked(false));
+        assert!(!app.session_locked);
+        assert!(app.queued_alerts.is_empty());
+        // Only the pair not fetched recently is fetched again.
+        assert_eq!(fetches(&app), vec![(stale.code(), FetchTrigger::Unlock)]);
+    }
+
+    #[test]
+    fn every_consumer_reads_the_change_of_the_pair_window() {
+        let (usd, eur) = (
+            Pair::parse("USDBRL").unwrap(),
+            Pair::parse("EURBRL").unwrap(),
+        );
+        let windows = [
+            ChangeWindow::PreviousClose,
+            ChangeWindow::LastRefresh,
+            ChangeWindow::Rolling24h,
+            ChangeWindow::SinceTime(NaiveTime::from_hms_opt(9, 0, 0).unwrap()),
+        ];
+        let now = Local::now();
+        for window in windows {
+            let mut app = YourApp::default();
+            let mut entry = PairEntry::new(usd.clone());
+            entry.change_window = window;
+            app.config.pairs = vec![entry, PairEntry::new(eur.clone())];
+            app.config.popup_sort = SortOrder::ChangeDescending;
+            let percent_rule = AlertCondition::ChangePercent(1.0);
+            app.config.alert_rules = vec![AlertRule {
+                pair: usd.clone(),
+                condition: percent_rule,
+            }];
+            app.exchange_rates.insert(
+                eur.clone(),
+                PairResponse {
+                    bid: 6.0,
+                    var_bid: 0.06,
+                    pct_change: 1.0,
+                    ..PairResponse::default()
+                },
+            );
+            // Thirty hours of quotes up to now, rising for six hours at a time and falling
+            // back.
+            for hour in 0..30 {
+                let bid = 5.0 + f64::from(hour % 7) * 0.03;
+                let quote = PairResponse {
+                    bid,
+                    var_bid: bid - 5.0,
+                    pct_change: (bid - 5.0) / 5.0 * 100.0,
+                    ..PairResponse::default()
+                };
+                let holds = |change: Option<Change>| {
+                    percent_rule.holds(&quote, change.map(|change| change.percent))
+                };
+                let before = app.change_for(&usd);
+                let incoming = app.incoming_change(&usd, &quote);
+                let fired = !app.fired_alerts(&usd, &quote).is_empty();
+                let had_quote = app.exchange_rates.contains_key(&usd);
+                assert_eq!(fired, holds(incoming) && !(had_quote && holds(before)));
+                let at = now - chrono::Duration::hours(29 - i64::from(hour));
+                let history = &mut app.pair_states.entry(usd.clone()).or_default().history;
+                history.push_front((at, bid));
+                app.exchange_rates.insert(usd.clone(), quote);
+
+                let change = app.change_for(&usd);
+                assert_eq!(change, incoming, "{window:?} at {hour}h");
+                if let Some(change) = change {
+                    let text = app.change_text(&usd);
+                    let percent = format_change_percent(change.percent, 2);
+                    assert!(text.starts_with(&percent), "{window:?}: {text}");
+                    if window != ChangeWindow::PreviousClose {
+                        assert!(text.ends_with(&window.short_label()), "{text}");
+                    }
+                    let first = app.sorted_indices()[0];
+                    assert_eq!(first == 0, change.percent.abs() >= 1.0, "{window:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_markup_works_against_the_user_both_ways() {
+        let mut app = calculator();
+        app.config.markup_pct = 10.0;
+        app.config.calculator_uses_markup = true;
+        // Selling 100 dollars at 5 / 1.1, buying them at 5.5 * 1.1.
+        type_in(&mut app, CalculatorSide::Base, "100");
+        assert_eq!(app.calculator_quote_input, "454.55");
+        type_in(&mut app, CalculatorSide::Quote, "605");
+        assert_eq!(app.calculator_base_input, "100.00");
+        // The effective rate line shows the rate the calculator buys at.
+        let pair = app.config.pairs[0].pair.clone();
+        let line = app.effective_rate_text(&pair).unwrap();
+        assert!(line.replace(',', ".").starts_with("6.05"), "{line}");
+    }
+
+    #[test]
+    fn the_calculator_converts_at_the_quote_unless_opted_in() {
+        let mut app = calculator();
+        app.config.markup_pct = 10.0;
+        type_in(&mut app, CalculatorSide::Base, "100");
+        assert_eq!(app.calculator_quote_input, "500.00");
+        let pair = app.config.pairs[0].pair.clone();
+        assert!(app.effective_rate_text(&pair).is_some());
+    }
+
+    #[test]
+    fn no_markup_is_no_effective_rate() {
+        let mut app = calculator();
+        app.config.calculator_uses_markup = true;
+        let pair = app.config.pairs[0].pair.clone();
+        for markup in [0.0, -5.0] {
+            app.config.markup_pct = markup;
+            type_in(&mut app, CalculatorSide::Base, "100");
+            assert_eq!(app.calculator_quote_input, "500.00");
+            assert_eq!(app.effective_rate_text(&pair), None);
+        }
+        // A pair's own markup of zero replaces the global one.
+        app.config.markup_pct = 10.0;
+        app.config.pairs[0].markup_pct = Some(0.0);
+        type_in(&mut app, CalculatorSide::Base, "100");
+        assert_eq!(app.calculator_quote_input, "500.00");
+        assert_eq!(app.effective_rate_text(&pair), None);
+    }
+
+    #[test]
+    fn a_batch_is_taken_in_by_one_update_with_the_alerts_of_each_pair() {
+        let pairs: Vec<Pair> = ["USDBRL", "EURBRL", "GBPBRL"]
+            .iter()
+            .map(|code| Pair::parse(code).unwrap())
+            .collect();
+        let quote = |bid| PairResponse {
+            bid,
+            ..PairResponse::default()
+        };
+        let mut app = YourApp::default();
+        app.config.pairs = pairs.iter().cloned().map(PairEntry::new).collect();
+        app.config.alert_rules = pairs
+            .iter()
+            .map(|pair| AlertRule {
+                pair: pair.clone(),
+                condition: AlertCondition::Above(5.5),
+            })
+            .collect();
+        for pair in &pairs {
+            app.exchange_rates.insert(pair.clone(), quote(5.0));
+        }
+        // The first two cross their alert with this batch, the third doesn't.
+        let bids = [5.6, 6.2, 5.4];
+        let firing: Vec<usize> = pairs
+            .iter()
+            .zip(bids)
+            .map(|(pair, bid)| app.fired_alerts(pair, &quote(bid)).len())
+            .collect();
+        assert_eq!(firing, [1, 1, 0]);
+
+        let fetched_at = Instant::now();
+        let results = pairs
+            .iter()
+            .zip(bids)
+            .map(|(pair, bid)| (pair.clone(), fetched_at, Ok(quote(bid))))
+            .collect();
+        let fetches = app.fetch_count;
+        let _ = app.update(Message::RatesFetched(results));
+        assert_eq!(app.fetch_count, fetches + 3);
+        for (pair, bid) in pairs.iter().zip(bids) {
+            assert_eq!(app.exchange_rates[pair].bid, bid);
+            assert_eq!(app.pair_states[pair].history.len(), 1);
+            // Each transition was taken in once: the same quote again fires nothing.
+            assert!(app.fired_alerts(pair, &quote(bid)).is_empty());
+        }
+    }
+
+    /// An app with the visual bell on and an alert nobody has seen yet.
+    fn alerted() -> YourApp {
+        let mut app = YourApp::default();
+        app.config.visual_bell = true;
+        app.alerts_unacknowledged = true;
+        app
+    }
+
+    #[test]
+    fn the_bell_pulses_until_acknowledged() {
+        let mut app = alerted();
+        let lit: Vec<bool> = (0..4)
+            .map(|_| {
+                let _ = app.update(Message::VisualBellTick);
+                app.bell_lit
+            })
+            .collect();
+        assert_eq!(lit, [true, false, true, false]);
+        let _ = app.update(Message::VisualBellTick);
+        let _ = app.update(Message::AcknowledgeAlerts);
+        assert!(!app.bell_lit);
+        let _ = app.update(Message::VisualBellTick);
+        assert!(!app.bell_lit);
+    }
+
+    #[test]
+    fn turning_the_bell_off_puts_it_out() {
+        let mut app = alerted();
+        let _ = app.update(Message::VisualBellTick);
+        let _ = app.update(Message::ToggleVisualBell(false));
+        assert!(!app.bell_lit);
+        // The alert is still unseen, only not pulsed.
+        assert!(app.alerts_unacknowledged);
+    }
+
+    #[tokio::test]
+    async fn every_way_of_acknowledging_puts_the_bell_out_at_once() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let ways = [
+            // The D-Bus method, and clicking the notification.
+            ("acknowledge", Message::AcknowledgeAlerts),
+            ("convert action", Message::ConvertAlerted(pair)),
+            ("popup", Message::TogglePopup),
+        ];
+        for (way, message) in ways {
+            let mut app = alerted();
+            let _ = app.update(Message::VisualBellTick);
+            assert!(app.bell_lit);
+            let _ = app.update(message);
+            assert!(!app.bell_lit, "{way}");
+            assert!(!app.alerts_unacknowledged, "{way}");
+        }
+    }
+
+    #[test]
+    fn expired_certificates_blame_the_clock_only_when_it_looks_wrong() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let status = |min_year, validity| {
+            let mut app = YourApp::default();
+            app.config.clock_min_year = min_year;
+            let error = RateError::Tls {
+                message: "invalid peer certificate: expired".to_string(),
+                validity,
+            };
+            let results = vec![(pair.clone(), Instant::now(), Err(error))];
+            let _ = app.update(Message::RatesFetched(results));
+            app.network_status
+        };
+        // Any clock reads a year before 9999, and none one before 1970.
+        assert_eq!(status(9999, true), NetworkStatus::ClockIncorrect);
+        assert_eq!(status(1970, true), NetworkStatus::Tls);
+        assert_eq!(status(9999, false), NetworkStatus::Tls);
+    }
+
+    #[test]
+    fn a_pair_failing_through_its_retries_reads_as_failing_until_the_next_refresh() {
+        let mut app = YourApp::default();
+        app.config.api_retry_count = 1;
+        let entry = app.config.pairs[0].clone();
+        let pair = entry.pair.clone();
+        let fetched = |result| Message::RatesFetched(vec![(pair.clone(), Instant::now(), result)]);
+        let failed = || fetched(Err(RateError::Parse("no quote".to_string())));
+        let breaker = |app: &YourApp| app.pair_pause(&entry) == Some(PauseReason::CircuitBreaker);
+
+        let _ = app.update(failed());
+        assert!(!breaker(&app), "a retry is coming");
+        let _ = app.update(Message::FetchRetry(pair.clone(), 1));
+        let _ = app.update(failed());
+        assert!(breaker(&app));
+        let _ = app.update(Message::FetchDue(pair.clone()));
+        assert!(!breaker(&app), "the refresh tries again");
+        let _ = app.update(failed());
+        let _ = app.update(fetched(Ok(PairResponse {
+            bid: 5.4,
+            ..PairResponse::default()
+        })));
+        assert!(!breaker(&app));
+
+        // Offline takes over when it is why the fetches fail.
+        app.config.api_retry_count = 0;
+        let _ = app.update(fetched(Err(RateError::Network("unreachable".to_string()))));
+        assert!(app.retries_exhausted(&pair));
+        assert_eq!(app.pair_pause(&entry), Some(PauseReason::Offline));
+    }
+
+    #[test]
+    fn the_open_popup_follows_the_precision_and_keeps_its_place() {
+        let mut app = calculator();
+        let pair = app.config.pairs[0].pair.clone();
+        app.config.market_precision = false;
+        app.popup = Some(Id::unique());
+        app.select_view(AppView::Converter);
+        app.pair_list_scrolled = true;
+        app.history_pages.insert(pair.clone(), 2);
+        type_in(&mut app, CalculatorSide::Base, "100");
+        assert_eq!(app.calculator_quote_input, "500.00");
+        let rate = app.rate_text(&pair);
+        let popup = app.popup;
+
+        let _ = app.update(Message::PrecisionChanged("4".to_string()));
+        assert_eq!(app.calculator_quote_input, "500.0000");
+        assert_eq!(app.calculator_base_input, "100");
+        assert_ne!(app.rate_text(&pair), rate);
+        assert!(
+            app.rate_text(&pair).ends_with("0000"),
+            "{}",
+            app.rate_text(&pair)
+        );
+        // Nothing the user was looking at moved.
+        assert_eq!(app.popup, popup);
+        assert_eq!(app.active_view, AppView::Converter);
+        assert!(app.pair_list_scrolled);
+        assert_eq!(app.history_pages.get(&pair), Some(&2));
+    }
+
+    #[test]
+    fn the_panel_trims_the_rate_and_not_its_label() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let mut app = YourApp::default();
+        app.config.pairs = vec![PairEntry::new(pair.clone())];
+        app.config.market_precision = false;
+        app.config.precision = Some(6);
+        let rate = |app: &YourApp, trailing_zeros| {
+            format_rate(
+                bundled_rate(&pair).unwrap().bid,
+                Some(6),
+                app.config.decimal_groups,
+                trailing_zeros,
+            )
+        };
+        // Only the bundled rate to show, labelled with words containing an 'e'.
+        for trailing_zeros in [false, true] {
+            app.config.panel_trailing_zeros = trailing_zeros;
+            assert_eq!(
+                app.panel_rate_text(&pair),
+                fl!("bundled-rate", rate = rate(&app, trailing_zeros))
+            );
+        }
+        assert_ne!(rate(&app, false), rate(&app, true));
+
+        app.exchange_rates.insert(
+            pair.clone(),
+            PairResponse {
+                bid: 5.4,
+                ..PairResponse::default()
+            },
+        );
+        app.config.panel_trailing_zeros = false;
+        assert_eq!(app.panel_rate_text(&pair).replace(',', "."), "5.4");
+        // The popup writes every decimal whatever the panel does.
+        assert_eq!(app.rate_text(&pair).replace(',', "."), "5.400000");
+        app.config.panel_trailing_zeros = true;
+        assert_eq!(app.panel_rate_text(&pair).replace(',', "."), "5.400000");
+    }
+
+    #[test]
+    fn fetching_resumes_within_the_jitter_after_maintenance() {
+        let now = Local.with_ymd_and_hms(2026, 6, 10, 12, 0, 0).unwrap();
+        let until = now + chrono::Duration::hours(1);
+        let window = Duration::from_secs(3600);
+        let jitter = Duration::from_millis(u64::from(MAINTENANCE_JITTER_MS));
+        assert_eq!(maintenance_resumption(until, now), window);
+        let mut waits = Vec::new();
+        for micros in [1, 12_345, 999_999] {
+            let now = now + chrono::Duration::microseconds(micros);
+            let wait = maintenance_resumption(until, now) + Duration::from_micros(micros as u64);
+            assert!(wait >= window && wait < window + jitter, "{wait:?}");
+            waits.push(wait);
+        }
+        // Clients pausing at different instants come back at different ones.
+        waits.dedup();
+        assert_eq!(waits.len(), 3);
+        // A window already over resumes at once, but for the jitter.
+        assert!(maintenance_resumption(now, until) < jitter);
+    }
+
+    #[tokio::test]
+    async fn maintenance_pauses_the_provider_until_it_is_over() {
+        let mut app = YourApp::default();
+        let until = Local::now() + chrono::Duration::hours(1);
+        let _ = app.pause_for_maintenance(AWESOMEAPI, until);
+        assert!(app.under_maintenance(AWESOMEAPI, Local::now()));
+        // Woken up before the end, as when a later announcement pushed it back, it goes on.
+        let _ = app.update(Message::MaintenanceOver(AWESOMEAPI.to_string()));
+        assert_eq!(app.maintenance.get(AWESOMEAPI), Some(&until));
+        // Once over, it is forgotten.
+        let until = Local::now() - chrono::Duration::seconds(1);
+        app.maintenance.insert(AWESOMEAPI.to_string(), until);
+        let _ = app.update(Message::MaintenanceOver(AWESOMEAPI.to_string()));
+        assert!(app.maintenance.is_empty());
+    }
+
+    #[test]
+    fn the_response_travels_with_the_fetch_into_the_pair_state() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let mut app = YourApp::default();
+        app.config.pairs = vec![PairEntry::new(pair.clone())];
+        let fetched = |bid, raw: &str| {
+            let quote = PairResponse {
+                bid,
+                raw_excerpt: Some(raw.to_string()),
+                ..PairResponse::default()
+            };
+            Message::RatesFetched(vec![(pair.clone(), Instant::now(), Ok(quote))])
+        };
+        let _ = app.update(fetched(61.0, r#"{"USDBRL": {"bid": "61.0"}}"#));
+        assert_eq!(
+            app.pair_states[&pair].raw_response.as_deref(),
+            Some(r#"{"USDBRL": {"bid": "61.0"}}"#)
+        );
+        // Held by the pair only, the stored quotes go without it.
+        assert_eq!(app.exchange_rates[&pair].raw_excerpt, None);
+        assert_eq!(app.held_counts().raw_responses, 1);
+
+        let _ = app.update(Message::FlagQuote(0));
+        let _ = app.update(fetched(5.4, r#"{"USDBRL": {"bid": "5.4"}}"#));
+        assert_eq!(
+            app.pair_states[&pair].raw_response.as_deref(),
+            Some(r#"{"USDBRL": {"bid": "5.4"}}"#)
+        );
+        let flagged = &app.state.flagged_quotes[0];
+        assert_eq!(flagged.quote.as_ref().map(|quote| quote.bid), Some(61.0));
+        assert_eq!(
+            flagged.response_excerpt.as_deref(),
+            Some(r#"{"USDBRL": {"bid": "61.0"}}"#)
+        );
+    }
+
+    #[test]
+    fn flagged_quotes_are_deleted_one_by_one_or_all_at_once() {
+        let mut app = calculator();
+        for _ in 0..3 {
+            let _ = app.update(Message::FlagQuote(0));
+        }
+        let rest = app.state.flagged_quotes[1..].to_vec();
+        let _ = app.update(Message::DeleteFlaggedQuote(0));
+        assert_eq!(app.state.flagged_quotes, rest);
+        let _ = app.update(Message::DeleteFlaggedQuote(5));
+        assert_eq!(app.state.flagged_quotes, rest);
+        let _ = app.update(Message::ClearFlaggedQuotes);
+        assert!(app.state.flagged_quotes.is_empty());
+    }
+
+    /// How long every write to a `SlowDisk` takes.
+    const SLOW_WRITE: Duration = Duration::from_millis(200);
+
+    /// The real writer thread in front of a disk that takes `SLOW_WRITE` for every write,
+    /// counting the writes that reached it.
+    #[derive(Debug)]
+    struct SlowDisk {
+        disk: DiskStore,
+        written: Arc<AtomicUsize>,
+    }
+
+    impl SettingsStore for SlowDisk {
+        fn writable(&self) -> bool {
+            self.disk.writable()
+        }
+
+        fn write(&self, what: &'static str, job: WriteJob) {
+            let written = self.written.clone();
+            self.disk.write(
+                what,
+                Box::new(move || {
+                    std::thread::sleep(SLOW_WRITE);
+                    written.fetch_add(1, AtomicOrdering::Relaxed);
+                    job()
+                }),
+            );
+        }
+
+        fn take_failure(&self) -> bool {
+            self.disk.take_failure()
+        }
+
+        fn has_failed(&self) -> bool {
+            self.disk.has_failed()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_slow_disk_and_a_stalled_provider_dont_hold_up_messages() {
+        let dir = std::env::temp_dir().join(format!(
+            "exchange-rate-app-{}-saturated",
+            std::process::id()
+        ));
+        let written = Arc::new(AtomicUsize::new(0));
+        let disk = SlowDisk {
+            disk: DiskStore::new(Some(dir.clone())),
+            written: written.clone(),
+        };
+        let mut app = calculator();
+        app.config_handler = cosmic_config::Config::with_custom_path(
+            YourApp::APP_ID,
+            AppletConfig::VERSION,
+            dir.clone(),
+        )
+        .ok();
+        app.persistence = Persistence::with_store(Some(dir.clone()), Arc::new(disk));
+        assert!(app.config_handler.is_some() && !app.persistence.is_memory_only());
+
+        // A provider that takes requests in and never answers them.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/json/last/USD-BRL",
+            listener.local_addr().unwrap()
+        );
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                held.push(stream);
+            }
+        });
+        let stalled = tokio::spawn(HTTP_CLIENT.get(url).send());
+
+        let pair = app.config.pairs[0].pair.clone();
+        let mut slowest = Duration::ZERO;
+        for round in 0..20 {
+            let quote = PairResponse {
+                bid: 5.0 + f64::from(round) / 100.0,
+                ..PairResponse::default()
+            };
+            for message in [
+                Message::PrecisionChanged((2 + round % 4).to_string()),
+                Message::RatesFetched(vec![(pair.clone(), Instant::now(), Ok(quote))]),
+            ] {
+                let started = Instant::now();
+                let _ = app.update(message);
+                slowest = slowest.max(started.elapsed());
+            }
+        }
+        assert!(slowest < UPDATE_BUDGET, "a message took {slowest:?}");
+        assert!(!stalled.is_finished());
+
+        // The twenty saves queued behind the first write were merged into one.
+        tokio::time::sleep(SLOW_WRITE * 4).await;
+        let written = written.load(AtomicOrdering::Relaxed);
+        assert!((1..=2).contains(&written), "{written} writes");
+        assert!(!app.persistence.is_memory_only());
+        stalled.abort();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn the_watchdog_names_the_message_and_not_its_fields() {
+        assert_eq!(
+            variant_name(&Message::PrecisionChanged("secret".to_string())),
+            "PrecisionChanged"
+        );
+        assert_eq!(variant_name(&Message::OpenStatus), "OpenStatus");
+    }
+
+    #[test]
+    fn the_probes_follow_what_the_open_popup_does() {
+        let mut app = YourApp::default();
+        let (id, closed) = (Id::unique(), Id::unique());
+        app.popup = Some(id);
+        app.popup_kind.insert(id, PopupKind::Main);
+
+        // Only the open popup's probes count.
+        let _ = app.update(Message::FocusProbeExpired(closed));
+        assert_eq!(app.popup_capabilities.focus, Probe::Untested);
+        let _ = app.update(Message::FocusProbeExpired(id));
+        assert_eq!(app.popup_capabilities.focus, Probe::Failed);
+        assert!(!capabilities::keyboard_navigation(app.popup_capabilities));
+        let _ = app.update(Message::WindowFocusChanged(id, true));
+        assert_eq!(app.popup_capabilities.focus, Probe::Works);
+        assert!(capabilities::focus_tracking(app.popup_capabilities));
+
+        app.resize_probe = Some(id);
+        let _ = app.update(Message::WindowResized(id, (300.0, 400.0)));
+        assert_eq!(app.popup_capabilities.resize, Probe::Works);
+        assert_eq!(app.resize_probe, None);
+        // A probe already answered doesn't expire.
+        let _ = app.update(Message::ResizeProbeExpired(id));
+        assert_eq!(app.popup_capabilities.resize, Probe::Works);
+    }
+
+    #[test]
+    fn a_popup_never_resized_is_reopened_from_then_on() {
+        let mut app = YourApp::default();
+        let id = Id::unique();
+        app.popup = Some(id);
+        app.popup_kind.insert(id, PopupKind::Main);
+        app.resize_probe = Some(id);
+        let _ = app.update(Message::ResizeProbeExpired(id));
+        assert_eq!(app.popup_capabilities.resize, Probe::Failed);
+        assert_eq!(app.resize_probe, None);
+        assert_eq!(capabilities::refit(app.popup_capabilities), Refit::Reopen);
+    }
+
+    #[test]
+    fn bundled_rates_read_as_approximate_until_the_first_fetch() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let mut app = YourApp::default();
+        app.config.pairs = vec![PairEntry::new(pair.clone())];
+        let bundled = bundled_rate(&pair).unwrap();
+        assert_eq!(
+            app.rate_text(&pair),
+            fl!(
+                "bundled-rate",
+                rate = app.format_rate(&pair, bundled.bid, true)
+            )
+        );
+        app.config.panel_trailing_zeros = true;
+        assert_eq!(app.panel_rate_text(&pair), app.rate_text(&pair));
+
+        let quote = PairResponse {
+            bid: 5.1,
+            ..PairResponse::default()
+        };
+        let _ = app.update(Message::RatesFetched(vec![(
+            pair.clone(),
+            Instant::now(),
+            Ok(quote),
+        )]));
+        assert!(!app.shows_bundled(&pair));
+        assert_eq!(app.rate_text(&pair), app.format_rate(&pair, 5.1, true));
+    }
+
+    #[test]
+    fn a_failing_provider_doesnt_hold_back_the_pairs_of_the_other() {
+        // EURBRL comes from the test provider that is never reached.
+        let [usdbrl, eurbrl] = ["USDBRL", "EURBRL"].map(|code| Pair::parse(code).unwrap());
+        let mut app = YourApp::default();
+        app.config.pairs = vec![
+            PairEntry::new(usdbrl.clone()),
+            PairEntry::new(eurbrl.clone()),
+        ];
+        app.config.pairs[1].provider = Some("Unreachable".to_string());
+        app.config.api_retry_count = 0;
+        app.config.fallback_provider = AWESOMEAPI.to_string();
+        app.config.fallback_after_secs = 0;
+        let groups: Vec<(&str, Vec<Pair>)> = app
+            .pairs_by_provider()
+            .into_iter()
+            .map(|(provider, pairs)| (provider.name(), pairs))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (AWESOMEAPI, vec![usdbrl.clone()]),
+                ("Unreachable", vec![eurbrl.clone()])
+            ]
+        );
+
+        for bid in [5.1, 5.2] {
+            let quote = PairResponse {
+                bid,
+                ..PairResponse::default()
+            };
+            let _ = app.update(Message::RatesFetched(vec![
+                (usdbrl.clone(), Instant::now(), Ok(quote)),
+                (
+                    eurbrl.clone(),
+                    Instant::now(),
+                    Err(RateError::Parse("no quote".to_string())),
+                ),
+            ]));
+        }
+        assert_eq!(app.exchange_rates[&usdbrl].bid, 5.2);
+        assert!(!app.exchange_rates.contains_key(&eurbrl));
+        assert!(app.pair_states[&usdbrl].failing_since.is_none());
+        assert!(app.pair_states[&eurbrl].failing_since.is_some());
+        // The failing pair alone gives up and moves to the fallback.
+        let [usdbrl_entry, eurbrl_entry] = [0, 1].map(|i| app.config.pairs[i].clone());
+        assert_eq!(
+            app.pair_pause(&eurbrl_entry),
+            Some(PauseReason::CircuitBreaker)
+        );
+        assert_ne!(
+            app.pair_pause(&usdbrl_entry),
+            Some(PauseReason::CircuitBreaker)
+        );
+        assert!(app.uses_fallback(&eurbrl, Instant::now()));
+        assert!(!app.uses_fallback(&usdbrl, Instant::now()));
+
+        // Nor does its maintenance, which only pauses its own pairs.
+        let until = app.clock.now_local() + chrono::Duration::hours(1);
+        app.maintenance.insert("Unreachable".to_string(), until);
+        assert_eq!(
+            app.pair_pause(&eurbrl_entry),
+            Some(PauseReason::Maintenance)
+        );
+        assert_ne!(
+            app.pair_pause(&usdbrl_entry),
+            Some(PauseReason::Maintenance)
+        );
+        assert_eq!(app.global_pause(), None);
+        let _ = app.fetch_active(FetchTrigger::Manual);
+        let fetched: Vec<&Pair> = app.provider_stats[AWESOMEAPI]
+            .recent_fetches
+            .iter()
+            .map(|record| &record.pair)
+            .collect();
+        assert_eq!(fetched, [&usdbrl]);
+        assert!(!app.provider_stats.contains_key("Unreachable"));
+    }
+
+    #[test]
+    fn heat_bars_grow_from_the_middle_towards_the_change() {
+        let half = HEAT_BAR_WIDTH / 2.0;
+        assert_eq!(
+            heat_span(Some(1.0)),
+            Some(HeatSpan {
+                before: half,
+                length: half,
+                rising: true
+            })
+        );
+        assert_eq!(
+            heat_span(Some(-0.5)),
+            Some(HeatSpan {
+                before: half / 2.0,
+                length: half / 2.0,
+                rising: false
+            })
+        );
+        // The smallest movers still show a pixel, which way they went.
+        assert_eq!(heat_span(Some(1e-9)).map(|span| span.length), Some(1.0));
+        assert_eq!(
+            heat_span(Some(-1e-9)).map(|span| span.before + span.length),
+            Some(half)
+        );
+        assert_eq!(heat_span(Some(0.0)), None);
+        assert_eq!(heat_span(None), None);
+    }
+
+    /// An app of USDBRL and EURUSD with an alert of the removed GBPUSD parked.
+    fn with_parked_alert() -> YourApp {
+        let mut app = YourApp::default();
+        app.config.pairs = ["USDBRL", "EURUSD"]
+            .iter()
+            .map(|code| PairEntry::new(Pair::parse(code).unwrap()))
+            .collect();
+        app.config.alert_rules = vec![AlertRule {
+            pair: Pair::parse("GBPUSD").unwrap(),
+            condition: AlertCondition::Above(1.3),
+        }];
+        app.config.reconcile_orphans();
+        app
+    }
+
+    #[test]
+    fn a_parked_alert_is_reattached_to_a_watched_pair() {
+        let mut app = with_parked_alert();
+        let _ = app.update(Message::ReattachAlert(0, 1));
+        assert!(app.config.unassigned_alerts.is_empty());
+        assert_eq!(
+            app.config.alert_rules,
+            [AlertRule {
+                pair: Pair::parse("EURUSD").unwrap(),
+                condition: AlertCondition::Above(1.3),
+            }]
+        );
+        // Reattached a second time to the same pair, the rule isn't added twice.
+        app.config.unassigned_alerts = app.config.alert_rules.clone();
+        let _ = app.update(Message::ReattachAlert(0, 1));
+        assert_eq!(app.config.alert_rules.len(), 1);
+        assert!(app.config.unassigned_alerts.is_empty());
+    }
+
+    #[test]
+    fn a_parked_alert_is_deleted_or_left_alone_for_indices_out_of_range() {
+        let mut app = with_parked_alert();
+        let parked = app.config.unassigned_alerts.clone();
+        let _ = app.update(Message::ReattachAlert(0, 2));
+        let _ = app.update(Message::ReattachAlert(1, 0));
+        let _ = app.update(Message::DeleteUnassignedAlert(1));
+        assert_eq!(app.config.unassigned_alerts, parked);
+        assert!(app.config.alert_rules.is_empty());
+        let _ = app.update(Message::DeleteUnassignedAlert(0));
+        assert!(app.config.unassigned_alerts.is_empty());
+        assert!(app.config.alert_rules.is_empty());
+    }
+
+    #[test]
+    fn the_popup_points_out_parked_alerts_until_they_are_reviewed() {
+        let mut app = with_parked_alert();
+        app.unassigned_notice = true;
+        let _ = app.update(Message::ReviewUnassignedAlerts);
+        assert!(!app.unassigned_notice);
+        assert_eq!(app.active_view, AppView::Alerts);
+    }
 }
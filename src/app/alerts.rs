@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The alerts tab: editing alert rules and the rules parked without a pair.
+
+use cosmic::app::Command;
+
+use super::Message;
+use crate::config::{rule_conflicts, AlertCondition, AlertRule};
+use crate::state::{AppView, YourApp};
+
+impl YourApp {
+    /// Handles the messages that edit alert rules.
+    pub(super) fn update_alerts(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::SetAlert(index) => {
+                // `pair_labels` follows the watchlist order, so the indices match.
+                self.alert_pair_index = Some(index);
+                self.select_view(AppView::Alerts);
+            }
+            Message::AlertPairSelected(index) => {
+                self.alert_pair_index = Some(index);
+            }
+            Message::AlertConditionSelected(index) => {
+                self.alert_condition_index = Some(index);
+            }
+            Message::AlertValueChanged(value) => {
+                self.alert_value_input = value;
+            }
+            Message::AlertAmountChanged(value) => {
+                self.alert_amount_input = value;
+            }
+            Message::SaveAlert => {
+                let pair = self
+                    .alert_pair_index
+                    .and_then(|index| self.config.pairs.get(index))
+                    .map(|e| e.pair.clone());
+                let condition = self.edited_alert_condition();
+                if let (Some(pair), Some(condition)) = (pair, condition) {
+                    let others: Vec<AlertCondition> = self
+                        .config
+                        .alert_rules
+                        .iter()
+                        .filter(|rule| rule.pair == pair)
+                        .map(|rule| rule.condition)
+                        .collect();
+                    self.alert_warnings = rule_conflicts(condition, &others);
+                    self.config.alert_rules.push(AlertRule { pair, condition });
+                    self.alert_value_input.clear();
+                    self.alert_amount_input.clear();
+                    self.save_config();
+                }
+            }
+            Message::RemoveAlert(index) => {
+                if index < self.config.alert_rules.len() {
+                    self.config.alert_rules.remove(index);
+                    self.alert_warnings.clear();
+                    self.save_config();
+                }
+            }
+            Message::ReattachAlert(index, pair_index) => {
+                let Some(pair) = self.config.pairs.get(pair_index).map(|e| e.pair.clone()) else {
+                    return Command::none();
+                };
+                if index < self.config.unassigned_alerts.len() {
+                    let mut rule = self.config.unassigned_alerts.remove(index);
+                    rule.pair = pair;
+                    // The pair may already have the same rule, it isn't added twice.
+                    if !self.config.alert_rules.contains(&rule) {
+                        self.config.alert_rules.push(rule);
+                    }
+                    self.save_config();
+                }
+            }
+            Message::DeleteUnassignedAlert(index) => {
+                if index < self.config.unassigned_alerts.len() {
+                    self.config.unassigned_alerts.remove(index);
+                    self.save_config();
+                }
+            }
+            Message::ReviewUnassignedAlerts => {
+                self.unassigned_notice = false;
+                self.select_view(AppView::Alerts);
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The calculator and the markup shown over rates.
+
+use cosmic::app::Command;
+
+use super::Message;
+use crate::format::parse_amount;
+use crate::state::{CalculatorSide, YourApp};
+
+impl YourApp {
+    /// Handles the messages of the calculator and of the markup settings.
+    pub(super) fn update_calculator(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::ConvertAlerted(pair) => {
+                self.acknowledge_alerts();
+                return self.open_calculator(Some(pair));
+            }
+            Message::CalculatorInputChanged(side, value) => {
+                match side {
+                    CalculatorSide::Base => self.calculator_base_input = value,
+                    CalculatorSide::Quote => self.calculator_quote_input = value,
+                }
+                self.calculator_last_edited = side;
+                self.recompute_calculator();
+            }
+            Message::PairMarkupChanged(index, text) => {
+                // Cleared, the pair follows the global markup again.
+                let markup = match text.trim() {
+                    "" => Some(None),
+                    value => parse_amount(value).filter(|m| *m >= 0.0).map(Some),
+                };
+                if let (Some(entry), Some(markup)) = (self.config.pairs.get_mut(index), markup) {
+                    entry.markup_pct = markup;
+                    self.save_config();
+                    self.recompute_calculator();
+                }
+                self.pair_markup_input = text;
+            }
+            Message::MarkupChanged(text) => {
+                let markup = match text.trim() {
+                    "" => Some(0.0),
+                    value => parse_amount(value).filter(|m| *m >= 0.0),
+                };
+                if let Some(markup) = markup {
+                    self.config.markup_pct = markup;
+                    self.save_config();
+                    self.recompute_calculator();
+                }
+                self.markup_input = text;
+            }
+            Message::ToggleCalculatorMarkup(enabled) => {
+                self.config.calculator_uses_markup = enabled;
+                self.save_config();
+                self.recompute_calculator();
+            }
+            Message::TogglePanelMarkup(enabled) => {
+                self.config.panel_uses_markup = enabled;
+                self.save_config();
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! How rates and their changes are drawn.
+
+use cosmic::app::Command;
+
+use super::{Message, ANIMATION_FRAME};
+use crate::config::{AnimationStyle, ChangeFormat, DecimalGroupStyle, PopupAnchor, MAX_PRECISION};
+use crate::state::YourApp;
+
+impl YourApp {
+    /// Handles the messages of the display settings.
+    pub(super) fn update_display(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::AnimationTick(now) => {
+                let elapsed = self
+                    .animation_ticked_at
+                    .map_or(ANIMATION_FRAME, |then| now.saturating_duration_since(then));
+                let mut playing = false;
+                for state in self.pair_states.values_mut() {
+                    playing |= state.advance_animation(elapsed);
+                }
+                self.animation_ticked_at = playing.then_some(now);
+            }
+            Message::DecimalGroupsChanged(index) => {
+                if let Some(&style) = DecimalGroupStyle::ALL.get(index) {
+                    self.config.decimal_groups = style;
+                    self.save_config();
+                }
+            }
+            Message::AnimationStyleChanged(index) => {
+                if let Some(&style) = AnimationStyle::ALL.get(index) {
+                    self.config.rate_change_animation = style;
+                    self.save_config();
+                }
+            }
+            Message::ToggleShowFlags(enabled) => {
+                self.config.show_flags = enabled;
+                self.save_config();
+            }
+            Message::ToggleChangeHeat(enabled) => {
+                self.config.show_change_heat = enabled;
+                self.save_config();
+            }
+            Message::ToggleMonospaceForRate(enabled) => {
+                self.config.use_monospace_for_rate = enabled;
+                self.save_config();
+            }
+            Message::FontFamilyChanged(value) => {
+                self.font_family_input = value;
+            }
+            Message::FontFamilySubmitted => {
+                self.config.font_family = self.font_family_input.trim().to_string();
+                self.save_config();
+            }
+            Message::PrecisionChanged(value) => {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    self.config.precision = None;
+                    self.save_config();
+                } else if let Ok(precision) = trimmed.parse::<usize>() {
+                    self.config.precision = Some(precision.min(MAX_PRECISION));
+                    self.save_config();
+                }
+                self.precision_input = value;
+            }
+            Message::ToggleMarketPrecision(enabled) => {
+                self.config.market_precision = enabled;
+                self.save_config();
+            }
+            Message::TogglePanelTrailingZeros(enabled) => {
+                self.config.panel_trailing_zeros = enabled;
+                self.save_config();
+            }
+            Message::ToggleChangeAtDisplayPrecision(enabled) => {
+                self.config.change_at_display_precision = enabled;
+                self.save_config();
+            }
+            Message::ChangeFormatChanged(index) => {
+                if let Some(&format) = ChangeFormat::ALL.get(index) {
+                    self.config.change_format = format;
+                    self.save_config();
+                }
+            }
+            Message::PopupAnchorChanged(index) => {
+                if let Some(&anchor) = PopupAnchor::ALL.get(index) {
+                    self.config.popup_anchor = anchor;
+                    self.save_config();
+                }
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
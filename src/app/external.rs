@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Requests from other programs, over D-Bus.
+
+use cosmic::app::Command;
+use cosmic::Application;
+
+use super::Message;
+use crate::dbus::Refused;
+use crate::fl;
+use crate::providers::PairResponse;
+use crate::state::{PauseReason, YourApp};
+
+impl YourApp {
+    /// Handles the requests of other programs and the settings allowing them.
+    pub(super) fn update_external(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::ExternalAddPair(_, reply)
+            | Message::ExternalRemovePair(_, reply)
+            | Message::ExternalSetPrimary(_, reply)
+                if !self.config.allow_external_watchlist =>
+            {
+                reply.send(Err(Refused::NotAllowed(fl!("external-watchlist-disabled"))));
+            }
+            Message::ExternalAddPair(pair, reply) => {
+                let name = pair.to_string();
+                match self.add_pair(pair, None) {
+                    Some(added) => {
+                        reply.send(Ok(()));
+                        return added;
+                    }
+                    None => reply.send(Err(Refused::Invalid(fl!(
+                        "pair-already-watched",
+                        pair = name
+                    )))),
+                }
+            }
+            Message::ExternalRemovePair(pair, reply) => match self.pair_index(&pair) {
+                Some(index) => {
+                    reply.send(Ok(()));
+                    return self.update(Message::RemovePair(index));
+                }
+                None => reply.send(Err(Refused::Invalid(fl!(
+                    "pair-not-watched",
+                    pair = pair.to_string()
+                )))),
+            },
+            Message::ExternalSetPrimary(pair, reply) => match self.pair_index(&pair) {
+                Some(index) => {
+                    reply.send(Ok(()));
+                    return self.update(Message::PinPanelPair(Some(index)));
+                }
+                None => reply.send(Err(Refused::Invalid(fl!(
+                    "pair-not-watched",
+                    pair = pair.to_string()
+                )))),
+            },
+            Message::ListPairs(reply) => {
+                let pairs = self
+                    .config
+                    .pairs
+                    .iter()
+                    .map(|entry| {
+                        let state = match self.pair_pause(entry) {
+                            _ if !entry.enabled => "disabled",
+                            Some(reason) => reason.id(),
+                            None => "active",
+                        };
+                        let rate = self
+                            .exchange_rates
+                            .get(&entry.pair)
+                            .map_or(f64::NAN, |quote| quote.bid);
+                        (String::from(entry.pair.clone()), state.to_string(), rate)
+                    })
+                    .collect();
+                reply.send(pairs);
+            }
+            Message::FormattedRate(pair, reply) => {
+                if self.pair_index(&pair).is_none() {
+                    reply.send(Err(fl!("pair-not-watched", pair = pair.to_string())));
+                    return Command::none();
+                }
+                let quote = self.exchange_rates.get(&pair);
+                reply.send(Ok((
+                    self.panel_rate_text(&pair),
+                    quote.map_or(f64::NAN, |quote| quote.bid),
+                    quote.map_or(0, |quote| quote.timestamp),
+                )));
+            }
+            Message::PauseReasons(reply) => {
+                let pairs = self
+                    .config
+                    .pairs
+                    .iter()
+                    .filter(|entry| entry.enabled)
+                    .filter_map(|entry| {
+                        let reason = self.pair_pause(entry)?;
+                        Some((String::from(entry.pair.clone()), reason.id().to_string()))
+                    })
+                    .collect();
+                let global = self.global_pause().map_or("", PauseReason::id);
+                reply.send((global.to_string(), pairs));
+            }
+            Message::RateSources(reply) => reply.send(self.rate_sources()),
+            Message::ExternalRateUpdate(pair, bid, reply) => {
+                if !self.config.allow_external_updates {
+                    reply.send(Err(Refused::NotAllowed(fl!("external-updates-disabled"))));
+                    return Command::none();
+                }
+                reply.send(Ok(()));
+                let quote = match self.exchange_rates.get(&pair) {
+                    Some(quote) => quote.moved_to(bid),
+                    None => PairResponse {
+                        bid,
+                        timestamp: self.clock.now_utc().timestamp(),
+                        ..Default::default()
+                    },
+                };
+                let at = self.clock.now_instant();
+                self.fetched_at.insert(pair.clone(), at);
+                return self.apply_quote(pair, quote, at);
+            }
+            Message::ToggleExternalUpdates(enabled) => {
+                self.config.allow_external_updates = enabled;
+                self.save_config();
+            }
+            Message::ToggleExternalWatchlist(enabled) => {
+                self.config.allow_external_watchlist = enabled;
+                self.save_config();
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
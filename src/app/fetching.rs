@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Fetching quotes: scheduled, retried, served by a fallback or simulated.
+
+use cosmic::app::Command;
+use std::time::{Duration, Instant};
+
+use super::{Message, DEFAULT_ACTION};
+use crate::config::{Pair, MAX_API_RETRY_COUNT, MIN_API_RETRY_DELAY_SECS};
+use crate::fl;
+use crate::format::format_countdown;
+use crate::providers::{clock_implausible, provider, PairResponse, RateError};
+use crate::scheduler::FetchTrigger;
+use crate::state::{EpisodeEvent, NetworkStatus, YourApp};
+
+/// Shows a low-urgency notification about fetching, answering with `on_click` if it is
+/// clicked.
+fn notify_status(summary: String, body: String, on_click: Option<Message>) -> Command<Message> {
+    let clickable = on_click.is_some();
+    Command::perform(
+        tokio::task::spawn_blocking(move || -> notify_rust::error::Result<bool> {
+            let actions = clickable
+                && notify_rust::get_capabilities()
+                    .is_ok_and(|capabilities| capabilities.iter().any(|c| c == "actions"));
+            let mut notification = notify_rust::Notification::new();
+            notification
+                .appname(&fl!("app-name"))
+                .summary(&summary)
+                .body(&body)
+                .urgency(notify_rust::Urgency::Low);
+            if actions {
+                notification.action(DEFAULT_ACTION, "");
+            }
+            let handle = notification.show()?;
+            let mut clicked = false;
+            if actions {
+                handle.wait_for_action(|action| clicked = action == DEFAULT_ACTION);
+            }
+            Ok(clicked)
+        }),
+        move |result| match (result, on_click) {
+            (Ok(Ok(true)), Some(message)) => cosmic::app::Message::App(message),
+            (Ok(Err(e)), _) => {
+                tracing::warn!("Error showing notification: {:?}", e);
+                cosmic::app::Message::None
+            }
+            _ => cosmic::app::Message::None,
+        },
+    )
+}
+
+impl YourApp {
+    /// Handles the messages of the fetch pipeline and its settings.
+    pub(super) fn update_fetching(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::RefreshAll => {
+                let fetches = self.fetch_active(FetchTrigger::Manual);
+                let close = self.close_popups();
+                return Command::batch(close.into_iter().chain([fetches]));
+            }
+            Message::FetchScheduled(pair, at) => {
+                self.next_fetch_at.insert(pair, at);
+            }
+            Message::FetchDue(pair) => {
+                self.retry_attempts.remove(&pair);
+                return self.fetch(pair, FetchTrigger::Interval);
+            }
+            Message::FetchRetry(pair, attempt) => {
+                // The pair may have been removed or disabled while waiting, or the session
+                // locked, after which the unlock fetches it if needed.
+                if !self.session_locked
+                    && self
+                        .config
+                        .pairs
+                        .iter()
+                        .any(|e| e.pair == pair && e.is_active())
+                {
+                    self.retry_attempts.insert(pair.clone(), attempt);
+                    return self.fetch(pair, FetchTrigger::Retry);
+                }
+            }
+            Message::DerivePair(pair) => return self.derive(pair),
+            Message::RateFetchedAt(pair, fetched_at, result) => {
+                self.save_api_usage();
+                let taken = self.take_fetch_result(pair.clone(), fetched_at, result);
+                return Command::batch([taken, self.derive_dependents(&[pair])]);
+            }
+            Message::FallbackFetchedAt(pair, fetched_at, result) => {
+                self.save_api_usage();
+                let failing = self
+                    .pair_states
+                    .get(&pair)
+                    .is_some_and(|state| state.failing_since.is_some());
+                match result {
+                    // Dropped if the primary provider answered again in the meantime.
+                    Ok(mut quote) if failing => {
+                        self.fetched_at.insert(pair.clone(), fetched_at);
+                        let state = self.pair_states.entry(pair.clone()).or_default();
+                        state.quoted_by = Some(provider(&self.config.fallback_provider).name());
+                        state.raw_response = quote.raw_excerpt.take();
+                        // Rates update again, if only from the fallback.
+                        let recovered = self.track_failures(true, fetched_at);
+                        let applied = self.apply_quote(pair.clone(), quote, fetched_at);
+                        return Command::batch([
+                            recovered,
+                            applied,
+                            self.derive_dependents(&[pair]),
+                        ]);
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Error fetching exchange rate from fallback: {:?}", e),
+                }
+            }
+            Message::MaintenanceOver(provider) => {
+                // A later announcement may have pushed the end of the window back.
+                if !self.under_maintenance(&provider, self.clock.now_local()) {
+                    self.maintenance.remove(&provider);
+                    return self.fetch_active(FetchTrigger::Resumed);
+                }
+            }
+            Message::RatesFetched(results) => {
+                // Every pair is taken in within this one update, each with its own alerts,
+                // so the view is only rebuilt once for the whole batch.
+                self.save_api_usage();
+                let pairs: Vec<Pair> = results.iter().map(|(pair, _, _)| pair.clone()).collect();
+                let mut commands: Vec<Command<Message>> = results
+                    .into_iter()
+                    .map(|(pair, fetched_at, result)| {
+                        self.take_fetch_result(pair, fetched_at, result)
+                    })
+                    .collect();
+                commands.push(self.derive_dependents(&pairs));
+                return Command::batch(commands);
+            }
+            Message::SimulateRateChange(bid) => {
+                let Some(pair) = self.panel_pair().cloned() else {
+                    return Command::none();
+                };
+                let Some(quote) = self.exchange_rates.get(&pair) else {
+                    return Command::none();
+                };
+                let quote = quote.moved_to(bid);
+                let applied = self.apply_quote(pair.clone(), quote, self.clock.now_instant());
+                return Command::batch([applied, self.derive_dependents(&[pair])]);
+            }
+            Message::RefreshSecsChanged(value) => {
+                if let Ok(secs) = value.trim().parse::<u64>() {
+                    if secs > 0 {
+                        self.config.refresh_secs = secs;
+                        self.save_config();
+                    }
+                }
+                self.refresh_input = value;
+            }
+            Message::CacheTtlChanged(value) => {
+                if let Ok(secs) = value.trim().parse::<u64>() {
+                    self.config.cache_ttl_secs = secs;
+                    self.save_config();
+                }
+                self.cache_ttl_input = value;
+            }
+            Message::RetryCountChanged(value) => {
+                if let Ok(count) = value.trim().parse::<u32>() {
+                    self.config.api_retry_count = count.min(MAX_API_RETRY_COUNT);
+                    self.save_config();
+                }
+                self.retry_count_input = value;
+            }
+            Message::RetryDelayChanged(value) => {
+                if let Ok(secs) = value.trim().parse::<u64>() {
+                    self.config.api_retry_delay_secs = secs.max(MIN_API_RETRY_DELAY_SECS);
+                    self.save_config();
+                }
+                self.retry_delay_input = value;
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+
+    /// Takes in the outcome of fetching `pair` at `fetched_at`: stores a quote, firing its
+    /// alerts, or schedules a retry of a failed fetch.
+    fn take_fetch_result(
+        &mut self,
+        pair: Pair,
+        fetched_at: Instant,
+        result: Result<PairResponse, RateError>,
+    ) -> Command<Message> {
+        self.fetched_at.insert(pair.clone(), fetched_at);
+        self.fetch_count += 1;
+        self.network_status = match &result {
+            Err(RateError::Network(_)) => NetworkStatus::Offline,
+            Err(RateError::CaptivePortal) => NetworkStatus::CaptivePortal,
+            Err(RateError::Tls { validity: true, .. })
+                if clock_implausible(self.clock.now_local(), self.config.clock_min_year) =>
+            {
+                NetworkStatus::ClockIncorrect
+            }
+            Err(RateError::Tls { .. }) => NetworkStatus::Tls,
+            _ => NetworkStatus::Online,
+        };
+        let digest = match &result {
+            Err(RateError::Maintenance { .. }) => Command::none(),
+            result => self.track_failures(result.is_ok(), fetched_at),
+        };
+        let taken = match result {
+            // Announced, so neither a failure of the pair nor a reason to retry it.
+            Err(RateError::Maintenance { until }) => {
+                let provider = provider(self.config.provider_of(&pair)).name();
+                self.pause_for_maintenance(provider, until)
+            }
+            Ok(mut response) => {
+                self.retry_attempts.remove(&pair);
+                let state = self.pair_states.entry(pair.clone()).or_default();
+                state.last_error = None;
+                state.failing_since = None;
+                state.quoted_by = Some(provider(self.config.provider_of(&pair)).name());
+                state.raw_response = response.raw_excerpt.take();
+                let expires_at = fetched_at + self.config.cache_ttl();
+                self.rate_cache
+                    .insert(pair.clone(), response.clone(), expires_at);
+                self.simulation_anchor.insert(pair.clone(), response.bid);
+                // Forwards are fetched along with the quote their carry is measured from, so
+                // the two never come from different refreshes.
+                let forward = self.fetch_forward(pair.clone());
+                Command::batch([self.apply_quote(pair, response, fetched_at), forward])
+            }
+            Err(e) => {
+                tracing::warn!("Error fetching exchange rate: {:?}", e);
+                let state = self.pair_states.entry(pair.clone()).or_default();
+                state.last_error = Some(e.to_string());
+                state.failing_since.get_or_insert(fetched_at);
+                let attempt = self.retry_attempts.get(&pair).copied().unwrap_or(0) + 1;
+                if attempt > self.config.api_retry_count {
+                    return digest;
+                }
+                let delay = Duration::from_secs(self.config.api_retry_delay_secs);
+                Command::perform(tokio::time::sleep(delay), move |_| {
+                    cosmic::app::Message::App(Message::FetchRetry(pair, attempt))
+                })
+            }
+        };
+        Command::batch([digest, taken])
+    }
+
+    /// Moves the `failure_episode` on with the outcome of a fetch at `at`, notifying once
+    /// every fetch has been failing for `failure_digest_after_mins`, and once they succeed
+    /// again after that.
+    fn track_failures(&mut self, ok: bool, at: Instant) -> Command<Message> {
+        let threshold = Duration::from_secs(self.config.failure_digest_after_mins * 60);
+        match self.failure_episode.step(ok, at, threshold) {
+            Some(EpisodeEvent::Digest(failing)) => notify_status(
+                fl!(
+                    "failure-digest-summary",
+                    duration = format_countdown(failing)
+                ),
+                fl!("failure-digest-body"),
+                Some(Message::OpenStatus),
+            ),
+            Some(EpisodeEvent::Recovered) => {
+                notify_status(fl!("rates-recovered"), String::new(), None)
+            }
+            None => Command::none(),
+        }
+    }
+
+    /// Derives again the derived pairs referring to one of `pairs`, once each, after they
+    /// got new quotes. Called once the whole of a batch is taken in, so a derived pair is
+    /// never evaluated over half updated quotes.
+    fn derive_dependents(&mut self, pairs: &[Pair]) -> Command<Message> {
+        let commands: Vec<Command<Message>> = self
+            .dependents_of(pairs)
+            .into_iter()
+            .map(|pair| self.derive(pair))
+            .collect();
+        Command::batch(commands)
+    }
+}
@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Forward rates, fetched from their own endpoint.
+
+use cosmic::app::Command;
+
+use super::Message;
+use crate::config::Pair;
+use crate::state::YourApp;
+
+impl YourApp {
+    /// Handles the messages of the forward rates.
+    pub(super) fn update_forward_rates(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::ToggleForwardRates(enabled) => {
+                self.config.show_forward_rates = enabled;
+                self.save_config();
+                let pairs: Vec<Pair> = self
+                    .config
+                    .pairs
+                    .iter()
+                    .filter(|entry| entry.is_active())
+                    .map(|entry| entry.pair.clone())
+                    .collect();
+                return Command::batch(pairs.into_iter().map(|pair| self.fetch_forward(pair)));
+            }
+            Message::ForwardRatesUrlChanged(url) => {
+                self.config.forward_rates_url = url.trim().to_string();
+                self.save_config();
+            }
+            Message::ForwardRatesFetched(pair, result) => match result {
+                Ok(rates) => {
+                    self.pair_states.entry(pair).or_default().forward_rates = Some(rates);
+                }
+                Err(e) => tracing::warn!("Error fetching forward rates of {}: {}", pair, e),
+            },
+            _ => {}
+        }
+        Command::none()
+    }
+}
@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The quote history of each pair and its export.
+
+use cosmic::app::Command;
+
+use super::Message;
+use crate::config::ExportFormat;
+use crate::export::export_history;
+use crate::fl;
+use crate::portal::{display_path, save_text, Saved};
+use crate::state::YourApp;
+
+impl YourApp {
+    /// Handles the messages that page through or export the quote history.
+    pub(super) fn update_history(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::ToggleHistory(pair) => {
+                if self.history_pages.remove(&pair).is_none() {
+                    self.history_pages.insert(pair, 1);
+                }
+            }
+            Message::ShowMoreHistory(pair) => {
+                *self.history_pages.entry(pair).or_default() += 1;
+            }
+            Message::ExportFormatChanged(index) => {
+                if let Some(&format) = ExportFormat::ALL.get(index) {
+                    self.config.history_export_format = format;
+                    self.save_config();
+                }
+            }
+            Message::ExportHistory => {
+                let entries = self.history_entries();
+                self.export_status = Some(fl!("history-exported", count = entries.len()));
+                let text = export_history(&entries, self.config.history_export_format);
+                return cosmic::iced::clipboard::write(text);
+            }
+            Message::SaveHistory => {
+                let format = self.config.history_export_format;
+                let text = export_history(&self.history_entries(), format);
+                let name = format!(
+                    "exchange-rates-{}.{}",
+                    self.clock.now_local().format("%Y-%m-%d"),
+                    format.extension()
+                );
+                return Command::perform(
+                    save_text(fl!("save-history-title"), name, text),
+                    |result| cosmic::app::Message::App(Message::HistorySaved(result)),
+                );
+            }
+            Message::HistorySaved(result) => {
+                self.export_status = match result {
+                    Ok(Saved::Chosen(path)) => {
+                        Some(fl!("history-saved", path = display_path(&path)))
+                    }
+                    Ok(Saved::Fallback(path)) => {
+                        Some(fl!("history-saved-fallback", path = display_path(&path)))
+                    }
+                    Ok(Saved::Cancelled) => None,
+                    Err(e) => {
+                        tracing::warn!("Error saving the history: {}", e);
+                        Some(fl!("history-save-failed", error = e))
+                    }
+                };
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Keyboard navigation of the popup and the order of its sections.
+
+use cosmic::app::Command;
+use cosmic::iced::widget::scrollable::{self, RelativeOffset};
+use cosmic::widget;
+
+use super::{Message, PAIR_INPUT_ID, PAIR_LIST_ID};
+use crate::config::AppletSection;
+use crate::state::{AppView, YourApp};
+
+impl YourApp {
+    /// Handles the messages of keyboard navigation.
+    pub(super) fn update_keyboard(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::FocusNext => return self.focus_section(true),
+            Message::FocusPrev => return self.focus_section(false),
+            Message::ActivateFocused => {
+                let message = match self.focused_section {
+                    Some(AppletSection::AddButton) => Message::AddPair,
+                    Some(AppletSection::RefreshButton) => Message::RefreshAll,
+                    _ => return Command::none(),
+                };
+                return Command::perform(std::future::ready(message), cosmic::app::Message::App);
+            }
+            Message::MoveTabSection(index, up) => {
+                let order = &mut self.config.tab_key_order;
+                let other = if up {
+                    index.checked_sub(1)
+                } else {
+                    Some(index + 1)
+                };
+                if let Some(other) = other.filter(|&other| other < order.len()) {
+                    order.swap(index, other);
+                    self.save_config();
+                }
+            }
+            Message::RemoveTabSection(index) => {
+                if index < self.config.tab_key_order.len() {
+                    self.config.tab_key_order.remove(index);
+                    self.save_config();
+                }
+            }
+            Message::AddTabSection(section) => {
+                if !self.config.tab_key_order.contains(&section) {
+                    self.config.tab_key_order.push(section);
+                    self.save_config();
+                }
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+
+    /// Moves to the section after the current one in `AppletConfig::tab_key_order`, or
+    /// before it unless `forward`, wrapping around at either end.
+    fn focus_section(&mut self, forward: bool) -> Command<Message> {
+        let order = &self.config.tab_key_order;
+        if order.is_empty() {
+            return Command::none();
+        }
+        let current = self
+            .focused_section
+            .and_then(|section| order.iter().position(|&s| s == section));
+        let next = match current {
+            Some(index) if forward => (index + 1) % order.len(),
+            Some(index) => (index + order.len() - 1) % order.len(),
+            None if forward => 0,
+            None => order.len() - 1,
+        };
+        let section = order[next];
+        self.focused_section = Some(section);
+        if section == AppletSection::SettingsPanel {
+            self.select_view(AppView::Settings);
+            return Command::none();
+        }
+        self.select_view(AppView::Watchlist);
+        match section {
+            AppletSection::PairList => {
+                scrollable::snap_to(PAIR_LIST_ID.clone(), RelativeOffset::START)
+            }
+            AppletSection::PairInput => widget::text_input::focus(PAIR_INPUT_ID.clone()),
+            // Buttons can't hold the focus, they are highlighted and pressed with Enter.
+            _ => Command::none(),
+        }
+    }
+}
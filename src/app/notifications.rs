@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! How fired alerts reach the user: notifications, the visual bell, email and webhooks.
+
+use cosmic::app::Command;
+
+use super::{notify_alert, post_webhook, Message, SmtpField};
+use crate::fl;
+use crate::state::YourApp;
+
+impl YourApp {
+    /// Handles the messages that deliver fired alerts.
+    pub(super) fn update_notifications(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::AcknowledgeAlerts => self.acknowledge_alerts(),
+            Message::VisualBellTick => {
+                self.bell_lit = !self.bell_lit && self.alerts_unacknowledged;
+            }
+            Message::ToggleVisualBell(enabled) => {
+                self.config.visual_bell = enabled;
+                if !enabled {
+                    self.bell_lit = false;
+                }
+                self.save_config();
+            }
+            Message::AlertBudgetChanged(value) => {
+                if let Ok(limit) = value.trim().parse::<u32>() {
+                    self.config.max_alert_notifications_per_hour = limit;
+                    self.save_config();
+                }
+                self.alert_budget_input = value;
+            }
+            Message::QueueAlert(pair, summary, body) => {
+                self.queued_alerts.push((pair, summary, body));
+            }
+            Message::ShowAlert(pair, summary, body) => {
+                return self.notify_within_budget(summary, body, Some(pair));
+            }
+            Message::NotificationBudgetReset => {
+                let limit = self.config.max_alert_notifications_per_hour;
+                match self
+                    .notification_budget
+                    .reset(self.clock.now_instant(), limit)
+                {
+                    Some(count) => {
+                        return notify_alert(
+                            fl!("alerts-suppressed-summary", count = count),
+                            fl!("alerts-suppressed-body"),
+                            None,
+                        );
+                    }
+                    // Woken up early, as by another alert taking the room meanwhile.
+                    None if self.notification_budget.suppressed > 0 => {
+                        return self.schedule_budget_reset();
+                    }
+                    None => {}
+                }
+            }
+            Message::FailureDigestChanged(value) => {
+                if let Ok(mins) = value.trim().parse::<u64>() {
+                    self.config.failure_digest_after_mins = mins;
+                    self.save_config();
+                }
+                self.failure_digest_input = value;
+            }
+            Message::SmtpFieldChanged(field, value) => {
+                let trimmed = value.trim().to_string();
+                match field {
+                    SmtpField::AlertEmail => {
+                        self.config.alert_email = (!trimmed.is_empty()).then_some(trimmed)
+                    }
+                    SmtpField::Server => self.config.smtp_server = trimmed,
+                    SmtpField::Port => {
+                        if let Ok(port) = trimmed.parse() {
+                            self.config.smtp_port = port;
+                        }
+                        self.smtp_port_input = value;
+                    }
+                    SmtpField::From => self.config.smtp_from = trimmed,
+                    SmtpField::CredentialKey => self.config.smtp_credential_key = trimmed,
+                }
+                self.save_config();
+            }
+            Message::WebhookUrlChanged(index, url) => {
+                if let Some(entry) = self.config.pairs.get_mut(index) {
+                    let url = url.trim();
+                    entry.webhook_url = (!url.is_empty()).then(|| url.to_owned());
+                    let pair = entry.pair.clone();
+                    // A result for another URL would be misleading.
+                    if let Some(state) = self.pair_states.get_mut(&pair) {
+                        state.webhook_last_test = None;
+                    }
+                    self.save_config();
+                }
+            }
+            Message::WebhookTestFired(index) => {
+                if let Some(entry) = self.config.pairs.get(index) {
+                    if let Some(url) = entry.webhook_url.clone() {
+                        let pair = entry.pair.clone();
+                        let payload = serde_json::json!({
+                            "test": true,
+                            "pair": pair.code(),
+                            "rate": 0.0,
+                        });
+                        return Command::perform(post_webhook(url, payload), move |result| {
+                            cosmic::app::Message::App(Message::WebhookTested(pair, result))
+                        });
+                    }
+                }
+            }
+            Message::WebhookTested(pair, result) => {
+                self.pair_states.entry(pair).or_default().webhook_last_test = Some(result);
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
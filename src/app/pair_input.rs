@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The pair input: typing, pasting and clearing a pair code.
+
+use cosmic::app::Command;
+use cosmic::{widget, Application};
+
+use super::{Message, PAIR_INPUT_ID};
+use crate::config::Pair;
+use crate::fl;
+use crate::state::{AppView, PopupKind, YourApp};
+
+impl YourApp {
+    /// Handles the messages of the pair input.
+    pub(super) fn update_pair_input(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::InputChanged(new_value) => {
+                self.input_value = new_value.to_uppercase();
+                self.paste_error = None;
+                self.cadence_note = None;
+            }
+            Message::PairInputFocused => {
+                // Typing replaces the code right away, blurring without adding puts it back.
+                if self.input_before_focus.is_none() {
+                    self.input_before_focus = Some(std::mem::take(&mut self.input_value));
+                }
+            }
+            Message::ClearPairInput => {
+                // Cleared on purpose, so blurring the input keeps it empty.
+                self.input_value.clear();
+                self.input_before_focus = None;
+                self.paste_error = None;
+                return widget::text_input::focus(PAIR_INPUT_ID.clone());
+            }
+            Message::PairInputBlurred => {
+                if let Some(previous) = self.input_before_focus.take() {
+                    self.input_value = previous;
+                }
+            }
+            Message::PasteFromClipboard => {
+                return cosmic::iced::clipboard::read(|contents| {
+                    let result = contents.ok_or_else(|| fl!("clipboard-unavailable"));
+                    cosmic::app::Message::App(Message::ClipboardRead(result))
+                });
+            }
+            Message::ClipboardRead(result) => {
+                let code = result.map(|contents| contents.trim().to_uppercase());
+                match code {
+                    Ok(code) if Pair::parse(&code).is_some() => {
+                        self.paste_error = None;
+                        self.input_before_focus = None;
+                        self.input_value = code;
+                        return self.update(Message::AddPair);
+                    }
+                    Ok(_) => self.paste_error = Some(fl!("clipboard-invalid-pair")),
+                    Err(e) => self.paste_error = Some(e),
+                }
+            }
+            Message::ShortcutsAvailable(available) => {
+                self.shortcuts_available = available;
+            }
+            Message::OpenPairInput => {
+                self.select_view(AppView::Watchlist);
+                // The focus is sent as a message of its own, so it reaches the input once
+                // a popup that was closed has been created.
+                let focus =
+                    Command::perform(std::future::ready(Message::FocusPairInput), |message| {
+                        cosmic::app::Message::App(message)
+                    });
+                if self.popup.is_some() {
+                    return focus;
+                }
+                return Command::batch([self.open_popup(PopupKind::Main), focus]);
+            }
+            Message::FocusPairInput => {
+                return widget::text_input::focus(PAIR_INPUT_ID.clone());
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
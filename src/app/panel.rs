@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Which pairs the panel shows and for how long.
+
+use cosmic::app::Command;
+
+use super::Message;
+use crate::config::MAX_PANEL_SLOTS;
+use crate::state::YourApp;
+
+impl YourApp {
+    /// Handles the messages that choose what the panel shows.
+    pub(super) fn update_panel(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::PinPanelPair(index) => {
+                self.config.first_panel_pair = index;
+                // The rotation restarts from the pinned pair.
+                self.panel_index = 0;
+                self.save_config();
+            }
+            Message::FirstPanelDwellChanged(value) => {
+                if let Ok(secs) = value.trim().parse::<u64>() {
+                    self.config.first_panel_dwell_secs = secs;
+                    self.save_config();
+                }
+                self.first_panel_dwell_input = value;
+            }
+            Message::PanelSlotChanged(slot, index) => {
+                let pair = index
+                    .and_then(|index| self.config.pairs.get(index))
+                    .map(|entry| entry.pair.clone());
+                let slots = &mut self.config.panel_slots;
+                match pair {
+                    // A pair takes one slot at most.
+                    Some(pair) if slots.contains(&pair) => return Command::none(),
+                    Some(pair) if slot < slots.len() => slots[slot] = pair,
+                    Some(pair) if slots.len() < MAX_PANEL_SLOTS => slots.push(pair),
+                    None if slot < slots.len() => {
+                        slots.remove(slot);
+                    }
+                    _ => return Command::none(),
+                }
+                self.save_config();
+            }
+            Message::CyclePanel => {
+                self.panel_index = self.panel_index.wrapping_add(1);
+                self.recompute_calculator();
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Pausing fetches while unfocused, locked or in low power mode.
+
+use cosmic::app::Command;
+
+use super::{locked_alerts_notification, Message};
+use crate::config::Pair;
+use crate::scheduler::FetchTrigger;
+use crate::state::YourApp;
+
+impl YourApp {
+    /// Handles the messages that pause or resume fetches.
+    pub(super) fn update_pausing(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::WindowFocusChanged(id, focused) => {
+                if self.popup == Some(id) {
+                    if focused {
+                        self.popup_capabilities.focus.succeeded();
+                    }
+                    self.popup_unfocused = !focused;
+                    // The animations resume from where they held, not from the last frame.
+                    self.animation_ticked_at = None;
+                }
+            }
+            Message::TogglePauseOnUnfocus(enabled) => {
+                self.config.pause_on_unfocus = enabled;
+                self.save_config();
+            }
+            Message::ToggleLowPower(enabled) => {
+                let was_low_power = self.low_power();
+                self.config.low_power = enabled;
+                self.save_config();
+                return self.left_low_power(was_low_power);
+            }
+            Message::LowPowerBelowChanged(value) => {
+                let was_low_power = self.low_power();
+                if let Ok(pct) = value.trim().parse::<u8>() {
+                    self.config.low_power_below_pct = pct.min(100);
+                    self.save_config();
+                }
+                self.low_power_below_input = value;
+                return self.left_low_power(was_low_power);
+            }
+            Message::BatteryChanged(battery) => {
+                let was_low_power = self.low_power();
+                self.battery = battery;
+                return self.left_low_power(was_low_power);
+            }
+            Message::SessionLocked(locked) => {
+                if locked == self.session_locked {
+                    return Command::none();
+                }
+                self.session_locked = locked;
+                if locked {
+                    self.next_fetch_at.clear();
+                    return Command::none();
+                }
+                return Command::batch([
+                    self.flush_queued_alerts(),
+                    self.fetch_stale(FetchTrigger::Unlock),
+                ]);
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+
+    /// Shows the alerts that fired while the session was locked: the notification itself
+    /// if there was one, a summary of them all otherwise.
+    fn flush_queued_alerts(&mut self) -> Command<Message> {
+        let queued = std::mem::take(&mut self.queued_alerts);
+        match locked_alerts_notification(queued) {
+            Some((summary, body, pair)) => self.notify_within_budget(summary, body, pair),
+            None => Command::none(),
+        }
+    }
+
+    /// Fetches the active pairs last fetched longer than the refresh interval ago, or never,
+    /// but those low power mode holds back.
+    fn fetch_stale(&mut self, trigger: FetchTrigger) -> Command<Message> {
+        let refresh = self.refresh_interval();
+        let now = self.clock.now_instant();
+        let stale: Vec<Pair> = self
+            .config
+            .pairs
+            .iter()
+            .filter(|e| e.is_active() && !self.paused_for_low_power(&e.pair))
+            .filter(|e| {
+                self.fetched_at
+                    .get(&e.pair)
+                    .map_or(true, |&at| now.saturating_duration_since(at) >= refresh)
+            })
+            .map(|e| e.pair.clone())
+            .collect();
+        Command::batch(stale.into_iter().map(|pair| self.fetch(pair, trigger)))
+    }
+
+    /// Catches up on the pairs low power mode held back if it was on and no longer is.
+    fn left_low_power(&mut self, was_low_power: bool) -> Command<Message> {
+        if was_low_power && !self.low_power() {
+            return self.fetch_stale(FetchTrigger::Resumed);
+        }
+        Command::none()
+    }
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Fitting the popup to its output and probing what popups support.
+
+use cosmic::app::Command;
+use cosmic::iced::wayland::popup::set_size;
+
+use super::Message;
+use crate::capabilities::{self, Refit, PROBE_TIMEOUT};
+use crate::state::{PopupKind, YourApp};
+
+impl YourApp {
+    /// Handles the messages about the size of outputs and popups.
+    pub(super) fn update_popup(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::OutputResized(name, size) => {
+                let ours = &self.core.applet.output_name;
+                let matches = match &name {
+                    Some(name) => name == ours,
+                    // An instance that doesn't know its output takes any report.
+                    None => ours.is_empty(),
+                };
+                if !matches || self.output_size == Some(size) {
+                    return Command::none();
+                }
+                tracing::debug!("output {:?} is now {:?}", name, size);
+                self.output_size = Some(size);
+                if let Some(kind) = self.open_popup_kind() {
+                    return self.refit_popup(kind);
+                }
+            }
+            Message::WindowResized(id, size) => {
+                if !self.popup_kind.contains_key(&id) {
+                    return Command::none();
+                }
+                self.popup_size = Some(size);
+                if self.resize_probe == Some(id) {
+                    self.resize_probe = None;
+                    self.popup_capabilities.resize.succeeded();
+                }
+            }
+            Message::FocusProbeExpired(id) => {
+                if self.popup == Some(id) {
+                    self.popup_capabilities.focus.timed_out();
+                    tracing::debug!("popup focus: {:?}", self.popup_capabilities.focus);
+                }
+            }
+            Message::ResizeProbeExpired(id) => {
+                if self.resize_probe != Some(id) {
+                    return Command::none();
+                }
+                self.resize_probe = None;
+                self.popup_capabilities.resize.timed_out();
+                tracing::warn!("The compositor didn't resize the popup, reopening it instead");
+                // The popup may be stuck at its old size, it is opened again at the new one.
+                if let Some(kind) = self.popup_kind.get(&id).copied() {
+                    return self.open_popup(kind);
+                }
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+
+    /// Fits the open popup of `kind` to the current `work_area`, resizing it in place if
+    /// the compositor allows, reopening it otherwise, see `capabilities::refit`.
+    fn refit_popup(&mut self, kind: PopupKind) -> Command<Message> {
+        let id = *self.popup_slot(kind);
+        let (Some(id), Some((width, height)), Some((max_width, max_height))) =
+            (id, self.popup_size, self.work_area())
+        else {
+            return self.open_popup(kind);
+        };
+        if capabilities::refit(self.popup_capabilities) == Refit::Reopen {
+            return self.open_popup(kind);
+        }
+        let (fitted_width, fitted_height) = (width.min(max_width), height.min(max_height));
+        if (fitted_width, fitted_height) == (width, height) {
+            return Command::none();
+        }
+        tracing::debug!("resizing the {kind:?} popup to {fitted_width}x{fitted_height}");
+        self.resize_probe = Some(id);
+        Command::batch([
+            set_size(id, fitted_width as u32, fitted_height as u32),
+            Command::perform(tokio::time::sleep(PROBE_TIMEOUT), move |_| {
+                cosmic::app::Message::App(Message::ResizeProbeExpired(id))
+            }),
+        ])
+    }
+
+    /// The popup that is open, for it to be reopened with new limits.
+    fn open_popup_kind(&self) -> Option<PopupKind> {
+        self.popup_kind.values().next().copied()
+    }
+}
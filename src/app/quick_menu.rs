@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The quick menu listing every pair, opened from the panel.
+
+use cosmic::app::Command;
+use cosmic::Application;
+
+use super::Message;
+use crate::config::QuickMenuClick;
+use crate::state::{AppView, PopupKind, YourApp};
+
+impl YourApp {
+    /// Handles the messages of the quick menu.
+    pub(super) fn update_quick_menu(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::ToggleQuickMenu => {
+                if self.quick_menu.is_some() {
+                    return Command::batch(self.close_popups());
+                }
+                // Opens on the pair the panel shows.
+                let shown = self.panel_pair().cloned();
+                self.quick_menu_selected = self
+                    .quick_menu_pairs()
+                    .iter()
+                    .position(|&index| Some(&self.config.pairs[index].pair) == shown.as_ref())
+                    .unwrap_or(0);
+                return self.open_popup(PopupKind::QuickMenu);
+            }
+            Message::QuickMenuSelect(index) => {
+                self.config.first_panel_pair = Some(index);
+                // The rotation restarts from, and rests on, the selected pair.
+                self.panel_index = 0;
+                self.save_config();
+                self.recompute_calculator();
+                return Command::batch(self.close_popups());
+            }
+            Message::QuickMenuMove(forward) => {
+                let rows = self.quick_menu_pairs().len();
+                if rows > 0 {
+                    self.quick_menu_selected = if forward {
+                        (self.quick_menu_selected + 1) % rows
+                    } else {
+                        (self.quick_menu_selected + rows - 1) % rows
+                    };
+                }
+            }
+            Message::QuickMenuActivate => {
+                if let Some(&index) = self.quick_menu_pairs().get(self.quick_menu_selected) {
+                    return self.update(Message::QuickMenuSelect(index));
+                }
+            }
+            Message::OpenSettings => {
+                self.select_view(AppView::Settings);
+                return self.open_popup(PopupKind::Main);
+            }
+            Message::QuickMenuClickChanged(index) => {
+                if let Some(&click) = QuickMenuClick::ALL.get(index) {
+                    self.config.quick_menu_click = click;
+                    self.save_config();
+                }
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The startup list and the startup notification.
+
+use cosmic::app::Command;
+
+use super::Message;
+use crate::config::Pair;
+use crate::state::YourApp;
+
+impl YourApp {
+    /// Handles the messages of the startup settings.
+    pub(super) fn update_startup(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::StartupInputChanged(value) => {
+                self.startup_input = value.to_uppercase();
+            }
+            Message::AddStartupPair => {
+                if let Some(pair) = Pair::parse(&self.startup_input) {
+                    self.startup_input.clear();
+                    if !self.config.startup_pairs.contains(&pair) {
+                        self.config.startup_pairs.push(pair);
+                        self.save_config();
+                    }
+                }
+            }
+            Message::RemoveStartupPair(index) => {
+                if index < self.config.startup_pairs.len() {
+                    self.config.startup_pairs.remove(index);
+                    self.save_config();
+                }
+            }
+            Message::ToggleStartupPairs(enabled) => {
+                self.config.startup_pairs_enabled = enabled;
+                self.save_config();
+            }
+            Message::ToggleStartupNotification(enabled) => {
+                self.config.startup_notification = enabled;
+                self.save_config();
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
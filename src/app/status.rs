@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The status page: usage, footprint, flagged quotes and problems with the config.
+
+use cosmic::app::Command;
+
+use super::Message;
+use crate::config::ApiUsage;
+use crate::report::diagnostic_report;
+use crate::state::{AppView, PopupKind, YourApp};
+
+impl YourApp {
+    /// Handles the messages of the status page.
+    pub(super) fn update_status(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::MeasureFootprint => return self.measure_footprint(),
+            Message::FootprintMeasured(footprint) => self.footprint = Some(footprint),
+            Message::FixConfigError(index) => {
+                if let Some(error) = self.config_errors.get(index) {
+                    if error.fixed_in_alerts() {
+                        self.select_view(AppView::Alerts);
+                    } else {
+                        self.select_view(AppView::Settings);
+                    }
+                }
+            }
+            Message::DismissConfigErrors => self.config_errors.clear(),
+            Message::DismissPersistenceNotice => {
+                self.persistence.notice_dismissed = true;
+            }
+            Message::OpenStatus => {
+                self.select_view(AppView::Status);
+                return Command::batch([
+                    self.open_popup(PopupKind::Main),
+                    self.measure_footprint(),
+                ]);
+            }
+            Message::SoftLimitChanged(value) => {
+                if let Ok(limit) = value.trim().parse::<u64>() {
+                    self.config.api_monthly_soft_limit = limit;
+                    self.save_config();
+                }
+                self.soft_limit_input = value;
+            }
+            Message::FlagQuote(index) => {
+                if let Some(entry) = self.config.pairs.get(index) {
+                    self.flag_quote(entry.pair.clone());
+                }
+            }
+            Message::DeleteFlaggedQuote(index) => {
+                if index < self.state.flagged_quotes.len() {
+                    self.state.flagged_quotes.remove(index);
+                    self.save_state();
+                }
+            }
+            Message::ClearFlaggedQuotes => {
+                self.state.flagged_quotes.clear();
+                self.save_state();
+            }
+            Message::ResetApiUsage => {
+                *self.usage.lock().unwrap() = ApiUsage::default();
+                self.save_api_usage();
+            }
+            Message::CopyDiagnosticReport => {
+                return cosmic::iced::clipboard::write(diagnostic_report(self));
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Adding, removing and arranging the pairs of the watchlist.
+
+use chrono::NaiveTime;
+use cosmic::app::Command;
+use cosmic::iced::widget::scrollable::{self, RelativeOffset};
+use std::time::Duration;
+
+use super::{Message, PAIR_LIST_ID};
+use crate::config::{ChangeWindow, Pair, SortOrder, MAX_VISIBLE_PAIRS, MIN_VISIBLE_PAIRS};
+use crate::scheduler::FetchTrigger;
+use crate::state::{RemovedPair, YourApp};
+
+/// How long the removal of a pair can be undone.
+const UNDO_REMOVAL: Duration = Duration::from_secs(5);
+
+impl YourApp {
+    /// Handles the messages that edit the watchlist or how it is listed.
+    pub(super) fn update_watchlist(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::AddPair => {
+                self.input_before_focus = None;
+                // "EURUSD = EURBRL / USDBRL" adds a derived pair, the expression is checked
+                // when it is evaluated and its errors shown on the row.
+                let (name, expression) = match self.input_value.split_once('=') {
+                    Some((name, expression)) => (name, Some(expression.trim().to_string())),
+                    None => (self.input_value.as_str(), None),
+                };
+                if let Some(pair) = Pair::parse(name.trim()) {
+                    self.input_value.clear();
+                    if let Some(added) = self.add_pair(pair, expression) {
+                        return added;
+                    }
+                }
+            }
+            Message::TogglePair(index, enabled) => {
+                if let Some(entry) = self.config.pairs.get_mut(index) {
+                    entry.enabled = enabled;
+                    let pair = entry.pair.clone();
+                    let fetch = entry.is_active();
+                    self.save_config();
+                    let warning = self.check_configured();
+                    if fetch {
+                        return Command::batch([
+                            warning,
+                            self.fetch(pair, FetchTrigger::PairChange),
+                        ]);
+                    }
+                    return warning;
+                }
+            }
+            Message::TogglePairQuiet(index) => {
+                if let Some(entry) = self.config.pairs.get_mut(index) {
+                    entry.quiet = !entry.quiet;
+                    self.save_config();
+                }
+            }
+            Message::ChangeWindowChanged(index, kind) => {
+                let time = ChangeWindow::default_time();
+                if let (Some(entry), Some(window)) = (
+                    self.config.pairs.get_mut(index),
+                    ChangeWindow::from_index(kind, time),
+                ) {
+                    entry.change_window = window;
+                    self.change_window_time_input = time.format("%H:%M").to_string();
+                    self.save_config();
+                }
+            }
+            Message::ChangeWindowTimeChanged(index, text) => {
+                // The time is kept while being typed, and applied once it reads as one.
+                if let (Some(entry), Ok(time)) = (
+                    self.config.pairs.get_mut(index),
+                    NaiveTime::parse_from_str(text.trim(), "%H:%M"),
+                ) {
+                    entry.change_window = ChangeWindow::SinceTime(time);
+                    self.save_config();
+                }
+                self.change_window_time_input = text;
+            }
+            Message::RemovePair(index) => {
+                if index < self.config.pairs.len() {
+                    let entry = self.config.pairs.remove(index);
+                    let pinned = self.config.first_panel_pair == Some(index);
+                    self.config.first_panel_pair = match self.config.first_panel_pair {
+                        Some(pinned) if pinned > index => Some(pinned - 1),
+                        Some(pinned) if pinned == index => None,
+                        other => other,
+                    };
+                    self.next_fetch_at.remove(&entry.pair);
+                    self.history_pages.remove(&entry.pair);
+                    self.simulation_anchor.remove(&entry.pair);
+                    // Replaces the previous removal, only the last one can be undone.
+                    let removed_at = self.clock.now_instant();
+                    self.removed_pair = Some(RemovedPair {
+                        index,
+                        quote: self.exchange_rates.remove(&entry.pair),
+                        fetched_at: self.fetched_at.remove(&entry.pair),
+                        state: self.pair_states.remove(&entry.pair),
+                        entry,
+                        pinned,
+                        removed_at,
+                    });
+                    self.save_config();
+                    let expiry = Command::perform(tokio::time::sleep(UNDO_REMOVAL), move |_| {
+                        cosmic::app::Message::App(Message::UndoExpired(removed_at))
+                    });
+                    return Command::batch([self.check_configured(), expiry]);
+                }
+            }
+            Message::UndoRemovePair => {
+                let Some(removed) = self.removed_pair.take() else {
+                    return Command::none();
+                };
+                // Added back in the meantime, the newer entry is kept.
+                if self
+                    .config
+                    .pairs
+                    .iter()
+                    .any(|e| e.pair == removed.entry.pair)
+                {
+                    return Command::none();
+                }
+                let pair = removed.entry.pair.clone();
+                let index = removed.index.min(self.config.pairs.len());
+                self.config.pairs.insert(index, removed.entry);
+                self.config.first_panel_pair = match self.config.first_panel_pair {
+                    _ if removed.pinned => Some(index),
+                    Some(pinned) if pinned >= index => Some(pinned + 1),
+                    other => other,
+                };
+                if let Some(state) = removed.state {
+                    self.pair_states.insert(pair.clone(), state);
+                }
+                if let Some(fetched_at) = removed.fetched_at {
+                    self.fetched_at.insert(pair.clone(), fetched_at);
+                }
+                self.save_config();
+                let fetch = match removed.quote {
+                    Some(quote) => {
+                        self.exchange_rates.insert(pair, quote);
+                        Command::none()
+                    }
+                    None => self.fetch(pair, FetchTrigger::PairChange),
+                };
+                return Command::batch([self.check_configured(), fetch]);
+            }
+            Message::UndoExpired(removed_at) => {
+                if self
+                    .removed_pair
+                    .as_ref()
+                    .is_some_and(|removed| removed.removed_at == removed_at)
+                {
+                    self.removed_pair = None;
+                }
+            }
+            Message::TogglePairMenu(index) => {
+                self.pair_menu = (self.pair_menu != Some(index)).then_some(index);
+                self.pair_markup_input = self
+                    .config
+                    .pairs
+                    .get(index)
+                    .and_then(|entry| entry.markup_pct)
+                    .map(|markup| markup.to_string())
+                    .unwrap_or_default();
+                if let Some(ChangeWindow::SinceTime(time)) = self
+                    .config
+                    .pairs
+                    .get(index)
+                    .map(|entry| entry.change_window)
+                {
+                    self.change_window_time_input = time.format("%H:%M").to_string();
+                }
+            }
+            Message::SortChanged(index) => {
+                if let Some(&order) = SortOrder::ALL.get(index) {
+                    self.config.popup_sort = order;
+                    self.save_config();
+                }
+            }
+            Message::ToggleSortPanelRotation(enabled) => {
+                self.config.sort_panel_rotation = enabled;
+                self.save_config();
+            }
+            Message::MaxVisibleChanged(value) => {
+                if let Ok(count) = value.trim().parse::<u8>() {
+                    self.config.pair_list_max_visible =
+                        count.clamp(MIN_VISIBLE_PAIRS, MAX_VISIBLE_PAIRS);
+                    self.save_config();
+                }
+                self.max_visible_input = value;
+            }
+            Message::PairListScrolled(scrolled) => {
+                self.pair_list_scrolled = scrolled;
+            }
+            Message::ScrollPairsToTop => {
+                self.pair_list_scrolled = false;
+                return scrollable::snap_to(PAIR_LIST_ID.clone(), RelativeOffset::START);
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+}
@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Approximate rates of the most common pairs, bundled from `res/bundled_rates.toml` so a
+//! first run on a network that can't fetch yet, such as behind a captive portal, doesn't
+//! start with a blank panel.
+//!
+//! They are only ever shown, labelled as approximate with the date of the table, and only
+//! for pairs with no quote fetched this session, see `YourApp::shown_rate`. They never
+//! reach `YourApp::exchange_rates`, so alerts, history and exports don't see them.
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use crate::config::Pair;
+
+/// The bundled table.
+const BUNDLED: &str = include_str!("../res/bundled_rates.toml");
+
+/// The layout of `res/bundled_rates.toml`.
+#[derive(Debug, Clone, Deserialize)]
+struct RateTable {
+    as_of: NaiveDate,
+    rates: Vec<RateEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RateEntry {
+    pair: Pair,
+    bid: f64,
+}
+
+/// An approximate rate of the bundled table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BundledRate {
+    pub bid: f64,
+    /// When the table was made.
+    pub as_of: NaiveDate,
+}
+
+/// The bundled rates by pair, empty if the table doesn't parse.
+static BUNDLED_RATES: LazyLock<HashMap<Pair, BundledRate>> = LazyLock::new(|| {
+    let table = match toml::from_str::<RateTable>(BUNDLED) {
+        Ok(table) => table,
+        Err(e) => {
+            tracing::error!("Error parsing the bundled rates: {}", e);
+            return HashMap::new();
+        }
+    };
+    table
+        .rates
+        .into_iter()
+        .filter(|entry| entry.bid.is_finite() && entry.bid > 0.0)
+        .map(|entry| {
+            let rate = BundledRate {
+                bid: entry.bid,
+                as_of: table.as_of,
+            };
+            (entry.pair, rate)
+        })
+        .collect()
+});
+
+/// The bundled rate of `pair`, `None` if the table has none.
+pub fn bundled_rate(pair: &Pair) -> Option<BundledRate> {
+    BUNDLED_RATES.get(pair).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_table_has_the_common_pairs() {
+        let table: RateTable = toml::from_str(BUNDLED).unwrap();
+        assert!(table.rates.len() >= 20, "{} rates", table.rates.len());
+        // Every entry made it, none was dropped for its bid.
+        assert_eq!(BUNDLED_RATES.len(), table.rates.len());
+        for code in ["USDBRL", "EURUSD", "USDJPY", "BTCUSD"] {
+            let rate = bundled_rate(&Pair::parse(code).unwrap()).unwrap();
+            assert_eq!(rate.as_of, table.as_of, "{code}");
+        }
+        assert!(table.as_of <= chrono::Local::now().date_naive());
+    }
+
+    #[test]
+    fn pairs_missing_from_the_table_have_no_rate() {
+        assert_eq!(bundled_rate(&Pair::parse("USDBOB").unwrap()), None);
+    }
+}
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! What the compositor was seen to do with the applet's popups, and the popup features that
+//! depend on it.
+//!
+//! Compositors and libcosmic versions differ in whether popups take the keyboard focus and
+//! can be resized once created, and attempting what they don't do leaves popups stuck.
+//! Each operation is probed the first time the applet needs it, and the features relying on
+//! it are decided by the functions below from what the probes found.
+
+use std::time::Duration;
+
+use crate::fl;
+
+/// How long a popup has to get the keyboard focus after opening, or to be configured to a
+/// requested size, before the operation is taken not to work.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What probing a popup operation found.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Probe {
+    /// The operation wasn't needed yet.
+    #[default]
+    Untested,
+    /// The operation succeeded at least once.
+    Works,
+    /// The operation was attempted and never succeeded.
+    Failed,
+}
+
+impl Probe {
+    /// The operation succeeded, for the rest of the session.
+    pub fn succeeded(&mut self) {
+        *self = Probe::Works;
+    }
+
+    /// The operation didn't succeed in time. A success seen before stands, so a busy
+    /// compositor once slow to answer doesn't turn a feature off.
+    pub fn timed_out(&mut self) {
+        if *self == Probe::Untested {
+            *self = Probe::Failed;
+        }
+    }
+
+    /// The id of the result in the diagnostic report.
+    pub fn id(self) -> &'static str {
+        match self {
+            Probe::Untested => "untested",
+            Probe::Works => "works",
+            Probe::Failed => "failed",
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Probe::Untested => fl!("probe-untested"),
+            Probe::Works => fl!("probe-works"),
+            Probe::Failed => fl!("probe-failed"),
+        }
+    }
+}
+
+/// The probed popup operations.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PopupCapabilities {
+    /// Whether the main popup gets the keyboard focus when it opens.
+    pub focus: Probe,
+    /// Whether a popup is configured to a new size requested after it was created.
+    pub resize: Probe,
+}
+
+/// How an open popup follows a change of the room it has, see `refit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refit {
+    /// Resized in place, keeping its scroll position and inputs.
+    Resize,
+    /// Closed and opened again with the new limits.
+    Reopen,
+}
+
+/// Whether Tab and Enter move through and activate the popup's sections: unless the popup
+/// was seen not to get the keyboard, when the highlighted section would never move.
+pub fn keyboard_navigation(capabilities: PopupCapabilities) -> bool {
+    capabilities.focus != Probe::Failed
+}
+
+/// Whether the popup may be taken as unfocused, for `AppletConfig::pause_on_unfocus`: only
+/// once it was seen to get the focus, as a popup that never gets it would stay paused.
+pub fn focus_tracking(capabilities: PopupCapabilities) -> bool {
+    capabilities.focus == Probe::Works
+}
+
+/// How an open popup follows a change of the output's size: resized in place unless that
+/// was seen to fail, the first attempt probing it.
+pub fn refit(capabilities: PopupCapabilities) -> Refit {
+    match capabilities.resize {
+        Probe::Untested | Probe::Works => Refit::Resize,
+        Probe::Failed => Refit::Reopen,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The capabilities with `focus` and `resize` probed to what they say.
+    fn probed(focus: Probe, resize: Probe) -> PopupCapabilities {
+        PopupCapabilities { focus, resize }
+    }
+
+    const PROBES: [Probe; 3] = [Probe::Untested, Probe::Works, Probe::Failed];
+
+    #[test]
+    fn an_operation_fails_when_it_never_succeeded_in_time() {
+        let mut probe = Probe::default();
+        assert_eq!(probe, Probe::Untested);
+        probe.timed_out();
+        assert_eq!(probe, Probe::Failed);
+        // Working late still counts.
+        probe.succeeded();
+        assert_eq!(probe, Probe::Works);
+    }
+
+    #[test]
+    fn a_success_stands_through_later_timeouts() {
+        let mut probe = Probe::Untested;
+        probe.succeeded();
+        probe.timed_out();
+        assert_eq!(probe, Probe::Works);
+    }
+
+    #[test]
+    fn keyboard_navigation_is_off_only_once_focus_failed() {
+        for resize in PROBES {
+            assert!(keyboard_navigation(probed(Probe::Untested, resize)));
+            assert!(keyboard_navigation(probed(Probe::Works, resize)));
+            assert!(!keyboard_navigation(probed(Probe::Failed, resize)));
+        }
+    }
+
+    #[test]
+    fn focus_is_tracked_only_once_seen_to_work() {
+        for resize in PROBES {
+            assert!(!focus_tracking(probed(Probe::Untested, resize)));
+            assert!(focus_tracking(probed(Probe::Works, resize)));
+            assert!(!focus_tracking(probed(Probe::Failed, resize)));
+        }
+    }
+
+    #[test]
+    fn popups_are_reopened_only_once_resizing_failed() {
+        for focus in PROBES {
+            assert_eq!(refit(probed(focus, Probe::Untested)), Refit::Resize);
+            assert_eq!(refit(probed(focus, Probe::Works)), Refit::Resize);
+            assert_eq!(refit(probed(focus, Probe::Failed)), Refit::Reopen);
+        }
+    }
+
+    #[test]
+    fn each_result_reads_differently() {
+        for (i, a) in PROBES.iter().enumerate() {
+            for b in &PROBES[i + 1..] {
+                assert_ne!(a.id(), b.id());
+                assert_ne!(a.label(), b.label());
+            }
+        }
+    }
+}
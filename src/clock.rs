@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Where the applet reads the time from: scheduling, staleness, change windows, maintenance
+//! windows, usage counters and every timestamp it shows or stores.
+//!
+//! The clock is made once at startup by `Flags::clock` and kept in `YourApp::clock`, which
+//! hands it to every component reading the time, so they all see the same time, including
+//! across a jump of the system clock. Only measurements of how long the applet's own code
+//! ran use `Instant::now` directly.
+
+use chrono::{DateTime, Local, Utc};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The clock every component of the applet reads, the system's by default.
+#[derive(Debug, Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new(clock: impl Clock + 'static) -> SharedClock {
+        SharedClock(Arc::new(clock))
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        SharedClock::new(SystemClock)
+    }
+}
+
+impl AsRef<dyn Clock> for SharedClock {
+    fn as_ref(&self) -> &dyn Clock {
+        self.0.as_ref()
+    }
+}
+
+impl std::ops::Deref for SharedClock {
+    type Target = dyn Clock;
+
+    fn deref(&self) -> &dyn Clock {
+        self.0.as_ref()
+    }
+}
+
+/// A source of the current time.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// The monotonic time, for durations and deadlines.
+    fn now_instant(&self) -> Instant;
+
+    /// The wall-clock time.
+    fn now_utc(&self) -> DateTime<Utc>;
+
+    /// The wall-clock time in the system timezone.
+    fn now_local(&self) -> DateTime<Local> {
+        self.now_utc().with_timezone(&Local)
+    }
+}
+
+/// The system's clocks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// The system's clocks with the wall-clock time shifted by `offset`, to try what happens
+/// at midnight or at a market close without waiting for it, see `Flags::clock_offset_secs`.
+/// The monotonic time isn't shifted, as only the durations it measures matter.
+#[derive(Debug, Clone, Copy)]
+pub struct OffsetClock {
+    pub offset: chrono::Duration,
+}
+
+impl Clock for OffsetClock {
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now() + self.offset
+    }
+}
+
+/// A clock that only moves when told to, for tests. Both times start at `start` and
+/// advance together.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct FakeClock {
+    now: std::sync::Mutex<(Instant, DateTime<Utc>)>,
+}
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn new(start: DateTime<Utc>) -> Arc<Self> {
+        Arc::new(FakeClock {
+            now: std::sync::Mutex::new((Instant::now(), start)),
+        })
+    }
+
+    pub fn advance(&self, by: std::time::Duration) {
+        let mut now = self.now.lock().unwrap();
+        now.0 += by;
+        now.1 += chrono::Duration::from_std(by).unwrap();
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now_instant(&self) -> Instant {
+        self.now.lock().unwrap().0
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.now.lock().unwrap().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiUsage, ChangeWindow};
+    use crate::providers::PairResponse;
+    use crate::state::{window_baseline, PairState};
+    use chrono::{NaiveTime, TimeZone};
+    use std::time::Duration;
+
+    /// Takes in a quote of `bid` now, returning the baseline of the change since midnight.
+    fn quote(clock: &FakeClock, state: &mut PairState, bid: f64) -> Option<f64> {
+        state.record_quote(bid, clock.now_local());
+        let quote = PairResponse {
+            bid,
+            ..PairResponse::default()
+        };
+        let midnight = ChangeWindow::SinceTime(NaiveTime::MIN);
+        window_baseline(
+            midnight,
+            &quote,
+            state.history.iter().skip(1),
+            clock.now_local(),
+        )
+    }
+
+    #[test]
+    fn crossing_midnight_rolls_the_day_over_once() {
+        let start = Local.with_ymd_and_hms(2026, 3, 10, 23, 59, 30).unwrap();
+        let clock = FakeClock::new(start.with_timezone(&Utc));
+        let mut usage = ApiUsage::default();
+        let mut state = PairState::default();
+        let request = |usage: &mut ApiUsage| {
+            let today = clock.now_local().date_naive();
+            usage.record("provider", today);
+            let counters = &usage.providers["provider"];
+            (counters.today, counters.this_month)
+        };
+
+        assert_eq!(quote(&clock, &mut state, 5.0), None);
+        assert_eq!(request(&mut usage), (1, 1));
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(quote(&clock, &mut state, 5.1), Some(5.0));
+        assert_eq!(request(&mut usage), (2, 2));
+
+        // Past midnight, the day starts from the last quote of the previous one.
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(
+            clock.now_local().date_naive(),
+            start.date_naive().succ_opt().unwrap()
+        );
+        assert_eq!(quote(&clock, &mut state, 5.2), Some(5.1));
+        assert_eq!(request(&mut usage), (1, 3));
+
+        // And it only rolls over once.
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(quote(&clock, &mut state, 5.3), Some(5.1));
+        assert_eq!(request(&mut usage), (2, 4));
+    }
+
+    #[test]
+    fn fake_clock_moves_both_times_together() {
+        let clock = FakeClock::new(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap());
+        let (instant, utc) = (clock.now_instant(), clock.now_utc());
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now_instant() - instant, Duration::from_secs(90));
+        assert_eq!(clock.now_utc() - utc, chrono::Duration::seconds(90));
+    }
+}
@@ -0,0 +1,2101 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The settings and persisted state of the applet, and the types they are made of.
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime};
+use cosmic::applet::cosmic_panel_config::PanelAnchor;
+use cosmic::cctk::wayland_protocols::xdg::shell::client::xdg_positioner::{Anchor, Gravity};
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::clock::{OffsetClock, SharedClock, SystemClock};
+use crate::email::SmtpConfig;
+use crate::exact::{decimal, worth};
+use crate::fl;
+use crate::format::format_percent;
+use crate::providers::{provider, PairResponse, AWESOMEAPI, PROVIDERS};
+use crate::query::QueryFormat;
+
+/// Highest accepted `AppletConfig::api_retry_count`.
+pub const MAX_API_RETRY_COUNT: u32 = 10;
+/// Lowest accepted `AppletConfig::api_retry_delay_secs`.
+pub const MIN_API_RETRY_DELAY_SECS: u64 = 5;
+/// Share of `AppletConfig::api_monthly_soft_limit` from which the user is warned.
+pub const API_SOFT_LIMIT_WARNING_RATIO: f64 = 0.9;
+/// Highest accepted `AppletConfig::precision`.
+pub const MAX_PRECISION: usize = 8;
+/// Lowest accepted `AppletConfig::pair_list_max_visible`.
+pub const MIN_VISIBLE_PAIRS: u8 = 3;
+/// Highest accepted `AppletConfig::pair_list_max_visible`.
+pub const MAX_VISIBLE_PAIRS: u8 = 20;
+/// Most pairs `AppletConfig::panel_slots` shows side by side.
+pub const MAX_PANEL_SLOTS: usize = 3;
+
+/// A currency pair such as USD to BRL, written `USDBRL` (or `USDT-BRL` when
+/// one of the codes is not three letters long).
+///
+/// Pairs are only built by `Pair::new`, which validates the codes and uppercases them, so
+/// `usdbrl` from a hand-edited config, a D-Bus client or the command line is the same key
+/// as `USDBRL` everywhere: one watchlist entry, one history and one set of alerts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pair {
+    base: String,
+    quote: String,
+}
+
+impl Pair {
+    /// The pair of the currency codes `base` and `quote` in any case, `None` if either
+    /// isn't 2 to 5 letters long.
+    pub fn new(base: &str, quote: &str) -> Option<Self> {
+        let valid =
+            |c: &str| (2..=5).contains(&c.len()) && c.chars().all(|c| c.is_ascii_alphabetic());
+        if !valid(base) || !valid(quote) {
+            return None;
+        }
+        Some(Pair {
+            base: base.to_ascii_uppercase(),
+            quote: quote.to_ascii_uppercase(),
+        })
+    }
+
+    /// Parses a pair code in any case, returning `None` if it is not a valid pair.
+    pub fn parse(code: &str) -> Option<Self> {
+        let code = code.trim();
+        let (base, quote) = match code.split_once('-') {
+            Some((base, quote)) => (base, quote),
+            None if code.len() == 6 && code.is_char_boundary(3) => code.split_at(3),
+            None => return None,
+        };
+        Pair::new(base, quote)
+    }
+
+    /// The code of the currency, e.g. `USD` in `USDBRL`.
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// The code of the currency the base is quoted in, e.g. `BRL` in `USDBRL`.
+    pub fn quote(&self) -> &str {
+        &self.quote
+    }
+
+    /// The code used as key in API responses, e.g. `USDBRL`.
+    pub fn code(&self) -> String {
+        format!("{}{}", self.base, self.quote)
+    }
+}
+
+impl fmt::Display for Pair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.base, self.quote)
+    }
+}
+
+impl TryFrom<String> for Pair {
+    type Error = String;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        Pair::parse(&code).ok_or_else(|| format!("invalid currency pair: {code}"))
+    }
+}
+
+impl From<Pair> for String {
+    fn from(pair: Pair) -> Self {
+        if pair.base.len() == 3 && pair.quote.len() == 3 {
+            pair.code()
+        } else {
+            format!("{}-{}", pair.base, pair.quote)
+        }
+    }
+}
+
+/// A pair tracked in the watchlist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairEntry {
+    pub pair: Pair,
+    /// Disabled pairs are kept in the watchlist but neither fetched nor shown.
+    pub enabled: bool,
+    /// Frozen pairs keep showing their last value but are no longer refreshed.
+    pub frozen: bool,
+    /// Where the fired alerts of this pair are posted as JSON, if anywhere.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// What the change of this pair is measured against, wherever it is shown or alerted on.
+    #[serde(default)]
+    pub change_window: ChangeWindow,
+    /// Quiet pairs are fetched and shown as usual, but alerts on how much they moved
+    /// don't fire.
+    #[serde(default)]
+    pub quiet: bool,
+    /// Replaces `AppletConfig::markup_pct` for this pair.
+    #[serde(default)]
+    pub markup_pct: Option<f64>,
+    /// Replaces `AppletConfig::provider` for this pair, one of `PROVIDERS`.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Makes this a derived pair, quoted by evaluating the expression over the quotes of
+    /// other pairs of the watchlist rather than fetched, see `crate::expression`.
+    #[serde(default)]
+    pub expression: Option<String>,
+}
+
+impl PairEntry {
+    pub fn new(pair: Pair) -> Self {
+        PairEntry {
+            pair,
+            enabled: true,
+            frozen: false,
+            webhook_url: None,
+            change_window: ChangeWindow::default(),
+            quiet: false,
+            markup_pct: None,
+            provider: None,
+            expression: None,
+        }
+    }
+
+    /// Whether this pair is derived from others by an `expression`.
+    pub fn is_derived(&self) -> bool {
+        self.expression.is_some()
+    }
+
+    /// Whether this pair is shown in the panel and refreshed.
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.frozen
+    }
+}
+
+/// How the panel button presents the watchlist.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayMode {
+    /// Show a single rate when one pair is active (or when cycling), otherwise a summary.
+    #[default]
+    Auto,
+    /// Always show a single rate.
+    Rate,
+    /// Show a compact summary of all active pairs.
+    Summary,
+    /// Show the applet icon with the number of active pairs, and the rates in a tooltip.
+    /// `Auto` picks it in the dock, where text does not fit the icon-sized slot.
+    Icon,
+}
+
+/// The order in which the popup lists the watchlist.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    /// The order the pairs were added in, which is the persisted order.
+    #[default]
+    Manual,
+    Alphabetical,
+    /// Biggest movers first, by magnitude of the daily change.
+    ChangeDescending,
+    RateAscending,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 4] = [
+        SortOrder::Manual,
+        SortOrder::Alphabetical,
+        SortOrder::ChangeDescending,
+        SortOrder::RateAscending,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            SortOrder::Manual => fl!("sort-manual"),
+            SortOrder::Alphabetical => fl!("sort-alphabetical"),
+            SortOrder::ChangeDescending => fl!("sort-change-descending"),
+            SortOrder::RateAscending => fl!("sort-rate-ascending"),
+        }
+    }
+}
+
+/// When an alert rule fires.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AlertCondition {
+    /// The rate rises above the value.
+    Above(f64),
+    /// The rate falls below the value.
+    Below(f64),
+    /// The change over the pair's `ChangeWindow`, in percent, exceeds the value either way.
+    ChangePercent(f64),
+    /// `amount` of the base currency becomes worth more than `target` of the quote currency.
+    /// Both are kept as entered so the rule reads the same when shown again, the rate they
+    /// stand for is `rate_bound`.
+    WorthAbove { amount: f64, target: f64 },
+    /// `amount` of the base currency becomes worth less than `target` of the quote currency.
+    WorthBelow { amount: f64, target: f64 },
+}
+
+impl AlertCondition {
+    /// The labels of the condition kinds, in `from_index` order.
+    pub fn labels() -> Vec<String> {
+        vec![
+            fl!("alert-above"),
+            fl!("alert-below"),
+            fl!("alert-change-percent"),
+            fl!("alert-worth-above"),
+            fl!("alert-worth-below"),
+        ]
+    }
+
+    /// Whether the condition kind at `index` of `labels()` is expressed for an amount.
+    pub fn needs_amount(index: usize) -> bool {
+        matches!(index, 3 | 4)
+    }
+
+    /// Builds the condition at `index` of `labels()` with `value`, and `amount` for the
+    /// kinds that `needs_amount`, which must be positive.
+    pub fn from_index(index: usize, value: f64, amount: Option<f64>) -> Option<Self> {
+        let amount = amount.filter(|&amount| amount > 0.0);
+        match index {
+            0 => Some(AlertCondition::Above(value)),
+            1 => Some(AlertCondition::Below(value)),
+            2 => Some(AlertCondition::ChangePercent(value)),
+            3 => amount.map(|amount| AlertCondition::WorthAbove {
+                amount,
+                target: value,
+            }),
+            4 => amount.map(|amount| AlertCondition::WorthBelow {
+                amount,
+                target: value,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The condition on the rate itself: amount conditions become the `Above` or `Below`
+    /// of the rate at which `amount` is worth exactly `target`.
+    pub fn rate_bound(self) -> AlertCondition {
+        match self {
+            AlertCondition::WorthAbove { amount, target } => AlertCondition::Above(target / amount),
+            AlertCondition::WorthBelow { amount, target } => AlertCondition::Below(target / amount),
+            condition => condition,
+        }
+    }
+
+    /// Whether the condition is about the movement of the rate rather than its level,
+    /// and so muted on quiet pairs.
+    pub fn is_movement(&self) -> bool {
+        matches!(self, AlertCondition::ChangePercent(_))
+    }
+
+    /// Whether the condition holds for `quote`, whose change over the pair's window is
+    /// `change_percent`, `None` while it can't be told.
+    pub fn holds(&self, quote: &PairResponse, change_percent: Option<f64>) -> bool {
+        match *self {
+            AlertCondition::Above(value) => quote.bid > value,
+            AlertCondition::Below(value) => quote.bid < value,
+            AlertCondition::ChangePercent(value) => {
+                change_percent.is_some_and(|change| change.abs() >= value)
+            }
+            // Compared in amounts rather than through `rate_bound`, so the rule fires exactly
+            // when the amount shown in the notification crosses the target.
+            AlertCondition::WorthAbove { amount, target } => worth(amount, quote.bid)
+                .zip(decimal(target))
+                .is_some_and(|(worth, target)| worth > target),
+            AlertCondition::WorthBelow { amount, target } => worth(amount, quote.bid)
+                .zip(decimal(target))
+                .is_some_and(|(worth, target)| worth < target),
+        }
+    }
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertCondition::Above(value) => write!(f, "> {value}"),
+            AlertCondition::Below(value) => write!(f, "< {value}"),
+            AlertCondition::ChangePercent(value) => {
+                // With as many decimals as the threshold was entered with.
+                let decimals = value
+                    .to_string()
+                    .split_once('.')
+                    .map_or(0, |(_, decimals)| decimals.len());
+                write!(f, "± {}", format_percent(*value, decimals))
+            }
+            AlertCondition::WorthAbove { amount, target } => write!(f, "{amount} × > {target}"),
+            AlertCondition::WorthBelow { amount, target } => write!(f, "{amount} × < {target}"),
+        }
+    }
+}
+
+/// Notifies the user when the rate of a pair meets a condition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub pair: Pair,
+    pub condition: AlertCondition,
+}
+
+impl AlertRule {
+    /// The condition in words, naming the currencies of amount conditions.
+    pub fn describe(&self) -> String {
+        match self.condition {
+            AlertCondition::WorthAbove { amount, target } => fl!(
+                "alert-worth-above-rule",
+                amount = amount.to_string(),
+                base = self.pair.base().to_string(),
+                target = target.to_string(),
+                quote = self.pair.quote().to_string()
+            ),
+            AlertCondition::WorthBelow { amount, target } => fl!(
+                "alert-worth-below-rule",
+                amount = amount.to_string(),
+                base = self.pair.base().to_string(),
+                target = target.to_string(),
+                quote = self.pair.quote().to_string()
+            ),
+            condition => condition.to_string(),
+        }
+    }
+}
+
+/// A problem found by comparing an alert rule with the other rules of its pair.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleConflict {
+    /// Both rules fire for every rate between `low` and `high`, and at any rate at least one
+    /// of them is active.
+    Overlap { low: f64, high: f64 },
+    /// The same rule already exists.
+    Duplicate,
+    /// The rule holds for any possible rate.
+    AlwaysTrue,
+    /// The rule can never hold.
+    NeverTrue,
+    /// A percentage rule next to an absolute one: whether they overlap depends on the
+    /// previous close, so it can't be told in advance.
+    MayOverlap,
+}
+
+impl RuleConflict {
+    pub fn message(&self) -> String {
+        match self {
+            RuleConflict::Overlap { low, high } => fl!(
+                "conflict-overlap",
+                low = low.to_string(),
+                high = high.to_string()
+            ),
+            RuleConflict::Duplicate => fl!("conflict-duplicate"),
+            RuleConflict::AlwaysTrue => fl!("conflict-always-true"),
+            RuleConflict::NeverTrue => fl!("conflict-never-true"),
+            RuleConflict::MayOverlap => fl!("conflict-may-overlap"),
+        }
+    }
+}
+
+/// A combination of settings that can't work as intended, found by `validate_config`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValidationError {
+    /// The retries of a failed fetch, `window` seconds in all, run into the next fetch.
+    RetriesOverlapRefresh { window: u64, refresh: u64 },
+    /// Quotes are to be reused for longer than `cache_ttl` allows, half of the refresh.
+    CacheTtlCapped { ttl: u64, refresh: u64 },
+    /// Two alert rules of `pair` both fire for every rate between `low` and `high`.
+    AlertsOverlap { pair: Pair, low: f64, high: f64 },
+    /// Alerts are to be emailed, but no SMTP server is set to send them through.
+    EmailWithoutServer,
+    /// Forward rates are to be shown, but there is no `forward_rates_url` to fetch them from.
+    ForwardRatesWithoutUrl,
+    /// `first_panel_pair` points past the end of the watchlist.
+    PinnedPairMissing,
+    /// The fallback provider is the primary one, or not one of `PROVIDERS`.
+    InvalidFallbackProvider,
+    /// `pair` is to be fetched from `provider`, which is unknown or doesn't quote it.
+    UnsupportedPairProvider { pair: Pair, provider: String },
+}
+
+impl ConfigValidationError {
+    pub fn message(&self) -> String {
+        match self {
+            ConfigValidationError::RetriesOverlapRefresh { window, refresh } => fl!(
+                "retries-overlap-warning",
+                window = window,
+                refresh = refresh
+            ),
+            ConfigValidationError::CacheTtlCapped { ttl, refresh } => {
+                fl!("cache-ttl-capped", ttl = ttl, refresh = refresh)
+            }
+            ConfigValidationError::AlertsOverlap { pair, low, high } => fl!(
+                "alerts-overlap",
+                pair = pair.to_string(),
+                low = low.to_string(),
+                high = high.to_string()
+            ),
+            ConfigValidationError::EmailWithoutServer => fl!("email-without-server"),
+            ConfigValidationError::ForwardRatesWithoutUrl => fl!("forward-rates-without-url"),
+            ConfigValidationError::PinnedPairMissing => fl!("pinned-pair-missing"),
+            ConfigValidationError::InvalidFallbackProvider => fl!("invalid-fallback-provider"),
+            ConfigValidationError::UnsupportedPairProvider { pair, provider } => fl!(
+                "unsupported-pair-provider",
+                pair = pair.to_string(),
+                provider = provider.clone()
+            ),
+        }
+    }
+
+    /// Whether the setting to fix is on the alerts tab rather than the settings tab.
+    pub fn fixed_in_alerts(&self) -> bool {
+        matches!(self, ConfigValidationError::AlertsOverlap { .. })
+    }
+}
+
+/// Every cross-field constraint `config` breaks, all of them so they can be fixed at once.
+pub fn validate_config(config: &AppletConfig) -> Vec<ConfigValidationError> {
+    let mut errors = Vec::new();
+    if config.retries_overlap_refresh() {
+        errors.push(ConfigValidationError::RetriesOverlapRefresh {
+            window: u64::from(config.api_retry_count).saturating_mul(config.api_retry_delay_secs),
+            refresh: config.refresh_secs,
+        });
+    }
+    if config.cache_ttl_secs > config.refresh_secs / 2 {
+        errors.push(ConfigValidationError::CacheTtlCapped {
+            ttl: config.cache_ttl_secs,
+            refresh: config.refresh_secs,
+        });
+    }
+    for (index, rule) in config.alert_rules.iter().enumerate() {
+        let earlier: Vec<AlertCondition> = config.alert_rules[..index]
+            .iter()
+            .filter(|other| other.pair == rule.pair)
+            .map(|other| other.condition)
+            .collect();
+        for conflict in rule_conflicts(rule.condition, &earlier) {
+            if let RuleConflict::Overlap { low, high } = conflict {
+                errors.push(ConfigValidationError::AlertsOverlap {
+                    pair: rule.pair.clone(),
+                    low,
+                    high,
+                });
+            }
+        }
+    }
+    if config.alert_email.is_some() && config.smtp_server.is_empty() {
+        errors.push(ConfigValidationError::EmailWithoutServer);
+    }
+    if config.show_forward_rates && config.forward_rates_url.is_empty() {
+        errors.push(ConfigValidationError::ForwardRatesWithoutUrl);
+    }
+    if config
+        .first_panel_pair
+        .is_some_and(|index| index >= config.pairs.len())
+    {
+        errors.push(ConfigValidationError::PinnedPairMissing);
+    }
+    let fallback = config.fallback_provider.as_str();
+    if !fallback.is_empty() && (fallback == config.provider || !PROVIDERS.contains(&fallback)) {
+        errors.push(ConfigValidationError::InvalidFallbackProvider);
+    }
+    for entry in &config.pairs {
+        let Some(name) = entry.provider.as_deref() else {
+            continue;
+        };
+        if !PROVIDERS.contains(&name) || !provider(name).supports(&entry.pair) {
+            errors.push(ConfigValidationError::UnsupportedPairProvider {
+                pair: entry.pair.clone(),
+                provider: name.to_string(),
+            });
+        }
+    }
+    errors
+}
+
+/// Compares `rule` with `others`, the rules already defined for the same pair.
+///
+/// Rates are never negative, so a rule's condition is treated as an interval of
+/// `[0, ∞)`: `Above(a)` is `(a, ∞)`, `Below(b)` is `[0, b)`. Amount conditions are
+/// compared through their `rate_bound`.
+pub fn rule_conflicts(rule: AlertCondition, others: &[AlertCondition]) -> Vec<RuleConflict> {
+    let rule = rule.rate_bound();
+    let others: Vec<AlertCondition> = others.iter().map(|other| other.rate_bound()).collect();
+    let mut conflicts = Vec::new();
+    match rule {
+        AlertCondition::Above(value) if value < 0.0 => conflicts.push(RuleConflict::AlwaysTrue),
+        AlertCondition::Below(value) if value <= 0.0 => conflicts.push(RuleConflict::NeverTrue),
+        AlertCondition::ChangePercent(value) if value <= 0.0 => {
+            conflicts.push(RuleConflict::AlwaysTrue)
+        }
+        _ => {}
+    }
+
+    for other in others {
+        if other == rule {
+            conflicts.push(RuleConflict::Duplicate);
+            continue;
+        }
+        match (rule, other) {
+            (AlertCondition::Above(low), AlertCondition::Below(high))
+            | (AlertCondition::Below(high), AlertCondition::Above(low))
+                if low < high =>
+            {
+                conflicts.push(RuleConflict::Overlap { low, high })
+            }
+            (
+                AlertCondition::ChangePercent(_),
+                AlertCondition::Above(_) | AlertCondition::Below(_),
+            )
+            | (
+                AlertCondition::Above(_) | AlertCondition::Below(_),
+                AlertCondition::ChangePercent(_),
+            ) => {
+                if !conflicts.contains(&RuleConflict::MayOverlap) {
+                    conflicts.push(RuleConflict::MayOverlap)
+                }
+            }
+            _ => {}
+        }
+    }
+    conflicts
+}
+
+/// A stop of the keyboard navigation of the popup, in `AppletConfig::tab_key_order`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AppletSection {
+    /// The watchlist, scrolled back to its first pair.
+    PairList,
+    PairInput,
+    /// The button adding the pair typed in the pair input.
+    AddButton,
+    /// The button refreshing every pair.
+    RefreshButton,
+    /// The settings tab.
+    SettingsPanel,
+}
+
+impl AppletSection {
+    pub const ALL: [AppletSection; 5] = [
+        AppletSection::PairList,
+        AppletSection::PairInput,
+        AppletSection::AddButton,
+        AppletSection::RefreshButton,
+        AppletSection::SettingsPanel,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            AppletSection::PairList => fl!("section-pair-list"),
+            AppletSection::PairInput => fl!("section-pair-input"),
+            AppletSection::AddButton => fl!("section-add-button"),
+            AppletSection::RefreshButton => fl!("section-refresh-button"),
+            AppletSection::SettingsPanel => fl!("section-settings-panel"),
+        }
+    }
+}
+
+/// Where popups open relative to the panel button.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PopupAnchor {
+    /// Follow the panel: below the button on a top panel, above it on a bottom panel.
+    #[default]
+    Auto,
+    /// Always below the button, as on a top panel.
+    TopCenter,
+    /// Always above the button, as on a bottom panel.
+    BottomCenter,
+}
+
+impl PopupAnchor {
+    pub const ALL: [PopupAnchor; 3] = [
+        PopupAnchor::Auto,
+        PopupAnchor::TopCenter,
+        PopupAnchor::BottomCenter,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            PopupAnchor::Auto => fl!("popup-anchor-auto"),
+            PopupAnchor::TopCenter => fl!("popup-anchor-top"),
+            PopupAnchor::BottomCenter => fl!("popup-anchor-bottom"),
+        }
+    }
+
+    /// The positioner anchor and gravity for a panel at `panel`, or `None` to keep the
+    /// ones libcosmic picked (vertical panels under `Auto`).
+    pub fn positioner(self, panel: PanelAnchor) -> Option<(Anchor, Gravity)> {
+        let below = (Anchor::Bottom, Gravity::Bottom);
+        let above = (Anchor::Top, Gravity::Top);
+        match (self, panel) {
+            (PopupAnchor::TopCenter, _) | (PopupAnchor::Auto, PanelAnchor::Top) => Some(below),
+            (PopupAnchor::BottomCenter, _) | (PopupAnchor::Auto, PanelAnchor::Bottom) => {
+                Some(above)
+            }
+            (PopupAnchor::Auto, _) => None,
+        }
+    }
+}
+
+/// The panel button click that opens the quick menu, the compact list of pairs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuickMenuClick {
+    /// The quick menu isn't used.
+    Off,
+    /// Middle-clicking opens it, left-clicking still opens the popup.
+    #[default]
+    Middle,
+    /// Left-clicking opens it instead of the popup, reached from its footer.
+    Left,
+}
+
+impl QuickMenuClick {
+    pub const ALL: [QuickMenuClick; 3] = [
+        QuickMenuClick::Off,
+        QuickMenuClick::Middle,
+        QuickMenuClick::Left,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            QuickMenuClick::Off => fl!("quick-menu-off"),
+            QuickMenuClick::Middle => fl!("quick-menu-middle-click"),
+            QuickMenuClick::Left => fl!("quick-menu-left-click"),
+        }
+    }
+}
+
+/// What the change of a pair is measured against.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeWindow {
+    /// The previous close, as sent by the provider.
+    #[default]
+    PreviousClose,
+    /// The quote received before the current one.
+    LastRefresh,
+    /// The quote of 24 hours ago, or the oldest one received until the applet has run
+    /// that long.
+    Rolling24h,
+    /// The quote at this time today, or yesterday until the time is reached.
+    SinceTime(NaiveTime),
+}
+
+impl ChangeWindow {
+    /// The time `SinceTime` starts with when picked.
+    pub fn default_time() -> NaiveTime {
+        NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default()
+    }
+
+    /// The labels of the window kinds, in `from_index` order.
+    pub fn labels() -> Vec<String> {
+        vec![
+            fl!("change-window-previous-close"),
+            fl!("change-window-last-refresh"),
+            fl!("change-window-rolling-24h"),
+            fl!("change-window-since-time"),
+        ]
+    }
+
+    /// The index of the window's kind in `labels()`.
+    pub fn index(self) -> usize {
+        match self {
+            ChangeWindow::PreviousClose => 0,
+            ChangeWindow::LastRefresh => 1,
+            ChangeWindow::Rolling24h => 2,
+            ChangeWindow::SinceTime(_) => 3,
+        }
+    }
+
+    /// The window at `index` of `labels()`, starting at `time` for `SinceTime`.
+    pub fn from_index(index: usize, time: NaiveTime) -> Option<Self> {
+        match index {
+            0 => Some(ChangeWindow::PreviousClose),
+            1 => Some(ChangeWindow::LastRefresh),
+            2 => Some(ChangeWindow::Rolling24h),
+            3 => Some(ChangeWindow::SinceTime(time)),
+            _ => None,
+        }
+    }
+
+    /// Shown next to a change to tell what it is measured against.
+    pub fn short_label(self) -> String {
+        match self {
+            ChangeWindow::PreviousClose => fl!("change-window-previous-close-short"),
+            ChangeWindow::LastRefresh => fl!("change-window-last-refresh-short"),
+            ChangeWindow::Rolling24h => fl!("change-window-rolling-24h-short"),
+            ChangeWindow::SinceTime(time) => fl!(
+                "change-window-since-time-short",
+                time = time.format("%H:%M").to_string()
+            ),
+        }
+    }
+}
+
+/// How popup rows show the change of a pair over its `ChangeWindow`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeFormat {
+    #[default]
+    Percent,
+    /// In pips of the pair's market convention, falling back to a percentage for pairs
+    /// without one.
+    Pips,
+}
+
+impl ChangeFormat {
+    pub const ALL: [ChangeFormat; 2] = [ChangeFormat::Percent, ChangeFormat::Pips];
+
+    pub fn label(self) -> String {
+        match self {
+            ChangeFormat::Percent => fl!("change-format-percent"),
+            ChangeFormat::Pips => fl!("change-format-pips"),
+        }
+    }
+}
+
+/// How rates are written out beyond their decimals, see `apply_rounding`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecimalGroupStyle {
+    #[default]
+    None,
+    /// Integer digits are grouped by three, see `group_thousands`.
+    Every3,
+    /// Ties are rounded to the nearest even digit at the displayed precision.
+    BankersRounding,
+}
+
+impl DecimalGroupStyle {
+    pub const ALL: [DecimalGroupStyle; 3] = [
+        DecimalGroupStyle::None,
+        DecimalGroupStyle::Every3,
+        DecimalGroupStyle::BankersRounding,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            DecimalGroupStyle::None => fl!("decimal-groups-none"),
+            DecimalGroupStyle::Every3 => fl!("decimal-groups-every-3"),
+            DecimalGroupStyle::BankersRounding => fl!("decimal-groups-bankers-rounding"),
+        }
+    }
+}
+
+/// How a popup row draws attention to a rate that just changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnimationStyle {
+    #[default]
+    None,
+    /// The rate's background lights up green when it rose, red when it fell, then fades.
+    Flash,
+    /// The new rate slides in from below when it rose, from above when it fell.
+    Slide,
+}
+
+impl AnimationStyle {
+    pub const ALL: [AnimationStyle; 3] = [
+        AnimationStyle::None,
+        AnimationStyle::Flash,
+        AnimationStyle::Slide,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            AnimationStyle::None => fl!("animation-none"),
+            AnimationStyle::Flash => fl!("animation-flash"),
+            AnimationStyle::Slide => fl!("animation-slide"),
+        }
+    }
+}
+
+/// How `export_history` writes the history.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Csv,
+    #[default]
+    JsonLines,
+    Toml,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Csv,
+        ExportFormat::JsonLines,
+        ExportFormat::Toml,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            ExportFormat::Csv => fl!("export-format-csv"),
+            ExportFormat::JsonLines => fl!("export-format-json-lines"),
+            ExportFormat::Toml => fl!("export-format-toml"),
+        }
+    }
+
+    /// The extension of files in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Toml => "toml",
+        }
+    }
+}
+
+/// The applet settings persisted through `cosmic-config`.
+#[derive(Debug, Clone, CosmicConfigEntry, PartialEq, Serialize, Deserialize)]
+#[version = 1]
+pub struct AppletConfig {
+    pub pairs: Vec<PairEntry>,
+    pub display_mode: DisplayMode,
+    /// Seconds between panel rotations through the active pairs, `0` disables cycling.
+    pub cycle_interval_secs: u64,
+    /// The index in `pairs` of the pair the rotation starts from and rests on for
+    /// `first_panel_dwell_secs` after showing every other pair.
+    pub first_panel_pair: Option<usize>,
+    /// Seconds the rotation rests on `first_panel_pair`, `0` for `cycle_interval_secs`.
+    pub first_panel_dwell_secs: u64,
+    /// The pairs the panel shows side by side instead of one at a time, up to
+    /// `MAX_PANEL_SLOTS`. Empty to rotate, as the panel also does when they don't fit.
+    pub panel_slots: Vec<Pair>,
+    /// Seconds between two fetches of the same pair.
+    pub refresh_secs: u64,
+    /// Whether animations pause and fetches slow down while the popup is open but unfocused.
+    pub pause_on_unfocus: bool,
+    /// Whether low power mode is on whatever the battery, see `crate::power`.
+    pub low_power: bool,
+    /// The battery charge in percent below which low power mode turns on while the battery
+    /// is discharging, `0` never.
+    pub low_power_below_pct: u8,
+    /// Minutes every fetch has to keep failing before a notification says so, `0` never.
+    pub failure_digest_after_mins: u64,
+    /// Seconds a fetched quote is reused instead of fetching the pair again, see `cache_ttl`.
+    pub cache_ttl_secs: u64,
+    /// How many times a failed fetch is retried before waiting for the next refresh.
+    pub api_retry_count: u32,
+    /// Seconds between two retries of a failed fetch.
+    pub api_retry_delay_secs: u64,
+    /// Requests per provider and month from which the user is warned, `0` disables the warning.
+    pub api_monthly_soft_limit: u64,
+    /// How the popup orders the watchlist, `pairs` itself always keeps the manual order.
+    pub popup_sort: SortOrder,
+    /// Whether the panel rotation follows `popup_sort` instead of the manual order.
+    pub sort_panel_rotation: bool,
+    /// The provider quotes are fetched from, one of `PROVIDERS`.
+    pub provider: String,
+    /// The provider a pair is also fetched from once `provider` has been failing for it for
+    /// `fallback_after_secs`, one of `PROVIDERS`. Empty for none.
+    pub fallback_provider: String,
+    pub fallback_after_secs: u64,
+    /// The year before which the system clock is taken to be wrong when certificates fail
+    /// to validate, see `clock_implausible`. Raise it as years go by.
+    pub clock_min_year: i32,
+    /// Whether popup rows start with the flag of the pair's base currency.
+    pub show_flags: bool,
+    /// Whether popup rows show a bar of their change next to the rate, see `change_heat`.
+    pub show_change_heat: bool,
+    /// Whether the panel label uses `font_family`, or a monospaced font while it's empty,
+    /// so its width doesn't change with the digits shown.
+    pub use_monospace_for_rate: bool,
+    /// The font of the panel label with `use_monospace_for_rate`, empty for the system's
+    /// monospaced font.
+    pub font_family: String,
+    /// Pairs shown in the popup before its list scrolls, so it fits the popup's max height.
+    pub pair_list_max_visible: u8,
+    /// Whether the popup shows the forward curve of each pair from `forward_rates_url`.
+    pub show_forward_rates: bool,
+    /// Where forward rates are fetched from, see `fetch_forward_rates`. Empty while the
+    /// user has none; AwesomeAPI itself doesn't quote forwards.
+    pub forward_rates_url: String,
+    pub alert_rules: Vec<AlertRule>,
+    /// Alert rules whose pair left the watchlist outside the applet, parked by
+    /// `reconcile_orphans` until reattached to a pair or deleted.
+    pub unassigned_alerts: Vec<AlertRule>,
+    pub popup_anchor: PopupAnchor,
+    pub quick_menu_click: QuickMenuClick,
+    /// Whether the panel button pulses after an alert fired, until the popup is opened.
+    pub visual_bell: bool,
+    /// Alert notifications shown per hour at most, across every rule, `0` for no limit.
+    pub max_alert_notifications_per_hour: u32,
+    /// The order Tab moves through the popup in, Shift+Tab going backwards. Sections left
+    /// out are skipped.
+    pub tab_key_order: Vec<AppletSection>,
+    /// Decimals of the rates shown, `None` to show them as the provider sent them.
+    pub precision: Option<usize>,
+    /// Whether pairs with a `MarketConvention` use its decimals instead of `precision`.
+    pub market_precision: bool,
+    /// Whether the panel shows every decimal of the precision, e.g. `5.4000` rather than
+    /// `5.4`. The popup always does.
+    pub panel_trailing_zeros: bool,
+    pub decimal_groups: DecimalGroupStyle,
+    pub change_format: ChangeFormat,
+    pub rate_change_animation: AnimationStyle,
+    /// The format the history tab exports in.
+    pub history_export_format: ExportFormat,
+    /// Whether a quote only counts as changed when its displayed value changes, rather
+    /// than on any change of the raw value.
+    pub change_at_display_precision: bool,
+    /// The watchlist to start with instead of `pairs` when `startup_pairs_enabled`, without
+    /// replacing the saved `pairs`. Ignored while empty.
+    pub startup_pairs: Vec<Pair>,
+    pub startup_pairs_enabled: bool,
+    /// Whether a notification announces that the applet was updated since it last ran.
+    pub startup_notification: bool,
+    /// Where fired alerts are also emailed, `None` to only notify on the desktop.
+    pub alert_email: Option<String>,
+    pub smtp_server: String,
+    pub smtp_port: u16,
+    /// The sender of alert emails, also used as SMTP login.
+    pub smtp_from: String,
+    /// The keyring entry holding the SMTP password, empty to send without logging in.
+    pub smtp_credential_key: String,
+    /// The estimated fees and spread of converting through a bank or card, in percent, for
+    /// the effective rates of `PairResponse::effective_rate`. `0` hides them.
+    pub markup_pct: f64,
+    /// Whether the calculator converts at the effective rate rather than the quoted one.
+    pub calculator_uses_markup: bool,
+    /// Whether the panel shows the effective rate of buying the base currency rather than
+    /// the quoted one.
+    pub panel_uses_markup: bool,
+    /// Whether `SetRate` of the D-Bus interface of `crate::dbus` is accepted, letting any
+    /// process of the session set rates. For testing and scripting only.
+    pub allow_external_updates: bool,
+    /// Whether the D-Bus interface of `crate::dbus` accepts changes to the watchlist, for
+    /// setup scripts.
+    pub allow_external_watchlist: bool,
+}
+
+impl AppletConfig {
+    /// The provider `pair` is fetched from: its own override, or `provider`.
+    pub fn provider_of(&self, pair: &Pair) -> &str {
+        self.pairs
+            .iter()
+            .find(|entry| &entry.pair == pair)
+            .and_then(|entry| entry.provider.as_deref())
+            .unwrap_or(&self.provider)
+    }
+
+    /// Collapses the entries of the same pair, which a hand-edited config can have as
+    /// `usdbrl` next to `USDBRL`, into the first of them, and the alert rules, startup pairs
+    /// and panel slots made the same by it. Returns whether anything was collapsed.
+    pub fn dedup_pairs(&mut self) -> bool {
+        let mut collapsed = false;
+        let mut index = 0;
+        while index < self.pairs.len() {
+            let first = self.pairs[..index]
+                .iter()
+                .position(|entry| entry.pair == self.pairs[index].pair);
+            let Some(first) = first else {
+                index += 1;
+                continue;
+            };
+            self.pairs.remove(index);
+            self.first_panel_pair = match self.first_panel_pair {
+                Some(pinned) if pinned == index => Some(first),
+                Some(pinned) if pinned > index => Some(pinned - 1),
+                other => other,
+            };
+            collapsed = true;
+        }
+        let rules = dedup_in_order(&mut self.alert_rules);
+        let startup = dedup_in_order(&mut self.startup_pairs);
+        let slots = dedup_in_order(&mut self.panel_slots);
+        collapsed || rules || startup || slots
+    }
+
+    /// Parks the alert rules of pairs the watchlist no longer has in `unassigned_alerts`,
+    /// and drops what merely pointed at such pairs: their panel slots, and a pinned pair
+    /// past the end of the watchlist. Editing the config outside the applet leaves these
+    /// behind. Returns how many rules were parked.
+    pub fn reconcile_orphans(&mut self) -> usize {
+        let watched = |pair: &Pair| self.pairs.iter().any(|entry| &entry.pair == pair);
+        let (kept, orphaned): (Vec<AlertRule>, Vec<AlertRule>) =
+            std::mem::take(&mut self.alert_rules)
+                .into_iter()
+                .partition(|rule| watched(&rule.pair));
+        self.alert_rules = kept;
+        let parked = orphaned.len();
+        for rule in orphaned {
+            if !self.unassigned_alerts.contains(&rule) {
+                self.unassigned_alerts.push(rule);
+            }
+        }
+        self.panel_slots.retain(|pair| watched(pair));
+        if self
+            .first_panel_pair
+            .is_some_and(|pinned| pinned >= self.pairs.len())
+        {
+            self.first_panel_pair = None;
+        }
+        parked
+    }
+
+    /// Whether the retries of a failed fetch could run into the next scheduled fetch.
+    pub fn retries_overlap_refresh(&self) -> bool {
+        u64::from(self.api_retry_count).saturating_mul(self.api_retry_delay_secs)
+            >= self.refresh_secs
+    }
+
+    /// How long a fetched quote is reused. Capped at half of `refresh_secs`, so scheduled
+    /// fetches always find the quote of the previous one expired.
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.cache_ttl_secs.min(self.refresh_secs / 2))
+    }
+
+    /// The markup of `pair` in percent, its own or else the global one.
+    pub fn markup_for(&self, pair: &Pair) -> f64 {
+        self.pairs
+            .iter()
+            .find(|entry| &entry.pair == pair)
+            .and_then(|entry| entry.markup_pct)
+            .unwrap_or(self.markup_pct)
+    }
+
+    /// How alerts are emailed, `None` unless both a recipient and a server are set.
+    pub fn smtp(&self) -> Option<SmtpConfig> {
+        let to = self.alert_email.clone()?;
+        if self.smtp_server.is_empty() {
+            return None;
+        }
+        Some(SmtpConfig {
+            to,
+            server: self.smtp_server.clone(),
+            port: self.smtp_port,
+            from: self.smtp_from.clone(),
+            credential_key: self.smtp_credential_key.clone(),
+        })
+    }
+}
+
+impl Default for AppletConfig {
+    fn default() -> Self {
+        AppletConfig {
+            pairs: vec![PairEntry::new(Pair::parse("USDBRL").unwrap())],
+            display_mode: DisplayMode::Auto,
+            cycle_interval_secs: 0,
+            first_panel_pair: None,
+            first_panel_dwell_secs: 60,
+            panel_slots: Vec::new(),
+            refresh_secs: 600, // 10 minutes
+            pause_on_unfocus: false,
+            low_power: false,
+            low_power_below_pct: 0,
+            failure_digest_after_mins: 60,
+            cache_ttl_secs: 300,
+            api_retry_count: 3,
+            api_retry_delay_secs: 30,
+            api_monthly_soft_limit: 0,
+            popup_sort: SortOrder::Manual,
+            sort_panel_rotation: false,
+            provider: AWESOMEAPI.to_string(),
+            fallback_provider: String::new(),
+            fallback_after_secs: 600,
+            clock_min_year: 2024,
+            show_flags: false,
+            show_change_heat: false,
+            use_monospace_for_rate: false,
+            font_family: String::new(),
+            pair_list_max_visible: 8,
+            show_forward_rates: false,
+            forward_rates_url: String::new(),
+            alert_rules: Vec::new(),
+            unassigned_alerts: Vec::new(),
+            popup_anchor: PopupAnchor::Auto,
+            quick_menu_click: QuickMenuClick::Middle,
+            visual_bell: false,
+            max_alert_notifications_per_hour: 20,
+            tab_key_order: AppletSection::ALL.to_vec(),
+            precision: None,
+            market_precision: false,
+            panel_trailing_zeros: true,
+            decimal_groups: DecimalGroupStyle::None,
+            change_format: ChangeFormat::Percent,
+            rate_change_animation: AnimationStyle::None,
+            history_export_format: ExportFormat::JsonLines,
+            change_at_display_precision: false,
+            startup_pairs: Vec::new(),
+            startup_pairs_enabled: false,
+            startup_notification: true,
+            alert_email: None,
+            smtp_server: String::new(),
+            smtp_port: 587,
+            smtp_from: String::new(),
+            smtp_credential_key: String::new(),
+            markup_pct: 0.0,
+            calculator_uses_markup: false,
+            panel_uses_markup: false,
+            allow_external_updates: false,
+            allow_external_watchlist: false,
+        }
+    }
+}
+
+/// Requests made to a single provider in the current day and month.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderUsage {
+    /// The day the counters were last updated.
+    pub day: NaiveDate,
+    pub today: u64,
+    pub this_month: u64,
+}
+
+impl ProviderUsage {
+    /// The counters as of `today`, zeroing those whose day or month has passed.
+    pub fn as_of(&self, today: NaiveDate) -> ProviderUsage {
+        let same_month = (self.day.year(), self.day.month()) == (today.year(), today.month());
+        ProviderUsage {
+            day: today,
+            today: if self.day == today { self.today } else { 0 },
+            this_month: if same_month { self.this_month } else { 0 },
+        }
+    }
+
+    /// Whether the requests this month come close to `limit`, the user's soft limit, `0`
+    /// for none.
+    pub fn near_limit(&self, limit: u64) -> bool {
+        limit > 0 && self.this_month as f64 >= limit as f64 * API_SOFT_LIMIT_WARNING_RATIO
+    }
+}
+
+/// Requests made to each provider, keyed by provider name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiUsage {
+    pub providers: BTreeMap<String, ProviderUsage>,
+}
+
+impl ApiUsage {
+    /// Counts one request to `provider` made on `today`.
+    pub fn record(&mut self, provider: &str, today: NaiveDate) {
+        let usage = self
+            .providers
+            .entry(provider.to_string())
+            .or_insert(ProviderUsage {
+                day: today,
+                today: 0,
+                this_month: 0,
+            });
+        *usage = usage.as_of(today);
+        usage.today += 1;
+        usage.this_month += 1;
+    }
+}
+
+/// Applet state that is persisted but not user-editable.
+#[derive(Debug, Clone, Default, CosmicConfigEntry, PartialEq, Serialize, Deserialize)]
+#[version = 1]
+pub struct AppletState {
+    pub api_usage: ApiUsage,
+    /// The version of the applet that last ran, empty before the first run.
+    pub last_seen_version: String,
+    /// The quotes the user flagged as looking wrong, oldest first.
+    pub flagged_quotes: Vec<FlaggedQuote>,
+}
+
+/// A quote the user flagged as looking wrong, with what it takes to tell why later: it is
+/// only ever kept locally, and shared by the user through the diagnostic report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlaggedQuote {
+    pub pair: Pair,
+    pub flagged_at: DateTime<Local>,
+    /// The provider configured, and the one the quote came from if it was another.
+    pub provider: String,
+    pub quoted_by: Option<String>,
+    /// The quote shown, `None` if the pair had none yet.
+    pub quote: Option<PairResponse>,
+    /// How long before being flagged the quote was fetched.
+    pub fetched_secs_ago: Option<u64>,
+    /// The start of the last response that quoted the pair, see `PairState::raw_response`.
+    pub response_excerpt: Option<String>,
+    /// The settings in effect, as JSON with their secrets redacted.
+    pub settings: String,
+}
+
+/// Drops the items of `items` equal to one before them. Returns whether it dropped any.
+fn dedup_in_order<T: PartialEq>(items: &mut Vec<T>) -> bool {
+    let len = items.len();
+    let mut kept: Vec<T> = Vec::with_capacity(len);
+    for item in items.drain(..) {
+        if !kept.contains(&item) {
+            kept.push(item);
+        }
+    }
+    *items = kept;
+    items.len() < len
+}
+
+/// The index in `to` of the pair at `index` in `from`, `None` if `to` doesn't have it.
+fn repin(index: Option<usize>, from: &[PairEntry], to: &[PairEntry]) -> Option<usize> {
+    let pair = &from.get(index?)?.pair;
+    to.iter().position(|entry| entry.pair == *pair)
+}
+
+/// Where the state is kept under the `--config-path` directory, apart from the settings.
+pub const CUSTOM_STATE_DIR: &str = "state";
+
+/// The directory `cosmic-config` keeps the applet's settings in: under `custom`, as given
+/// with `--config-path`, or else under `$XDG_CONFIG_HOME/cosmic`.
+pub fn settings_dir(custom: Option<&Path>) -> Option<PathBuf> {
+    let base = match custom {
+        Some(custom) => custom.to_path_buf(),
+        None => std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?
+            .join("cosmic"),
+    };
+    Some(base.join(<crate::app::YourApp as cosmic::Application>::APP_ID))
+}
+
+/// The directory `cosmic-config` keeps the applet's state in: under the `CUSTOM_STATE_DIR`
+/// of `custom`, so a run with `--config-path` leaves the real state alone too, or else under
+/// `$XDG_STATE_HOME/cosmic`.
+pub fn state_dir(custom: Option<&Path>) -> Option<PathBuf> {
+    let base = match custom {
+        Some(custom) => custom.join(CUSTOM_STATE_DIR),
+        None => std::env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+            })?
+            .join("cosmic"),
+    };
+    Some(base.join(<crate::app::YourApp as cosmic::Application>::APP_ID))
+}
+
+/// Startup overrides passed on the command line, e.g. by tests or panel presets.
+///
+/// Settings are resolved as flags > config file > defaults: every field that is set here
+/// replaces the value loaded from the config, which itself replaces `AppletConfig::default()`.
+#[derive(Debug, Clone, Default)]
+pub struct Flags {
+    /// `--pair CODE`, repeatable: replaces the watchlist.
+    pub pairs: Option<Vec<Pair>>,
+    /// `--provider NAME`
+    pub provider: Option<String>,
+    /// `--refresh-secs SECS`
+    pub refresh_secs: Option<u64>,
+    /// `--config-path DIR`: reads and writes the config there instead of the user's config
+    /// directory, so the real config is never touched.
+    pub config_path: Option<PathBuf>,
+    /// `--test-mode`: shows a slider in the debug tab that simulates rate changes.
+    pub test_mode: bool,
+    /// `--clock-offset-secs SECS`: shifts the applet's wall clock by that many seconds,
+    /// negative for the past, see `crate::clock::OffsetClock`.
+    pub clock_offset_secs: Option<i64>,
+    /// `--query CODE`: prints the rate of the pair from the running applet and exits, see
+    /// `crate::query`.
+    pub query: Option<Pair>,
+    /// `--format display|raw|json`, how `--query` prints the rate.
+    pub query_format: QueryFormat,
+}
+
+impl Flags {
+    /// Parses the command line arguments, without the program name. Invalid arguments are
+    /// reported and skipped so the applet still starts.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut flags = Flags::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().unwrap_or_default();
+            match arg.as_str() {
+                "--pair" => match Pair::parse(&value()) {
+                    Some(pair) => {
+                        let pairs = flags.pairs.get_or_insert_with(Vec::new);
+                        if !pairs.contains(&pair) {
+                            pairs.push(pair);
+                        }
+                    }
+                    None => tracing::warn!("Ignoring invalid --pair"),
+                },
+                "--provider" => {
+                    let name = value();
+                    match PROVIDERS.iter().find(|p| p.eq_ignore_ascii_case(&name)) {
+                        Some(provider) => flags.provider = Some(provider.to_string()),
+                        None => tracing::warn!("Ignoring unknown --provider {name}"),
+                    }
+                }
+                "--refresh-secs" => match value().parse() {
+                    Ok(secs) if secs > 0 => flags.refresh_secs = Some(secs),
+                    _ => tracing::warn!("Ignoring invalid --refresh-secs"),
+                },
+                "--config-path" => flags.config_path = Some(PathBuf::from(value())),
+                "--test-mode" => flags.test_mode = true,
+                "--clock-offset-secs" => match value().parse() {
+                    Ok(secs) => flags.clock_offset_secs = Some(secs),
+                    _ => tracing::warn!("Ignoring invalid --clock-offset-secs"),
+                },
+                "--query" => match Pair::parse(&value()) {
+                    Some(pair) => flags.query = Some(pair),
+                    None => tracing::warn!("Ignoring invalid --query"),
+                },
+                "--format" => {
+                    let name = value();
+                    match QueryFormat::from_name(&name) {
+                        Some(format) => flags.query_format = format,
+                        None => tracing::warn!("Ignoring unknown --format {name}"),
+                    }
+                }
+                _ => tracing::warn!("Ignoring unknown argument {arg}"),
+            }
+        }
+        flags
+    }
+
+    /// The clock the applet reads the time from: the system's, shifted by
+    /// `clock_offset_secs` if given.
+    pub fn clock(&self) -> SharedClock {
+        match self.clock_offset_secs {
+            Some(secs) => SharedClock::new(OffsetClock {
+                offset: chrono::Duration::seconds(secs),
+            }),
+            None => SharedClock::new(SystemClock),
+        }
+    }
+
+    /// Applies the overrides on top of `config`, keeping the values they replace in
+    /// `overrides` so they are what gets saved.
+    pub fn apply(&self, config: &mut AppletConfig, overrides: &mut SessionOverrides) {
+        if let Some(pairs) = &self.pairs {
+            overrides.replace_pairs(config, pairs.iter().cloned().map(PairEntry::new).collect());
+        }
+        if let Some(provider) = &self.provider {
+            overrides.provider = Some(Overridden {
+                persisted: std::mem::replace(&mut config.provider, provider.clone()),
+                session: provider.clone(),
+            });
+        }
+        if let Some(secs) = self.refresh_secs {
+            overrides.refresh_secs = Some(Overridden {
+                persisted: std::mem::replace(&mut config.refresh_secs, secs),
+                session: secs,
+            });
+        }
+    }
+}
+
+/// The settings replaced for this session only, by the startup list or by `Flags`, with the
+/// values the config file has for them. `persisted` puts those back into what is saved, so
+/// nothing replaced for a session ever reaches the config file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionOverrides {
+    /// The saved watchlist. Edits to the session's list aren't saved while it is replaced.
+    pub pairs: Option<Vec<PairEntry>>,
+    /// The saved `first_panel_pair`, an index into the saved watchlist, while `pairs` is
+    /// replaced. The session's pin is into the session's list.
+    pub first_panel_pair: Option<Overridden<Option<usize>>>,
+    pub provider: Option<Overridden<String>>,
+    pub refresh_secs: Option<Overridden<u64>>,
+}
+
+/// A setting replaced for this session: the value the config file has, and the one that
+/// replaced it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overridden<T> {
+    pub persisted: T,
+    pub session: T,
+}
+
+impl<T: Clone + PartialEq> Overridden<T> {
+    /// What to save for the setting now at `current`: the persisted value while the
+    /// override stands, `current` once the user changed the setting in the popup.
+    fn saved(&self, current: &T) -> T {
+        if *current == self.session {
+            self.persisted.clone()
+        } else {
+            current.clone()
+        }
+    }
+}
+
+impl SessionOverrides {
+    /// Shows `pairs` instead of the watchlist of `config` for this session.
+    pub fn replace_pairs(&mut self, config: &mut AppletConfig, pairs: Vec<PairEntry>) {
+        let previous = std::mem::replace(&mut config.pairs, pairs);
+        let pin = repin(config.first_panel_pair, &previous, &config.pairs);
+        let previous_pin = std::mem::replace(&mut config.first_panel_pair, pin);
+        // Replaced by the startup list then by `--pair`, the file still has the first.
+        match &mut self.first_panel_pair {
+            Some(overridden) => overridden.session = pin,
+            None => {
+                self.first_panel_pair = Some(Overridden {
+                    persisted: previous_pin,
+                    session: pin,
+                })
+            }
+        }
+        self.pairs.get_or_insert(previous);
+    }
+
+    /// `config` as it is to be saved, with the persisted value of every replaced setting.
+    pub fn persisted(&self, config: &AppletConfig) -> AppletConfig {
+        let mut saved = config.clone();
+        if let Some(pairs) = &self.pairs {
+            saved.pairs = pairs.clone();
+        }
+        if let (Some(pairs), Some(pin)) = (&self.pairs, &self.first_panel_pair) {
+            saved.first_panel_pair = if config.first_panel_pair == pin.session {
+                pin.persisted
+            } else {
+                repin(config.first_panel_pair, &config.pairs, pairs)
+            };
+        }
+        if let Some(provider) = &self.provider {
+            saved.provider = provider.saved(&config.provider);
+        }
+        if let Some(secs) = &self.refresh_secs {
+            saved.refresh_secs = secs.saved(&config.refresh_secs);
+        }
+        saved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AlertCondition::{Above, Below, ChangePercent, WorthAbove, WorthBelow};
+
+    fn flags(args: &[&str]) -> Flags {
+        Flags::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn pairs(codes: &[&str]) -> Vec<PairEntry> {
+        codes
+            .iter()
+            .map(|code| PairEntry::new(Pair::parse(code).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn overrides_apply_to_the_session() {
+        let mut config = AppletConfig::default();
+        let mut overrides = SessionOverrides::default();
+        flags(&[
+            "--pair",
+            "EURUSD",
+            "--provider",
+            AWESOMEAPI,
+            "--refresh-secs",
+            "30",
+        ])
+        .apply(&mut config, &mut overrides);
+        assert_eq!(config.pairs, pairs(&["EURUSD"]));
+        assert_eq!(config.provider, AWESOMEAPI);
+        assert_eq!(config.refresh_secs, 30);
+    }
+
+    #[test]
+    fn overrides_do_not_persist() {
+        let saved = AppletConfig {
+            provider: "Elsewhere".to_string(),
+            ..AppletConfig::default()
+        };
+        let mut config = saved.clone();
+        let mut overrides = SessionOverrides::default();
+        flags(&[
+            "--pair",
+            "EURUSD",
+            "--provider",
+            AWESOMEAPI,
+            "--refresh-secs",
+            "30",
+        ])
+        .apply(&mut config, &mut overrides);
+        assert_eq!(overrides.persisted(&config), saved);
+
+        // Other settings changed in the session are saved.
+        config.pause_on_unfocus = !saved.pause_on_unfocus;
+        assert_eq!(
+            overrides.persisted(&config).pause_on_unfocus,
+            config.pause_on_unfocus
+        );
+    }
+
+    #[test]
+    fn settings_changed_from_the_override_persist() {
+        let mut config = AppletConfig::default();
+        let mut overrides = SessionOverrides::default();
+        flags(&["--refresh-secs", "30"]).apply(&mut config, &mut overrides);
+        config.refresh_secs = 120;
+        assert_eq!(overrides.persisted(&config).refresh_secs, 120);
+    }
+
+    #[test]
+    fn startup_list_then_pair_flag_keeps_the_saved_watchlist() {
+        let saved = AppletConfig::default();
+        let mut config = saved.clone();
+        let mut overrides = SessionOverrides::default();
+        overrides.replace_pairs(&mut config, pairs(&["BTCUSD"]));
+        flags(&["--pair", "EURUSD"]).apply(&mut config, &mut overrides);
+        assert_eq!(config.pairs, pairs(&["EURUSD"]));
+        assert_eq!(overrides.persisted(&config).pairs, saved.pairs);
+    }
+
+    #[test]
+    fn first_panel_pair_follows_its_pair_between_lists() {
+        let saved = AppletConfig {
+            pairs: pairs(&["USDBRL", "EURUSD", "BTCUSD"]),
+            first_panel_pair: Some(1),
+            ..AppletConfig::default()
+        };
+        let mut config = saved.clone();
+        let mut overrides = SessionOverrides::default();
+        overrides.replace_pairs(&mut config, pairs(&["BTCUSD", "EURUSD"]));
+        assert_eq!(config.first_panel_pair, Some(1));
+        assert_eq!(overrides.persisted(&config).first_panel_pair, Some(1));
+
+        // Pinned in the session, the pair is pinned where it is in the saved list.
+        config.first_panel_pair = Some(0);
+        assert_eq!(overrides.persisted(&config).first_panel_pair, Some(2));
+    }
+
+    #[test]
+    fn first_panel_pair_missing_from_the_session_stays_pinned() {
+        let saved = AppletConfig {
+            pairs: pairs(&["USDBRL", "EURUSD"]),
+            first_panel_pair: Some(0),
+            ..AppletConfig::default()
+        };
+        let mut config = saved.clone();
+        let mut overrides = SessionOverrides::default();
+        overrides.replace_pairs(&mut config, pairs(&["BTCUSD"]));
+        assert_eq!(config.first_panel_pair, None);
+        assert_eq!(overrides.persisted(&config), saved);
+
+        // A pair the saved list doesn't have can't be pinned in it.
+        config.first_panel_pair = Some(0);
+        assert_eq!(overrides.persisted(&config).first_panel_pair, None);
+    }
+
+    #[test]
+    fn without_overrides_everything_persists() {
+        let mut config = AppletConfig::default();
+        let mut overrides = SessionOverrides::default();
+        flags(&[]).apply(&mut config, &mut overrides);
+        config.refresh_secs = 120;
+        assert_eq!(overrides.persisted(&config), config);
+    }
+
+    #[test]
+    fn custom_path_is_isolated() {
+        let custom = Path::new("/tmp/exchange-rate-test");
+        let settings = settings_dir(Some(custom)).unwrap();
+        let state = state_dir(Some(custom)).unwrap();
+        assert!(settings.starts_with(custom));
+        assert!(state.starts_with(custom));
+        assert!(!state.starts_with(&settings) && !settings.starts_with(&state));
+        if let (Some(real_settings), Some(real_state)) = (settings_dir(None), state_dir(None)) {
+            assert!(!real_settings.starts_with(custom));
+            assert!(!real_state.starts_with(custom));
+        }
+    }
+
+    #[test]
+    fn above_and_below_overlap_between_their_values() {
+        assert_eq!(
+            rule_conflicts(Below(5.5), &[Above(5.4)]),
+            [RuleConflict::Overlap {
+                low: 5.4,
+                high: 5.5
+            }]
+        );
+        // The same region whichever of the two is the rule being saved.
+        assert_eq!(
+            rule_conflicts(Above(5.4), &[Below(5.5)]),
+            [RuleConflict::Overlap {
+                low: 5.4,
+                high: 5.5
+            }]
+        );
+    }
+
+    #[test]
+    fn disjoint_and_touching_rules_do_not_overlap() {
+        assert!(rule_conflicts(Above(5.5), &[Below(5.4)]).is_empty());
+        assert!(rule_conflicts(Below(5.4), &[Above(5.5)]).is_empty());
+        // At exactly 5.4 neither fires, so there is a rate where both are quiet.
+        assert!(rule_conflicts(Above(5.4), &[Below(5.4)]).is_empty());
+        // Rules on the same side nest, one firing before the other is intended.
+        assert!(rule_conflicts(Above(5.4), &[Above(5.5)]).is_empty());
+        assert!(rule_conflicts(Below(5.4), &[Below(5.5)]).is_empty());
+    }
+
+    #[test]
+    fn each_overlapping_rule_is_reported() {
+        assert_eq!(
+            rule_conflicts(Above(5.0), &[Below(5.5), Below(4.0), Below(6.0)]),
+            [
+                RuleConflict::Overlap {
+                    low: 5.0,
+                    high: 5.5
+                },
+                RuleConflict::Overlap {
+                    low: 5.0,
+                    high: 6.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicates() {
+        assert_eq!(
+            rule_conflicts(Above(5.4), &[Above(5.4)]),
+            [RuleConflict::Duplicate]
+        );
+        assert_eq!(
+            rule_conflicts(ChangePercent(2.0), &[ChangePercent(2.0)]),
+            [RuleConflict::Duplicate]
+        );
+        assert!(rule_conflicts(ChangePercent(2.0), &[ChangePercent(3.0)]).is_empty());
+    }
+
+    #[test]
+    fn always_and_never_true_rules() {
+        assert_eq!(rule_conflicts(Above(-1.0), &[]), [RuleConflict::AlwaysTrue]);
+        assert_eq!(rule_conflicts(Below(0.0), &[]), [RuleConflict::NeverTrue]);
+        assert_eq!(rule_conflicts(Below(-2.0), &[]), [RuleConflict::NeverTrue]);
+        assert_eq!(
+            rule_conflicts(ChangePercent(0.0), &[]),
+            [RuleConflict::AlwaysTrue]
+        );
+        assert_eq!(
+            rule_conflicts(ChangePercent(-1.0), &[]),
+            [RuleConflict::AlwaysTrue]
+        );
+        assert!(rule_conflicts(Above(0.0), &[]).is_empty());
+        assert!(rule_conflicts(Below(0.01), &[]).is_empty());
+        assert!(rule_conflicts(ChangePercent(0.5), &[]).is_empty());
+    }
+
+    #[test]
+    fn a_degenerate_rule_is_flagged_along_with_its_overlaps() {
+        // Always true itself, and overlapping every `Below` there is.
+        assert_eq!(
+            rule_conflicts(Above(-1.0), &[Below(5.0)]),
+            [
+                RuleConflict::AlwaysTrue,
+                RuleConflict::Overlap {
+                    low: -1.0,
+                    high: 5.0
+                },
+            ]
+        );
+        // Never true, so it overlaps nothing.
+        assert_eq!(
+            rule_conflicts(Below(0.0), &[Above(5.0)]),
+            [RuleConflict::NeverTrue]
+        );
+    }
+
+    #[test]
+    fn amount_rules_are_compared_by_their_rate() {
+        // 1000 worth above 5400 is a rate above 5.4.
+        let worth_above = WorthAbove {
+            amount: 1000.0,
+            target: 5400.0,
+        };
+        assert_eq!(
+            rule_conflicts(worth_above, &[Below(5.5)]),
+            [RuleConflict::Overlap {
+                low: 5.4,
+                high: 5.5
+            }]
+        );
+        assert_eq!(
+            rule_conflicts(Above(5.4), &[worth_above]),
+            [RuleConflict::Duplicate]
+        );
+        let worth_below = WorthBelow {
+            amount: 100.0,
+            target: 530.0,
+        };
+        assert!(rule_conflicts(worth_above, &[worth_below]).is_empty());
+        assert_eq!(
+            rule_conflicts(
+                worth_below,
+                &[WorthAbove {
+                    amount: 10.0,
+                    target: 50.0
+                }]
+            ),
+            [RuleConflict::Overlap {
+                low: 5.0,
+                high: 5.3
+            }]
+        );
+    }
+
+    #[test]
+    fn percentage_next_to_absolute_rules_warns_softly_once() {
+        assert_eq!(
+            rule_conflicts(ChangePercent(2.0), &[Above(5.4)]),
+            [RuleConflict::MayOverlap]
+        );
+        assert_eq!(
+            rule_conflicts(Below(5.4), &[ChangePercent(2.0)]),
+            [RuleConflict::MayOverlap]
+        );
+        assert_eq!(
+            rule_conflicts(
+                ChangePercent(2.0),
+                &[
+                    Above(5.4),
+                    Below(5.0),
+                    WorthAbove {
+                        amount: 2.0,
+                        target: 11.0
+                    }
+                ]
+            ),
+            [RuleConflict::MayOverlap]
+        );
+        // A definite overlap is still reported next to the soft warning.
+        assert_eq!(
+            rule_conflicts(Above(5.0), &[ChangePercent(1.0), Below(5.5)]),
+            [
+                RuleConflict::MayOverlap,
+                RuleConflict::Overlap {
+                    low: 5.0,
+                    high: 5.5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn pair_codes_are_read_in_any_case() {
+        let usdbrl = Pair::parse("USDBRL").unwrap();
+        for code in ["usdbrl", "UsdBrl", " usdBRL ", "usd-brl", "USD-brl"] {
+            assert_eq!(Pair::parse(code).as_ref(), Some(&usdbrl), "{code}");
+        }
+        assert_eq!(Pair::new("usd", "Brl").as_ref(), Some(&usdbrl));
+        assert_eq!(usdbrl.base(), "USD");
+        assert_eq!(usdbrl.quote(), "BRL");
+        assert_eq!(
+            Pair::parse("usdt-brl").map(String::from),
+            Some("USDT-BRL".to_string())
+        );
+        for invalid in ["usdbr", "u$dbrl", "usd-", "usdbrlx", ""] {
+            assert_eq!(Pair::parse(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn hand_edited_codes_deserialize_uppercase() {
+        let pair: Pair = serde_json::from_str("\"usdbrl\"").unwrap();
+        assert_eq!(pair, Pair::parse("USDBRL").unwrap());
+        assert_eq!(String::from(pair), "USDBRL");
+        assert!(serde_json::from_str::<Pair>("\"usd\"").is_err());
+    }
+
+    #[test]
+    fn entries_differing_by_case_collapse_with_their_alerts() {
+        let mut config = AppletConfig {
+            pairs: vec![
+                PairEntry::new(serde_json::from_str("\"usdbrl\"").unwrap()),
+                PairEntry::new(Pair::parse("EURUSD").unwrap()),
+                PairEntry::new(serde_json::from_str("\"USDBRL\"").unwrap()),
+            ],
+            first_panel_pair: Some(2),
+            alert_rules: ["usdbrl", "USDBRL", "UsdBrl"]
+                .iter()
+                .map(|code| AlertRule {
+                    pair: Pair::parse(code).unwrap(),
+                    condition: AlertCondition::Above(5.5),
+                })
+                .collect(),
+            startup_pairs: vec![
+                Pair::parse("eurusd").unwrap(),
+                Pair::parse("EURUSD").unwrap(),
+            ],
+            ..AppletConfig::default()
+        };
+        assert!(config.dedup_pairs());
+        assert_eq!(config.pairs, pairs(&["USDBRL", "EURUSD"]));
+        // The pin on the repeated entry moves to the one kept.
+        assert_eq!(config.first_panel_pair, Some(0));
+        assert_eq!(config.alert_rules.len(), 1);
+        assert_eq!(config.startup_pairs, [Pair::parse("EURUSD").unwrap()]);
+        assert!(!config.dedup_pairs());
+    }
+
+    #[test]
+    fn entries_are_fetched_from_their_own_provider_or_the_global_one() {
+        let mut config = AppletConfig {
+            pairs: pairs(&["USDBRL", "EURBRL", "BTCBRL"]),
+            ..AppletConfig::default()
+        };
+        config.pairs[1].provider = Some(AWESOMEAPI.to_string());
+        config.pairs[2].provider = Some("CoinGecko".to_string());
+        let [usdbrl, eurbrl, btcbrl] =
+            ["USDBRL", "EURBRL", "BTCBRL"].map(|code| Pair::parse(code).unwrap());
+        assert_eq!(config.provider_of(&usdbrl), config.provider);
+        assert_eq!(config.provider_of(&eurbrl), AWESOMEAPI);
+        assert_eq!(config.provider_of(&btcbrl), "CoinGecko");
+        // Only the override naming a provider the applet doesn't have is flagged.
+        assert_eq!(
+            validate_config(&config)
+                .into_iter()
+                .filter(|error| matches!(
+                    error,
+                    ConfigValidationError::UnsupportedPairProvider { .. }
+                ))
+                .collect::<Vec<_>>(),
+            [ConfigValidationError::UnsupportedPairProvider {
+                pair: btcbrl,
+                provider: "CoinGecko".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn panel_slots_differing_by_case_collapse_in_the_same_pass() {
+        let mut config = AppletConfig {
+            pairs: pairs(&["USDBRL", "EURUSD"]),
+            panel_slots: ["usdbrl", "EURUSD", "USDBRL", "eurusd"]
+                .iter()
+                .map(|code| serde_json::from_str(&format!("\"{code}\"")).unwrap())
+                .collect(),
+            ..AppletConfig::default()
+        };
+        assert!(config.dedup_pairs());
+        assert_eq!(
+            config.panel_slots,
+            [
+                Pair::parse("USDBRL").unwrap(),
+                Pair::parse("EURUSD").unwrap()
+            ]
+        );
+        assert!(!config.dedup_pairs());
+        // And stay collapsed once saved and read back.
+        let saved = serde_json::to_string(&config.panel_slots).unwrap();
+        assert_eq!(saved, r#"["USDBRL","EURUSD"]"#);
+    }
+
+    #[test]
+    fn items_are_deduplicated_keeping_the_first_of_each() {
+        let mut items = vec![3, 1, 3, 2, 1];
+        assert!(dedup_in_order(&mut items));
+        assert_eq!(items, [3, 1, 2]);
+        assert!(!dedup_in_order(&mut items));
+        let mut empty: Vec<u8> = Vec::new();
+        assert!(!dedup_in_order(&mut empty));
+    }
+
+    #[test]
+    fn command_line_pairs_are_read_in_any_case() {
+        let upper = flags(&["--query", "BTCUSD", "--pair", "EURUSD"]);
+        let lower = flags(&["--query", "btcusd", "--pair", "eurusd", "--pair", "EurUsd"]);
+        assert_eq!(lower.query, Some(Pair::parse("BTCUSD").unwrap()));
+        assert_eq!(lower.query, upper.query);
+        assert_eq!(lower.pairs, upper.pairs);
+        assert_eq!(flags(&["--query", "btc"]).query, None);
+    }
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn usage(on: NaiveDate, today: u64, this_month: u64) -> ProviderUsage {
+        ProviderUsage {
+            day: on,
+            today,
+            this_month,
+        }
+    }
+
+    #[test]
+    fn usage_rolls_over_by_day_and_month() {
+        let counters = usage(day(2024, 3, 15), 4, 40);
+        assert_eq!(counters.as_of(day(2024, 3, 15)), counters);
+        assert_eq!(
+            counters.as_of(day(2024, 3, 16)),
+            usage(day(2024, 3, 16), 0, 40)
+        );
+        assert_eq!(
+            counters.as_of(day(2024, 4, 1)),
+            usage(day(2024, 4, 1), 0, 0)
+        );
+        // The same month of another year is another month.
+        assert_eq!(
+            counters.as_of(day(2025, 3, 15)),
+            usage(day(2025, 3, 15), 0, 0)
+        );
+    }
+
+    #[test]
+    fn requests_are_counted_in_the_month_they_are_made() {
+        let mut counters = ApiUsage::default();
+        counters.record(AWESOMEAPI, day(2024, 1, 31));
+        counters.record(AWESOMEAPI, day(2024, 1, 31));
+        assert_eq!(
+            counters.providers[AWESOMEAPI],
+            usage(day(2024, 1, 31), 2, 2)
+        );
+        counters.record(AWESOMEAPI, day(2024, 2, 1));
+        assert_eq!(counters.providers[AWESOMEAPI], usage(day(2024, 2, 1), 1, 1));
+        counters.record("other", day(2024, 2, 1));
+        assert_eq!(counters.providers.len(), 2);
+    }
+
+    #[test]
+    fn usage_survives_a_restart() {
+        let mut state = AppletState::default();
+        state.api_usage.record(AWESOMEAPI, day(2024, 5, 10));
+        state.api_usage.record(AWESOMEAPI, day(2024, 5, 11));
+        let saved = serde_json::to_string(&state).unwrap();
+        let mut restored: AppletState = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored, state);
+        // Counting goes on from the saved counters.
+        restored.api_usage.record(AWESOMEAPI, day(2024, 5, 11));
+        assert_eq!(
+            restored.api_usage.providers[AWESOMEAPI],
+            usage(day(2024, 5, 11), 2, 3)
+        );
+    }
+
+    #[test]
+    fn usage_warns_close_to_the_soft_limit() {
+        let on = day(2024, 6, 1);
+        assert!(!usage(on, 0, 10_000).near_limit(0));
+        assert!(!usage(on, 0, 899).near_limit(1000));
+        assert!(usage(on, 0, 900).near_limit(1000));
+        assert!(usage(on, 0, 1200).near_limit(1000));
+        assert!(!usage(on, 0, 0).near_limit(1));
+        assert!(usage(on, 0, 1).near_limit(1));
+    }
+
+    fn bid(bid: f64) -> PairResponse {
+        PairResponse {
+            bid,
+            ..PairResponse::default()
+        }
+    }
+
+    #[test]
+    fn amount_rules_keep_the_amounts_as_entered() {
+        let condition = AlertCondition::from_index(3, 5600.0, Some(1000.0)).unwrap();
+        assert_eq!(
+            condition,
+            WorthAbove {
+                amount: 1000.0,
+                target: 5600.0,
+            }
+        );
+        assert_eq!(condition.rate_bound(), Above(5.6));
+        assert_eq!(
+            AlertCondition::from_index(4, 5600.0, Some(1000.0))
+                .unwrap()
+                .rate_bound(),
+            Below(5.6)
+        );
+        // An amount is required, and must be positive.
+        assert_eq!(AlertCondition::from_index(3, 5600.0, None), None);
+        assert_eq!(AlertCondition::from_index(3, 5600.0, Some(0.0)), None);
+        assert_eq!(AlertCondition::from_index(4, 5600.0, Some(-1.0)), None);
+    }
+
+    #[test]
+    fn amount_rules_read_back_the_same() {
+        // Amounts whose rate has no exact float, saved and loaded again.
+        let rule = AlertRule {
+            pair: Pair::new("USD", "BRL").unwrap(),
+            condition: WorthBelow {
+                amount: 3.0,
+                target: 16.3,
+            },
+        };
+        let saved = serde_json::to_string(&rule).unwrap();
+        let loaded: AlertRule = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded, rule);
+        assert_eq!(loaded.describe(), rule.describe());
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), saved);
+    }
+
+    #[test]
+    fn amount_rules_fire_when_the_amount_crosses_the_target() {
+        let above = WorthAbove {
+            amount: 1000.0,
+            target: 5600.0,
+        };
+        assert!(!above.holds(&bid(5.6), None));
+        assert!(above.holds(&bid(5.6001), None));
+        // 3 × 5.4321 is 16.2963 exactly, where the float product isn't.
+        let below = WorthBelow {
+            amount: 3.0,
+            target: 16.2963,
+        };
+        assert!(!below.holds(&bid(5.4321), None));
+        assert!(below.holds(&bid(5.432), None));
+    }
+
+    #[test]
+    fn flagged_quotes_survive_a_restart() {
+        let mut state = AppletState::default();
+        state.flagged_quotes.push(FlaggedQuote {
+            pair: Pair::parse("USDBRL").unwrap(),
+            flagged_at: Local::now(),
+            provider: AWESOMEAPI.to_string(),
+            quoted_by: Some(AWESOMEAPI.to_string()),
+            quote: Some(bid(61.0)),
+            fetched_secs_ago: Some(12),
+            response_excerpt: Some(r#"{"USDBRL": {"bid": "61.0"}}"#.to_string()),
+            settings: r#"{"provider": "AwesomeAPI"}"#.to_string(),
+        });
+        let saved = serde_json::to_string(&state).unwrap();
+        let restored: AppletState = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored, state);
+    }
+
+    /// A watchlist of USDBRL and EURUSD left with every kind of orphan `GBPUSD` and
+    /// `JPYUSD` were removed from it: alert rules, panel slots and a pinned pair.
+    fn orphaned() -> AppletConfig {
+        let rule = |code: &str, above| AlertRule {
+            pair: Pair::parse(code).unwrap(),
+            condition: AlertCondition::Above(above),
+        };
+        AppletConfig {
+            pairs: ["USDBRL", "EURUSD"]
+                .iter()
+                .map(|code| PairEntry::new(Pair::parse(code).unwrap()))
+                .collect(),
+            first_panel_pair: Some(3),
+            alert_rules: vec![
+                rule("USDBRL", 5.5),
+                rule("GBPUSD", 1.3),
+                rule("EURUSD", 1.1),
+                rule("JPYUSD", 0.01),
+            ],
+            panel_slots: ["GBPUSD", "EURUSD", "JPYUSD"]
+                .iter()
+                .map(|code| Pair::parse(code).unwrap())
+                .collect(),
+            ..AppletConfig::default()
+        }
+    }
+
+    #[test]
+    fn orphaned_rules_are_parked_and_what_pointed_at_their_pairs_dropped() {
+        let mut config = orphaned();
+        assert_eq!(config.reconcile_orphans(), 2);
+        let pairs = |rules: &[AlertRule]| -> Vec<String> {
+            rules.iter().map(|rule| rule.pair.to_string()).collect()
+        };
+        assert_eq!(pairs(&config.alert_rules), ["USDBRL", "EURUSD"]);
+        assert_eq!(pairs(&config.unassigned_alerts), ["GBPUSD", "JPYUSD"]);
+        assert_eq!(config.panel_slots, [Pair::parse("EURUSD").unwrap()]);
+        assert_eq!(config.first_panel_pair, None);
+    }
+
+    #[test]
+    fn a_reconciled_config_is_left_as_it_is() {
+        let mut config = orphaned();
+        config.reconcile_orphans();
+        let reconciled = config.clone();
+        assert_eq!(config.reconcile_orphans(), 0);
+        assert_eq!(config, reconciled);
+        // A pinned pair within the watchlist stays pinned.
+        config.first_panel_pair = Some(1);
+        config.reconcile_orphans();
+        assert_eq!(config.first_panel_pair, Some(1));
+    }
+
+    #[test]
+    fn a_rule_orphaned_again_is_parked_once() {
+        let mut config = orphaned();
+        config.reconcile_orphans();
+        let parked = config.unassigned_alerts.clone();
+        config.alert_rules.push(parked[0].clone());
+        assert_eq!(config.reconcile_orphans(), 1);
+        assert_eq!(config.unassigned_alerts, parked);
+    }
+}
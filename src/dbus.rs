@@ -0,0 +1,493 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The applet's D-Bus interface, served on the session bus.
+//!
+//! Any process of the session can call it, so what changes rates is refused unless
+//! `AppletConfig::allow_external_updates` is set: the rates `SetRate` sets are taken as if
+//! a provider had quoted them, alerts included. It is meant for tests and scripts. E.g.
+//!
+//! ```sh
+//! busctl --user call com.example.CosmicAppletTemplate /com/example/CosmicAppletTemplate \
+//!     com.example.CosmicAppletTemplate SetRate sd USDBRL 5.25
+//! ```
+//!
+//! The rest is always served. `AcknowledgeAlerts` stops the visual bell, as opening the
+//! popup does. `PauseReasons` tells why rates aren't refreshing, for scripts to react to.
+//! `GetFormattedRate` gives the string the panel shows for a pair, which `--query` prints,
+//! see `crate::query`.
+//!
+//! The watchlist can be configured too, through the same paths as the popup: `AddPair`,
+//! `RemovePair` and `SetPrimary` to pin the pair the panel starts from. They are refused
+//! unless `AppletConfig::allow_external_watchlist` is set, a setting of its own as changing
+//! the watchlist is a different trust than feeding test rates: it is what setup scripts
+//! need, and fakes no quote. Reading it is always served: `ListPairs`, and the
+//! `WatchlistChanged` signal emitted whenever the pairs or the primary one change, whoever
+//! changed them.
+
+use cosmic::iced::futures::channel::mpsc::Sender;
+use cosmic::iced::futures::channel::oneshot;
+use cosmic::iced::futures::SinkExt;
+use cosmic::iced::Subscription;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::app::{Message, YourApp};
+use crate::config::Pair;
+use crate::fl;
+
+/// Where `Applet` is served.
+const OBJECT_PATH: &str = "/com/example/CosmicAppletTemplate";
+/// How long a call waits for the message loop to answer it before failing.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Notified by `watchlist_changed`, for `serve` to emit `WatchlistChanged`. Changes made
+/// before the signal went out are announced once.
+static WATCHLIST_CHANGED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Has `WatchlistChanged` emitted, once the interface is served.
+pub fn watchlist_changed() {
+    WATCHLIST_CHANGED.notify_one();
+}
+
+/// Where the message loop answers a call that needs the applet's state. Called once, later
+/// calls are ignored.
+#[derive(Debug, Clone)]
+pub struct Reply<T>(Arc<Mutex<Option<oneshot::Sender<T>>>>);
+
+impl<T> Reply<T> {
+    pub fn send(&self, value: T) {
+        if let Some(sender) = self.0.lock().ok().and_then(|mut sender| sender.take()) {
+            let _ = sender.send(value);
+        }
+    }
+}
+
+/// Why the message loop refused a call, as its client is told.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Refused {
+    /// The setting allowing the call is off.
+    NotAllowed(String),
+    /// The call doesn't hold, with the error the popup would have shown.
+    Invalid(String),
+}
+
+impl From<Refused> for zbus::fdo::Error {
+    fn from(refused: Refused) -> Self {
+        match refused {
+            Refused::NotAllowed(message) => zbus::fdo::Error::AccessDenied(message),
+            Refused::Invalid(message) => zbus::fdo::Error::InvalidArgs(message),
+        }
+    }
+}
+
+/// Forwards the calls of D-Bus clients into the message loop.
+struct Applet {
+    output: Sender<Message>,
+}
+
+#[zbus::interface(name = "com.example.CosmicAppletTemplate")]
+impl Applet {
+    /// Sets the rate of `pair`, given by its code, as if a fetch had returned `rate`.
+    /// Refused unless external updates are allowed.
+    async fn set_rate(&self, pair: &str, rate: f64) -> zbus::fdo::Result<()> {
+        let parsed = Pair::parse(pair).ok_or_else(|| {
+            zbus::fdo::Error::InvalidArgs(format!("invalid currency pair: {pair}"))
+        })?;
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "invalid rate: {rate}"
+            )));
+        }
+        Ok(self
+            .ask(|reply| Message::ExternalRateUpdate(parsed, rate, reply))
+            .await??)
+    }
+
+    /// Acknowledges the fired alerts, as opening the popup does.
+    async fn acknowledge_alerts(&self) -> zbus::fdo::Result<()> {
+        self.output
+            .clone()
+            .send(Message::AcknowledgeAlerts)
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    /// The reason no pair is refreshed, empty unless one applies to them all, and the reason
+    /// of every paused pair by its code. The reasons are the ids of `PauseReason`.
+    async fn pause_reasons(&self) -> zbus::fdo::Result<(String, HashMap<String, String>)> {
+        self.ask(Message::PauseReasons).await
+    }
+
+    /// Where the rate shown of every pair with one comes from, by its code: `fetched` with
+    /// the Unix timestamp of the quote, or `bundled` with the `YYYY-MM-DD` date of the
+    /// approximate rates bundled with the applet, shown until a quote is fetched.
+    async fn rate_sources(&self) -> zbus::fdo::Result<HashMap<String, (String, String)>> {
+        self.ask(Message::RateSources).await
+    }
+
+    /// Adds `pair` to the watchlist, failing if it is not a pair code or already there, or
+    /// if the watchlist can't be changed over D-Bus.
+    async fn add_pair(&self, pair: &str) -> zbus::fdo::Result<()> {
+        let pair = parse_pair(pair)?;
+        Ok(self
+            .ask(|reply| Message::ExternalAddPair(pair, reply))
+            .await??)
+    }
+
+    /// Removes `pair` from the watchlist, failing unless it is there and the watchlist can
+    /// be changed over D-Bus.
+    async fn remove_pair(&self, pair: &str) -> zbus::fdo::Result<()> {
+        let pair = parse_pair(pair)?;
+        Ok(self
+            .ask(|reply| Message::ExternalRemovePair(pair, reply))
+            .await??)
+    }
+
+    /// Pins `pair` as the one the panel starts from and rests on, failing unless it is in
+    /// the watchlist and the watchlist can be changed over D-Bus.
+    async fn set_primary(&self, pair: &str) -> zbus::fdo::Result<()> {
+        let pair = parse_pair(pair)?;
+        Ok(self
+            .ask(|reply| Message::ExternalSetPrimary(pair, reply))
+            .await??)
+    }
+
+    /// Every pair of the watchlist in order, with its state and its rate, `NaN` while it
+    /// has none fetched, see `RateSources`. The state is `disabled`, `active`, or the id of the `PauseReason` it is
+    /// paused for.
+    async fn list_pairs(&self) -> zbus::fdo::Result<Vec<(String, String, f64)>> {
+        self.ask(Message::ListPairs).await
+    }
+
+    /// The rate of `pair` as the panel shows it, formatted by the same code with the same
+    /// settings, with the rate it was formatted from, `NaN` while it has none fetched, and
+    /// the Unix timestamp of its quote, `0` without one. Fails unless the pair is in the
+    /// watchlist.
+    async fn get_formatted_rate(&self, pair: &str) -> zbus::fdo::Result<(String, f64, i64)> {
+        let pair = parse_pair(pair)?;
+        self.ask(|reply| Message::FormattedRate(pair, reply))
+            .await?
+            .map_err(zbus::fdo::Error::InvalidArgs)
+    }
+
+    /// The watchlist or its primary pair changed.
+    #[zbus(signal)]
+    async fn watchlist_changed(ctxt: &zbus::SignalContext<'_>) -> zbus::Result<()>;
+}
+
+impl Applet {
+    /// Sends the message `ask` makes, then waits up to `REPLY_TIMEOUT` for the message
+    /// loop to answer it.
+    async fn ask<T>(&self, ask: impl FnOnce(Reply<T>) -> Message) -> zbus::fdo::Result<T> {
+        let (sender, receiver) = oneshot::channel();
+        self.output
+            .clone()
+            .send(ask(Reply(Arc::new(Mutex::new(Some(sender))))))
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+        match tokio::time::timeout(REPLY_TIMEOUT, receiver).await {
+            Ok(reply) => reply.map_err(|e| zbus::fdo::Error::Failed(e.to_string())),
+            Err(_) => Err(zbus::fdo::Error::TimedOut("the applet is busy".into())),
+        }
+    }
+}
+
+/// Parses a pair code given by a client, as typed in the watchlist's input.
+fn parse_pair(code: &str) -> zbus::fdo::Result<Pair> {
+    Pair::parse(code)
+        .ok_or_else(|| zbus::fdo::Error::InvalidArgs(fl!("pair-code-invalid", code = code)))
+}
+
+/// Serves `Applet` for as long as the subscription runs, forwarding its calls into the
+/// message loop, e.g. `Message::ExternalRateUpdate` for every `SetRate` call.
+pub fn interface() -> Subscription<Message> {
+    cosmic::iced::subscription::channel("dbus-interface", 4, |output| async move {
+        if let Err(e) = serve(output).await {
+            tracing::warn!("Error serving the D-Bus interface: {}", e);
+        }
+        std::future::pending().await
+    })
+}
+
+async fn serve(output: Sender<Message>) -> zbus::Result<()> {
+    // Dropping the connection, when the subscription stops, releases the name.
+    let connection = zbus::connection::Builder::session()?
+        .name(<YourApp as cosmic::Application>::APP_ID)?
+        .serve_at(OBJECT_PATH, Applet { output })?
+        .build()
+        .await?;
+    let ctxt = zbus::SignalContext::new(&connection, OBJECT_PATH)?;
+    loop {
+        WATCHLIST_CHANGED.notified().await;
+        if let Err(e) = Applet::watchlist_changed(&ctxt).await {
+            tracing::warn!("Error emitting WatchlistChanged: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Flags;
+    use crate::providers::PairResponse;
+    use crate::query::{output, QueryFormat};
+    use cosmic::iced::futures::channel::mpsc;
+    use cosmic::iced::futures::StreamExt;
+    use cosmic::Application;
+    use std::future::Future;
+    use std::path::PathBuf;
+
+    #[zbus::proxy(
+        interface = "com.example.CosmicAppletTemplate",
+        default_service = "com.example.CosmicAppletTemplate",
+        default_path = "/com/example/CosmicAppletTemplate"
+    )]
+    trait Client {
+        fn set_rate(&self, pair: &str, rate: f64) -> zbus::Result<()>;
+        fn add_pair(&self, pair: &str) -> zbus::Result<()>;
+        fn remove_pair(&self, pair: &str) -> zbus::Result<()>;
+        fn set_primary(&self, pair: &str) -> zbus::Result<()>;
+        fn list_pairs(&self) -> zbus::Result<Vec<(String, String, f64)>>;
+        fn get_formatted_rate(&self, pair: &str) -> zbus::Result<(String, f64, i64)>;
+    }
+
+    /// The applet started from a config of its own, under a directory removed on drop,
+    /// with `Applet` served to `client` over a private peer-to-peer bus.
+    struct Harness {
+        app: YourApp,
+        client: ClientProxy<'static>,
+        messages: mpsc::Receiver<Message>,
+        // Serves `Applet` for as long as it is kept.
+        _server: zbus::Connection,
+        dir: PathBuf,
+    }
+
+    impl Harness {
+        async fn new(name: &str) -> Harness {
+            let dir =
+                std::env::temp_dir().join(format!("exchange-rate-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            let flags = Flags {
+                config_path: Some(dir.clone()),
+                ..Flags::default()
+            };
+            let (app, _) = YourApp::init(cosmic::app::Core::default(), flags);
+            let (output, messages) = mpsc::channel(4);
+            let (client, server) = tokio::net::UnixStream::pair().unwrap();
+            let (client, server) = tokio::try_join!(
+                zbus::connection::Builder::unix_stream(client).p2p().build(),
+                zbus::connection::Builder::unix_stream(server)
+                    .server(zbus::Guid::generate())
+                    .unwrap()
+                    .p2p()
+                    .serve_at(OBJECT_PATH, Applet { output })
+                    .unwrap()
+                    .build(),
+            )
+            .unwrap();
+            Harness {
+                app,
+                client: ClientProxy::new(&client).await.unwrap(),
+                messages,
+                _server: server,
+                dir,
+            }
+        }
+
+        /// Waits for `call` while the applet handles the messages it is sent, as its
+        /// message loop does.
+        async fn call<T>(&mut self, call: impl Future<Output = T>) -> T {
+            tokio::pin!(call);
+            loop {
+                tokio::select! {
+                    result = &mut call => return result,
+                    Some(message) = self.messages.next() => {
+                        let _ = self.app.update(message);
+                    }
+                }
+            }
+        }
+    }
+
+    impl Drop for Harness {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// The state every test starts from: USDBRL, the default watchlist, quoted at `bid`.
+    fn quote(harness: &mut Harness, bid: f64) -> Pair {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let quote = PairResponse {
+            bid,
+            timestamp: 1_760_000_000,
+            ..PairResponse::default()
+        };
+        harness.app.exchange_rates.insert(pair.clone(), quote);
+        pair
+    }
+
+    #[tokio::test]
+    async fn panel_dbus_and_query_outputs_match() {
+        let mut harness = Harness::new("formatted-rate").await;
+        let pair = quote(&mut harness, 5.4300);
+        for trailing_zeros in [false, true] {
+            harness.app.config.panel_trailing_zeros = trailing_zeros;
+            let panel = harness.app.panel_rate_text(&pair);
+            let client = harness.client.clone();
+            let answer = harness
+                .call(client.get_formatted_rate("USDBRL"))
+                .await
+                .unwrap();
+            assert_eq!(answer, (panel.clone(), 5.43, 1_760_000_000));
+            let code = String::from(pair.clone());
+            assert_eq!(
+                output(&code, answer.clone(), QueryFormat::Display),
+                Ok(panel)
+            );
+            assert_eq!(
+                output(&code, answer, QueryFormat::Raw),
+                Ok("5.43".to_string())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn formatted_rate_is_served_without_external_updates() {
+        let mut harness = Harness::new("read-only").await;
+        quote(&mut harness, 5.43);
+        assert!(!harness.app.config.allow_external_updates);
+        let client = harness.client.clone();
+        assert!(harness
+            .call(client.get_formatted_rate("USDBRL"))
+            .await
+            .is_ok());
+        assert!(harness
+            .call(client.get_formatted_rate("EURUSD"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn set_rate_needs_external_updates() {
+        let mut harness = Harness::new("set-rate").await;
+        let pair = quote(&mut harness, 5.43);
+        let client = harness.client.clone();
+        assert!(harness.call(client.set_rate("USDBRL", 6.0)).await.is_err());
+        assert_eq!(harness.app.exchange_rates[&pair].bid, 5.43);
+
+        harness.app.config.allow_external_updates = true;
+        harness.call(client.set_rate("USDBRL", 6.0)).await.unwrap();
+        assert_eq!(harness.app.exchange_rates[&pair].bid, 6.0);
+    }
+
+    /// The name of the D-Bus error `result` failed with.
+    fn error_name<T: std::fmt::Debug>(result: zbus::Result<T>) -> String {
+        match result {
+            Err(zbus::Error::MethodError(name, _, _)) => name.to_string(),
+            other => panic!("expected a D-Bus error, got {other:?}"),
+        }
+    }
+
+    const ACCESS_DENIED: &str = "org.freedesktop.DBus.Error.AccessDenied";
+    const INVALID_ARGS: &str = "org.freedesktop.DBus.Error.InvalidArgs";
+
+    /// The codes `ListPairs` answers with, in order.
+    async fn listed(harness: &mut Harness) -> Vec<String> {
+        let client = harness.client.clone();
+        let pairs = harness.call(client.list_pairs()).await.unwrap();
+        pairs.into_iter().map(|(code, _, _)| code).collect()
+    }
+
+    #[tokio::test]
+    async fn watchlist_changes_need_their_own_setting() {
+        let mut harness = Harness::new("watchlist-denied").await;
+        // Allowing test rates doesn't allow changing the watchlist.
+        harness.app.config.allow_external_updates = true;
+        let client = harness.client.clone();
+        let added = harness.call(client.add_pair("EURUSD")).await;
+        assert_eq!(error_name(added), ACCESS_DENIED);
+        let removed = harness.call(client.remove_pair("USDBRL")).await;
+        assert_eq!(error_name(removed), ACCESS_DENIED);
+        let pinned = harness.call(client.set_primary("USDBRL")).await;
+        assert_eq!(error_name(pinned), ACCESS_DENIED);
+        assert_eq!(harness.app.config.first_panel_pair, None);
+        // Reading it is always served.
+        assert_eq!(listed(&mut harness).await, ["USDBRL"]);
+    }
+
+    #[tokio::test]
+    async fn add_pair_goes_through_the_popup_validation() {
+        let mut harness = Harness::new("add-pair").await;
+        harness.app.config.allow_external_watchlist = true;
+        let client = harness.client.clone();
+        harness.call(client.add_pair("EURUSD")).await.unwrap();
+        assert_eq!(listed(&mut harness).await, ["USDBRL", "EURUSD"]);
+
+        let repeated = harness.call(client.add_pair("EURUSD")).await;
+        assert_eq!(error_name(repeated), INVALID_ARGS);
+        let invalid = harness.call(client.add_pair("EUR")).await;
+        assert_eq!(error_name(invalid), INVALID_ARGS);
+        assert_eq!(listed(&mut harness).await, ["USDBRL", "EURUSD"]);
+    }
+
+    #[tokio::test]
+    async fn remove_pair_and_set_primary_need_a_watched_pair() {
+        let mut harness = Harness::new("remove-pair").await;
+        harness.app.config.allow_external_watchlist = true;
+        let client = harness.client.clone();
+        harness.call(client.add_pair("EURUSD")).await.unwrap();
+
+        harness.call(client.set_primary("EURUSD")).await.unwrap();
+        assert_eq!(harness.app.config.first_panel_pair, Some(1));
+        let unwatched = harness.call(client.set_primary("BTCUSD")).await;
+        assert_eq!(error_name(unwatched), INVALID_ARGS);
+
+        harness.call(client.remove_pair("USDBRL")).await.unwrap();
+        assert_eq!(listed(&mut harness).await, ["EURUSD"]);
+        let unwatched = harness.call(client.remove_pair("USDBRL")).await;
+        assert_eq!(error_name(unwatched), INVALID_ARGS);
+        let invalid = harness.call(client.remove_pair("not a pair")).await;
+        assert_eq!(error_name(invalid), INVALID_ARGS);
+    }
+
+    #[tokio::test]
+    async fn list_pairs_gives_the_rates() {
+        let mut harness = Harness::new("list-pairs").await;
+        quote(&mut harness, 5.43);
+        let client = harness.client.clone();
+        let pairs = harness.call(client.list_pairs()).await.unwrap();
+        let [(code, _, rate)] = pairs.as_slice() else {
+            panic!("expected a single pair, got {pairs:?}");
+        };
+        assert_eq!((code.as_str(), *rate), ("USDBRL", 5.43));
+    }
+
+    #[tokio::test]
+    async fn pairs_are_read_in_any_case() {
+        let mut harness = Harness::new("any-case").await;
+        let usdbrl = quote(&mut harness, 5.43);
+        harness.app.config.allow_external_updates = true;
+        harness.app.config.allow_external_watchlist = true;
+        let client = harness.client.clone();
+
+        harness.call(client.add_pair("eurusd")).await.unwrap();
+        assert_eq!(listed(&mut harness).await, ["USDBRL", "EURUSD"]);
+        // The same pair in another case is already watched.
+        let repeated = harness.call(client.add_pair("EurUsd")).await;
+        assert_eq!(error_name(repeated), INVALID_ARGS);
+
+        harness.call(client.set_rate("usdbrl", 6.0)).await.unwrap();
+        assert_eq!(harness.app.exchange_rates[&usdbrl].bid, 6.0);
+        let lower = harness.call(client.get_formatted_rate("usdbrl")).await;
+        let upper = harness.call(client.get_formatted_rate("USDBRL")).await;
+        assert_eq!(lower.unwrap(), upper.unwrap());
+
+        harness.call(client.set_primary("eurusd")).await.unwrap();
+        assert_eq!(harness.app.config.first_panel_pair, Some(1));
+        harness.call(client.remove_pair("Usd-Brl")).await.unwrap();
+        assert_eq!(listed(&mut harness).await, ["EURUSD"]);
+    }
+}
@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Alert emails, sent through the SMTP server configured in `AppletConfig`.
+
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+
+/// Keyring service the SMTP passwords are stored under.
+const KEYRING_SERVICE: &str = "com.example.CosmicAppletTemplate";
+
+/// Where and how alert emails are sent, see `AppletConfig::smtp`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmtpConfig {
+    /// The address alerts are sent to.
+    pub to: String,
+    pub server: String,
+    pub port: u16,
+    /// The sender address, also used as login.
+    pub from: String,
+    /// The keyring entry holding the SMTP password, empty to send without logging in.
+    pub credential_key: String,
+}
+
+impl SmtpConfig {
+    /// Local servers, such as a Mailhog instance used for testing, are spoken to in
+    /// plain text. Any other server must support STARTTLS.
+    fn is_local(&self) -> bool {
+        matches!(self.server.as_str(), "localhost" | "127.0.0.1" | "::1")
+    }
+
+    /// Reads the password from the keyring, `None` if no login is configured.
+    async fn credentials(&self) -> Result<Option<Credentials>, String> {
+        if self.credential_key.is_empty() {
+            return Ok(None);
+        }
+        let key = self.credential_key.clone();
+        let password = tokio::task::spawn_blocking(move || {
+            keyring::Entry::new(KEYRING_SERVICE, &key).and_then(|entry| entry.get_password())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("could not read SMTP password from keyring: {e}"))?;
+        Ok(Some(Credentials::new(self.from.clone(), password)))
+    }
+}
+
+/// An alert that fired, as shown in the desktop notification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    pub summary: String,
+    pub body: String,
+}
+
+/// Emails `alert` as configured in `config`.
+pub async fn send_rate_alert_email(config: &SmtpConfig, alert: &AlertEvent) -> Result<(), String> {
+    let email = lettre::Message::builder()
+        .from(
+            config
+                .from
+                .parse()
+                .map_err(|e| format!("invalid sender: {e}"))?,
+        )
+        .to(config
+            .to
+            .parse()
+            .map_err(|e| format!("invalid recipient: {e}"))?)
+        .subject(alert.summary.clone())
+        .body(alert.body.clone())
+        .map_err(|e| e.to_string())?;
+
+    let mut transport = if config.is_local() {
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.server)
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.server)
+            .map_err(|e| e.to_string())?
+    }
+    .port(config.port);
+    if let Some(credentials) = config.credentials().await? {
+        transport = transport.credentials(credentials);
+    }
+
+    transport
+        .build()
+        .send(email)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// A Mailhog-like SMTP server on a free local port, accepting a single email and
+    /// handing over its message.
+    async fn mailhog() -> (u16, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"220 mailhog ESMTP\r\n").await.unwrap();
+            let mut message = String::new();
+            let mut in_data = false;
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if in_data {
+                    if line == "." {
+                        in_data = false;
+                        writer.write_all(b"250 queued\r\n").await.unwrap();
+                    } else {
+                        message.push_str(&line);
+                        message.push('\n');
+                    }
+                    continue;
+                }
+                let command = line.get(..4).unwrap_or(&line).to_ascii_uppercase();
+                let reply: &[u8] = match command.as_str() {
+                    "DATA" => {
+                        in_data = true;
+                        b"354 end with a dot\r\n"
+                    }
+                    "QUIT" => {
+                        writer.write_all(b"221 bye\r\n").await.unwrap();
+                        break;
+                    }
+                    _ => b"250 ok\r\n",
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            message
+        });
+        (port, server)
+    }
+
+    fn config(port: u16) -> SmtpConfig {
+        SmtpConfig {
+            to: "me@example.com".to_string(),
+            server: "127.0.0.1".to_string(),
+            port,
+            from: "applet@example.com".to_string(),
+            credential_key: String::new(),
+        }
+    }
+
+    fn alert() -> AlertEvent {
+        AlertEvent {
+            summary: "USD/BRL alert".to_string(),
+            body: "The rate is now 5.6 (> 5.5)".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn alerts_are_emailed_as_notified() {
+        let (port, server) = mailhog().await;
+        send_rate_alert_email(&config(port), &alert())
+            .await
+            .unwrap();
+        let message = server.await.unwrap();
+        assert!(message.contains("From: applet@example.com"), "{message}");
+        assert!(message.contains("To: me@example.com"), "{message}");
+        assert!(message.contains("Subject: USD/BRL alert"), "{message}");
+        assert!(message.contains("The rate is now 5.6 (> 5.5)"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn invalid_addresses_are_refused_before_connecting() {
+        let mut config = config(1);
+        config.to = "not an address".to_string();
+        let error = send_rate_alert_email(&config, &alert()).await.unwrap_err();
+        assert!(error.starts_with("invalid recipient"), "{error}");
+        config.to = "me@example.com".to_string();
+        config.from = String::new();
+        let error = send_rate_alert_email(&config, &alert()).await.unwrap_err();
+        assert!(error.starts_with("invalid sender"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn an_unreachable_server_is_an_error() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        assert!(send_rate_alert_email(&config(port), &alert())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn only_local_servers_go_without_tls() {
+        let mut config = config(25);
+        for server in ["localhost", "127.0.0.1", "::1"] {
+            config.server = server.to_string();
+            assert!(config.is_local());
+        }
+        config.server = "smtp.example.com".to_string();
+        assert!(!config.is_local());
+    }
+}
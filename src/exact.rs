@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Decimal-exact arithmetic for the amounts users check against their bank or a
+//! spreadsheet, so converting 999,999.99 at 5.4321 gives 5,432,099.95 to the cent.
+//!
+//! The precision of each operation:
+//! - Quotes, markups and amounts from the settings are taken at the shortest decimal that
+//!   reads back as their float, which is the number the provider sent or the user typed.
+//! - Effective rates, conversions and cross rates keep every digit `Decimal` holds (28
+//!   significant ones), as guard digits for what is computed from them.
+//! - Results are rounded once, where they are displayed, by `round_for_display`.
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::config::DecimalGroupStyle;
+
+/// `value` as the shortest decimal that reads back as it, `None` if it isn't finite or is
+/// out of `Decimal`'s range.
+pub fn decimal(value: f64) -> Option<Decimal> {
+    if !value.is_finite() {
+        return None;
+    }
+    // `Display` writes the shortest round-tripping digits, never in exponent notation.
+    value.to_string().parse().ok()
+}
+
+/// Parses a number typed by the user, accepting either a dot or a comma as decimal
+/// separator, as `crate::format::parse_amount` does.
+pub fn parse_decimal(text: &str) -> Option<Decimal> {
+    text.trim().replace(',', ".").parse().ok()
+}
+
+/// `value` rounded to `decimals` as `style` rounds ties: to even with `BankersRounding`,
+/// away from zero otherwise, as spreadsheets do.
+pub fn round_for_display(value: Decimal, decimals: u32, style: DecimalGroupStyle) -> Decimal {
+    let strategy = match style {
+        DecimalGroupStyle::BankersRounding => RoundingStrategy::MidpointNearestEven,
+        _ => RoundingStrategy::MidpointAwayFromZero,
+    };
+    value.round_dp_with_strategy(decimals, strategy)
+}
+
+/// `value` with `decimals` decimals, rounded by `round_for_display` and padded with zeros.
+pub fn format_decimal(value: Decimal, decimals: u32, style: DecimalGroupStyle) -> String {
+    let rounded = round_for_display(value, decimals, style);
+    format!("{:.*}", decimals as usize, rounded)
+}
+
+/// What `amount` of a pair's base currency is worth in its quote currency at `rate`.
+pub fn worth(amount: f64, rate: f64) -> Option<Decimal> {
+    decimal(amount)?.checked_mul(decimal(rate)?)
+}
+
+/// The inverse of `rate`, `None` for a zero rate.
+pub fn inverse(rate: Decimal) -> Option<Decimal> {
+    Decimal::ONE.checked_div(rate)
+}
+
+/// `value` as a float, for comparisons and formatting that don't need every digit.
+pub fn to_f64(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn worth_is_exact_to_the_cent() {
+        let value = worth(999_999.99, 5.4321).unwrap();
+        assert_eq!(value, dec("5432099.945679"));
+        assert_eq!(
+            format_decimal(value, 2, DecimalGroupStyle::None),
+            "5432099.95"
+        );
+        // Where floats drift, the decimals don't.
+        assert_ne!(0.1 * 3.0, 0.3);
+        assert_eq!(worth(0.1, 3.0), Some(dec("0.3")));
+    }
+
+    #[test]
+    fn worth_matches_integer_arithmetic_across_magnitudes() {
+        for exponent in 0..=9 {
+            for cents in [1i128, 7, 99, 12_345, 99_999] {
+                let amount_cents = cents * 10i128.pow(exponent);
+                for rate_units in [1i128, 9_999, 54_321, 1_234_567, 99_999_999] {
+                    let (amount, rate) = (amount_cents as f64 / 100.0, rate_units as f64 / 1e4);
+                    let expected = Decimal::from_i128_with_scale(amount_cents * rate_units, 6);
+                    assert_eq!(worth(amount, rate), Some(expected), "{amount} at {rate}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn worth_of_what_isnt_a_number() {
+        assert_eq!(worth(f64::NAN, 5.0), None);
+        assert_eq!(worth(1.0, f64::INFINITY), None);
+    }
+
+    #[test]
+    fn round_for_display_rounds_the_decimal_not_the_float() {
+        // 1.005 is stored as 1.00499999…, which float rounding takes down.
+        let rounded = round_for_display(decimal(1.005).unwrap(), 2, DecimalGroupStyle::None);
+        assert_eq!(rounded, dec("1.01"));
+        assert_eq!(format!("{:.2}", 1.005), "1.00");
+    }
+
+    #[test]
+    fn round_for_display_rounds_ties_by_style() {
+        let away = |value| round_for_display(dec(value), 2, DecimalGroupStyle::None);
+        let even = |value| round_for_display(dec(value), 2, DecimalGroupStyle::BankersRounding);
+        assert_eq!(away("2.345"), dec("2.35"));
+        assert_eq!(even("2.345"), dec("2.34"));
+        assert_eq!(even("2.355"), dec("2.36"));
+        assert_eq!(away("-2.345"), dec("-2.35"));
+        assert_eq!(even("-2.345"), dec("-2.34"));
+        assert_eq!(even("2.3451"), dec("2.35"));
+        assert_eq!(away("9.995"), dec("10.00"));
+        assert_eq!(format_decimal(dec("5"), 2, DecimalGroupStyle::None), "5.00");
+    }
+
+    #[test]
+    fn inverse_keeps_the_guard_digits() {
+        assert_eq!(inverse(dec("4")), Some(dec("0.25")));
+        assert_eq!(inverse(Decimal::ZERO), None);
+        let rate = dec("5.4321");
+        let inverted = inverse(rate).unwrap();
+        assert!((inverted * rate - Decimal::ONE).abs() < Decimal::new(1, 26));
+        assert_eq!(
+            round_for_display(inverse(inverted).unwrap(), 20, DecimalGroupStyle::None),
+            rate
+        );
+    }
+}
@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Exporting the quotes received this session, for other tools to read.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ExportFormat, Pair};
+
+/// A quote of the history, as exported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub pair: Pair,
+    /// When the quote was received, exported as RFC 3339.
+    pub at: DateTime<Local>,
+    pub bid: f64,
+}
+
+/// Serializes `history` in `format`:
+/// - `Csv`: a `pair,at,bid` header, then one comma-delimited row per entry.
+/// - `JsonLines`: one JSON object per line.
+/// - `Toml`: a `history` array with an inline table per entry.
+pub fn export_history(history: &[HistoryEntry], format: ExportFormat) -> String {
+    let mut out = String::new();
+    match format {
+        ExportFormat::Csv => {
+            out.push_str("pair,at,bid\n");
+            for entry in history {
+                out.push_str(&format!(
+                    "{},{},{:?}\n",
+                    String::from(entry.pair.clone()),
+                    entry.at.to_rfc3339(),
+                    entry.bid
+                ));
+            }
+        }
+        ExportFormat::JsonLines => {
+            for entry in history {
+                // Serializing a struct of strings and numbers can't fail.
+                if let Ok(line) = serde_json::to_string(entry) {
+                    out.push_str(&line);
+                    out.push('\n');
+                }
+            }
+        }
+        ExportFormat::Toml => {
+            out.push_str("history = [\n");
+            for entry in history {
+                // A TOML basic string, codes are plain letters and dashes.
+                out.push_str(&format!(
+                    "    {{ pair = \"{}\", at = {}, bid = {:?} }},\n",
+                    String::from(entry.pair.clone()),
+                    entry.at.to_rfc3339(),
+                    entry.bid
+                ));
+            }
+            out.push_str("]\n");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Entries with what is easy to lose on the way: nanoseconds, digits past those a
+    /// float usually prints with, a pair code with a dash and rates in exponent notation.
+    fn history() -> Vec<HistoryEntry> {
+        let at = Local.with_ymd_and_hms(2026, 3, 10, 9, 30, 0).unwrap();
+        vec![
+            HistoryEntry {
+                pair: Pair::parse("USDBRL").unwrap(),
+                at: at + chrono::Duration::nanoseconds(123_456_789),
+                bid: 5.4321,
+            },
+            HistoryEntry {
+                pair: Pair::parse("USDT-BRL").unwrap(),
+                at: at + chrono::Duration::seconds(90),
+                bid: 0.1 + 0.2,
+            },
+            HistoryEntry {
+                pair: Pair::parse("BTCUSD").unwrap(),
+                at: at + chrono::Duration::days(1),
+                bid: 1e20,
+            },
+            HistoryEntry {
+                pair: Pair::parse("JPYBRL").unwrap(),
+                at,
+                bid: 3.2e-7,
+            },
+        ]
+    }
+
+    fn parse_at(at: &str) -> DateTime<Local> {
+        DateTime::parse_from_rfc3339(at)
+            .unwrap()
+            .with_timezone(&Local)
+    }
+
+    fn parse_csv(text: &str) -> Vec<HistoryEntry> {
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("pair,at,bid"));
+        lines
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                let [pair, at, bid] = fields[..] else {
+                    panic!("malformed row {line}");
+                };
+                HistoryEntry {
+                    pair: Pair::parse(pair).unwrap(),
+                    at: parse_at(at),
+                    bid: bid.parse().unwrap(),
+                }
+            })
+            .collect()
+    }
+
+    fn parse_json_lines(text: &str) -> Vec<HistoryEntry> {
+        text.lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn parse_toml(text: &str) -> Vec<HistoryEntry> {
+        let table: toml::Table = text.parse().unwrap();
+        table["history"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| HistoryEntry {
+                pair: Pair::parse(entry["pair"].as_str().unwrap()).unwrap(),
+                at: parse_at(&entry["at"].as_datetime().unwrap().to_string()),
+                bid: entry["bid"].as_float().unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let history = history();
+        let text = export_history(&history, ExportFormat::Csv);
+        assert_eq!(parse_csv(&text), history);
+    }
+
+    #[test]
+    fn json_lines_round_trip() {
+        let history = history();
+        let text = export_history(&history, ExportFormat::JsonLines);
+        assert_eq!(parse_json_lines(&text), history);
+    }
+
+    #[test]
+    fn toml_round_trips() {
+        let history = history();
+        let text = export_history(&history, ExportFormat::Toml);
+        assert_eq!(parse_toml(&text), history);
+    }
+
+    #[test]
+    fn empty_history_round_trips() {
+        assert!(parse_csv(&export_history(&[], ExportFormat::Csv)).is_empty());
+        assert!(parse_json_lines(&export_history(&[], ExportFormat::JsonLines)).is_empty());
+        assert!(parse_toml(&export_history(&[], ExportFormat::Toml)).is_empty());
+    }
+}
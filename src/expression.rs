@@ -0,0 +1,313 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Derived pairs: watchlist entries computed from the quotes of other pairs by an
+//! arithmetic expression, e.g. `EURBRL / USDBRL` for the EURUSD rate they imply, or
+//! `(USDBRL + USDTBRL) / 2` for an average across sources.
+//!
+//! An expression is made of the codes of pairs of the watchlist, numbers, `+ - * /` and
+//! parentheses, with the usual precedence. It is evaluated again each time one of the
+//! pairs it refers to gets a new quote, see `YourApp::derived_quote`.
+
+use crate::fl;
+
+/// What derived pairs are quoted by, in place of the name of a provider.
+pub const DERIVED: &str = "derived";
+/// Most parentheses and negations nested in one another, deeper is a syntax error rather
+/// than a parser recursing as deep as the text goes.
+const MAX_DEPTH: usize = 32;
+
+/// A parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    /// The rate of a pair, by its code in upper case.
+    Pair(String),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, Operator, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+/// Why an expression can't be evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    /// The text isn't a valid expression, from the character at this index on.
+    Syntax(usize),
+    /// A code that isn't a fetched pair of the watchlist.
+    UnknownPair(String),
+    /// A pair without a quote yet.
+    NoQuote(String),
+    DivisionByZero,
+    /// A result too large to be a rate.
+    OutOfRange,
+}
+
+impl ExprError {
+    pub fn message(&self) -> String {
+        match self {
+            ExprError::Syntax(at) => fl!("expression-syntax", at = at + 1),
+            ExprError::UnknownPair(code) => fl!("expression-unknown-pair", pair = code.clone()),
+            ExprError::NoQuote(code) => fl!("expression-no-quote", pair = code.clone()),
+            ExprError::DivisionByZero => fl!("expression-division-by-zero"),
+            ExprError::OutOfRange => fl!("expression-out-of-range"),
+        }
+    }
+}
+
+impl Expr {
+    /// The codes of the pairs the expression refers to, each once, in order.
+    pub fn pairs(&self) -> Vec<&str> {
+        let mut codes = Vec::new();
+        self.collect_pairs(&mut codes);
+        codes
+    }
+
+    fn collect_pairs<'a>(&'a self, codes: &mut Vec<&'a str>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Pair(code) => {
+                if !codes.contains(&code.as_str()) {
+                    codes.push(code);
+                }
+            }
+            Expr::Negate(operand) => operand.collect_pairs(codes),
+            Expr::Binary(left, _, right) => {
+                left.collect_pairs(codes);
+                right.collect_pairs(codes);
+            }
+        }
+    }
+
+    /// The value of the expression with each pair at the rate `rate` gives for its code.
+    /// The errors of `rate` are passed on, the first met wins.
+    pub fn evaluate(
+        &self,
+        rate: &dyn Fn(&str) -> Result<f64, ExprError>,
+    ) -> Result<f64, ExprError> {
+        let value = match self {
+            Expr::Number(value) => *value,
+            Expr::Pair(code) => rate(code)?,
+            Expr::Negate(operand) => -operand.evaluate(rate)?,
+            Expr::Binary(left, operator, right) => {
+                let (left, right) = (left.evaluate(rate)?, right.evaluate(rate)?);
+                match operator {
+                    Operator::Add => left + right,
+                    Operator::Subtract => left - right,
+                    Operator::Multiply => left * right,
+                    Operator::Divide if right == 0.0 => return Err(ExprError::DivisionByZero),
+                    Operator::Divide => left / right,
+                }
+            }
+        };
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err(ExprError::OutOfRange)
+        }
+    }
+}
+
+/// Parses `text` as an expression.
+pub fn parse(text: &str) -> Result<Expr, ExprError> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        at: 0,
+        depth: 0,
+    };
+    let expr = parser.sum()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => Err(ExprError::Syntax(parser.at)),
+    }
+}
+
+/// A recursive descent parser, one method per precedence level. Expressions are ASCII,
+/// any other byte is a syntax error where it is.
+struct Parser<'a> {
+    text: &'a [u8],
+    at: usize,
+    /// How many parentheses and negations the current byte is in.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// The next byte that isn't a space, without taking it.
+    fn peek(&mut self) -> Option<u8> {
+        while self.text.get(self.at).is_some_and(u8::is_ascii_whitespace) {
+            self.at += 1;
+        }
+        self.text.get(self.at).copied()
+    }
+
+    /// Terms added or subtracted.
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let mut expr = self.product()?;
+        loop {
+            let operator = match self.peek() {
+                Some(b'+') => Operator::Add,
+                Some(b'-') => Operator::Subtract,
+                _ => return Ok(expr),
+            };
+            self.at += 1;
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(self.product()?));
+        }
+    }
+
+    /// Factors multiplied or divided.
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let mut expr = self.factor()?;
+        loop {
+            let operator = match self.peek() {
+                Some(b'*') => Operator::Multiply,
+                Some(b'/') => Operator::Divide,
+                _ => return Ok(expr),
+            };
+            self.at += 1;
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(self.factor()?));
+        }
+    }
+
+    /// A number, a pair, a negated factor or a parenthesized expression.
+    fn factor(&mut self) -> Result<Expr, ExprError> {
+        let next = self.peek();
+        let start = self.at;
+        match next {
+            Some(b'-') => {
+                self.at += 1;
+                Ok(Expr::Negate(Box::new(self.nested(start, Self::factor)?)))
+            }
+            Some(b'(') => {
+                self.at += 1;
+                let expr = self.nested(start, Self::sum)?;
+                if self.peek() != Some(b')') {
+                    return Err(ExprError::Syntax(self.at));
+                }
+                self.at += 1;
+                Ok(expr)
+            }
+            Some(byte) if byte.is_ascii_digit() || byte == b'.' => {
+                let token = self.take(|byte| byte.is_ascii_digit() || byte == b'.');
+                token
+                    .parse()
+                    .map(Expr::Number)
+                    .map_err(|_| ExprError::Syntax(start))
+            }
+            Some(byte) if byte.is_ascii_alphabetic() => {
+                let token = self.take(|byte| byte.is_ascii_alphabetic());
+                Ok(Expr::Pair(token.to_ascii_uppercase()))
+            }
+            _ => Err(ExprError::Syntax(self.at)),
+        }
+    }
+
+    /// Parses with `parse` one level deeper, failing beyond `MAX_DEPTH` with an error at
+    /// `start`, where the level opened.
+    fn nested(
+        &mut self,
+        start: usize,
+        parse: fn(&mut Self) -> Result<Expr, ExprError>,
+    ) -> Result<Expr, ExprError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ExprError::Syntax(start));
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    /// Takes the bytes from the current one on while `accept` accepts them.
+    fn take(&mut self, accept: impl Fn(u8) -> bool) -> &str {
+        let start = self.at;
+        while self.text.get(self.at).is_some_and(|&byte| accept(byte)) {
+            self.at += 1;
+        }
+        // The accepted bytes are all ASCII.
+        std::str::from_utf8(&self.text[start..self.at]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The value of `text` with EURBRL at 6 and USDBRL at 5, other pairs unknown.
+    fn value(text: &str) -> Result<f64, ExprError> {
+        parse(text)?.evaluate(&|code| match code {
+            "EURBRL" => Ok(6.0),
+            "USDBRL" => Ok(5.0),
+            _ => Err(ExprError::UnknownPair(code.to_string())),
+        })
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(value("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(value("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(value("2 * -3 + 1"), Ok(-5.0));
+        assert_eq!(value("-(1 + 2)"), Ok(-3.0));
+        assert_eq!(value("8 / 4 / 2"), Ok(1.0));
+        assert_eq!(value("8 - 4 - 2"), Ok(2.0));
+        assert_eq!(value("(USDBRL + EURBRL) / 2"), Ok(5.5));
+    }
+
+    #[test]
+    fn pairs_are_read_by_code() {
+        assert_eq!(value("eurbrl / usdbrl"), Ok(1.2));
+        let expr = parse("EURBRL / USDBRL + eurbrl").unwrap();
+        assert_eq!(expr.pairs(), ["EURBRL", "USDBRL"]);
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(value("1 / 0"), Err(ExprError::DivisionByZero));
+        assert_eq!(
+            value("EURBRL / (USDBRL - 5)"),
+            Err(ExprError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn unknown_pair() {
+        assert_eq!(
+            value("EURBRL / GBPBRL"),
+            Err(ExprError::UnknownPair("GBPBRL".to_string()))
+        );
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert_eq!(value("1e308"), Err(ExprError::Syntax(1)));
+        let huge = format!("1{} * 10", "0".repeat(308));
+        assert_eq!(value(&huge), Err(ExprError::OutOfRange));
+    }
+
+    #[test]
+    fn syntax_errors_point_at_the_offending_character() {
+        assert_eq!(value("1 + "), Err(ExprError::Syntax(4)));
+        assert_eq!(value("1 + $"), Err(ExprError::Syntax(4)));
+        assert_eq!(value("(1 + 2"), Err(ExprError::Syntax(6)));
+        assert_eq!(value("1 2"), Err(ExprError::Syntax(2)));
+        // The column of a malformed number is where it starts, past the spaces before it.
+        assert_eq!(value("1 +   1.2.3"), Err(ExprError::Syntax(6)));
+    }
+
+    #[test]
+    fn nesting_is_capped() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(value(&nested(MAX_DEPTH)), Ok(1.0));
+        assert_eq!(
+            value(&nested(MAX_DEPTH + 1)),
+            Err(ExprError::Syntax(MAX_DEPTH))
+        );
+        assert!(matches!(value(&nested(100_000)), Err(ExprError::Syntax(_))));
+        let negated = format!("{}1", "-".repeat(100_000));
+        assert!(matches!(value(&negated), Err(ExprError::Syntax(_))));
+    }
+}
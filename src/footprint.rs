@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The applet's own resource use, shown in the status page for those wondering whether it
+//! is heavy on their device.
+//!
+//! What the applet holds is counted from its state by `YourApp::held_counts`, which is
+//! cheap. What takes reading files, its resident memory and the size of its settings and
+//! state on disk, is measured by `measure` off the UI thread, and only while the status
+//! page is open.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How often the status page measures the footprint again while it is open.
+pub const FOOTPRINT_REFRESH: Duration = Duration::from_secs(5);
+
+/// What the applet holds in memory, as counted by `YourApp::held_counts`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HeldCounts {
+    /// Quotes in the session history of every pair.
+    pub history_points: usize,
+    /// Quotes in the rate cache, expired ones included until they are replaced.
+    pub cached_quotes: usize,
+    /// Raw responses kept for flagged quotes, one per pair at most.
+    pub raw_responses: usize,
+    /// Requests made today to every provider, those not persisted yet included.
+    pub requests_today: u64,
+}
+
+/// What `measure` read, each `None` when it couldn't be.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Footprint {
+    /// The resident memory of the applet.
+    pub resident_bytes: Option<u64>,
+    /// The size of the settings files.
+    pub settings_bytes: Option<u64>,
+    /// The size of the persisted state files, usage counters and flagged quotes.
+    pub state_bytes: Option<u64>,
+}
+
+/// Measures the footprint of the applet keeping its settings in `settings_dir` and its
+/// state in `state_dir`. Blocking, run it with `spawn_blocking`.
+pub fn measure(settings_dir: Option<PathBuf>, state_dir: Option<PathBuf>) -> Footprint {
+    Footprint {
+        resident_bytes: resident_bytes(),
+        settings_bytes: settings_dir.as_deref().and_then(dir_size),
+        state_bytes: state_dir.as_deref().and_then(dir_size),
+    }
+}
+
+/// The resident memory of the process, from the `VmRSS` line of `/proc/self/status`: the
+/// count `/proc/self/statm` gives in pages, already in kilobytes so the page size isn't
+/// needed. `None` where `/proc` isn't mounted or readable.
+fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// The total size of the files under `dir`, `None` if it can't be read at all. Entries
+/// that vanish or can't be read while walking it are skipped.
+fn dir_size(dir: &Path) -> Option<u64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            total += dir_size(&entry.path()).unwrap_or(0);
+        } else {
+            total += metadata.len();
+        }
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directories_are_sized_with_their_subdirectories() {
+        let dir = std::env::temp_dir().join(format!(
+            "exchange-rate-footprint-{}-sized",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(dir.join("state")).unwrap();
+        std::fs::write(dir.join("config"), [0; 100]).unwrap();
+        std::fs::write(dir.join("state").join("usage"), [0; 28]).unwrap();
+        assert_eq!(dir_size(&dir), Some(128));
+        assert_eq!(dir_size(&dir.join("state")), Some(28));
+        let footprint = measure(Some(dir.clone()), Some(dir.join("state")));
+        assert_eq!(footprint.settings_bytes, Some(128));
+        assert_eq!(footprint.state_bytes, Some(28));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(dir_size(&dir), None);
+    }
+
+    #[test]
+    fn nothing_is_sized_without_directories() {
+        let footprint = measure(None, None);
+        assert_eq!(footprint.settings_bytes, None);
+        assert_eq!(footprint.state_bytes, None);
+    }
+}
@@ -0,0 +1,743 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use chrono::{DateTime, Local, Offset, TimeZone};
+use chrono_tz::Tz;
+use std::fmt;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crate::config::{DecimalGroupStyle, Pair};
+use crate::fl;
+use crate::metadata::metadata;
+
+/// Shown for currencies without a single country, such as EUR, XDR or cryptocurrencies.
+const GENERIC_CURRENCY_FLAG: &str = "💱";
+
+impl Pair {
+    /// How forex markets quote this pair, `None` if it has no convention.
+    pub fn market_convention(&self) -> Option<MarketConvention> {
+        metadata().convention(&self.code())
+    }
+}
+
+/// How a pair is quoted on forex markets, as listed in the currency metadata.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarketConvention {
+    /// Decimals of a quote, including the fractional pip.
+    pub decimals: usize,
+    /// The smallest price move usually quoted, e.g. `0.0001` for EURUSD.
+    pub pip: f64,
+}
+
+impl MarketConvention {
+    /// `change` expressed in pips.
+    pub fn pips(self, change: f64) -> f64 {
+        change / self.pip
+    }
+}
+
+/// The flag emoji of `currency`, spelled with the regional indicators of its country.
+pub fn currency_flag(currency: &str) -> String {
+    let Some(country) = metadata().country(currency) else {
+        return GENERIC_CURRENCY_FLAG.to_string();
+    };
+    country
+        .bytes()
+        .filter_map(|letter| char::from_u32(0x1F1E6 + u32::from(letter - b'A')))
+        .collect()
+}
+
+/// Parses a number typed by the user, accepting either a dot or a comma as decimal separator.
+pub fn parse_amount(text: &str) -> Option<f64> {
+    text.trim().replace(',', ".").parse().ok()
+}
+
+/// The separator `group_thousands` puts between groups of digits, a thin space as in SI
+/// style so it can't be mistaken for either decimal separator `parse_amount` accepts.
+const GROUP_SEPARATOR: char = '\u{2009}';
+
+/// The minus sign of negative changes, U+2212 rather than the shorter hyphen-minus.
+const MINUS_SIGN: char = '\u{2212}';
+
+/// The languages writing decimals with a comma, by their ISO 639-1 code.
+const DECIMAL_COMMA_LANGUAGES: &[&str] = &[
+    "cs", "da", "de", "es", "fi", "fr", "id", "it", "nb", "nl", "pl", "pt", "ru", "sv", "tr", "uk",
+];
+/// The languages separating a number from its percent sign with a no-break space.
+const SPACED_PERCENT_LANGUAGES: &[&str] =
+    &["cs", "da", "de", "fi", "fr", "nb", "pt", "ru", "sv", "uk"];
+
+/// The locale of the session, read once from the environment.
+static NUMBER_LOCALE: LazyLock<NumberLocale> = LazyLock::new(NumberLocale::from_env);
+
+/// How the locale of the session writes percentages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberLocale {
+    pub decimal_separator: char,
+    /// Whether a no-break space goes between the number and `%`.
+    pub spaced_percent: bool,
+}
+
+impl NumberLocale {
+    /// The locale of a tag such as `pt_BR.UTF-8` or `de-DE`, going by its language.
+    pub fn from_tag(tag: &str) -> Self {
+        let language = tag
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        NumberLocale {
+            decimal_separator: if DECIMAL_COMMA_LANGUAGES.contains(&language.as_str()) {
+                ','
+            } else {
+                '.'
+            },
+            spaced_percent: SPACED_PERCENT_LANGUAGES.contains(&language.as_str()),
+        }
+    }
+
+    /// The numeric locale as POSIX resolves it: `LC_ALL`, then `LC_NUMERIC`, then `LANG`.
+    fn from_env() -> Self {
+        let tag = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        NumberLocale::from_tag(&tag)
+    }
+
+    /// `number`, formatted by Rust, with the decimal separator of the locale.
+    fn localize(&self, number: &str) -> String {
+        number
+            .chars()
+            .map(|c| match c {
+                '.' => self.decimal_separator,
+                c => c,
+            })
+            .collect()
+    }
+}
+
+/// `value` as a percentage with `decimals` decimals, in the locale of the session, e.g.
+/// `1.50%` or `1,50 %`.
+pub fn format_percent(value: f64, decimals: usize) -> String {
+    percent_in(value, decimals, false, &NUMBER_LOCALE)
+}
+
+/// `value` as a signed change in percent, e.g. `+0.57%`, `−0,57 %`. A change rounding to
+/// zero is shown as `+0.00%` rather than with a minus.
+pub fn format_change_percent(value: f64, decimals: usize) -> String {
+    percent_in(value, decimals, true, &NUMBER_LOCALE)
+}
+
+/// `value` in percent written the way `locale` does, with a sign if `signed`.
+fn percent_in(value: f64, decimals: usize, signed: bool, locale: &NumberLocale) -> String {
+    let digits = format!("{:.*}", decimals, value.abs());
+    let negative = value < 0.0 && digits.bytes().any(|digit| (b'1'..=b'9').contains(&digit));
+    let mut text = String::new();
+    if negative {
+        text.push(MINUS_SIGN);
+    } else if signed {
+        text.push('+');
+    }
+    text.push_str(&locale.localize(&digits));
+    if locale.spaced_percent {
+        text.push('\u{a0}');
+    }
+    text.push('%');
+    text
+}
+
+/// Smallest rate, in size, `abbreviate_rate` writes in scientific notation. Bitcoin quoted
+/// in the weakest currencies stays below it.
+pub const ABBREVIATE_ABOVE: f64 = 1e12;
+/// Largest nonzero rate, in size, below which `abbreviate_rate` writes it in scientific
+/// notation.
+pub const ABBREVIATE_BELOW: f64 = 1e-12;
+
+/// `rate` in scientific notation with 3 decimals, e.g. `1.235e15`, when it is too large or
+/// too small to be written out in the popup and panel without pushing everything else out:
+/// from `ABBREVIATE_ABOVE`, or nonzero below `ABBREVIATE_BELOW`. Such rates are wrong quotes
+/// more often than not, but they are shown as sent. `None` for every other rate.
+pub fn abbreviate_rate(rate: f64) -> Option<String> {
+    let size = rate.abs();
+    let abbreviated =
+        size.is_finite() && (size >= ABBREVIATE_ABOVE || (size > 0.0 && size < ABBREVIATE_BELOW));
+    abbreviated.then(|| format!("{rate:.3e}"))
+}
+
+/// `rate` as the panel and popup write it: with `precision` decimals rounded as `style`
+/// rounds them, without the zeros ending them unless `trailing_zeros`, or as many as it
+/// has without a precision, see `abbreviate_rate` for the rates it can't be written out
+/// with, which keep their 3 decimals either way. Depends on nothing but its arguments, so
+/// what the D-Bus interface and `--query` print is what is on screen. Written in the locale
+/// of the session, as changes are, so a row never mixes decimal separators.
+pub fn format_rate(
+    rate: f64,
+    precision: Option<usize>,
+    style: DecimalGroupStyle,
+    trailing_zeros: bool,
+) -> String {
+    format_rate_in(rate, precision, style, trailing_zeros, &NUMBER_LOCALE)
+}
+
+/// `format_rate` written the way `locale` does.
+fn format_rate_in(
+    rate: f64,
+    precision: Option<usize>,
+    style: DecimalGroupStyle,
+    trailing_zeros: bool,
+    locale: &NumberLocale,
+) -> String {
+    if !rate.is_finite() {
+        return fl!("not-available");
+    }
+    if let Some(abbreviated) = abbreviate_rate(rate) {
+        return locale.localize(&abbreviated);
+    }
+    let text = match precision {
+        Some(precision) => format!("{:.*}", precision, apply_rounding(rate, precision, style)),
+        None => rate.to_string(),
+    };
+    let text = if trailing_zeros {
+        text
+    } else {
+        trim_trailing_zeros(&text)
+    };
+    if style == DecimalGroupStyle::Every3 {
+        group_thousands(&text, locale)
+    } else {
+        locale.localize(&text)
+    }
+}
+
+/// `rate` rounded to `precision` decimals as `style` rounds it, for display.
+///
+/// `BankersRounding` rounds a tie to the nearest even digit: 5.125 gives 5.12 and 5.135
+/// gives 5.14. Always rounding ties up skews every total and average taken from the
+/// displayed rates upward, rounding them to even cancels out over many values, which is why
+/// accounting and banking standards specify it. Ties are those of the decimal the user
+/// sees, the shortest one reading back as `rate`, and are rounded on its digits with
+/// integer arithmetic: 5.135 is stored as 5.13499…, which float rounding would round down.
+/// Other styles, and rates too large to round this way, are returned as they are.
+pub fn apply_rounding(rate: f64, precision: usize, style: DecimalGroupStyle) -> f64 {
+    if style != DecimalGroupStyle::BankersRounding || !rate.is_finite() {
+        return rate;
+    }
+    // `Display` never uses exponent notation, so this is all the digits there are.
+    let text = rate.abs().to_string();
+    let (int, frac) = text.split_once('.').unwrap_or((&text, ""));
+    if frac.len() <= precision {
+        return rate;
+    }
+    let Some(scale) = 10u128.checked_pow(precision as u32) else {
+        return rate;
+    };
+    let Ok(mut kept) = format!("{int}{}", &frac[..precision]).parse::<u128>() else {
+        return rate;
+    };
+    let mut dropped = frac[precision..].bytes();
+    let first = dropped.next().map_or(0, |digit| digit - b'0');
+    let beyond_half = dropped.any(|digit| digit != b'0');
+    if first > 5 || (first == 5 && (beyond_half || kept % 2 == 1)) {
+        kept += 1;
+    }
+    let rounded: f64 = format!("{}.{:0>precision$}", kept / scale, kept % scale)
+        .parse()
+        .unwrap_or(rate.abs());
+    rounded.copysign(rate)
+}
+
+/// `number` as formatted by Rust, with its integer digits grouped by three and the decimal
+/// separator of `locale`, e.g. `15 234.50` or `15 234,50`.
+pub fn group_thousands(number: &str, locale: &NumberLocale) -> String {
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", number),
+    };
+    let (int, frac) = match digits.find('.') {
+        Some(dot) => digits.split_at(dot),
+        None => (digits, ""),
+    };
+    let mut grouped = String::from(sign);
+    for (i, digit) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            grouped.push(GROUP_SEPARATOR);
+        }
+        grouped.push(digit);
+    }
+    grouped + &locale.localize(frac)
+}
+
+/// `number` as formatted by Rust without the zeros ending its decimals, nor the point if
+/// none are left, e.g. `5.4` for `5.4000`. Numbers without decimals are kept as they are.
+fn trim_trailing_zeros(number: &str) -> String {
+    if !number.contains('.') {
+        return number.to_string();
+    }
+    number
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// `bytes` for display, in the largest binary unit keeping it at least 1, e.g. `12.3 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Formats the time left until a fetch, e.g. `3m 42s`.
+pub fn format_countdown(left: Duration) -> String {
+    let secs = left.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
+}
+
+/// When a quote was taken, on the clock of its market and on the user's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteTime {
+    /// e.g. `17:59 -03`.
+    pub market: String,
+    /// e.g. `21:59`, `None` when the user's clock showed the same time as the market's.
+    pub local: Option<String>,
+}
+
+/// Places the Unix `timestamp` of a quote in the `market` zone and in the local one.
+/// `None` when the provider sent no timestamp.
+///
+/// Zones are compared by their offset at `timestamp` rather than by name, so a user in
+/// any zone currently agreeing with the market's gets a single time, whichever side of
+/// a DST transition each is on.
+pub fn quote_time(timestamp: i64, market: Tz) -> Option<QuoteTime> {
+    quote_time_in(timestamp, market, &Local)
+}
+
+/// `quote_time` for a user whose clock is in the `local` zone.
+fn quote_time_in<L>(timestamp: i64, market: Tz, local: &L) -> Option<QuoteTime>
+where
+    L: TimeZone,
+    L::Offset: fmt::Display,
+{
+    if timestamp <= 0 {
+        return None;
+    }
+    let at_market = market.timestamp_opt(timestamp, 0).single()?;
+    let at_local = local.timestamp_opt(timestamp, 0).single()?;
+    let same_clock = at_market.offset().fix() == at_local.offset().fix();
+    // tzdata names many zones only by their offset, e.g. `-03` for São Paulo.
+    let zone = at_market.format("%Z").to_string();
+    let zone = if zone.starts_with(['+', '-']) {
+        format!("UTC{zone}")
+    } else {
+        zone
+    };
+    Some(QuoteTime {
+        market: format!("{} {zone}", at_market.format("%H:%M")),
+        local: (!same_clock).then(|| at_local.format("%H:%M").to_string()),
+    })
+}
+
+/// The time of day of `at` on the user's clock, e.g. `21:59:03`, in the zone the system
+/// is in now rather than the one `at` was taken in.
+pub fn clock_time<T: TimeZone>(at: &DateTime<T>) -> String {
+    clock_time_in(at, &Local)
+}
+
+/// `clock_time` for a system in the `zone`.
+fn clock_time_in<T: TimeZone, Z>(at: &DateTime<T>, zone: &Z) -> String
+where
+    Z: TimeZone,
+    Z::Offset: fmt::Display,
+{
+    at.with_timezone(zone).format("%H:%M:%S").to_string()
+}
+
+/// Shortens `text` to at most `max_chars` characters, ending it with an ellipsis if cut.
+pub fn ellipsize(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    short.push('…');
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn trim_trailing_zeros_keeps_integer_digits() {
+        assert_eq!(trim_trailing_zeros("5.4000"), "5.4");
+        assert_eq!(trim_trailing_zeros("100.00"), "100");
+        assert_eq!(trim_trailing_zeros("100"), "100");
+        assert_eq!(trim_trailing_zeros("-0.50"), "-0.5");
+    }
+
+    /// `rate` in en_US, with and without its trailing zeros.
+    fn padded_and_trimmed(
+        rate: f64,
+        precision: Option<usize>,
+        style: DecimalGroupStyle,
+    ) -> (String, String) {
+        let en_us = NumberLocale::from_tag("en_US");
+        (
+            format_rate_in(rate, precision, style, true, &en_us),
+            format_rate_in(rate, precision, style, false, &en_us),
+        )
+    }
+
+    #[test]
+    fn padding_writes_every_decimal_of_the_precision() {
+        let style = DecimalGroupStyle::None;
+        // Exact integers.
+        assert_eq!(
+            padded_and_trimmed(5.0, Some(4), style),
+            ("5.0000".to_string(), "5".to_string())
+        );
+        assert_eq!(
+            padded_and_trimmed(100.0, Some(2), style),
+            ("100.00".to_string(), "100".to_string())
+        );
+        // Fewer natural decimals than the precision.
+        assert_eq!(
+            padded_and_trimmed(5.4, Some(4), style),
+            ("5.4000".to_string(), "5.4".to_string())
+        );
+        // More of them, rounded first, then trimmed.
+        assert_eq!(
+            padded_and_trimmed(5.40004, Some(4), style),
+            ("5.4000".to_string(), "5.4".to_string())
+        );
+        // Without a precision, as many as there are either way.
+        assert_eq!(
+            padded_and_trimmed(5.4, None, style),
+            ("5.4".to_string(), "5.4".to_string())
+        );
+    }
+
+    #[test]
+    fn precision_zero_has_no_decimals_to_pad_or_trim() {
+        let style = DecimalGroupStyle::None;
+        assert_eq!(
+            padded_and_trimmed(5.4, Some(0), style),
+            ("5".to_string(), "5".to_string())
+        );
+        assert_eq!(
+            padded_and_trimmed(100.0, Some(0), style),
+            ("100".to_string(), "100".to_string())
+        );
+    }
+
+    #[test]
+    fn padding_composes_with_grouping_and_the_locale() {
+        assert_eq!(
+            padded_and_trimmed(15234.5, Some(4), DecimalGroupStyle::Every3),
+            (
+                "15\u{2009}234.5000".to_string(),
+                "15\u{2009}234.5".to_string()
+            )
+        );
+        assert_eq!(
+            padded_and_trimmed(1000.0, Some(2), DecimalGroupStyle::Every3),
+            ("1\u{2009}000.00".to_string(), "1\u{2009}000".to_string())
+        );
+        let pt_br = NumberLocale::from_tag("pt_BR");
+        assert_eq!(
+            format_rate_in(15234.5, Some(4), DecimalGroupStyle::Every3, false, &pt_br),
+            "15\u{2009}234,5"
+        );
+        assert_eq!(
+            format_rate_in(5.4, Some(4), DecimalGroupStyle::None, false, &pt_br),
+            "5,4"
+        );
+    }
+
+    #[test]
+    fn abbreviated_rates_ignore_padding() {
+        let style = DecimalGroupStyle::None;
+        assert_eq!(
+            padded_and_trimmed(1e20, Some(4), style),
+            ("1.000e20".to_string(), "1.000e20".to_string())
+        );
+        assert_eq!(
+            padded_and_trimmed(1.5e-13, Some(4), style),
+            ("1.500e-13".to_string(), "1.500e-13".to_string())
+        );
+    }
+
+    #[test]
+    fn bankers_rounding_rounds_ties_to_even() {
+        let round =
+            |rate, precision| apply_rounding(rate, precision, DecimalGroupStyle::BankersRounding);
+        // Ties, whichever way the binary value leans.
+        assert_eq!(round(5.125, 2), 5.12);
+        assert_eq!(round(5.135, 2), 5.14);
+        assert_eq!(round(5.145, 2), 5.14);
+        assert_eq!(round(1.005, 2), 1.0);
+        assert_eq!(round(0.5, 0), 0.0);
+        assert_eq!(round(1.5, 0), 2.0);
+        assert_eq!(round(2.5, 0), 2.0);
+        // Anything past the tie rounds up, anything short of it down.
+        assert_eq!(round(5.1251, 2), 5.13);
+        assert_eq!(round(5.1249, 2), 5.12);
+        // Negatives round as their size does.
+        assert_eq!(round(-2.5, 0), -2.0);
+        assert_eq!(round(-5.135, 2), -5.14);
+        assert_eq!(round(-5.1251, 2), -5.13);
+        // Carries across the decimal point.
+        assert_eq!(round(9.995, 2), 10.0);
+        assert_eq!(round(0.9995, 3), 1.0);
+        assert_eq!(round(99.5, 0), 100.0);
+        // A precision beyond the digits there are leaves the rate as it is.
+        assert_eq!(round(5.12, 4), 5.12);
+        assert_eq!(round(5.0, 2), 5.0);
+        assert_eq!(round(123.0, 0), 123.0);
+    }
+
+    #[test]
+    fn only_bankers_rounding_rounds() {
+        assert_eq!(apply_rounding(5.125, 2, DecimalGroupStyle::None), 5.125);
+        assert_eq!(apply_rounding(5.125, 2, DecimalGroupStyle::Every3), 5.125);
+        let round =
+            |rate, precision| apply_rounding(rate, precision, DecimalGroupStyle::BankersRounding);
+        assert!(round(f64::NAN, 2).is_nan());
+        assert_eq!(round(f64::INFINITY, 2), f64::INFINITY);
+        assert_eq!(round(1e300, 2), 1e300);
+    }
+
+    fn percent(tag: &str, value: f64, decimals: usize, signed: bool) -> String {
+        percent_in(value, decimals, signed, &NumberLocale::from_tag(tag))
+    }
+
+    #[test]
+    fn locales_are_read_by_language() {
+        let comma_spaced = NumberLocale {
+            decimal_separator: ',',
+            spaced_percent: true,
+        };
+        let point = NumberLocale {
+            decimal_separator: '.',
+            spaced_percent: false,
+        };
+        assert_eq!(NumberLocale::from_tag("pt_BR.UTF-8"), comma_spaced);
+        assert_eq!(NumberLocale::from_tag("de-DE"), comma_spaced);
+        assert_eq!(NumberLocale::from_tag("de_AT@euro"), comma_spaced);
+        assert_eq!(NumberLocale::from_tag("en_US.UTF-8"), point);
+        assert_eq!(NumberLocale::from_tag("C"), point);
+        assert_eq!(NumberLocale::from_tag(""), point);
+        assert_eq!(
+            NumberLocale::from_tag("es_ES"),
+            NumberLocale {
+                decimal_separator: ',',
+                spaced_percent: false,
+            }
+        );
+    }
+
+    #[test]
+    fn changes_in_pt_br() {
+        assert_eq!(percent("pt_BR.UTF-8", 0.57, 2, true), "+0,57\u{a0}%");
+        assert_eq!(
+            percent("pt_BR.UTF-8", -0.57, 2, true),
+            "\u{2212}0,57\u{a0}%"
+        );
+        assert_eq!(
+            percent("pt_BR.UTF-8", -12.345, 1, true),
+            "\u{2212}12,3\u{a0}%"
+        );
+        assert_eq!(percent("pt_BR.UTF-8", 1.5, 2, false), "1,50\u{a0}%");
+    }
+
+    #[test]
+    fn changes_in_en_us() {
+        assert_eq!(percent("en_US.UTF-8", 0.57, 2, true), "+0.57%");
+        assert_eq!(percent("en_US.UTF-8", -0.57, 2, true), "\u{2212}0.57%");
+        assert_eq!(percent("en_US.UTF-8", 3.0, 0, true), "+3%");
+        assert_eq!(percent("en_US.UTF-8", 1.5, 2, false), "1.50%");
+    }
+
+    #[test]
+    fn changes_in_de_de() {
+        assert_eq!(percent("de_DE.UTF-8", 0.57, 2, true), "+0,57\u{a0}%");
+        assert_eq!(
+            percent("de_DE.UTF-8", -0.57, 2, true),
+            "\u{2212}0,57\u{a0}%"
+        );
+        assert_eq!(
+            percent("de_DE.UTF-8", -100.0, 2, true),
+            "\u{2212}100,00\u{a0}%"
+        );
+    }
+
+    #[test]
+    fn negatives_use_the_minus_sign_not_a_hyphen() {
+        for tag in ["pt_BR", "en_US", "de_DE"] {
+            let text = percent(tag, -0.57, 2, true);
+            assert!(text.starts_with(MINUS_SIGN), "{tag}: {text}");
+            assert!(!text.contains('-'), "{tag}: {text}");
+            // Unsigned percentages still show when they are negative.
+            assert!(percent(tag, -0.57, 2, false).starts_with(MINUS_SIGN));
+        }
+    }
+
+    #[test]
+    fn changes_rounding_to_zero_are_positive() {
+        assert_eq!(percent("en_US", -0.004, 2, true), "+0.00%");
+        assert_eq!(percent("de_DE", -0.004, 2, true), "+0,00\u{a0}%");
+        assert_eq!(percent("pt_BR", -0.0, 2, true), "+0,00\u{a0}%");
+        assert_eq!(percent("en_US", -0.004, 2, false), "0.00%");
+        assert_eq!(percent("en_US", -0.006, 2, true), "\u{2212}0.01%");
+    }
+
+    #[test]
+    fn changes_are_counted_in_pips_of_the_pair() {
+        let eurusd = MarketConvention {
+            decimals: 5,
+            pip: 0.0001,
+        };
+        let usdjpy = MarketConvention {
+            decimals: 3,
+            pip: 0.01,
+        };
+        assert_eq!(format!("{:+.0}", eurusd.pips(1.0862 - 1.0850)), "+12");
+        assert_eq!(format!("{:+.0}", usdjpy.pips(151.37 - 151.62)), "-25");
+        assert_eq!(format!("{:+.1}", usdjpy.pips(0.005)), "+0.5");
+    }
+
+    const SAO_PAULO: Tz = chrono_tz::America::Sao_Paulo;
+
+    fn times(market: &str, local: Option<&str>) -> QuoteTime {
+        QuoteTime {
+            market: market.to_string(),
+            local: local.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn quote_times_follow_the_dst_of_the_user() {
+        // Berlin moves to summer time at 01:00 UTC on the 31st of March 2024.
+        let berlin = chrono_tz::Europe::Berlin;
+        assert_eq!(
+            quote_time_in(1711845000, SAO_PAULO, &berlin),
+            Some(times("21:30 UTC-03", Some("01:30")))
+        );
+        assert_eq!(
+            quote_time_in(1711848600, SAO_PAULO, &berlin),
+            Some(times("22:30 UTC-03", Some("03:30")))
+        );
+    }
+
+    #[test]
+    fn quote_times_follow_the_dst_of_the_market() {
+        // New York moves to summer time at 07:00 UTC on the 10th of March 2024, and
+        // Toronto with it.
+        let (new_york, toronto) = (chrono_tz::America::New_York, chrono_tz::America::Toronto);
+        assert_eq!(
+            quote_time_in(1710052200, new_york, &toronto),
+            Some(times("01:30 EST", None))
+        );
+        assert_eq!(
+            quote_time_in(1710055800, new_york, &toronto),
+            Some(times("03:30 EDT", None))
+        );
+        assert_eq!(
+            quote_time_in(1710055800, new_york, &Utc),
+            Some(times("03:30 EDT", Some("07:30")))
+        );
+    }
+
+    #[test]
+    fn a_clock_agreeing_with_the_market_shows_one_time() {
+        assert_eq!(
+            quote_time_in(1711845000, SAO_PAULO, &SAO_PAULO),
+            Some(times("21:30 UTC-03", None))
+        );
+        // Another zone, on the same offset.
+        let buenos_aires = chrono_tz::America::Argentina::Buenos_Aires;
+        assert_eq!(
+            quote_time_in(1711845000, SAO_PAULO, &buenos_aires),
+            Some(times("21:30 UTC-03", None))
+        );
+    }
+
+    #[test]
+    fn quotes_without_a_timestamp_have_no_time() {
+        assert_eq!(quote_time_in(0, SAO_PAULO, &Utc), None);
+        assert_eq!(quote_time_in(-1, SAO_PAULO, &Utc), None);
+    }
+
+    #[test]
+    fn clock_times_follow_the_zone_the_system_is_in() {
+        let berlin = chrono_tz::Europe::Berlin;
+        // Taken at 21:59:03 in São Paulo.
+        let at = SAO_PAULO.timestamp_opt(1711846743, 0).unwrap();
+        assert_eq!(clock_time_in(&at, &SAO_PAULO), "21:59:03");
+        // The system moved to Berlin since, where it was 01:59:03 already.
+        assert_eq!(clock_time_in(&at, &berlin), "01:59:03");
+        assert_eq!(
+            clock_time_in(&at.with_timezone(&berlin), &SAO_PAULO),
+            "21:59:03"
+        );
+        assert_eq!(clock_time_in(&at, &Utc), "00:59:03");
+    }
+
+    #[test]
+    fn rates_use_the_decimal_separator_of_changes() {
+        let pt_br = NumberLocale::from_tag("pt_BR.UTF-8");
+        let rate = |rate, style| format_rate_in(rate, Some(4), style, true, &pt_br);
+        let row = format!(
+            "{} {}",
+            rate(5.4321, DecimalGroupStyle::None),
+            percent("pt_BR.UTF-8", 0.57, 2, true)
+        );
+        assert_eq!(row, "5,4321 +0,57\u{a0}%");
+        assert_eq!(
+            rate(15234.5, DecimalGroupStyle::Every3),
+            "15\u{2009}234,5000"
+        );
+        assert_eq!(rate(1e20, DecimalGroupStyle::None), "1,000e20");
+        let en_us = NumberLocale::from_tag("en_US.UTF-8");
+        assert_eq!(
+            format_rate_in(15234.5, Some(2), DecimalGroupStyle::Every3, true, &en_us),
+            "15\u{2009}234.50"
+        );
+    }
+
+    #[test]
+    fn thousands_are_grouped_on_the_integer_digits_only() {
+        let en_us = NumberLocale::from_tag("en_US");
+        let de_de = NumberLocale::from_tag("de_DE");
+        assert_eq!(
+            group_thousands("1234567.891", &en_us),
+            "1\u{2009}234\u{2009}567.891"
+        );
+        assert_eq!(group_thousands("-1234.5", &de_de), "-1\u{2009}234,5");
+        assert_eq!(group_thousands("123", &de_de), "123");
+        assert_eq!(group_thousands("0.0001", &de_de), "0,0001");
+    }
+
+    #[test]
+    fn sizes_are_shown_in_the_largest_unit_they_fill() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(12_900_000), "12.3 MiB");
+        assert_eq!(format_bytes(5 << 40), "5120.0 GiB");
+    }
+}
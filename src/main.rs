@@ -1,15 +1,55 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
-use app::YourApp;
+use app::{Flags, YourApp};
+use tracing_subscriber::EnvFilter;
 /// The `app` module is used by convention to indicate the main component of our application.
 mod app;
+mod bundled;
+mod capabilities;
+mod clock;
+mod config;
 mod core;
+mod dbus;
+mod email;
+mod exact;
+mod export;
+mod expression;
+mod footprint;
+mod format;
+mod market;
+mod metadata;
+mod portal;
+mod power;
+mod providers;
+mod query;
+mod report;
+mod scheduler;
+mod session;
+mod shortcuts;
+mod state;
 
 /// The `cosmic::app::run()` function is the starting point of your application.
 /// It takes two arguments:
 /// - `settings` is a structure that contains everything relevant with your app's configuration, such as antialiasing, themes, icons, etc...
-/// - `()` is the flags that your app needs to use before it starts.
-///  If your app does not need any flags, you can pass in `()`.
+/// - `flags` is the data that your app needs to use before it starts, here the startup
+///  overrides parsed from the command line.
+///
+/// With `--query`, the running applet is asked for a rate instead, see `query`.
 fn main() -> cosmic::iced::Result {
-    cosmic::applet::run::<YourApp>(true, ())
+    init_logging();
+    let flags = Flags::from_args(std::env::args().skip(1));
+    metadata::init(flags.config_path.as_deref());
+    if let Some(pair) = flags.query.clone() {
+        std::process::exit(query::run(pair, flags.query_format));
+    }
+    cosmic::applet::run::<YourApp>(true, flags)
+}
+
+/// Logs to stderr at the levels `RUST_LOG` asks for, warnings and errors by default.
+fn init_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
 }
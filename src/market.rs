@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! How old a quote may get before it is stale, given when its market trades.
+//!
+//! Fiat pairs trade on the forex market, open around the clock from Sunday 17:00 to Friday
+//! 17:00 New York time. Over the weekend their latest quote is Friday's close however old
+//! it gets, which is normal rather than stale. Pairs with a cryptocurrency trade every day
+//! and keep strict age rules. Holidays aren't known, quotes go stale on them as on any
+//! trading day.
+
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::config::Pair;
+use crate::metadata::metadata;
+
+/// The zone the forex week opens and closes in.
+const FOREX_ZONE: Tz = chrono_tz::America::New_York;
+/// The hour the forex week opens on Sunday and closes on Friday, in `FOREX_ZONE`.
+const FOREX_EDGE_HOUR: u32 = 17;
+
+impl Pair {
+    /// Whether the pair trades every day, as those with a cryptocurrency do, rather than on
+    /// the forex week.
+    pub fn trades_all_week(&self) -> bool {
+        metadata().is_crypto(self.base()) || metadata().is_crypto(self.quote())
+    }
+}
+
+/// How the age of a quote reads, see `classify_age`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteAge {
+    /// No older than the pair's quotes usually get.
+    Fresh,
+    /// Older, but from before the forex market closed for the weekend.
+    MarketClosed,
+    /// Older while the pair's market trades.
+    Stale,
+}
+
+impl QuoteAge {
+    /// The id of the age in the diagnostic report.
+    pub fn id(self) -> &'static str {
+        match self {
+            QuoteAge::Fresh => "fresh",
+            QuoteAge::MarketClosed => "market_closed",
+            QuoteAge::Stale => "stale",
+        }
+    }
+}
+
+/// When the forex market closed for the weekend, `None` while it is open at `now`.
+pub fn forex_closed_since(now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let market = now.with_timezone(&FOREX_ZONE);
+    let after_edge = market.hour() >= FOREX_EDGE_HOUR;
+    let days_since_friday = match market.weekday() {
+        Weekday::Fri if after_edge => 0,
+        Weekday::Sat => 1,
+        Weekday::Sun if !after_edge => 2,
+        _ => return None,
+    };
+    let friday = market.date_naive() - Duration::days(days_since_friday);
+    let close = friday.and_hms_opt(FOREX_EDGE_HOUR, 0, 0)?;
+    let close = FOREX_ZONE.from_local_datetime(&close).earliest()?;
+    Some(close.with_timezone(&Utc))
+}
+
+/// Whether the market of `pair` is closed at `now`: that of fiat pairs is over the weekend.
+pub fn market_closed(pair: &Pair, now: DateTime<Utc>) -> bool {
+    !pair.trades_all_week() && forex_closed_since(now).is_some()
+}
+
+/// How old the quote taken at `quoted_at` reads at `now`, for a pair whose quotes are
+/// stale once older than `max_age`.
+///
+/// Pairs that `trades_all_week` are stale from `max_age` whatever the day. Fiat pairs
+/// aren't while the forex market is closed, as long as the quote is no older than
+/// `max_age` before the close: it is then the last quote of the week.
+pub fn classify_age(
+    quoted_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    max_age: Duration,
+    trades_all_week: bool,
+) -> QuoteAge {
+    if now - quoted_at <= max_age {
+        return QuoteAge::Fresh;
+    }
+    match forex_closed_since(now) {
+        Some(close) if !trades_all_week && quoted_at >= close - max_age => QuoteAge::MarketClosed,
+        _ => QuoteAge::Stale,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How old quotes of the pairs usually get.
+    fn max_age() -> Duration {
+        Duration::minutes(10)
+    }
+
+    /// The instant at `hour:minute` on `day` of October 2026 in New York, when it is on
+    /// daylight saving time. October 9th is a Friday.
+    fn new_york(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        FOREX_ZONE
+            .with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    /// How a quote from Friday 16:55, just before the close, reads at `now` for a fiat
+    /// pair and for a crypto one.
+    fn friday_quote_at(now: DateTime<Utc>) -> (QuoteAge, QuoteAge) {
+        let quoted_at = new_york(9, 16, 55);
+        (
+            classify_age(quoted_at, now, max_age(), false),
+            classify_age(quoted_at, now, max_age(), true),
+        )
+    }
+
+    #[test]
+    fn the_market_closes_friday_and_opens_sunday_at_five() {
+        let close = new_york(9, 17, 0);
+        assert_eq!(close, Utc.with_ymd_and_hms(2026, 10, 9, 21, 0, 0).unwrap());
+        assert_eq!(forex_closed_since(new_york(9, 16, 59)), None);
+        assert_eq!(forex_closed_since(close), Some(close));
+        assert_eq!(forex_closed_since(new_york(10, 12, 0)), Some(close));
+        assert_eq!(forex_closed_since(new_york(11, 16, 59)), Some(close));
+        assert_eq!(forex_closed_since(new_york(11, 17, 0)), None);
+        assert_eq!(forex_closed_since(new_york(12, 9, 0)), None);
+    }
+
+    #[test]
+    fn the_close_follows_new_york_off_daylight_saving_time() {
+        // January 9th 2027 is a Saturday, New York is then five hours behind UTC.
+        let saturday = Utc.with_ymd_and_hms(2027, 1, 9, 12, 0, 0).unwrap();
+        assert_eq!(
+            forex_closed_since(saturday),
+            Some(Utc.with_ymd_and_hms(2027, 1, 8, 22, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn friday_close_plus_an_hour() {
+        assert_eq!(
+            friday_quote_at(new_york(9, 18, 0)),
+            (QuoteAge::MarketClosed, QuoteAge::Stale)
+        );
+    }
+
+    #[test]
+    fn saturday() {
+        assert_eq!(
+            friday_quote_at(new_york(10, 12, 0)),
+            (QuoteAge::MarketClosed, QuoteAge::Stale)
+        );
+    }
+
+    #[test]
+    fn sunday_night_before_the_open() {
+        assert_eq!(
+            friday_quote_at(new_york(11, 16, 50)),
+            (QuoteAge::MarketClosed, QuoteAge::Stale)
+        );
+    }
+
+    #[test]
+    fn monday_after_the_open() {
+        assert_eq!(
+            friday_quote_at(new_york(12, 9, 0)),
+            (QuoteAge::Stale, QuoteAge::Stale)
+        );
+        // So is it right after the open on Sunday.
+        assert_eq!(
+            friday_quote_at(new_york(11, 17, 30)),
+            (QuoteAge::Stale, QuoteAge::Stale)
+        );
+        let monday = new_york(12, 9, 0);
+        assert_eq!(
+            classify_age(monday - Duration::minutes(2), monday, max_age(), false),
+            QuoteAge::Fresh
+        );
+    }
+
+    #[test]
+    fn a_quote_frozen_before_the_close_is_stale_over_the_weekend() {
+        // Stuck since Friday noon: the weekend doesn't excuse the hours before the close.
+        let quoted_at = new_york(9, 12, 0);
+        assert_eq!(
+            classify_age(quoted_at, new_york(10, 12, 0), max_age(), false),
+            QuoteAge::Stale
+        );
+        // The last moment still counted as the week's final quote.
+        let quoted_at = new_york(9, 16, 50);
+        assert_eq!(
+            classify_age(quoted_at, new_york(10, 12, 0), max_age(), false),
+            QuoteAge::MarketClosed
+        );
+    }
+
+    #[test]
+    fn recent_quotes_are_fresh_any_day() {
+        for now in [
+            new_york(9, 18, 0),
+            new_york(10, 12, 0),
+            new_york(11, 16, 50),
+        ] {
+            let quoted_at = now - Duration::minutes(5);
+            assert_eq!(
+                classify_age(quoted_at, now, max_age(), false),
+                QuoteAge::Fresh
+            );
+            assert_eq!(
+                classify_age(quoted_at, now, max_age(), true),
+                QuoteAge::Fresh
+            );
+        }
+    }
+
+    #[test]
+    fn crypto_trades_all_week() {
+        assert!(Pair::parse("BTCUSD").unwrap().trades_all_week());
+        assert!(Pair::parse("USDT-BRL").unwrap().trades_all_week());
+        assert!(!Pair::parse("USDBRL").unwrap().trades_all_week());
+    }
+
+    #[test]
+    fn only_fiat_markets_close_for_the_weekend() {
+        let fiat = Pair::parse("USDBRL").unwrap();
+        let crypto = Pair::parse("BTCUSD").unwrap();
+        assert!(!market_closed(&fiat, new_york(9, 16, 59)));
+        assert!(market_closed(&fiat, new_york(9, 17, 0)));
+        assert!(market_closed(&fiat, new_york(10, 12, 0)));
+        assert!(!market_closed(&fiat, new_york(11, 17, 0)));
+        assert!(!market_closed(&crypto, new_york(10, 12, 0)));
+    }
+}
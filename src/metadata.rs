@@ -0,0 +1,458 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Currency metadata: the countries of currencies, which ones are cryptocurrencies, the
+//! market conventions of pairs and how often providers update pairs they don't update at
+//! their usual pace.
+//!
+//! The data is bundled from `res/currencies.toml` and parsed at startup, then the user's
+//! `currencies.toml` in the applet's settings directory, the one under `--config-path` if
+//! given, is merged on top so entries can be corrected without a new release. Everything
+//! reads it through [`metadata`].
+
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::config::{settings_dir, Pair, MAX_PRECISION};
+use crate::format::MarketConvention;
+use crate::providers::{Cadence, PROVIDERS};
+
+/// The bundled metadata.
+const BUNDLED: &str = include_str!("../res/currencies.toml");
+/// Name of the user's override file, next to the applet's COSMIC config.
+const OVERRIDE_FILE: &str = "currencies.toml";
+
+/// A currency issued by a single country.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct CurrencyEntry {
+    code: String,
+    /// ISO 3166 alpha-2 code of the country.
+    country: String,
+}
+
+/// The market convention of a pair.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct ConventionEntry {
+    /// The pair's code, e.g. `EURUSD`.
+    pair: String,
+    decimals: usize,
+    pip: f64,
+}
+
+/// How often a provider updates a pair, when it isn't at the provider's usual pace.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct CadenceEntry {
+    /// One of `PROVIDERS`, in any case.
+    provider: String,
+    /// The pair's code, e.g. `USDBOB`.
+    pair: String,
+    cadence: Cadence,
+}
+
+/// The layout of `res/currencies.toml` and of the override file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+struct MetadataFile {
+    #[serde(default)]
+    currencies: Vec<CurrencyEntry>,
+    /// The codes of cryptocurrencies, which trade every day.
+    #[serde(default)]
+    crypto: Vec<String>,
+    #[serde(default)]
+    conventions: Vec<ConventionEntry>,
+    #[serde(default)]
+    cadences: Vec<CadenceEntry>,
+}
+
+/// Currency metadata, merged from the bundled file and the user's override.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    countries: HashMap<String, String>,
+    crypto: HashSet<String>,
+    conventions: HashMap<String, MarketConvention>,
+    /// Keyed by provider name and pair code.
+    cadences: HashMap<(String, String), Cadence>,
+}
+
+impl Metadata {
+    /// Used if the bundled file doesn't parse, enough for the default watchlist.
+    fn minimal() -> Self {
+        let mut metadata = Metadata::default();
+        for (code, country) in [("USD", "US"), ("BRL", "BR")] {
+            metadata
+                .countries
+                .insert(code.to_string(), country.to_string());
+        }
+        metadata.conventions.insert(
+            "USDBRL".to_string(),
+            MarketConvention {
+                decimals: 5,
+                pip: 0.0001,
+            },
+        );
+        metadata
+    }
+
+    /// The country of `currency`, `None` for currencies without a single country.
+    pub fn country(&self, currency: &str) -> Option<&str> {
+        self.countries.get(currency).map(String::as_str)
+    }
+
+    /// Whether `currency` is a cryptocurrency.
+    pub fn is_crypto(&self, currency: &str) -> bool {
+        self.crypto.contains(currency)
+    }
+
+    /// The market convention of the pair with `code`, `None` if it has none.
+    pub fn convention(&self, code: &str) -> Option<MarketConvention> {
+        self.conventions.get(code).copied()
+    }
+
+    /// How often `provider` updates the pair with `code`, `None` if at its usual pace.
+    pub fn cadence(&self, provider: &str, code: &str) -> Option<Cadence> {
+        self.cadences
+            .get(&(provider.to_string(), code.to_string()))
+            .copied()
+    }
+
+    /// Adds the valid entries of `file`, replacing those with the same code. Invalid or
+    /// repeated entries are skipped with a warning naming `source`.
+    fn merge(&mut self, file: MetadataFile, source: &str) {
+        let mut seen = Vec::new();
+        for entry in file.currencies {
+            let valid_code =
+                entry.code.len() >= 3 && entry.code.chars().all(|c| c.is_ascii_uppercase());
+            let valid_country =
+                entry.country.len() == 2 && entry.country.chars().all(|c| c.is_ascii_uppercase());
+            if !valid_code || !valid_country {
+                tracing::warn!("Ignoring invalid currency {:?} in {source}", entry.code);
+            } else if seen.contains(&entry.code) {
+                tracing::warn!("Ignoring repeated currency {:?} in {source}", entry.code);
+            } else {
+                seen.push(entry.code.clone());
+                self.countries.insert(entry.code, entry.country);
+            }
+        }
+
+        for code in file.crypto {
+            if code.len() >= 3 && code.chars().all(|c| c.is_ascii_uppercase()) {
+                self.crypto.insert(code);
+            } else {
+                tracing::warn!("Ignoring invalid cryptocurrency {code:?} in {source}");
+            }
+        }
+
+        let mut seen = Vec::new();
+        for entry in file.conventions {
+            // Pair codes are written in any case, as everywhere else.
+            let code = Pair::parse(&entry.pair).map(|pair| pair.code());
+            let Some(code) = code.filter(|_| {
+                entry.decimals <= MAX_PRECISION && entry.pip.is_finite() && entry.pip > 0.0
+            }) else {
+                tracing::warn!(
+                    "Ignoring invalid convention of {:?} in {source}",
+                    entry.pair
+                );
+                continue;
+            };
+            if seen.contains(&code) {
+                tracing::warn!(
+                    "Ignoring repeated convention of {:?} in {source}",
+                    entry.pair
+                );
+            } else {
+                seen.push(code.clone());
+                self.conventions.insert(
+                    code,
+                    MarketConvention {
+                        decimals: entry.decimals,
+                        pip: entry.pip,
+                    },
+                );
+            }
+        }
+
+        let mut seen = Vec::new();
+        for entry in file.cadences {
+            let code = Pair::parse(&entry.pair).map(|pair| pair.code());
+            let Some(code) = code.filter(|_| entry.cadence != Cadence::Every(0)) else {
+                tracing::warn!("Ignoring invalid cadence of {:?} in {source}", entry.pair);
+                continue;
+            };
+            // Looked up by the provider's own spelling, whichever one the entry uses.
+            let Some(provider) = PROVIDERS
+                .iter()
+                .find(|name| name.eq_ignore_ascii_case(&entry.provider))
+            else {
+                tracing::warn!(
+                    "Ignoring cadence of {:?} for unknown provider {:?} in {source}",
+                    entry.pair,
+                    entry.provider
+                );
+                continue;
+            };
+            let key = (provider.to_string(), code);
+            if seen.contains(&key) {
+                tracing::warn!("Ignoring repeated cadence of {:?} in {source}", entry.pair);
+            } else {
+                seen.push(key.clone());
+                self.cadences.insert(key, entry.cadence);
+            }
+        }
+    }
+}
+
+static METADATA: OnceLock<Metadata> = OnceLock::new();
+
+/// The currency metadata, loaded on first use if `init` wasn't called.
+pub fn metadata() -> &'static Metadata {
+    METADATA.get_or_init(|| load(settings_dir(None)))
+}
+
+/// Loads the metadata with the override of the settings kept under `custom`, as given with
+/// `--config-path`, see `settings_dir`. Called before anything reads it.
+pub fn init(custom: Option<&Path>) {
+    if METADATA.set(load(settings_dir(custom))).is_err() {
+        tracing::warn!("The currency metadata was read before the override was located");
+    }
+}
+
+/// The bundled metadata with the override in `settings`, the applet's settings directory.
+fn load(settings: Option<PathBuf>) -> Metadata {
+    let mut metadata = parse_bundled(BUNDLED);
+    if let Some(settings) = settings {
+        merge_override(&mut metadata, &settings.join(OVERRIDE_FILE));
+    }
+    metadata
+}
+
+/// The metadata listed in `text`, the `minimal` set if it doesn't parse.
+fn parse_bundled(text: &str) -> Metadata {
+    match toml::from_str::<MetadataFile>(text) {
+        Ok(file) => {
+            let mut metadata = Metadata::default();
+            metadata.merge(file, "the bundled currencies");
+            metadata
+        }
+        Err(e) => {
+            tracing::error!("Error parsing the bundled currencies: {}", e);
+            Metadata::minimal()
+        }
+    }
+}
+
+/// Merges the override file at `path` into `metadata`. A missing file is no override, one
+/// that can't be read or parsed is skipped with a warning.
+fn merge_override(metadata: &mut Metadata, path: &Path) {
+    match std::fs::read_to_string(path) {
+        Ok(text) => match toml::from_str::<MetadataFile>(&text) {
+            Ok(file) => metadata.merge(file, &path.display().to_string()),
+            Err(e) => tracing::warn!("Ignoring {}: {}", path.display(), e),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => tracing::warn!("Error reading {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::AWESOMEAPI;
+
+    /// The bundled metadata alone, without the override of whoever runs the tests.
+    fn bundled() -> Metadata {
+        parse_bundled(BUNDLED)
+    }
+
+    fn file(text: &str) -> MetadataFile {
+        toml::from_str(text).unwrap()
+    }
+
+    /// A settings directory of its own for the test `name`, empty.
+    fn settings(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "exchange-rate-metadata-{}-{name}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn forex_pairs_have_their_market_convention() {
+        let metadata = bundled();
+        assert_eq!(
+            metadata.convention("EURUSD"),
+            Some(MarketConvention {
+                decimals: 5,
+                pip: 0.0001,
+            })
+        );
+        assert_eq!(
+            metadata.convention("USDJPY"),
+            Some(MarketConvention {
+                decimals: 3,
+                pip: 0.01,
+            })
+        );
+        // An exotic pair, and a pair only the other way round.
+        assert_eq!(metadata.convention("USDTRY"), None);
+        assert_eq!(metadata.convention("USDEUR"), None);
+    }
+
+    #[test]
+    fn every_bundled_entry_is_valid() {
+        let listed = file(BUNDLED);
+        let metadata = bundled();
+        assert_eq!(metadata.countries.len(), listed.currencies.len());
+        assert_eq!(metadata.crypto.len(), listed.crypto.len());
+        assert_eq!(metadata.conventions.len(), listed.conventions.len());
+        assert_eq!(metadata.cadences.len(), listed.cadences.len());
+        assert_eq!(metadata.country("BRL"), Some("BR"));
+        assert_eq!(metadata.country("EUR"), None);
+        assert!(metadata.is_crypto("BTC"));
+    }
+
+    #[test]
+    fn a_bundle_that_does_not_parse_falls_back_to_the_minimal_set() {
+        let metadata = parse_bundled("currencies = [{ code = ");
+        assert_eq!(metadata, Metadata::minimal());
+        assert_eq!(metadata.country("USD"), Some("US"));
+        assert_eq!(metadata.country("BRL"), Some("BR"));
+        assert!(metadata.convention("USDBRL").is_some());
+    }
+
+    #[test]
+    fn overrides_replace_and_add_entries() {
+        let mut metadata = bundled();
+        metadata.merge(
+            file(
+                r#"
+                currencies = [{ code = "BRL", country = "PT" }, { code = "XAF", country = "CM" }]
+                crypto = ["PEPE"]
+                conventions = [{ pair = "EURUSD", decimals = 4, pip = 0.0001 }]
+                "#,
+            ),
+            "test",
+        );
+        assert_eq!(metadata.country("BRL"), Some("PT"));
+        assert_eq!(metadata.country("XAF"), Some("CM"));
+        assert_eq!(metadata.country("USD"), Some("US"));
+        assert!(metadata.is_crypto("PEPE") && metadata.is_crypto("BTC"));
+        assert_eq!(metadata.convention("EURUSD").unwrap().decimals, 4);
+        assert_eq!(metadata.convention("USDJPY").unwrap().decimals, 3);
+    }
+
+    #[test]
+    fn invalid_and_repeated_entries_are_skipped() {
+        let mut metadata = Metadata::default();
+        metadata.merge(
+            file(
+                r#"
+                currencies = [
+                    { code = "usd", country = "US" },
+                    { code = "BRL", country = "Brazil" },
+                    { code = "JPY", country = "JP" },
+                    { code = "JPY", country = "CN" },
+                ]
+                crypto = ["btc", "X"]
+                conventions = [
+                    { pair = "EURUSD", decimals = 99, pip = 0.0001 },
+                    { pair = "GBPUSD", decimals = 5, pip = 0 },
+                    { pair = "not a pair", decimals = 5, pip = 0.0001 },
+                    { pair = "USDJPY", decimals = 3, pip = 0.01 },
+                    { pair = "usdjpy", decimals = 2, pip = 0.01 },
+                ]
+                "#,
+            ),
+            "test",
+        );
+        assert_eq!(metadata.country("USD"), None);
+        assert_eq!(metadata.country("BRL"), None);
+        // The first of repeated entries is kept.
+        assert_eq!(metadata.country("JPY"), Some("JP"));
+        assert!(metadata.crypto.is_empty());
+        assert_eq!(metadata.convention("EURUSD"), None);
+        assert_eq!(metadata.convention("GBPUSD"), None);
+        assert_eq!(metadata.conventions.len(), 1);
+        assert_eq!(metadata.convention("USDJPY").unwrap().decimals, 3);
+    }
+
+    #[test]
+    fn pair_codes_are_read_in_any_case() {
+        let mut metadata = Metadata::default();
+        metadata.merge(
+            file(
+                r#"
+                conventions = [
+                    { pair = "usdbrl", decimals = 4, pip = 0.0001 },
+                    { pair = "Eur-Usd", decimals = 5, pip = 0.0001 },
+                ]
+                "#,
+            ),
+            "test",
+        );
+        assert_eq!(metadata.convention("USDBRL").unwrap().decimals, 4);
+        assert_eq!(metadata.convention("EURUSD").unwrap().decimals, 5);
+        assert_eq!(metadata.convention("usdbrl"), None);
+    }
+
+    #[test]
+    fn the_override_is_read_from_the_settings_directory() {
+        let dir = settings("override");
+        // Without a file, only the bundle.
+        assert_eq!(load(Some(dir.clone())), bundled());
+        std::fs::write(
+            dir.join(OVERRIDE_FILE),
+            r#"currencies = [{ code = "BRL", country = "PT" }]"#,
+        )
+        .unwrap();
+        assert_eq!(load(Some(dir.clone())).country("BRL"), Some("PT"));
+        // One that doesn't parse is ignored as a whole.
+        std::fs::write(dir.join(OVERRIDE_FILE), "currencies = [{ code = ").unwrap();
+        assert_eq!(load(Some(dir.clone())), bundled());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_custom_config_path_has_its_own_override() {
+        let custom = settings("custom");
+        let dir = settings_dir(Some(&custom)).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(OVERRIDE_FILE),
+            r#"currencies = [{ code = "XAF", country = "CM" }]"#,
+        )
+        .unwrap();
+        assert_eq!(load(settings_dir(Some(&custom))).country("XAF"), Some("CM"));
+        std::fs::remove_dir_all(&custom).unwrap();
+    }
+
+    #[test]
+    fn cadences_are_kept_under_the_providers_own_name() {
+        let mut metadata = Metadata::default();
+        metadata.merge(
+            file(
+                r#"
+                cadences = [
+                    { provider = "awesomeapi", pair = "usdbob", cadence = "business-day" },
+                    { provider = "AwesomeAPI", pair = "USDARS", cadence = { every = 600 } },
+                    { provider = "nosuchapi", pair = "USDCLP", cadence = "business-day" },
+                ]
+                "#,
+            ),
+            "test",
+        );
+        assert_eq!(
+            metadata.cadence(AWESOMEAPI, "USDBOB"),
+            Some(Cadence::BusinessDay)
+        );
+        assert_eq!(
+            metadata.cadence(AWESOMEAPI, "USDARS"),
+            Some(Cadence::Every(600))
+        );
+        // An unknown provider would never be looked up, it is not kept at all.
+        assert_eq!(metadata.cadences.len(), 2);
+    }
+}
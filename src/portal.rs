@@ -0,0 +1,382 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Talking to the XDG desktop portals, and saving files through their file chooser.
+//!
+//! Sessions without `xdg-desktop-portal` leave portal calls unanswered rather than failing
+//! them, so the portal is only relied on once it answered within `PORTAL_TIMEOUT`. Without
+//! it files are saved straight into the user's documents directory, see `save_text`.
+
+use cosmic::iced::futures::StreamExt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+/// Prefix of the tokens the portal derives its request and session paths from.
+pub const TOKEN_PREFIX: &str = "cosmic_applet_exchange_rate";
+/// How long the portal has to take a request before the session is taken to have none. The
+/// user's answer to the request itself is waited for as long as it takes.
+const PORTAL_TIMEOUT: Duration = Duration::from_secs(3);
+/// Token of the file chooser's save requests.
+const SAVE_TOKEN: &str = "save";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.portal.Request",
+    default_service = "org.freedesktop.portal.Desktop"
+)]
+pub trait Request {
+    #[zbus(signal)]
+    fn response(&self, response: u32, results: HashMap<String, OwnedValue>) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.portal.FileChooser",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait FileChooser {
+    fn save_file(
+        &self,
+        parent_window: &str,
+        title: &str,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+}
+
+/// How the user answered a portal request.
+#[derive(Debug)]
+pub enum Answer {
+    Accepted(HashMap<String, OwnedValue>),
+    /// The user dismissed the request.
+    Cancelled,
+}
+
+/// Where `save_text` saved a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Saved {
+    /// Where the user chose in the file chooser.
+    Chosen(PathBuf),
+    /// In the documents directory, without a file chooser to ask with.
+    Fallback(PathBuf),
+    /// The user dismissed the file chooser, nothing was saved.
+    Cancelled,
+}
+
+/// The part of the caller's unique name on `connection` the portal puts in the paths of
+/// its requests and sessions.
+pub fn sender(connection: &zbus::Connection) -> zbus::Result<String> {
+    Ok(connection
+        .unique_name()
+        .ok_or_else(|| zbus::Error::Failure("no unique name on the session bus".into()))?
+        .trim_start_matches(':')
+        .replace('.', "_"))
+}
+
+/// Starts listening for the response to the request with `token`, before it is made.
+///
+/// The portal derives the paths of requests from the caller's unique name and the tokens
+/// given, so their responses can be listened to before they are sent.
+pub async fn request_response(
+    connection: &zbus::Connection,
+    sender: &str,
+    token: &str,
+) -> zbus::Result<ResponseStream<'static>> {
+    let path = format!("/org/freedesktop/portal/desktop/request/{sender}/{TOKEN_PREFIX}_{token}");
+    RequestProxy::builder(connection)
+        .path(path)?
+        .build()
+        .await?
+        .receive_response()
+        .await
+}
+
+/// Waits for a request to be answered, failing if the portal couldn't carry it out.
+pub async fn await_answer(mut responses: ResponseStream<'static>) -> zbus::Result<Answer> {
+    let response = responses
+        .next()
+        .await
+        .ok_or_else(|| zbus::Error::Failure("no response from the portal".into()))?;
+    let args = response.args()?;
+    match *args.response() {
+        0 => Ok(Answer::Accepted(args.results().clone())),
+        1 => Ok(Answer::Cancelled),
+        code => Err(zbus::Error::Failure(format!(
+            "the portal failed the request ({code})"
+        ))),
+    }
+}
+
+/// Waits for a request to be answered, failing unless the user accepted it.
+pub async fn await_success(responses: ResponseStream<'static>) -> zbus::Result<()> {
+    match await_answer(responses).await? {
+        Answer::Accepted(_) => Ok(()),
+        Answer::Cancelled => Err(zbus::Error::Failure("the user declined the request".into())),
+    }
+}
+
+/// `future`, failed if the portal doesn't answer it within `PORTAL_TIMEOUT`.
+pub async fn with_timeout<T>(future: impl Future<Output = zbus::Result<T>>) -> zbus::Result<T> {
+    within(PORTAL_TIMEOUT, future).await
+}
+
+/// `future`, failed if it isn't answered within `timeout`.
+async fn within<T>(
+    timeout: Duration,
+    future: impl Future<Output = zbus::Result<T>>,
+) -> zbus::Result<T> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .unwrap_or_else(|_| Err(zbus::Error::Failure("the portal didn't answer".into())))
+}
+
+/// Saves `contents` where the user chooses in the file chooser portal, suggesting `name`.
+/// Without the portal, it is saved as `name` in the documents directory instead.
+pub async fn save_text(title: String, name: String, contents: String) -> Result<Saved, String> {
+    let fallback = documents_dir();
+    save_through(
+        ask_save(&title, &name),
+        PORTAL_TIMEOUT,
+        &fallback,
+        &name,
+        &contents,
+    )
+    .await
+}
+
+/// `save_text` with `ask` for the request to the file chooser, which has `timeout` to take
+/// it before `contents` is saved as `name` in `fallback` instead.
+async fn save_through<A>(
+    ask: impl Future<Output = zbus::Result<A>>,
+    timeout: Duration,
+    fallback: &Path,
+    name: &str,
+    contents: &str,
+) -> Result<Saved, String>
+where
+    A: Future<Output = zbus::Result<Answer>>,
+{
+    let chosen = match within(timeout, ask).await {
+        Ok(pending) => match pending.await {
+            Ok(Answer::Accepted(results)) => chosen_path(&results)
+                .ok_or_else(|| "the file chooser returned no local file".to_string())?,
+            Ok(Answer::Cancelled) => return Ok(Saved::Cancelled),
+            Err(e) => return Err(e.to_string()),
+        },
+        Err(e) => {
+            tracing::debug!("saving {name} without the file chooser: {e}");
+            let path = fallback.join(name);
+            write(&path, contents).await?;
+            return Ok(Saved::Fallback(path));
+        }
+    };
+    write(&chosen, contents).await?;
+    Ok(Saved::Chosen(chosen))
+}
+
+/// Opens the file chooser to save `name`, answering with the future of the user's answer
+/// once the portal took the request.
+async fn ask_save(
+    title: &str,
+    name: &str,
+) -> zbus::Result<impl Future<Output = zbus::Result<Answer>>> {
+    let connection = zbus::Connection::session().await?;
+    let portal = FileChooserProxy::new(&connection).await?;
+    let responses = request_response(&connection, &sender(&connection)?, SAVE_TOKEN).await?;
+    portal
+        .save_file(
+            "",
+            title,
+            HashMap::from([
+                (
+                    "handle_token",
+                    Value::from(format!("{TOKEN_PREFIX}_{SAVE_TOKEN}")),
+                ),
+                ("current_name", Value::from(name)),
+            ]),
+        )
+        .await?;
+    Ok(async move {
+        // The connection has to outlive the request for its response to arrive.
+        let _connection = connection;
+        await_answer(responses).await
+    })
+}
+
+/// The local path of the first file in the `uris` of a file chooser answer.
+fn chosen_path(results: &HashMap<String, OwnedValue>) -> Option<PathBuf> {
+    let uris = <Vec<String>>::try_from(results.get("uris")?.try_clone().ok()?).ok()?;
+    reqwest::Url::parse(uris.first()?).ok()?.to_file_path().ok()
+}
+
+/// The user's documents directory, see `documents_dir_of`.
+fn documents_dir() -> PathBuf {
+    let home = std::env::var_os("HOME").map_or_else(|| PathBuf::from("."), PathBuf::from);
+    let config =
+        std::env::var_os("XDG_CONFIG_HOME").map_or_else(|| home.join(".config"), PathBuf::from);
+    documents_dir_of(&home, &config)
+}
+
+/// The documents directory of a user with `home`, as set in the `user-dirs.dirs` of their
+/// `config` directory, else `~/Documents` if it exists, else the home directory.
+fn documents_dir_of(home: &Path, config: &Path) -> PathBuf {
+    let configured = std::fs::read_to_string(config.join("user-dirs.dirs"))
+        .ok()
+        .and_then(|dirs| {
+            dirs.lines()
+                .find_map(|line| line.trim().strip_prefix("XDG_DOCUMENTS_DIR="))
+                .map(|value| {
+                    let value = value.trim_matches('"');
+                    match value.strip_prefix("$HOME") {
+                        Some(rest) => home.join(rest.trim_start_matches('/')),
+                        None => PathBuf::from(value),
+                    }
+                })
+        });
+    match configured {
+        Some(dir) if dir.is_dir() => dir,
+        _ if home.join("Documents").is_dir() => home.join("Documents"),
+        _ => home.to_path_buf(),
+    }
+}
+
+/// `path` with the home directory shortened to `~`, to tell the user where a file went.
+pub fn display_path(path: &Path) -> String {
+    std::env::var_os("HOME")
+        .and_then(|home| path.strip_prefix(home).ok())
+        .map_or_else(
+            || path.display().to_string(),
+            |rest| format!("~/{}", rest.display()),
+        )
+}
+
+async fn write(path: &Path, contents: &str) -> Result<(), String> {
+    tokio::fs::write(path, contents)
+        .await
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of its own for the test `name`, empty.
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "exchange-rate-portal-{}-{name}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// How long the fake portals have to take a request, short for the tests to be quick.
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    /// A file chooser answering `answer` as soon as it is asked.
+    async fn answering(answer: Answer) -> zbus::Result<impl Future<Output = zbus::Result<Answer>>> {
+        Ok(async move { Ok(answer) })
+    }
+
+    /// The answer of a file chooser where the user picked `path`.
+    fn picked(path: &Path) -> Answer {
+        let uri = reqwest::Url::from_file_path(path).unwrap().to_string();
+        let uris = OwnedValue::try_from(Value::from(vec![uri])).unwrap();
+        Answer::Accepted(HashMap::from([("uris".to_string(), uris)]))
+    }
+
+    #[tokio::test]
+    async fn a_portal_that_never_answers_is_given_up_on() {
+        let never = std::future::pending::<zbus::Result<()>>();
+        assert!(within(TIMEOUT, never).await.is_err());
+        let answered = async { Ok::<_, zbus::Error>(7) };
+        assert_eq!(within(TIMEOUT, answered).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn without_a_portal_the_file_goes_to_the_fallback() {
+        let fallback = dir("absent");
+        // Unanswered, as in sessions without xdg-desktop-portal.
+        let never =
+            std::future::pending::<zbus::Result<std::future::Ready<zbus::Result<Answer>>>>();
+        let saved = save_through(never, TIMEOUT, &fallback, "rates.csv", "a,b").await;
+        assert_eq!(saved, Ok(Saved::Fallback(fallback.join("rates.csv"))));
+        assert_eq!(
+            std::fs::read_to_string(fallback.join("rates.csv")).unwrap(),
+            "a,b"
+        );
+        // Refused outright, as without a session bus.
+        let refused = async {
+            Err::<std::future::Ready<zbus::Result<Answer>>, _>(zbus::Error::Failure(
+                "no session bus".into(),
+            ))
+        };
+        let saved = save_through(refused, TIMEOUT, &fallback, "other.csv", "c").await;
+        assert_eq!(saved, Ok(Saved::Fallback(fallback.join("other.csv"))));
+        std::fs::remove_dir_all(&fallback).unwrap();
+    }
+
+    #[tokio::test]
+    async fn with_a_portal_the_file_goes_where_the_user_chose() {
+        let fallback = dir("present-fallback");
+        let chosen = dir("present").join("chosen.csv");
+        let answer = answering(picked(&chosen));
+        let saved = save_through(answer, TIMEOUT, &fallback, "rates.csv", "a,b").await;
+        assert_eq!(saved, Ok(Saved::Chosen(chosen.clone())));
+        assert_eq!(std::fs::read_to_string(&chosen).unwrap(), "a,b");
+        assert!(!fallback.join("rates.csv").exists());
+        // The user takes as long as they like: only taking the request is timed.
+        let slow = async {
+            Ok::<_, zbus::Error>(async {
+                tokio::time::sleep(TIMEOUT * 4).await;
+                Ok::<_, zbus::Error>(Answer::Cancelled)
+            })
+        };
+        let saved = save_through(slow, TIMEOUT, &fallback, "rates.csv", "a,b").await;
+        assert_eq!(saved, Ok(Saved::Cancelled));
+        assert!(!fallback.join("rates.csv").exists());
+        std::fs::remove_dir_all(&fallback).unwrap();
+        std::fs::remove_dir_all(chosen.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_failed_request_saves_nothing() {
+        let fallback = dir("failed");
+        let failed = async {
+            Ok::<_, zbus::Error>(async {
+                Err::<Answer, _>(zbus::Error::Failure(
+                    "the portal failed the request (2)".into(),
+                ))
+            })
+        };
+        let saved = save_through(failed, TIMEOUT, &fallback, "rates.csv", "a,b").await;
+        assert!(saved.is_err());
+        let empty = answering(Answer::Accepted(HashMap::new()));
+        let saved = save_through(empty, TIMEOUT, &fallback, "rates.csv", "a,b").await;
+        assert!(saved.is_err());
+        assert!(!fallback.join("rates.csv").exists());
+        std::fs::remove_dir_all(&fallback).unwrap();
+    }
+
+    #[test]
+    fn the_documents_directory_follows_user_dirs() {
+        let home = dir("home");
+        let config = home.join(".config");
+        std::fs::create_dir_all(&config).unwrap();
+        // Neither configured nor there: the home directory.
+        assert_eq!(documents_dir_of(&home, &config), home);
+        std::fs::create_dir_all(home.join("Documents")).unwrap();
+        assert_eq!(documents_dir_of(&home, &config), home.join("Documents"));
+        std::fs::write(
+            config.join("user-dirs.dirs"),
+            "XDG_DESKTOP_DIR=\"$HOME/Desktop\"\nXDG_DOCUMENTS_DIR=\"$HOME/Dokumente\"\n",
+        )
+        .unwrap();
+        // Configured but missing, then there.
+        assert_eq!(documents_dir_of(&home, &config), home.join("Documents"));
+        std::fs::create_dir_all(home.join("Dokumente")).unwrap();
+        assert_eq!(documents_dir_of(&home, &config), home.join("Dokumente"));
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
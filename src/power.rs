@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Low power mode, turned on by hand or by UPower reporting the battery discharging below
+//! `low_power_below_pct`.
+//!
+//! The mode is an overlay, applied where the settings are read rather than written into
+//! them, so turning it off restores exactly what they say. While it is on pairs are
+//! fetched `LOW_POWER_INTERVAL_FACTOR` times less often, only those the panel shows are
+//! fetched at all, the panel stops rotating and rates don't animate.
+
+use cosmic::iced::futures::channel::mpsc::Sender;
+use cosmic::iced::futures::{stream, SinkExt, Stream, StreamExt};
+use cosmic::iced::Subscription;
+use std::future::Future;
+use std::time::Duration;
+
+use crate::app::Message;
+
+/// How many times less often pairs are fetched in low power mode.
+pub const LOW_POWER_INTERVAL_FACTOR: u32 = 3;
+
+/// How long UPower has to answer before the device is taken to have no battery.
+const UPOWER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The `State` UPower reports for a battery running down.
+const STATE_DISCHARGING: u32 = 2;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UPower.Device",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower/devices/DisplayDevice"
+)]
+trait Device {
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn percentage(&self) -> zbus::Result<f64>;
+}
+
+/// Whether low power mode is on: turned on by hand with `manual`, or by the battery
+/// discharging with a `battery` charge below `below_pct`, which is never when `0`.
+pub fn low_power_active(manual: bool, below_pct: u8, battery: Option<f64>) -> bool {
+    manual || (below_pct > 0 && battery.is_some_and(|charge| charge < f64::from(below_pct)))
+}
+
+/// Sends `Message::BatteryChanged` with the charge left while the battery is discharging,
+/// or `None` while it isn't, once connected and then on every change. Sends nothing if
+/// UPower can't be reached, so the applet then behaves as if on AC.
+pub fn battery_changes() -> Subscription<Message> {
+    cosmic::iced::subscription::channel("battery", 4, |mut output| async move {
+        if let Err(e) = watch_battery(&mut output).await {
+            tracing::warn!("Error watching the battery: {}", e);
+        }
+        std::future::pending().await
+    })
+}
+
+async fn watch_battery(output: &mut Sender<Message>) -> zbus::Result<()> {
+    let device = tokio::time::timeout(UPOWER_TIMEOUT, async {
+        let connection = zbus::Connection::system().await?;
+        DeviceProxy::new(&connection).await
+    })
+    .await
+    .map_err(|_| zbus::Error::Failure("UPower didn't answer".into()))??;
+    // The state and the charge change separately, either is a reason to read both again.
+    let states = device.receive_state_changed().await.map(|_| ());
+    let charges = device.receive_percentage_changed().await.map(|_| ());
+    forward_changes(
+        || discharging_charge(&device),
+        stream::select(states, charges),
+        output,
+    )
+    .await
+}
+
+/// Sends the charge `read` gives once, then again on every one of `changes`, until they
+/// end or a read fails.
+async fn forward_changes<R, F>(
+    mut read: R,
+    changes: impl Stream<Item = ()>,
+    output: &mut Sender<Message>,
+) -> zbus::Result<()>
+where
+    R: FnMut() -> F,
+    F: Future<Output = zbus::Result<Option<f64>>>,
+{
+    let _ = output.send(Message::BatteryChanged(read().await?)).await;
+    let mut changes = std::pin::pin!(changes);
+    while changes.next().await.is_some() {
+        let _ = output.send(Message::BatteryChanged(read().await?)).await;
+    }
+    Ok(())
+}
+
+/// The charge left in percent if the battery is discharging.
+async fn discharging_charge(device: &DeviceProxy<'_>) -> zbus::Result<Option<f64>> {
+    if device.state().await? != STATE_DISCHARGING {
+        return Ok(None);
+    }
+    Ok(Some(device.percentage().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic::iced::futures::channel::mpsc;
+    use std::collections::VecDeque;
+
+    /// What a mocked UPower sends for `charges` read one after another, with a change
+    /// signalled between each, until the reads run out and fail.
+    async fn forwarded(charges: &[Option<f64>], signals: usize) -> (bool, Vec<Option<f64>>) {
+        let mut device: VecDeque<Option<f64>> = charges.iter().copied().collect();
+        let (mut output, received) = mpsc::channel(charges.len() + 1);
+        let result = forward_changes(
+            || {
+                let charge = device
+                    .pop_front()
+                    .ok_or_else(|| zbus::Error::Failure("UPower went away".into()));
+                async move { charge }
+            },
+            stream::iter(std::iter::repeat(()).take(signals)),
+            &mut output,
+        )
+        .await;
+        drop(output);
+        let sent: Vec<Option<f64>> = received
+            .map(|message| match message {
+                Message::BatteryChanged(charge) => charge,
+                _ => panic!("only battery changes are sent"),
+            })
+            .collect()
+            .await;
+        (result.is_ok(), sent)
+    }
+
+    #[test]
+    fn low_power_is_on_by_hand_or_below_the_threshold_while_discharging() {
+        assert!(low_power_active(true, 0, None));
+        assert!(low_power_active(true, 20, Some(80.0)));
+        assert!(low_power_active(false, 20, Some(19.5)));
+        assert!(!low_power_active(false, 20, Some(20.0)));
+        // On AC, or with the threshold at 0, the charge doesn't matter.
+        assert!(!low_power_active(false, 20, None));
+        assert!(!low_power_active(false, 0, Some(1.0)));
+    }
+
+    #[tokio::test]
+    async fn the_charge_is_sent_once_connected_then_on_every_change() {
+        let charges = [None, Some(40.0), Some(39.0)];
+        assert_eq!(forwarded(&charges, 2).await, (true, charges.to_vec()));
+    }
+
+    #[tokio::test]
+    async fn the_watch_stops_when_upower_stops_answering() {
+        let (ok, sent) = forwarded(&[Some(40.0)], 3).await;
+        assert!(!ok);
+        assert_eq!(sent, vec![Some(40.0)]);
+        assert_eq!(forwarded(&[], 0).await, (false, Vec::new()));
+    }
+}
@@ -0,0 +1,260 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use chrono::{DateTime, Local};
+use chrono_tz::Tz;
+use cosmic::iced::futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Mutex;
+
+use super::{
+    api_get, parse_quote, raw_excerpt, Cadence, PairResponse, Provider, RateError, ResponseShape,
+    AWESOMEAPI,
+};
+use crate::clock::Clock;
+use crate::config::{ApiUsage, Pair};
+
+/// The layouts AwesomeAPI's endpoint variants have answered in, tried in this order.
+const SHAPES: [ResponseShape; 3] = [
+    ResponseShape {
+        name: "object keyed by pair",
+        quote: keyed_by_code,
+    },
+    ResponseShape {
+        name: "array of quotes",
+        quote: array_entry,
+    },
+    ResponseShape {
+        name: "object keyed by dashed pair",
+        quote: keyed_by_dashed_code,
+    },
+];
+
+/// The layout of AwesomeAPI's errors, e.g. `{"status": 404, "code": "CoinNotExists",
+/// "message": "moeda nao encontrada USD-XXX"}`. Maintenance is one of code `Maintenance`
+/// with its end in `until`, an RFC 3339 time.
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    code: String,
+    until: Option<String>,
+}
+
+/// <https://docs.awesomeapi.com.br/api-de-moedas>, free and keyless.
+pub struct AwesomeApi;
+
+impl Provider for AwesomeApi {
+    fn name(&self) -> &'static str {
+        AWESOMEAPI
+    }
+
+    /// Major pairs are refreshed about every 30 seconds.
+    fn cadence(&self) -> Cadence {
+        Cadence::Every(30)
+    }
+
+    /// AwesomeAPI relays the Brazilian market, its `create_date` is São Paulo time.
+    fn timezone(&self) -> Tz {
+        chrono_tz::America::Sao_Paulo
+    }
+
+    fn request_url(&self, pairs: &[Pair]) -> String {
+        last_url(pairs)
+    }
+
+    fn announced_maintenance(&self, body: &str) -> Option<DateTime<Local>> {
+        let error: ErrorBody = serde_json::from_str(body).ok()?;
+        if !error.code.eq_ignore_ascii_case("maintenance") {
+            return None;
+        }
+        let until = DateTime::parse_from_rfc3339(error.until?.trim()).ok()?;
+        Some(until.with_timezone(&Local))
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        pair: &'a Pair,
+        usage: &'a Mutex<ApiUsage>,
+        clock: &'a dyn Clock,
+    ) -> BoxFuture<'a, Result<PairResponse, RateError>> {
+        Box::pin(fetch_exchange_rate(pair, usage, clock))
+    }
+
+    /// AwesomeAPI quotes a comma separated list of pairs in a single request.
+    fn fetch_many<'a>(
+        &'a self,
+        pairs: &'a [Pair],
+        usage: &'a Mutex<ApiUsage>,
+        clock: &'a dyn Clock,
+    ) -> BoxFuture<'a, Vec<(Pair, Result<PairResponse, RateError>)>> {
+        Box::pin(fetch_exchange_rates(pairs, usage, clock))
+    }
+}
+
+async fn fetch_exchange_rate(
+    pair: &Pair,
+    usage: &Mutex<ApiUsage>,
+    clock: &dyn Clock,
+) -> Result<PairResponse, RateError> {
+    let (response, raw) = fetch_last(std::slice::from_ref(pair), usage, clock).await?;
+    quote_of(&response, pair).map(|quote| PairResponse {
+        raw_excerpt: Some(raw),
+        ..quote
+    })
+}
+
+async fn fetch_exchange_rates(
+    pairs: &[Pair],
+    usage: &Mutex<ApiUsage>,
+    clock: &dyn Clock,
+) -> Vec<(Pair, Result<PairResponse, RateError>)> {
+    if pairs.is_empty() {
+        return Vec::new();
+    }
+    // A failed request fails every pair of it alike.
+    let response = fetch_last(pairs, usage, clock).await;
+    pairs
+        .iter()
+        .map(|pair| {
+            let quote = match &response {
+                Ok((response, raw)) => quote_of(response, pair).map(|quote| PairResponse {
+                    raw_excerpt: Some(raw.clone()),
+                    ..quote
+                }),
+                Err(e) => Err(e.clone()),
+            };
+            (pair.clone(), quote)
+        })
+        .collect()
+}
+
+/// The URL of the `last` endpoint for `pairs`.
+fn last_url(pairs: &[Pair]) -> String {
+    let codes: Vec<String> = pairs
+        .iter()
+        .map(|pair| format!("{}-{}", pair.base(), pair.quote()))
+        .collect();
+    format!(
+        "https://economia.awesomeapi.com.br/last/{}",
+        codes.join(",")
+    )
+}
+
+/// The response of the `last` endpoint for `pairs`, an object with a quote per pair code,
+/// with the `raw_excerpt` of its body.
+async fn fetch_last(
+    pairs: &[Pair],
+    usage: &Mutex<ApiUsage>,
+    clock: &dyn Clock,
+) -> Result<(Value, String), RateError> {
+    let body = api_get(AWESOMEAPI, &last_url(pairs), usage, clock).await?;
+    let response = serde_json::from_str(&body).map_err(|e| RateError::Parse(e.to_string()))?;
+    Ok((response, raw_excerpt(&body)))
+}
+
+/// Parses the quote of `pair` out of a `last` response.
+fn quote_of(response: &Value, pair: &Pair) -> Result<PairResponse, RateError> {
+    parse_quote(AWESOMEAPI, &SHAPES, response, pair)
+}
+
+/// `{"USDBRL": {...}}`, as the `last` endpoint answers.
+fn keyed_by_code<'a>(response: &'a Value, pair: &Pair) -> Option<&'a Value> {
+    response.get(pair.code()).filter(|quote| !quote.is_null())
+}
+
+/// `[{"code": "USD", "codein": "BRL", ...}]`, as the `json` endpoint answers. A single
+/// quote is taken even without codes, the endpoint being asked for one pair at a time.
+fn array_entry<'a>(response: &'a Value, pair: &Pair) -> Option<&'a Value> {
+    let quotes = response.as_array()?;
+    let single = quotes.len() == 1;
+    quotes.iter().find(|quote| {
+        let codes = (quote["code"].as_str(), quote["codein"].as_str());
+        codes == (Some(pair.base()), Some(pair.quote())) || single
+    })
+}
+
+/// `{"USD-BRL": {...}}`, keyed like the pairs of the request.
+fn keyed_by_dashed_code<'a>(response: &'a Value, pair: &Pair) -> Option<&'a Value> {
+    let code = format!("{}-{}", pair.base(), pair.quote());
+    response.get(&code).filter(|quote| !quote.is_null())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn every_shape_parses_strings_and_numbers() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let as_strings = json!({"bid": "5.1", "ask": "5.2", "timestamp": "1700000000"});
+        let as_numbers = json!({"bid": 5.1, "ask": 5.2, "timestamp": 1700000000});
+        for quote in [as_strings, as_numbers] {
+            let mut with_codes = quote.clone();
+            with_codes["code"] = json!("USD");
+            with_codes["codein"] = json!("BRL");
+            for response in [
+                json!({"USDBRL": quote.clone()}),
+                json!([with_codes]),
+                json!({"USD-BRL": quote.clone()}),
+            ] {
+                let parsed = quote_of(&response, &pair).unwrap();
+                assert_eq!(
+                    (parsed.bid, parsed.ask, parsed.timestamp),
+                    (5.1, 5.2, 1700000000),
+                    "{quote}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn missing_pair_is_a_parse_error() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let response = json!({"EURBRL": {"bid": "6"}});
+        assert_eq!(
+            quote_of(&response, &pair),
+            Err(RateError::Parse("USDBRL missing from response".to_string()))
+        );
+    }
+
+    #[test]
+    fn the_described_request_follows_the_pairs() {
+        let usdbrl = Pair::parse("USDBRL").unwrap();
+        let eurusd = Pair::parse("EURUSD").unwrap();
+        assert_eq!(
+            AwesomeApi.describe_request(std::slice::from_ref(&usdbrl)),
+            "https://economia.awesomeapi.com.br/last/USD-BRL"
+        );
+        assert_eq!(
+            AwesomeApi.describe_request(&[usdbrl, eurusd]),
+            "https://economia.awesomeapi.com.br/last/USD-BRL,EUR-USD"
+        );
+    }
+
+    #[test]
+    fn maintenance_is_an_error_of_its_own_code() {
+        let until = "2026-06-10T14:00:00-03:00";
+        let expected = DateTime::parse_from_rfc3339(until)
+            .unwrap()
+            .with_timezone(&Local);
+        for code in ["Maintenance", "maintenance"] {
+            let body = json!({"status": 503, "code": code, "until": until}).to_string();
+            assert_eq!(AwesomeApi.announced_maintenance(&body), Some(expected));
+        }
+    }
+
+    #[test]
+    fn other_bodies_announce_no_maintenance() {
+        for body in [
+            json!({"USDBRL": {"bid": "5.1"}}).to_string(),
+            json!({"status": 404, "code": "CoinNotExists", "message": "moeda nao encontrada"})
+                .to_string(),
+            // Maintenance without an end to pause until.
+            json!({"status": 503, "code": "Maintenance"}).to_string(),
+            json!({"status": 503, "code": "Maintenance", "until": "14:00"}).to_string(),
+            "<html>Service Unavailable</html>".to_string(),
+        ] {
+            assert_eq!(AwesomeApi.announced_maintenance(&body), None, "{body}");
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! `--query PAIR`: prints the rate of a pair from the running applet, then exits instead of
+//! starting one. E.g.
+//!
+//! ```sh
+//! cosmic-applet-template --query USDBRL --format json
+//! ```
+//!
+//! The running applet is asked through `GetFormattedRate` on its D-Bus interface, always
+//! served. `display` prints the string its panel shows, formatted there with its settings,
+//! `raw` the rate as fetched, and `json` both with the quote's timestamp.
+
+use serde_json::json;
+
+use crate::config::Pair;
+
+/// What `--format` prints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueryFormat {
+    /// The rate as the panel shows it.
+    #[default]
+    Display,
+    /// The rate as fetched, every digit of it.
+    Raw,
+    Json,
+}
+
+impl QueryFormat {
+    /// The format `--format` names, `None` for none of them.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "display" => Some(QueryFormat::Display),
+            "raw" => Some(QueryFormat::Raw),
+            "json" => Some(QueryFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[zbus::proxy(
+    interface = "com.example.CosmicAppletTemplate",
+    default_service = "com.example.CosmicAppletTemplate",
+    default_path = "/com/example/CosmicAppletTemplate"
+)]
+trait Applet {
+    fn get_formatted_rate(&self, pair: &str) -> zbus::Result<(String, f64, i64)>;
+}
+
+/// Prints the rate of `pair` in `format`, returning the exit code: `0` once printed, `1`
+/// if the applet couldn't be asked or has no such rate.
+pub fn run(pair: Pair, format: QueryFormat) -> i32 {
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Error starting the query: {}", e);
+            return 1;
+        }
+    };
+    let code = String::from(pair);
+    let answer = match runtime.block_on(formatted_rate(&code)) {
+        Ok(answer) => answer,
+        Err(e) => {
+            eprintln!("Error asking the applet for {code}, is it running? {e}");
+            return 1;
+        }
+    };
+    match output(&code, answer, format) {
+        Ok(line) => {
+            println!("{line}");
+            0
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+/// What `run` prints in `format` for the `GetFormattedRate` answer about the pair `code`,
+/// or the error it prints instead.
+pub fn output(
+    code: &str,
+    (display, rate, timestamp): (String, f64, i64),
+    format: QueryFormat,
+) -> Result<String, String> {
+    match format {
+        QueryFormat::Display => Ok(display),
+        QueryFormat::Raw if rate.is_nan() => Err(format!("{code} has no rate fetched yet")),
+        QueryFormat::Raw => Ok(rate.to_string()),
+        QueryFormat::Json => Ok(json!({
+            "pair": code,
+            "display": display,
+            "rate": rate.is_finite().then_some(rate),
+            "timestamp": (timestamp > 0).then_some(timestamp),
+        })
+        .to_string()),
+    }
+}
+
+async fn formatted_rate(code: &str) -> zbus::Result<(String, f64, i64)> {
+    let connection = zbus::Connection::session().await?;
+    let applet = AppletProxy::new(&connection).await?;
+    applet.get_formatted_rate(code).await
+}
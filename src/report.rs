@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The diagnostic report users paste into bug reports, describing what the applet is
+//! configured to do and what it did this session.
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use crate::config::AppletConfig;
+use crate::market::QuoteAge;
+use crate::state::{PauseReason, YourApp};
+
+/// How many of the provider's recent fetches the report lists.
+const REPORT_FETCHES: usize = 10;
+/// Settings that may hold credentials, addresses or private URLs, wherever they are nested.
+const SECRET_FIELDS: [&str; 6] = [
+    "alert_email",
+    "smtp_server",
+    "smtp_from",
+    "smtp_credential_key",
+    "forward_rates_url",
+    "webhook_url",
+];
+/// What the value of a set secret field is replaced with.
+const REDACTED: &str = "<redacted>";
+
+/// Assembles the report as pretty-printed JSON, with every `SECRET_FIELDS` setting redacted.
+pub fn diagnostic_report(app: &YourApp) -> String {
+    let now = app.clock.now_instant();
+    let secs_ago = |at: Option<&Instant>| at.map(|at| now.saturating_duration_since(*at).as_secs());
+
+    let settings = redacted_settings(&app.config);
+
+    let pairs: Vec<Value> = app
+        .config
+        .pairs
+        .iter()
+        .map(|entry| {
+            let state = app.pair_states.get(&entry.pair);
+            json!({
+                "pair": entry.pair.to_string(),
+                "enabled": entry.enabled,
+                "frozen": entry.frozen,
+                "quoted": app.exchange_rates.contains_key(&entry.pair),
+                "shows_bundled_rate": app.shows_bundled(&entry.pair),
+                "quote_age": app.quote_age(&entry.pair).map(QuoteAge::id),
+                "fetched_secs_ago": secs_ago(app.fetched_at.get(&entry.pair)),
+                "changed_secs_ago": secs_ago(state.and_then(|state| state.changed_at.as_ref())),
+                "next_fetch_in_secs": app
+                    .next_fetch_at
+                    .get(&entry.pair)
+                    .map(|at| at.saturating_duration_since(now).as_secs()),
+                "retry_attempts": app.retry_attempts.get(&entry.pair).copied().unwrap_or(0),
+                "last_error": state.and_then(|state| state.last_error.clone()),
+                "provider": app.config.provider_of(&entry.pair),
+                "quoted_by": state.and_then(|state| state.quoted_by),
+                "expression": entry.expression,
+                "uses_fallback": app.uses_fallback(&entry.pair, now),
+                "pause_reason": app.pair_pause(entry).map(PauseReason::id),
+            })
+        })
+        .collect();
+
+    let fetches: Vec<Value> = app
+        .providers_in_use()
+        .into_iter()
+        .filter_map(|name| Some((name, app.provider_stats.get(name)?)))
+        .flat_map(|(name, stats)| {
+            stats
+                .recent_fetches
+                .iter()
+                .take(REPORT_FETCHES)
+                .map(move |record| (name, record))
+        })
+        .map(|(name, record)| {
+            json!({
+                "provider": name,
+                "pair": record.pair.to_string(),
+                "trigger": format!("{:?}", record.trigger),
+                "at": record.at.to_rfc3339(),
+            })
+        })
+        .collect();
+
+    let report = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "provider": app.config.provider,
+        "network_status": format!("{:?}", app.network_status),
+        "pause_reason": app.global_pause().map(PauseReason::id),
+        "low_power": app.low_power(),
+        "battery_pct": app.battery,
+        "maintenance_until": app
+            .maintenance
+            .iter()
+            .map(|(name, until)| (name.clone(), until.to_rfc3339()))
+            .collect::<BTreeMap<_, _>>(),
+        "settings_writable": !app.persistence.is_memory_only(),
+        "popup_capabilities": {
+            "focus": app.popup_capabilities.focus.id(),
+            "resize": app.popup_capabilities.resize.id(),
+        },
+        "settings": settings,
+        "pairs": pairs,
+        "recent_fetches": fetches,
+        "flagged_quotes": serde_json::to_value(&app.state.flagged_quotes).unwrap_or(Value::Null),
+        "platform": {
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "desktop": std::env::var("XDG_CURRENT_DESKTOP").ok(),
+            "session_type": std::env::var("XDG_SESSION_TYPE").ok(),
+        },
+    });
+    serde_json::to_string_pretty(&report).unwrap_or_default()
+}
+
+/// `config` as JSON, with every `SECRET_FIELDS` setting redacted.
+pub fn redacted_settings(config: &AppletConfig) -> Value {
+    let mut settings = serde_json::to_value(config).unwrap_or(Value::Null);
+    redact(&mut settings);
+    settings
+}
+
+/// Replaces the values of the `SECRET_FIELDS` set in `value`, at any depth. Unset ones,
+/// `null` or empty, are kept as they are since they tell whether the feature is used.
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                let set = !matches!(field, Value::Null) && field.as_str() != Some("");
+                if SECRET_FIELDS.contains(&name.as_str()) && set {
+                    *field = Value::from(REDACTED);
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Pair, PairEntry};
+
+    /// Values for every `SECRET_FIELDS` setting, easy to look for.
+    const SECRETS: [&str; 6] = [
+        "me@example.com",
+        "smtp.example.com",
+        "applet@example.com",
+        "keyring-entry",
+        "https://forwards.example.com/?key=hunter2",
+        "https://hooks.example.com/T000/B000/XXXX",
+    ];
+
+    /// An app with every secret setting set to one of `SECRETS`.
+    fn with_secrets() -> YourApp {
+        let mut app = YourApp::default();
+        let config = &mut app.config;
+        config.alert_email = Some(SECRETS[0].to_string());
+        config.smtp_server = SECRETS[1].to_string();
+        config.smtp_from = SECRETS[2].to_string();
+        config.smtp_credential_key = SECRETS[3].to_string();
+        config.forward_rates_url = SECRETS[4].to_string();
+        let mut entry = PairEntry::new(Pair::parse("USDBRL").unwrap());
+        entry.webhook_url = Some(SECRETS[5].to_string());
+        config.pairs = vec![entry];
+        app
+    }
+
+    #[test]
+    fn no_secret_appears_in_the_report() {
+        let report = diagnostic_report(&with_secrets());
+        for secret in SECRETS {
+            assert!(!report.contains(secret), "{secret} in {report}");
+        }
+        assert!(report.contains(REDACTED));
+        // Nor does anything else of them, such as the host.
+        assert!(!report.contains("example.com"), "{report}");
+    }
+
+    #[test]
+    fn unset_secrets_are_kept_unset() {
+        let report: Value = serde_json::from_str(&diagnostic_report(&YourApp::default())).unwrap();
+        let settings = &report["settings"];
+        assert_eq!(settings["alert_email"], Value::Null);
+        assert_eq!(settings["smtp_server"], "");
+        assert!(!report.to_string().contains(REDACTED));
+    }
+
+    #[test]
+    fn secrets_are_redacted_at_any_depth() {
+        let mut value = json!({
+            "pairs": [{ "pair": "USDBRL", "webhook_url": "https://hooks.example.com" }],
+            "nested": { "smtp_server": "smtp.example.com", "smtp_port": 587 },
+            "provider": "AwesomeAPI",
+        });
+        redact(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "pairs": [{ "pair": "USDBRL", "webhook_url": REDACTED }],
+                "nested": { "smtp_server": REDACTED, "smtp_port": 587 },
+                "provider": "AwesomeAPI",
+            })
+        );
+    }
+}
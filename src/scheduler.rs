@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use chrono::Offset;
+use cosmic::app::Command;
+use cosmic::iced::futures::SinkExt;
+use cosmic::iced::Subscription;
+use std::time::{Duration, Instant};
+
+use crate::app::Message;
+use crate::clock::SharedClock;
+use crate::config::Pair;
+use crate::fl;
+use crate::providers::{PairResponse, Provider, RateError, SharedUsage};
+
+/// How often `timezone_changes` looks at the local offset.
+const TIMEZONE_POLL: Duration = Duration::from_secs(30);
+
+/// Why a fetch happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchTrigger {
+    /// The first fetch after the applet started.
+    Startup,
+    /// The refresh interval elapsed.
+    Interval,
+    /// The user asked for a refresh.
+    Manual,
+    /// A previous fetch failed.
+    Retry,
+    /// The pair was added or enabled.
+    PairChange,
+    /// The session was unlocked after the quote went stale.
+    Unlock,
+    /// The provider's maintenance window ended, or low power mode was turned off.
+    Resumed,
+}
+
+impl FetchTrigger {
+    pub fn label(self) -> String {
+        match self {
+            FetchTrigger::Startup => fl!("trigger-startup"),
+            FetchTrigger::Interval => fl!("trigger-interval"),
+            FetchTrigger::Manual => fl!("trigger-manual"),
+            FetchTrigger::Retry => fl!("trigger-retry"),
+            FetchTrigger::PairChange => fl!("trigger-pair-change"),
+            FetchTrigger::Unlock => fl!("trigger-unlock"),
+            FetchTrigger::Resumed => fl!("trigger-resumed"),
+        }
+    }
+}
+
+/// Fetches `pair` from `provider` in the background, answering with `answer`:
+/// `Message::RateFetchedAt`, or `Message::FallbackFetchedAt` for the fallback provider.
+pub fn fetch_command(
+    provider: &'static dyn Provider,
+    pair: Pair,
+    answer: fn(Pair, Instant, Result<PairResponse, RateError>) -> Message,
+    usage: SharedUsage,
+    clock: SharedClock,
+) -> Command<Message> {
+    Command::perform(
+        async move {
+            let result = provider.fetch(&pair, &usage, clock.as_ref()).await;
+            (pair, clock.now_instant(), result)
+        },
+        move |(pair, fetched_at, result)| {
+            cosmic::app::Message::App(answer(pair, fetched_at, result))
+        },
+    )
+}
+
+/// Fetches `pairs` from `provider` together in the background, answering with a single
+/// `Message::RatesFetched` that also carries the `cached` outcomes.
+pub fn fetch_many_command(
+    provider: &'static dyn Provider,
+    pairs: Vec<Pair>,
+    cached: Vec<(Pair, Instant, Result<PairResponse, RateError>)>,
+    usage: SharedUsage,
+    clock: SharedClock,
+) -> Command<Message> {
+    Command::perform(
+        async move {
+            let fetched = if pairs.is_empty() {
+                Vec::new()
+            } else {
+                provider.fetch_many(&pairs, &usage, clock.as_ref()).await
+            };
+            let fetched_at = clock.now_instant();
+            cached
+                .into_iter()
+                .chain(
+                    fetched
+                        .into_iter()
+                        .map(|(pair, result)| (pair, fetched_at, result)),
+                )
+                .collect()
+        },
+        |results| cosmic::app::Message::App(Message::RatesFetched(results)),
+    )
+}
+
+/// Periodically asks for `pair` to be fetched, and reports each upcoming fetch through
+/// `Message::FetchScheduled` so it can be shown in the debug tab. The first fetch is left
+/// to whoever started tracking the pair: `init` at startup, or adding/enabling the pair.
+pub fn fetch_schedule(pair: Pair, refresh: Duration, clock: SharedClock) -> Subscription<Message> {
+    cosmic::iced::subscription::channel(
+        ("fetch-schedule", pair.clone(), refresh),
+        4,
+        move |mut output| async move {
+            loop {
+                let scheduled_at = clock.now_instant() + refresh;
+                let _ = output
+                    .send(Message::FetchScheduled(pair.clone(), scheduled_at))
+                    .await;
+                tokio::time::sleep(refresh).await;
+                let _ = output.send(Message::FetchDue(pair.clone())).await;
+            }
+        },
+    )
+}
+
+/// Sends `Message::TimezoneChanged` whenever the local UTC offset changes, because the
+/// system timezone was changed or a DST transition passed. chrono rereads the system
+/// timezone when it changes, so polling it is enough.
+pub fn timezone_changes(clock: SharedClock) -> Subscription<Message> {
+    cosmic::iced::subscription::channel("timezone-changes", 1, |mut output| async move {
+        let mut offset = clock.now_local().offset().fix();
+        loop {
+            tokio::time::sleep(TIMEZONE_POLL).await;
+            let now = clock.now_local().offset().fix();
+            if now != offset {
+                offset = now;
+                let _ = output.send(Message::TimezoneChanged).await;
+            }
+        }
+    })
+}
@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Whether the session is locked, read from logind.
+
+use cosmic::iced::futures::{SinkExt, StreamExt};
+use cosmic::iced::Subscription;
+use std::time::Duration;
+
+use crate::app::Message;
+
+/// How long logind has to answer before the session is taken to have none.
+const LOGIND_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    #[zbus(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+}
+
+/// Sends `Message::SessionLocked` with the lock state of the session once connected,
+/// then on every change. Sends nothing if logind can't be reached, so the applet then
+/// behaves as if the session were never locked.
+pub fn lock_changes() -> Subscription<Message> {
+    cosmic::iced::subscription::channel("session-lock", 4, |mut output| async move {
+        if let Err(e) = watch_lock(&mut output).await {
+            tracing::warn!("Error watching the session lock state: {}", e);
+        }
+        std::future::pending().await
+    })
+}
+
+async fn watch_lock(
+    output: &mut cosmic::iced::futures::channel::mpsc::Sender<Message>,
+) -> zbus::Result<()> {
+    let (session, locked) = tokio::time::timeout(LOGIND_TIMEOUT, async {
+        let connection = zbus::Connection::system().await?;
+        let session = SessionProxy::new(&connection).await?;
+        let locked = session.locked_hint().await?;
+        zbus::Result::Ok((session, locked))
+    })
+    .await
+    .map_err(|_| zbus::Error::Failure("logind didn't answer".into()))??;
+    let _ = output.send(Message::SessionLocked(locked)).await;
+    let mut changes = session.receive_locked_hint_changed().await;
+    while let Some(change) = changes.next().await {
+        let _ = output
+            .send(Message::SessionLocked(change.get().await?))
+            .await;
+    }
+    Ok(())
+}
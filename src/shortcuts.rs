@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Global keyboard shortcuts, registered through the GlobalShortcuts portal so they can be
+//! assigned in COSMIC Settings and work while the popup is closed.
+
+use cosmic::iced::futures::channel::mpsc::Sender;
+use cosmic::iced::futures::{SinkExt, StreamExt};
+use cosmic::iced::Subscription;
+use std::collections::HashMap;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use crate::app::Message;
+use crate::fl;
+use crate::portal::{await_success, request_response, sender, with_timeout, TOKEN_PREFIX};
+
+/// Shortcut id that moves the panel to the next pair.
+const CYCLE_PAIR: &str = "cycle-pair";
+/// Shortcut id that opens the popup with the pair input focused.
+const OPEN_PAIR_INPUT: &str = "open-pair-input";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.portal.GlobalShortcuts",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait GlobalShortcuts {
+    fn create_session(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<OwnedObjectPath>;
+
+    fn bind_shortcuts(
+        &self,
+        session_handle: &OwnedObjectPath,
+        shortcuts: &[(&str, HashMap<&str, Value<'_>>)],
+        parent_window: &str,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(signal)]
+    fn activated(
+        &self,
+        session_handle: OwnedObjectPath,
+        shortcut_id: String,
+        timestamp: u64,
+        options: HashMap<String, OwnedValue>,
+    ) -> zbus::Result<()>;
+}
+
+/// Binds the applet's shortcuts, then sends `Message::ShortcutsAvailable` with whether that
+/// worked, followed by a message for every activation: `Message::CyclePanel` or
+/// `Message::OpenPairInput`. Without the portal, or when the user declined, only
+/// `ShortcutsAvailable(false)` is sent and the shortcuts are not offered.
+pub fn shortcut_activations() -> Subscription<Message> {
+    cosmic::iced::subscription::channel("global-shortcuts", 4, |mut output| async move {
+        if let Err(e) = watch_shortcuts(&mut output).await {
+            tracing::warn!("Error binding global shortcuts: {}", e);
+            let _ = output.send(Message::ShortcutsAvailable(false)).await;
+        }
+        std::future::pending().await
+    })
+}
+
+async fn watch_shortcuts(output: &mut Sender<Message>) -> zbus::Result<()> {
+    let connection = with_timeout(zbus::Connection::session()).await?;
+    let portal = with_timeout(GlobalShortcutsProxy::new(&connection)).await?;
+    let sender = sender(&connection)?;
+
+    let session_token = format!("{TOKEN_PREFIX}_session");
+    let created = request_response(&connection, &sender, "create").await?;
+    with_timeout(portal.create_session(HashMap::from([
+        (
+            "handle_token",
+            Value::from(format!("{TOKEN_PREFIX}_create")),
+        ),
+        ("session_handle_token", Value::from(session_token.clone())),
+    ])))
+    .await?;
+    // Creating the session asks the user nothing, unlike binding the shortcuts below.
+    with_timeout(await_success(created)).await?;
+    let session = OwnedObjectPath::try_from(format!(
+        "/org/freedesktop/portal/desktop/session/{sender}/{session_token}"
+    ))?;
+
+    let cycle_description = fl!("shortcut-cycle-pair");
+    let open_description = fl!("shortcut-open-pair-input");
+    let shortcuts = [
+        (
+            CYCLE_PAIR,
+            HashMap::from([("description", Value::from(cycle_description.as_str()))]),
+        ),
+        (
+            OPEN_PAIR_INPUT,
+            HashMap::from([("description", Value::from(open_description.as_str()))]),
+        ),
+    ];
+    let bound = request_response(&connection, &sender, "bind").await?;
+    with_timeout(portal.bind_shortcuts(
+        &session,
+        &shortcuts,
+        "",
+        HashMap::from([("handle_token", Value::from(format!("{TOKEN_PREFIX}_bind")))]),
+    ))
+    .await?;
+    await_success(bound).await?;
+    let _ = output.send(Message::ShortcutsAvailable(true)).await;
+
+    let mut activations = portal.receive_activated().await?;
+    while let Some(activation) = activations.next().await {
+        let args = activation.args()?;
+        if args.session_handle() != &session {
+            continue;
+        }
+        let message = match args.shortcut_id().as_str() {
+            CYCLE_PAIR => Message::CyclePanel,
+            OPEN_PAIR_INPUT => Message::OpenPairInput,
+            _ => continue,
+        };
+        let _ = output.send(message).await;
+    }
+    Ok(())
+}
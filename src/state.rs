@@ -0,0 +1,3311 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! What the applet holds at runtime, and the methods that update it without drawing.
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use cosmic::app::{Command, Core};
+use cosmic::applet::PanelType;
+use cosmic::cosmic_config::{self, CosmicConfigEntry};
+use cosmic::iced::window::Id;
+use cosmic::widget::segmented_button;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Condvar, Mutex, Once};
+use std::time::{Duration, Instant};
+
+use crate::app::Message;
+use crate::bundled::{bundled_rate, BundledRate};
+use crate::capabilities::PopupCapabilities;
+use crate::clock::SharedClock;
+use crate::config::{
+    validate_config, AlertCondition, AnimationStyle, AppletConfig, AppletSection, AppletState,
+    ChangeWindow, ConfigValidationError, DisplayMode, FlaggedQuote, Pair, PairEntry, RuleConflict,
+    SessionOverrides, SortOrder, MAX_PANEL_SLOTS,
+};
+use crate::dbus::watchlist_changed;
+use crate::exact::{self, decimal, format_decimal, parse_decimal};
+use crate::export::HistoryEntry;
+use crate::expression::{self, ExprError};
+use crate::fl;
+use crate::footprint::{Footprint, HeldCounts};
+use crate::market::{classify_age, market_closed, QuoteAge};
+use crate::metadata::metadata;
+use crate::power::{low_power_active, LOW_POWER_INTERVAL_FACTOR};
+use crate::providers::{self, pair_cadence, Cadence, PairResponse, Provider, SharedUsage};
+use crate::report::redacted_settings;
+use crate::scheduler::{fetch_command, fetch_many_command, FetchTrigger};
+
+/// The provider that serves a pair right now: `primary`, unless it has been failing for the
+/// pair since `failing_since` for at least `after` and a `fallback` other than it is set.
+///
+/// The primary keeps being asked while the fallback serves, so the pair goes back to it as
+/// soon as it answers again and clears `failing_since`.
+pub fn serving_provider<'a>(
+    primary: &'a str,
+    fallback: &'a str,
+    failing_since: Option<Instant>,
+    now: Instant,
+    after: Duration,
+) -> &'a str {
+    let failing_long =
+        failing_since.is_some_and(|since| now.saturating_duration_since(since) >= after);
+    if failing_long && !fallback.is_empty() && fallback != primary {
+        fallback
+    } else {
+        primary
+    }
+}
+
+/// The note shown when `pair` is added that it gets new quotes less often than every
+/// `refresh_secs`, so it doesn't look stuck between fetches. `None` for pairs that keep up.
+pub fn cadence_note(pair: &Pair, cadence: Cadence, refresh_secs: u64) -> Option<String> {
+    if cadence.secs() <= refresh_secs {
+        return None;
+    }
+    Some(match cadence {
+        Cadence::BusinessDay => fl!("cadence-note-business-day", pair = pair.to_string()),
+        Cadence::Every(secs) => fl!(
+            "cadence-note-every",
+            pair = pair.to_string(),
+            minutes = secs.div_ceil(60)
+        ),
+    })
+}
+
+/// The tag on the row of a pair getting new quotes less often than every `refresh_secs`.
+pub fn cadence_tag(cadence: Cadence, refresh_secs: u64) -> Option<String> {
+    if cadence.secs() <= refresh_secs {
+        return None;
+    }
+    Some(match cadence {
+        Cadence::BusinessDay => fl!("cadence-tag-business-day"),
+        Cadence::Every(secs) => fl!("cadence-tag-every", minutes = secs.div_ceil(60)),
+    })
+}
+
+/// How many times the interval between new quotes of a pair its quote may be old before
+/// it is stale, see `YourApp::quote_age`.
+const STALE_AFTER_CADENCES: u64 = 3;
+/// How many fetches `ProviderStats` remembers.
+const RECENT_FETCHES: usize = 20;
+/// How many quotes `PairState::history` remembers at most, whatever the refresh interval.
+const HISTORY_LEN: usize = 2000;
+/// How long `PairState::history` remembers quotes, enough for `ChangeWindow::Rolling24h`.
+const HISTORY_MAX_AGE_HOURS: i64 = 25;
+/// How long the `rate_change_animation` of a changed rate plays.
+const RATE_ANIMATION_DURATION: Duration = Duration::from_millis(500);
+/// How many flagged quotes are kept, the oldest are dropped beyond.
+const MAX_FLAGGED_QUOTES: usize = 20;
+/// Decimals of the amounts the calculator computes, unless a display precision is set.
+const CALCULATOR_DECIMALS: usize = 2;
+
+/// A side of the two-way calculator.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CalculatorSide {
+    /// The amount in the pair's base currency.
+    #[default]
+    Base,
+    /// The amount in the pair's quote currency.
+    Quote,
+}
+
+/// Whether the API could be reached by the last fetch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkStatus {
+    #[default]
+    Unknown,
+    Online,
+    Offline,
+    /// Requests are answered by a captive portal until the user logs into the network.
+    CaptivePortal,
+    /// The TLS connection failed for another reason than the clock.
+    Tls,
+    /// Certificates look expired or not yet valid because the system clock is wrong.
+    ClockIncorrect,
+}
+
+/// What `WatchlistChanged` announces changes of: every pair with whether it is enabled and
+/// frozen, and the index of the primary pair.
+pub type Watchlist = (Vec<(Pair, bool, bool)>, Option<usize>);
+
+/// The `Watchlist` of `config`.
+pub fn watchlist_of(config: &AppletConfig) -> Watchlist {
+    let pairs = config
+        .pairs
+        .iter()
+        .map(|entry| (entry.pair.clone(), entry.enabled, entry.frozen))
+        .collect();
+    (pairs, config.first_panel_pair)
+}
+
+/// Why a pair isn't being refreshed, or its rate isn't moving, declared in order of
+/// precedence: when several apply, the first is shown, so a pair the user froze never reads
+/// as merely offline, and one offline over the weekend reads as offline rather than closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PauseReason {
+    /// The user froze the pair, it is paused whatever else happens.
+    Frozen,
+    /// Fetching stops while the session is locked.
+    SessionLocked,
+    /// The provider announced maintenance, fetches resume once it is over.
+    Maintenance,
+    /// The last fetch couldn't reach the network.
+    Offline,
+    /// The pair kept failing through its retries, it is tried again at the next refresh.
+    CircuitBreaker,
+    /// The forex market is closed for the weekend, the rate holds until it opens.
+    MarketClosed,
+    /// Low power mode only fetches the pairs the panel shows.
+    LowPower,
+}
+
+impl PauseReason {
+    /// The id scripts see, on D-Bus and in the diagnostic report.
+    pub fn id(self) -> &'static str {
+        match self {
+            PauseReason::Frozen => "frozen",
+            PauseReason::SessionLocked => "session-locked",
+            PauseReason::Maintenance => "maintenance",
+            PauseReason::Offline => "offline",
+            PauseReason::CircuitBreaker => "circuit-breaker",
+            PauseReason::MarketClosed => "market-closed",
+            PauseReason::LowPower => "low-power",
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            PauseReason::Frozen => "media-playback-pause-symbolic",
+            PauseReason::SessionLocked => "system-lock-screen-symbolic",
+            PauseReason::Maintenance => "emblem-system-symbolic",
+            PauseReason::Offline => "network-offline-symbolic",
+            PauseReason::CircuitBreaker => "dialog-warning-symbolic",
+            PauseReason::MarketClosed => "alarm-symbolic",
+            PauseReason::LowPower => "battery-caution-symbolic",
+        }
+    }
+
+    /// What is shown instead of the rate while there is none.
+    pub fn label(self) -> String {
+        match self {
+            PauseReason::Frozen => fl!("pause-frozen"),
+            PauseReason::SessionLocked => fl!("pause-session-locked"),
+            PauseReason::Maintenance => fl!("pause-maintenance"),
+            PauseReason::Offline => fl!("pause-offline"),
+            PauseReason::CircuitBreaker => fl!("pause-circuit-breaker"),
+            PauseReason::MarketClosed => fl!("pause-market-closed"),
+            PauseReason::LowPower => fl!("pause-low-power"),
+        }
+    }
+
+    pub fn explanation(self) -> String {
+        match self {
+            PauseReason::Frozen => fl!("pause-frozen-explanation"),
+            PauseReason::SessionLocked => fl!("pause-session-locked-explanation"),
+            PauseReason::Maintenance => fl!("pause-maintenance-explanation"),
+            PauseReason::Offline => fl!("pause-offline-explanation"),
+            PauseReason::CircuitBreaker => fl!("pause-circuit-breaker-explanation"),
+            PauseReason::MarketClosed => fl!("pause-market-closed-explanation"),
+            PauseReason::LowPower => fl!("pause-low-power-explanation"),
+        }
+    }
+}
+
+/// The reason of `reasons` that takes precedence, see `PauseReason`.
+pub fn pause_reason(reasons: impl IntoIterator<Item = PauseReason>) -> Option<PauseReason> {
+    reasons.into_iter().min()
+}
+
+/// Where the rate shown for a pair comes from, see `select_rate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateSource<'a> {
+    /// A quote fetched this session, live or kept by the `RateCache`.
+    Fetched(&'a PairResponse),
+    /// The approximate rate of `crate::bundled`, only until a quote is fetched.
+    Bundled(BundledRate),
+}
+
+/// The rate to show of a pair: its `live` quote, else the last one the `cache` kept, else
+/// the `bundled` approximation.
+pub fn select_rate<'a>(
+    live: Option<&'a PairResponse>,
+    cached: Option<&'a PairResponse>,
+    bundled: Option<BundledRate>,
+) -> Option<RateSource<'a>> {
+    live.or(cached)
+        .map(RateSource::Fetched)
+        .or_else(|| bundled.map(RateSource::Bundled))
+}
+
+/// A fetch that was started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchRecord {
+    pub pair: Pair,
+    pub trigger: FetchTrigger,
+    pub at: DateTime<Local>,
+}
+
+/// What the applet did with a provider this session.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderStats {
+    /// The last `RECENT_FETCHES` fetches, newest first.
+    pub recent_fetches: VecDeque<FetchRecord>,
+}
+
+impl ProviderStats {
+    fn record(&mut self, record: FetchRecord) {
+        self.recent_fetches.push_front(record);
+        self.recent_fetches.truncate(RECENT_FETCHES);
+    }
+}
+
+/// A quote kept to answer fetches of the same pair until `expires_at`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub response: PairResponse,
+    pub expires_at: Instant,
+}
+
+/// The last quote fetched of every pair, so fetching a pair again within the TTL, e.g. by
+/// quickly toggling it, doesn't send another request.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateCache {
+    pub entries: HashMap<Pair, CacheEntry>,
+}
+
+impl RateCache {
+    /// The cached quote of `pair`, unless it expired by `now`.
+    pub fn fresh(&self, pair: &Pair, now: Instant) -> Option<&PairResponse> {
+        self.entries
+            .get(pair)
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| &entry.response)
+    }
+
+    pub fn insert(&mut self, pair: Pair, response: PairResponse, expires_at: Instant) {
+        self.entries.insert(
+            pair,
+            CacheEntry {
+                response,
+                expires_at,
+            },
+        );
+    }
+}
+
+/// A settings write, run on the writer thread.
+pub type WriteJob = Box<dyn FnOnce() -> Result<(), String> + Send>;
+
+/// Where `Persistence` writes settings to: the disk, or a store of the tests'.
+pub trait SettingsStore: fmt::Debug + Send + Sync {
+    /// Whether settings can be written at all.
+    fn writable(&self) -> bool;
+
+    /// Runs `job`, which writes `what`, now or later.
+    fn write(&self, what: &'static str, job: WriteJob);
+
+    /// Whether a write failed since `take_failure` last said so, clearing it.
+    fn take_failure(&self) -> bool;
+
+    /// Whether a write failed that `take_failure` hasn't reported yet.
+    fn has_failed(&self) -> bool;
+}
+
+/// Writes settings to the directory `dir` on a writer thread of its own, off the message
+/// loop so a slow disk never stalls the panel.
+#[derive(Debug)]
+pub struct DiskStore {
+    dir: Option<PathBuf>,
+    queue: Arc<WriteQueue>,
+    writer: Once,
+}
+
+/// The writes the writer thread hasn't run yet, by what they write. Only the latest of
+/// each is kept, a newer config supersedes one still waiting, so the queue never grows
+/// past one write per kind.
+#[derive(Default)]
+struct WriteQueue {
+    pending: Mutex<BTreeMap<&'static str, WriteJob>>,
+    queued: Condvar,
+    /// Set once a write failed, see `SettingsStore::take_failure`.
+    failed: AtomicBool,
+}
+
+impl fmt::Debug for WriteQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteQueue")
+            .field("failed", &self.failed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DiskStore {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        DiskStore {
+            dir,
+            queue: Arc::default(),
+            writer: Once::new(),
+        }
+    }
+}
+
+impl SettingsStore for DiskStore {
+    /// Whether a file can be created in `dir`, creating the directory if needed.
+    fn writable(&self) -> bool {
+        let Some(dir) = &self.dir else {
+            return false;
+        };
+        let probe = dir.join(".write-probe");
+        let result = std::fs::create_dir_all(dir)
+            .and_then(|()| std::fs::write(&probe, b""))
+            .and_then(|()| std::fs::remove_file(&probe));
+        if let Err(e) = &result {
+            tracing::debug!("{} is not writable: {}", dir.display(), e);
+        }
+        result.is_ok()
+    }
+
+    fn write(&self, what: &'static str, job: WriteJob) {
+        self.writer.call_once(|| {
+            let queue = self.queue.clone();
+            std::thread::spawn(move || run_writes(&queue));
+        });
+        self.queue.pending.lock().unwrap().insert(what, job);
+        self.queue.queued.notify_one();
+    }
+
+    fn take_failure(&self) -> bool {
+        self.queue.failed.swap(false, AtomicOrdering::Relaxed)
+    }
+
+    fn has_failed(&self) -> bool {
+        self.queue.failed.load(AtomicOrdering::Relaxed)
+    }
+}
+
+/// Runs the writes of `queue` as they are queued.
+fn run_writes(queue: &WriteQueue) {
+    loop {
+        let (what, job) = {
+            let mut pending = queue.pending.lock().unwrap();
+            loop {
+                if let Some(write) = pending.pop_first() {
+                    break write;
+                }
+                pending = queue.queued.wait(pending).unwrap();
+            }
+        };
+        if let Err(e) = job() {
+            tracing::warn!("Error saving {what}, keeping settings in memory only: {e}");
+            queue.failed.store(true, AtomicOrdering::Relaxed);
+        }
+    }
+}
+
+/// Whether settings reach the disk. After a failed write the session keeps them in memory
+/// only and stops writing, so a read-only or missing config directory doesn't fail, and
+/// log, every save again.
+#[derive(Debug, Clone)]
+pub struct Persistence {
+    /// The directory writes go to, `None` when there is none to write to.
+    pub dir: Option<PathBuf>,
+    store: Arc<dyn SettingsStore>,
+    pub memory_only: bool,
+    /// Whether the user dismissed the memory-only notice, which is only shown once per
+    /// session even if writes start failing again.
+    pub notice_dismissed: bool,
+}
+
+impl Default for Persistence {
+    fn default() -> Self {
+        Persistence {
+            dir: None,
+            store: Arc::new(DiskStore::new(None)),
+            memory_only: false,
+            notice_dismissed: false,
+        }
+    }
+}
+
+impl Persistence {
+    /// Probes `dir`, starting in memory-only mode unless it is writable.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        let store = Arc::new(DiskStore::new(dir.clone()));
+        Persistence::with_store(dir, store)
+    }
+
+    /// Writes to `store`, starting in memory-only mode unless it is writable.
+    pub fn with_store(dir: Option<PathBuf>, store: Arc<dyn SettingsStore>) -> Self {
+        let memory_only = !store.writable();
+        Persistence {
+            dir,
+            store,
+            memory_only,
+            notice_dismissed: false,
+        }
+    }
+
+    /// Hands `write` to the store, unless in memory-only mode. A write that fails switches
+    /// to it from the next one on.
+    pub fn write<E: fmt::Debug>(
+        &mut self,
+        what: &'static str,
+        write: impl FnOnce() -> Result<(), E> + Send + 'static,
+    ) {
+        if self.store.take_failure() {
+            self.memory_only = true;
+        }
+        if self.memory_only {
+            return;
+        }
+        self.store.write(
+            what,
+            Box::new(move || write().map_err(|e| format!("{e:?}"))),
+        );
+    }
+
+    /// Whether settings are kept in memory only, including after a write that failed since
+    /// the last one was handed to the store.
+    pub fn is_memory_only(&self) -> bool {
+        self.memory_only || self.store.has_failed()
+    }
+
+    /// Leaves memory-only mode if the store became writable, e.g. once a drive was
+    /// remounted.
+    pub fn reprobe(&mut self) {
+        if self.is_memory_only() && self.store.writable() {
+            self.store.take_failure();
+            self.memory_only = false;
+        }
+    }
+
+    /// Whether the popup tells the user that settings are not being saved.
+    pub fn show_notice(&self) -> bool {
+        self.is_memory_only() && !self.notice_dismissed
+    }
+}
+
+/// What the applet knows about a pair beyond its config and quote, for this session only.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PairState {
+    /// The outcome of the last "Test webhook" delivery: the status code it was answered
+    /// with, or why it could not be delivered.
+    pub webhook_last_test: Option<Result<reqwest::StatusCode, String>>,
+    /// The bids received this session with when they arrived, newest first, for the last
+    /// `HISTORY_MAX_AGE_HOURS`.
+    pub history: VecDeque<(DateTime<Local>, f64)>,
+    /// When the quote last changed according to `quote_changed`, as opposed to
+    /// `YourApp::fetched_at` which is when it was last confirmed.
+    pub changed_at: Option<Instant>,
+    /// The forward rates last fetched with the quote, by tenor, `None` until they were.
+    pub forward_rates: Option<HashMap<String, f64>>,
+    /// Why the last fetch of the pair failed, `None` once one succeeded.
+    pub last_error: Option<String>,
+    /// When fetching the pair from the primary provider started failing, `None` while it
+    /// answers.
+    pub failing_since: Option<Instant>,
+    /// The provider the quote was fetched from.
+    pub quoted_by: Option<&'static str>,
+    /// The start of the response body the quote was last fetched in, kept until the next
+    /// one so a quote flagged as wrong is kept with what the provider sent.
+    pub raw_response: Option<String>,
+    /// How much of the `rate_change_animation` is left to play, from 1 when the rate just
+    /// changed down to 0 once it is over.
+    pub animation_progress: f32,
+    /// Whether the rate animated by `animation_progress` rose, rather than fell.
+    pub animation_rising: bool,
+}
+
+impl PairState {
+    /// Remembers a bid received at `now`.
+    pub fn record_quote(&mut self, bid: f64, now: DateTime<Local>) {
+        self.history.push_front((now, bid));
+        let oldest = now - chrono::Duration::hours(HISTORY_MAX_AGE_HOURS);
+        while self.history.back().is_some_and(|&(at, _)| at < oldest) {
+            self.history.pop_back();
+        }
+        self.history.truncate(HISTORY_LEN);
+    }
+
+    /// Plays the rate change animation again, from the start.
+    pub fn start_animation(&mut self, rising: bool) {
+        self.animation_progress = 1.0;
+        self.animation_rising = rising;
+    }
+
+    /// Moves the animation `elapsed` further, returning whether it is still playing.
+    pub fn advance_animation(&mut self, elapsed: Duration) -> bool {
+        let step = elapsed.as_secs_f32() / RATE_ANIMATION_DURATION.as_secs_f32();
+        self.animation_progress = (self.animation_progress - step).max(0.0);
+        self.animation_progress > 0.0
+    }
+}
+
+/// A stretch of fetches that all failed, tracked by `FailureEpisode::step` to announce it
+/// once it lasted long enough, and its end once announced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FailureEpisode {
+    /// The last fetch succeeded, or there was none yet.
+    #[default]
+    None,
+    /// Every fetch failed since `since`, not announced yet.
+    Failing { since: Instant },
+    /// Every fetch failed since `since`, and the digest announced it.
+    Announced { since: Instant },
+}
+
+/// What `FailureEpisode::step` asks to be announced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpisodeEvent {
+    /// Fetches have been failing for this long.
+    Digest(Duration),
+    /// A fetch succeeded again after the digest.
+    Recovered,
+}
+
+impl FailureEpisode {
+    /// Moves on with the outcome of a fetch at `now`: a success ends the episode, a failure
+    /// starts one or goes on with it. The digest is due once when it lasted `threshold`,
+    /// never with a zero `threshold`.
+    pub fn step(&mut self, ok: bool, now: Instant, threshold: Duration) -> Option<EpisodeEvent> {
+        match (*self, ok) {
+            (FailureEpisode::Announced { .. }, true) => {
+                *self = FailureEpisode::None;
+                Some(EpisodeEvent::Recovered)
+            }
+            (_, true) => {
+                *self = FailureEpisode::None;
+                None
+            }
+            (FailureEpisode::None, false) => {
+                *self = FailureEpisode::Failing { since: now };
+                None
+            }
+            (FailureEpisode::Failing { since }, false) => {
+                let failing = now.saturating_duration_since(since);
+                if threshold.is_zero() || failing < threshold {
+                    return None;
+                }
+                *self = FailureEpisode::Announced { since };
+                Some(EpisodeEvent::Digest(failing))
+            }
+            (FailureEpisode::Announced { .. }, false) => None,
+        }
+    }
+}
+
+/// How long `NotificationBudget` counts the alert notifications shown.
+const NOTIFICATION_BUDGET_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// The alert notifications shown within the last `NOTIFICATION_BUDGET_WINDOW`, to cap them
+/// at `AppletConfig::max_alert_notifications_per_hour` across every rule and pair. Alerts
+/// over the cap are counted instead of shown, and summarized by a single notification once
+/// the window has room again, see `NotificationBudget::reset`.
+#[derive(Debug, Default, Clone)]
+pub struct NotificationBudget {
+    /// When each notification in the window was shown, oldest first.
+    shown: VecDeque<Instant>,
+    /// The alerts not shown since the budget ran out.
+    pub suppressed: usize,
+}
+
+impl NotificationBudget {
+    /// Takes a notification shown at `now` out of a budget of `limit` per window, `0` being
+    /// unlimited. If the budget is spent, the alert is counted as suppressed instead and
+    /// `false` returned.
+    pub fn take(&mut self, now: Instant, limit: u32) -> bool {
+        if self.is_spent(now, limit) {
+            self.suppressed += 1;
+            return false;
+        }
+        self.shown.push_back(now);
+        true
+    }
+
+    /// When the oldest notification in the window leaves it, making room for another.
+    pub fn next_room(&self) -> Option<Instant> {
+        self.shown
+            .front()
+            .map(|&shown| shown + NOTIFICATION_BUDGET_WINDOW)
+    }
+
+    /// The number of alerts suppressed, if there are any and the window has room at `now`
+    /// for the notification summarizing them. That notification is taken out of the budget
+    /// and the count starts over.
+    pub fn reset(&mut self, now: Instant, limit: u32) -> Option<usize> {
+        if self.suppressed == 0 || self.is_spent(now, limit) {
+            return None;
+        }
+        self.shown.push_back(now);
+        Some(std::mem::take(&mut self.suppressed))
+    }
+
+    /// Forgets the notifications that left the window at `now`, telling whether the
+    /// remaining ones spend a budget of `limit`.
+    fn is_spent(&mut self, now: Instant, limit: u32) -> bool {
+        while self.shown.front().is_some_and(|&shown| {
+            now.saturating_duration_since(shown) >= NOTIFICATION_BUDGET_WINDOW
+        }) {
+            self.shown.pop_front();
+        }
+        limit != 0 && self.shown.len() >= limit as usize
+    }
+}
+
+/// A pair just removed from the watchlist, kept for `UNDO_REMOVAL_SECS` so the removal
+/// can be undone with everything the applet knew about the pair.
+#[derive(Debug, Clone)]
+pub struct RemovedPair {
+    /// Where the pair was in `AppletConfig::pairs`.
+    pub index: usize,
+    pub entry: PairEntry,
+    pub quote: Option<PairResponse>,
+    pub fetched_at: Option<Instant>,
+    pub state: Option<PairState>,
+    /// Whether it was the `first_panel_pair`.
+    pub pinned: bool,
+    /// When it was removed, which also tells its expiry apart from an earlier removal's.
+    pub removed_at: Instant,
+}
+
+/// The views of the popup, each one an item of `YourApp::nav_model`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AppView {
+    #[default]
+    Watchlist,
+    Converter,
+    Alerts,
+    History,
+    Settings,
+    Status,
+    Debug,
+}
+
+impl AppView {
+    pub const ALL: [AppView; 7] = [
+        AppView::Watchlist,
+        AppView::Converter,
+        AppView::Alerts,
+        AppView::History,
+        AppView::Settings,
+        AppView::Status,
+        AppView::Debug,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            AppView::Watchlist => fl!("tab-watchlist"),
+            AppView::Converter => fl!("tab-converter"),
+            AppView::Alerts => fl!("tab-alerts"),
+            AppView::History => fl!("tab-history"),
+            AppView::Settings => fl!("tab-settings"),
+            AppView::Status => fl!("tab-status"),
+            AppView::Debug => fl!("tab-debug"),
+        }
+    }
+}
+
+/// The different popups the applet can open, only one of them is open at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupKind {
+    /// The popup opened by clicking the panel button.
+    Main,
+    /// The menu opened by right-clicking the panel button.
+    ContextMenu,
+    /// The compact list of pairs opened by the `quick_menu_click`.
+    QuickMenu,
+    Calculator,
+    About,
+}
+
+/// This is the struct that represents your application.
+/// It is used to define the data that will be used by your application.
+#[derive(Default)]
+pub struct YourApp {
+    /// Application state which is managed by the COSMIC runtime.
+    pub(crate) core: Core,
+    /// Where the time is read from, see `crate::clock`.
+    pub(crate) clock: SharedClock,
+    /// The API usage counters every request is counted in, see `save_api_usage`.
+    pub(crate) usage: SharedUsage,
+    /// The popup id.
+    pub(crate) popup: Option<Id>,
+    /// The context menu id.
+    pub(crate) context_menu: Option<Id>,
+    /// The quick menu id.
+    pub(crate) quick_menu: Option<Id>,
+    /// The calculator popup id.
+    pub(crate) calculator_popup: Option<Id>,
+    /// The about popup id.
+    pub(crate) about_popup: Option<Id>,
+    /// What each open popup id is, so close requests can be routed to the right state.
+    pub(crate) popup_kind: HashMap<Id, PopupKind>,
+    /// Handle used to persist `config`, `None` if the config could not be opened.
+    pub(crate) config_handler: Option<cosmic_config::Config>,
+    /// The persisted applet settings.
+    pub(crate) config: AppletConfig,
+    /// Handle used to persist `state`.
+    pub(crate) state_handler: Option<cosmic_config::Config>,
+    /// The persisted applet state.
+    pub(crate) state: AppletState,
+    // Add a state for the text input
+    pub(crate) input_value: String,
+    // The pair input's text from before it was focused and cleared, restored if it loses
+    // focus without the pair being added
+    pub(crate) input_before_focus: Option<String>,
+    // The text of the pair input of the startup list editor
+    pub(crate) startup_input: String,
+    // The saved values of the settings the startup list and the command line replace for
+    // this session, written back on every save so the session never replaces them
+    pub(crate) session_overrides: SessionOverrides,
+    // When the maintenance windows the providers announced end, keyed by provider name.
+    // Nothing is fetched from a provider until its window is over
+    pub(crate) maintenance: HashMap<String, DateTime<Local>>,
+    // Why the last paste into the input was rejected
+    pub(crate) paste_error: Option<String>,
+    // That the pair added last updates less often than it is fetched, until the input is
+    // used again
+    pub(crate) cadence_note: Option<String>,
+    // Add a state for the exchange rates, keyed by pair
+    pub(crate) exchange_rates: HashMap<Pair, PairResponse>,
+    pub(crate) rate_cache: RateCache,
+    // When the scheduler will fetch each pair next
+    pub(crate) next_fetch_at: HashMap<Pair, Instant>,
+    // When each pair was last fetched, successfully or not
+    pub(crate) fetched_at: HashMap<Pair, Instant>,
+    // Index into the active pairs of the one shown in the panel
+    pub(crate) panel_index: usize,
+    // The view shown in the popup, kept in sync with the active item of `nav_model`
+    pub(crate) active_view: AppView,
+    // The navigation items of the popup, one per `AppView`
+    pub(crate) nav_model: segmented_button::SingleSelectModel,
+    // How many times each pair has been retried since its last scheduled fetch
+    pub(crate) retry_attempts: HashMap<Pair, u32>,
+    // Whether every fetch has been failing lately, and if that was announced
+    pub(crate) failure_episode: FailureEpisode,
+    // The text of the settings number inputs, kept apart from the config while being edited
+    pub(crate) refresh_input: String,
+    pub(crate) failure_digest_input: String,
+    pub(crate) max_visible_input: String,
+    pub(crate) font_family_input: String,
+    // Whether the watchlist rows are scrolled down, showing the scroll-to-top button
+    pub(crate) pair_list_scrolled: bool,
+    // Whether no pair is enabled, labelling the panel "No pairs" and showing the
+    // get-started banner in the popup
+    pub(crate) unconfigured: bool,
+    // Whether an alert fired since the user last looked, see `YourApp::acknowledge_alerts`
+    pub(crate) alerts_unacknowledged: bool,
+    // Whether the visual bell currently highlights the panel button
+    pub(crate) bell_lit: bool,
+    // Whether the open popup lost focus, see `YourApp::paused_for_focus`
+    pub(crate) popup_unfocused: bool,
+    // The watchlist as of the last `WatchlistChanged` signal, or startup
+    pub(crate) announced_watchlist: Watchlist,
+    // The logical size of the output the panel is on, `None` until the compositor told
+    pub(crate) output_size: Option<(f32, f32)>,
+    // What the popup operations the applet relies on were seen to do on this system
+    pub(crate) popup_capabilities: PopupCapabilities,
+    // The size the open popup was last configured to, `None` until it was
+    pub(crate) popup_size: Option<(f32, f32)>,
+    // The popup a new size was requested for, until it is configured to it
+    pub(crate) resize_probe: Option<Id>,
+    // The footprint last measured for the status page, `None` until it was opened
+    pub(crate) footprint: Option<Footprint>,
+    // The section of the popup Tab last moved to, `None` until it was used
+    pub(crate) focused_section: Option<AppletSection>,
+    // Whether the global shortcuts were bound, they are only mentioned in settings if so
+    pub(crate) shortcuts_available: bool,
+    // Whether settings are written to disk or only kept for this session
+    pub(crate) persistence: Persistence,
+    // The directory the state is kept in, for the footprint
+    pub(crate) state_dir: Option<PathBuf>,
+    // The pages of quotes shown for each pair whose history is expanded, collapsed pairs
+    // have no entry
+    pub(crate) history_pages: HashMap<Pair, usize>,
+    // The last pair removed while its removal can still be undone, forgotten when the
+    // popup closes
+    pub(crate) removed_pair: Option<RemovedPair>,
+    // The errors `validate_config` found in the config loaded at startup, until fixed or
+    // dismissed
+    pub(crate) config_errors: Vec<ConfigValidationError>,
+    pub(crate) first_panel_dwell_input: String,
+    // Whether the session is locked, pausing fetches and queueing notifications
+    pub(crate) session_locked: bool,
+    // The battery charge in percent while it is discharging, `None` on AC or when UPower
+    // isn't watched
+    pub(crate) battery: Option<f64>,
+    pub(crate) low_power_below_input: String,
+    // The pair, summary and body of the alert notifications held back while locked
+    pub(crate) queued_alerts: Vec<(Pair, String, String)>,
+    // The alert notifications shown lately, and those suppressed over the cap
+    pub(crate) notification_budget: NotificationBudget,
+    pub(crate) alert_budget_input: String,
+    // Whether the popup has yet to point out the alerts `reconcile_orphans` parked at
+    // startup, which it does once
+    pub(crate) unassigned_notice: bool,
+    // Whether `--test-mode` was passed, showing the rate simulation in the debug tab
+    pub(crate) test_mode: bool,
+    // The last fetched bid of each pair, around which rates are simulated
+    pub(crate) simulation_anchor: HashMap<Pair, f64>,
+    pub(crate) cache_ttl_input: String,
+    pub(crate) retry_count_input: String,
+    pub(crate) retry_delay_input: String,
+    pub(crate) soft_limit_input: String,
+    pub(crate) smtp_port_input: String,
+    // Session state of each pair
+    pub(crate) pair_states: HashMap<Pair, PairState>,
+    // The watchlist index of the row whose action menu is open
+    pub(crate) pair_menu: Option<usize>,
+    // The labels of the change window selector, in `ChangeWindow::labels()` order
+    pub(crate) change_window_labels: Vec<String>,
+    // The start time being entered for a `ChangeWindow::SinceTime` in the open row menu
+    pub(crate) change_window_time_input: String,
+    // The markup being entered for the pair of the open row menu, empty for the global one
+    pub(crate) pair_markup_input: String,
+    // The text of the global markup input
+    pub(crate) markup_input: String,
+    // Fetches completed this session, successful or not
+    pub(crate) fetch_count: u64,
+    // Whether the API could be reached by the last fetch
+    pub(crate) network_status: NetworkStatus,
+    // What was fetched from each provider and why, keyed by provider name
+    pub(crate) provider_stats: HashMap<String, ProviderStats>,
+    // The labels of the sort selector, in `SortOrder::ALL` order
+    pub(crate) sort_labels: Vec<String>,
+    // The labels of the popup anchor selector, in `PopupAnchor::ALL` order
+    pub(crate) anchor_labels: Vec<String>,
+    // The labels of the quick menu click selector, in `QuickMenuClick::ALL` order
+    pub(crate) quick_menu_click_labels: Vec<String>,
+    // The row of the quick menu the arrow keys moved to, among `quick_menu_pairs`
+    pub(crate) quick_menu_selected: usize,
+    // The labels of the change format selector, in `ChangeFormat::ALL` order
+    pub(crate) change_format_labels: Vec<String>,
+    // The labels of the number display selector, in `DecimalGroupStyle::ALL` order
+    pub(crate) decimal_group_labels: Vec<String>,
+    // The labels of the rate change animation selector, in `AnimationStyle::ALL` order
+    pub(crate) animation_labels: Vec<String>,
+    // When the rate change animations last moved, `None` while none plays
+    pub(crate) animation_ticked_at: Option<Instant>,
+    // The labels of the history export format selector, in `ExportFormat::ALL` order
+    pub(crate) export_format_labels: Vec<String>,
+    // The outcome of the last history export, shown below its button
+    pub(crate) export_status: Option<String>,
+    // The text of the precision input, empty for the provider's own precision
+    pub(crate) precision_input: String,
+    // The watchlist codes offered by pair selectors, in watchlist order
+    pub(crate) pair_labels: Vec<String>,
+    // The labels of the alert condition selector, in `AlertCondition::labels()` order
+    pub(crate) condition_labels: Vec<String>,
+    // The alert rule being edited
+    pub(crate) alert_pair_index: Option<usize>,
+    pub(crate) alert_condition_index: Option<usize>,
+    pub(crate) alert_value_input: String,
+    pub(crate) alert_amount_input: String,
+    // What is wrong with the last saved alert rule, if anything
+    pub(crate) alert_warnings: Vec<RuleConflict>,
+    // The amounts shown in the calculator popup. Only the side typed in last holds the
+    // user's raw text, the other one is derived from it.
+    pub(crate) calculator_base_input: String,
+    pub(crate) calculator_quote_input: String,
+    pub(crate) calculator_last_edited: CalculatorSide,
+    // The pair the calculator was opened on from an alert notification, `None` to follow
+    // the panel
+    pub(crate) calculator_pair: Option<Pair>,
+}
+
+impl YourApp {
+    /// The number of enabled, non-frozen pairs in the watchlist.
+    pub(crate) fn active_pairs_count(&self) -> usize {
+        self.config
+            .pairs
+            .iter()
+            .filter(|entry| entry.is_active())
+            .count()
+    }
+
+    /// The display mode the panel should use right now, resolving `DisplayMode::Auto`.
+    pub(crate) fn effective_display_mode(&self) -> DisplayMode {
+        resolve_display_mode(
+            self.config.display_mode,
+            &self.core.applet.panel_type,
+            self.active_pairs_count(),
+            self.config.cycle_interval_secs > 0,
+        )
+    }
+
+    /// Shows `view` in the popup, selecting its navigation item.
+    pub(crate) fn select_view(&mut self, view: AppView) {
+        let entity = self
+            .nav_model
+            .iter()
+            .find(|&entity| self.nav_model.data::<AppView>(entity) == Some(&view));
+        if let Some(entity) = entity {
+            self.nav_model.activate(entity);
+        }
+        self.active_view = view;
+    }
+
+    /// Whether the applet sits in the dock rather than a panel.
+    pub(crate) fn in_dock(&self) -> bool {
+        self.core.applet.panel_type == PanelType::Dock
+    }
+
+    /// The indices of the active pairs in the order the panel rotates through them, the
+    /// `first_panel_pair` first if it is active.
+    fn panel_rotation(&self) -> Vec<usize> {
+        let mut order = if self.config.sort_panel_rotation {
+            self.sorted_indices()
+        } else {
+            (0..self.config.pairs.len()).collect()
+        };
+        order.retain(|&index| self.config.pairs[index].is_active());
+        if let Some(position) = self
+            .config
+            .first_panel_pair
+            .and_then(|pinned| order.iter().position(|&index| index == pinned))
+        {
+            let pinned = order.remove(position);
+            order.insert(0, pinned);
+        }
+        order
+    }
+
+    /// The active pairs of `panel_slots`, in slot order, each once.
+    pub(crate) fn panel_slot_pairs(&self) -> Vec<&Pair> {
+        let mut slots: Vec<&Pair> = Vec::new();
+        for pair in &self.config.panel_slots {
+            let active = self
+                .config
+                .pairs
+                .iter()
+                .any(|entry| &entry.pair == pair && entry.is_active());
+            if active && !slots.contains(&pair) {
+                slots.push(pair);
+            }
+        }
+        slots.truncate(MAX_PANEL_SLOTS);
+        slots
+    }
+
+    /// The pair currently shown in the panel when in `DisplayMode::Rate`.
+    pub(crate) fn panel_pair(&self) -> Option<&Pair> {
+        let rotation = self.panel_rotation();
+        if rotation.is_empty() {
+            return None;
+        }
+        Some(&self.config.pairs[rotation[self.panel_index % rotation.len()]].pair)
+    }
+
+    /// How long the panel shows its current pair before rotating: `first_panel_dwell_secs`
+    /// on the pinned pair, `cycle_interval_secs` on the others.
+    pub(crate) fn panel_dwell(&self) -> Duration {
+        let rotation = self.panel_rotation();
+        let on_pinned = !rotation.is_empty()
+            && self.config.first_panel_pair == Some(rotation[self.panel_index % rotation.len()]);
+        let secs = match self.config.first_panel_dwell_secs {
+            dwell if on_pinned && dwell > 0 => dwell,
+            _ => self.config.cycle_interval_secs,
+        };
+        Duration::from_secs(secs)
+    }
+
+    /// Indices into the watchlist in `popup_sort` order. The sort is stable and pairs
+    /// without a quote yet always come last.
+    pub(crate) fn sorted_indices(&self) -> Vec<usize> {
+        let pairs = &self.config.pairs;
+        let mut indices: Vec<usize> = (0..pairs.len()).collect();
+        let quote = |index: usize| self.exchange_rates.get(&pairs[index].pair);
+        match self.config.popup_sort {
+            SortOrder::Manual => {}
+            SortOrder::Alphabetical => indices.sort_by_key(|&index| pairs[index].pair.to_string()),
+            SortOrder::ChangeDescending => {
+                let change = |index: usize| self.change_for(&pairs[index].pair);
+                indices.sort_by(|&a, &b| {
+                    missing_last(change(a), change(b), |a, b| {
+                        b.percent.abs().total_cmp(&a.percent.abs())
+                    })
+                })
+            }
+            SortOrder::RateAscending => indices
+                .sort_by(|&a, &b| missing_last(quote(a), quote(b), |a, b| a.bid.total_cmp(&b.bid))),
+        }
+        indices
+    }
+
+    /// The quotes of enabled pairs that disagree with their inverse or a cross, from the
+    /// quotes already fetched.
+    pub(crate) fn quote_inconsistencies(&self) -> Vec<Inconsistency> {
+        let quotes: Vec<(&Pair, f64)> = self
+            .config
+            .pairs
+            .iter()
+            .filter(|entry| entry.enabled)
+            .filter_map(|entry| Some((&entry.pair, self.exchange_rates.get(&entry.pair)?.bid)))
+            .collect();
+        inconsistencies(&quotes)
+    }
+
+    /// The history of every active pair as exported, oldest first within each pair.
+    pub(crate) fn history_entries(&self) -> Vec<HistoryEntry> {
+        self.config
+            .pairs
+            .iter()
+            .filter(|entry| entry.is_active())
+            .filter_map(|entry| Some((&entry.pair, self.pair_states.get(&entry.pair)?)))
+            .flat_map(|(pair, state)| {
+                state.history.iter().rev().map(|&(at, bid)| HistoryEntry {
+                    pair: pair.clone(),
+                    at,
+                    bid,
+                })
+            })
+            .collect()
+    }
+
+    /// The window the change of `pair` is measured over.
+    pub(crate) fn change_window(&self, pair: &Pair) -> ChangeWindow {
+        self.config
+            .pairs
+            .iter()
+            .find(|entry| &entry.pair == pair)
+            .map(|entry| entry.change_window)
+            .unwrap_or_default()
+    }
+
+    /// The change of the current quote of `pair` over its window. Everything showing,
+    /// sorting by or alerting on a change reads it from here, or from `incoming_change`
+    /// for a quote not stored yet, so they all agree.
+    pub(crate) fn change_for(&self, pair: &Pair) -> Option<Change> {
+        let quote = self.exchange_rates.get(pair)?;
+        // The newest entry of the history is the current quote itself.
+        let earlier = self
+            .pair_states
+            .get(pair)
+            .into_iter()
+            .flat_map(|state| state.history.iter().skip(1));
+        window_change(
+            self.change_window(pair),
+            quote,
+            earlier,
+            self.clock.now_local(),
+        )
+    }
+
+    /// The `change_heat` of every pair of the watchlist, by index, from the same change
+    /// `SortOrder::ChangeDescending` sorts by. Disabled and quiet pairs have none and don't
+    /// count towards the largest change.
+    pub(crate) fn watchlist_heat(&self) -> Vec<Option<f64>> {
+        let percents: Vec<Option<f64>> = self
+            .config
+            .pairs
+            .iter()
+            .map(|entry| {
+                if !entry.enabled || entry.quiet {
+                    return None;
+                }
+                self.change_for(&entry.pair).map(|change| change.percent)
+            })
+            .collect();
+        change_heat(&percents)
+    }
+
+    /// The bid the change of the current quote of `pair` is measured from, as `change_for`
+    /// measures it.
+    pub(crate) fn change_baseline(&self, pair: &Pair) -> Option<f64> {
+        let quote = self.exchange_rates.get(pair)?;
+        let earlier = self
+            .pair_states
+            .get(pair)
+            .into_iter()
+            .flat_map(|state| state.history.iter().skip(1));
+        window_baseline(
+            self.change_window(pair),
+            quote,
+            earlier,
+            self.clock.now_local(),
+        )
+    }
+
+    /// The change `quote` will have over the window of `pair` once stored as its current
+    /// quote.
+    pub(crate) fn incoming_change(&self, pair: &Pair, quote: &PairResponse) -> Option<Change> {
+        let earlier = self
+            .pair_states
+            .get(pair)
+            .into_iter()
+            .flat_map(|state| state.history.iter());
+        window_change(
+            self.change_window(pair),
+            quote,
+            earlier,
+            self.clock.now_local(),
+        )
+    }
+
+    /// The index of `pair` in the watchlist, if it is there.
+    pub(crate) fn pair_index(&self, pair: &Pair) -> Option<usize> {
+        self.config
+            .pairs
+            .iter()
+            .position(|entry| &entry.pair == pair)
+    }
+
+    /// Persists the config.
+    pub(crate) fn save_config(&mut self) {
+        // Errors found at startup go away once fixed, new ones aren't raised while editing.
+        let remaining = validate_config(&self.config);
+        self.config_errors.retain(|error| remaining.contains(error));
+        // Settings change while the popup is open, derived text follows them right away.
+        self.recompute_calculator();
+        self.pair_labels = self
+            .config
+            .pairs
+            .iter()
+            .map(|e| e.pair.to_string())
+            .collect();
+        let watchlist = watchlist_of(&self.config);
+        if watchlist != self.announced_watchlist {
+            self.announced_watchlist = watchlist;
+            watchlist_changed();
+        }
+        // Saves follow a change by the user, a good time to see if writes work again.
+        self.persistence.reprobe();
+        if let Some(handler) = &self.config_handler {
+            let config = self.session_overrides.persisted(&self.config);
+            let handler = handler.clone();
+            self.persistence
+                .write("config", move || config.write_entry(&handler));
+        }
+    }
+
+    /// The expression `pair` is derived by, `None` unless it is a derived pair.
+    pub(crate) fn expression_of(&self, pair: &Pair) -> Option<&str> {
+        self.config
+            .pairs
+            .iter()
+            .find(|e| &e.pair == pair)
+            .and_then(|e| e.expression.as_deref())
+    }
+
+    /// The derived pairs whose expression refers to one of `pairs`, see `derive_dependents`.
+    pub(crate) fn dependents_of(&self, pairs: &[Pair]) -> Vec<Pair> {
+        self.config
+            .pairs
+            .iter()
+            .filter(|e| e.is_active())
+            .filter(|e| {
+                let Some(Ok(expr)) = e.expression.as_deref().map(expression::parse) else {
+                    return false;
+                };
+                let codes = expr.pairs();
+                pairs
+                    .iter()
+                    .any(|pair| codes.contains(&pair.code().as_str()))
+            })
+            .map(|e| e.pair.clone())
+            .collect()
+    }
+
+    /// The quote of a derived pair, evaluating `expression` over the current quotes of the
+    /// pairs it refers to, which may only be fetched pairs of the watchlist so derived pairs
+    /// never refer to each other in circles.
+    ///
+    /// The previous close is the expression evaluated over the closes of those pairs, for
+    /// the change, and the quote is as old as the oldest of theirs.
+    pub(crate) fn derived_quote(&self, expression: &str) -> Result<PairResponse, ExprError> {
+        let expr = expression::parse(expression)?;
+        let quote_of = |code: &str| -> Result<&PairResponse, ExprError> {
+            let entry = self
+                .config
+                .pairs
+                .iter()
+                .find(|e| !e.is_derived() && e.pair.code() == code)
+                .ok_or_else(|| ExprError::UnknownPair(code.to_string()))?;
+            self.exchange_rates
+                .get(&entry.pair)
+                .ok_or_else(|| ExprError::NoQuote(code.to_string()))
+        };
+        let bid = expr.evaluate(&|code| Ok(quote_of(code)?.bid))?;
+        let close = expr.evaluate(&|code| {
+            let quote = quote_of(code)?;
+            Ok(quote.bid - quote.var_bid)
+        });
+        let timestamp = expr
+            .pairs()
+            .into_iter()
+            .filter_map(|code| quote_of(code).ok())
+            .map(|quote| quote.timestamp)
+            .min()
+            .unwrap_or(0);
+        let mut quote = PairResponse {
+            bid,
+            timestamp,
+            ..PairResponse::default()
+        };
+        if let Ok(close) = close {
+            if close.is_normal() {
+                quote.var_bid = bid - close;
+                quote.pct_change = quote.var_bid / close * 100.0;
+            }
+        }
+        Ok(quote)
+    }
+
+    /// The webhook configured for `pair`, if any.
+    pub(crate) fn webhook_url(&self, pair: &Pair) -> Option<&str> {
+        self.config
+            .pairs
+            .iter()
+            .find(|e| &e.pair == pair)
+            .and_then(|e| e.webhook_url.as_deref())
+    }
+
+    /// Fetches `pair`, recording why in the provider stats. A derived pair is evaluated
+    /// again instead, with a `Message::DerivePair`.
+    pub(crate) fn fetch(&mut self, pair: Pair, trigger: FetchTrigger) -> Command<Message> {
+        if self.expression_of(&pair).is_some() {
+            let message = Message::DerivePair(pair);
+            return Command::perform(std::future::ready(message), cosmic::app::Message::App);
+        }
+        // A manual refresh is the user asking for a new quote, never answer it from the cache.
+        if trigger != FetchTrigger::Manual {
+            if let Some(response) = self.rate_cache.fresh(&pair, self.clock.now_instant()) {
+                tracing::debug!("answering {pair} from the cache ({trigger:?})");
+                // Replayed with the time of the fetch it came from, so it isn't cached anew.
+                let fetched_at = self
+                    .fetched_at
+                    .get(&pair)
+                    .copied()
+                    .unwrap_or_else(Instant::now);
+                let message = Message::RateFetchedAt(pair, fetched_at, Ok(response.clone()));
+                return Command::perform(std::future::ready(message), cosmic::app::Message::App);
+            }
+        }
+        let provider = providers::provider(self.config.provider_of(&pair));
+        if self.under_maintenance(provider.name(), self.clock.now_local()) {
+            tracing::debug!("not fetching {pair} during the maintenance ({trigger:?})");
+            return Command::none();
+        }
+        tracing::debug!(
+            "fetching {pair} from {} ({trigger:?})",
+            provider.describe_request(std::slice::from_ref(&pair))
+        );
+        self.provider_stats
+            .entry(provider.name().to_string())
+            .or_default()
+            .record(FetchRecord {
+                pair: pair.clone(),
+                trigger,
+                at: self.clock.now_local(),
+            });
+        let mut commands = self.fetch_fallbacks(std::slice::from_ref(&pair), trigger);
+        commands.push(fetch_command(
+            provider,
+            pair,
+            Message::RateFetchedAt,
+            self.usage.clone(),
+            self.clock.clone(),
+        ));
+        Command::batch(commands)
+    }
+
+    /// Whether the maintenance window `provider` announced is still on at `now`.
+    pub(crate) fn under_maintenance(&self, provider: &str, now: DateTime<Local>) -> bool {
+        self.maintenance
+            .get(provider)
+            .is_some_and(|until| *until > now)
+    }
+
+    /// The rate shown for `pair`, see `select_rate`.
+    pub(crate) fn shown_rate(&self, pair: &Pair) -> Option<RateSource> {
+        select_rate(
+            self.exchange_rates.get(pair),
+            self.rate_cache
+                .entries
+                .get(pair)
+                .map(|entry| &entry.response),
+            bundled_rate(pair),
+        )
+    }
+
+    /// Where the rate shown of each pair of the watchlist that has one comes from, for the
+    /// `RateSources` D-Bus call: `fetched` with the quote's timestamp, or `bundled` with the
+    /// date of the table.
+    pub(crate) fn rate_sources(&self) -> HashMap<String, (String, String)> {
+        self.config
+            .pairs
+            .iter()
+            .filter_map(|entry| {
+                let (source, as_of) = match self.shown_rate(&entry.pair)? {
+                    RateSource::Fetched(quote) => ("fetched", quote.timestamp.to_string()),
+                    RateSource::Bundled(rate) => ("bundled", rate.as_of.to_string()),
+                };
+                Some((
+                    String::from(entry.pair.clone()),
+                    (source.to_string(), as_of),
+                ))
+            })
+            .collect()
+    }
+
+    /// Whether `pair` only has its bundled approximation to show.
+    pub(crate) fn shows_bundled(&self, pair: &Pair) -> bool {
+        matches!(self.shown_rate(pair), Some(RateSource::Bundled(_)))
+    }
+
+    /// The providers the active pairs are fetched from, each once and by name, or just
+    /// `config.provider` while there are none.
+    pub(crate) fn providers_in_use(&self) -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = Vec::new();
+        for entry in self
+            .config
+            .pairs
+            .iter()
+            .filter(|e| e.is_active() && !e.is_derived())
+        {
+            let name = providers::provider(self.config.provider_of(&entry.pair)).name();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        if names.is_empty() {
+            names.push(providers::provider(&self.config.provider).name());
+        }
+        names
+    }
+
+    /// Why no pair is being refreshed right now, if any reason applies to them all.
+    pub(crate) fn global_pause(&self) -> Option<PauseReason> {
+        let now = self.clock.now_local();
+        let all_under_maintenance = self
+            .providers_in_use()
+            .into_iter()
+            .all(|provider| self.under_maintenance(provider, now));
+        pause_reason(
+            [
+                self.session_locked.then_some(PauseReason::SessionLocked),
+                all_under_maintenance.then_some(PauseReason::Maintenance),
+                (self.network_status == NetworkStatus::Offline).then_some(PauseReason::Offline),
+            ]
+            .into_iter()
+            .flatten(),
+        )
+    }
+
+    /// Why `entry` isn't being refreshed right now, `None` while it is.
+    pub(crate) fn pair_pause(&self, entry: &PairEntry) -> Option<PauseReason> {
+        let provider = providers::provider(self.config.provider_of(&entry.pair));
+        pause_reason(
+            entry
+                .frozen
+                .then_some(PauseReason::Frozen)
+                .into_iter()
+                .chain(
+                    self.under_maintenance(provider.name(), self.clock.now_local())
+                        .then_some(PauseReason::Maintenance),
+                )
+                .chain(self.global_pause())
+                .chain(
+                    self.retries_exhausted(&entry.pair)
+                        .then_some(PauseReason::CircuitBreaker),
+                )
+                .chain(
+                    market_closed(&entry.pair, self.clock.now_utc())
+                        .then_some(PauseReason::MarketClosed),
+                )
+                .chain(
+                    self.paused_for_low_power(&entry.pair)
+                        .then_some(PauseReason::LowPower),
+                ),
+        )
+    }
+
+    /// Whether `pair` failed on every retry it was given, so that it waits for the next
+    /// refresh.
+    pub(crate) fn retries_exhausted(&self, pair: &Pair) -> bool {
+        let failing = self
+            .pair_states
+            .get(pair)
+            .is_some_and(|state| state.failing_since.is_some());
+        failing
+            && self.retry_attempts.get(pair).copied().unwrap_or(0) >= self.config.api_retry_count
+    }
+
+    /// Whether low power mode is on, see `low_power_active`.
+    pub(crate) fn low_power(&self) -> bool {
+        low_power_active(
+            self.config.low_power,
+            self.config.low_power_below_pct,
+            self.battery,
+        )
+    }
+
+    /// Whether low power mode holds back the fetches of `pair`, one the panel doesn't show.
+    pub(crate) fn paused_for_low_power(&self, pair: &Pair) -> bool {
+        self.low_power()
+            && self.panel_pair() != Some(pair)
+            && !self.panel_slot_pairs().contains(&pair)
+    }
+
+    /// The time between two fetches of the same pair, `refresh_secs` lengthened in low
+    /// power mode.
+    pub(crate) fn refresh_interval(&self) -> Duration {
+        let refresh = Duration::from_secs(self.config.refresh_secs.max(1));
+        if self.low_power() {
+            refresh * LOW_POWER_INTERVAL_FACTOR
+        } else {
+            refresh
+        }
+    }
+
+    /// How changed rates animate, `rate_change_animation` unless low power mode is on.
+    pub(crate) fn animation_style(&self) -> AnimationStyle {
+        if self.low_power() {
+            AnimationStyle::None
+        } else {
+            self.config.rate_change_animation
+        }
+    }
+
+    /// Whether the fallback provider serves `pair` right now instead of the provider the
+    /// pair is fetched from, see `serving_provider`.
+    pub(crate) fn uses_fallback(&self, pair: &Pair, now: Instant) -> bool {
+        let failing_since = self
+            .pair_states
+            .get(pair)
+            .and_then(|state| state.failing_since);
+        let primary = self.config.provider_of(pair);
+        let provider = serving_provider(
+            primary,
+            &self.config.fallback_provider,
+            failing_since,
+            now,
+            Duration::from_secs(self.config.fallback_after_secs),
+        );
+        provider != primary
+    }
+
+    /// Also fetches those of `pairs` the fallback provider serves from it, answering with
+    /// `Message::FallbackFetchedAt`. They are fetched one by one, as there are usually few.
+    fn fetch_fallbacks(&mut self, pairs: &[Pair], trigger: FetchTrigger) -> Vec<Command<Message>> {
+        let now = self.clock.now_instant();
+        let fallbacks: Vec<Pair> = pairs
+            .iter()
+            .filter(|pair| self.uses_fallback(pair, now))
+            .cloned()
+            .collect();
+        let provider = providers::provider(&self.config.fallback_provider);
+        let stats = self
+            .provider_stats
+            .entry(provider.name().to_string())
+            .or_default();
+        fallbacks
+            .into_iter()
+            .map(|pair| {
+                tracing::debug!(
+                    "also fetching {pair} from {} ({trigger:?})",
+                    provider.name()
+                );
+                stats.record(FetchRecord {
+                    pair: pair.clone(),
+                    trigger,
+                    at: self.clock.now_local(),
+                });
+                fetch_command(
+                    provider,
+                    pair,
+                    Message::FallbackFetchedAt,
+                    self.usage.clone(),
+                    self.clock.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// The active pairs grouped by the provider they are fetched from, in the order the
+    /// providers first appear in the watchlist. Derived pairs aren't fetched, they are in
+    /// none.
+    pub(crate) fn pairs_by_provider(&self) -> Vec<(&'static dyn Provider, Vec<Pair>)> {
+        let mut groups: Vec<(&'static dyn Provider, Vec<Pair>)> = Vec::new();
+        for entry in self
+            .config
+            .pairs
+            .iter()
+            .filter(|e| e.is_active() && !e.is_derived())
+        {
+            let provider = providers::provider(self.config.provider_of(&entry.pair));
+            match groups
+                .iter_mut()
+                .find(|(other, _)| other.name() == provider.name())
+            {
+                Some((_, pairs)) => pairs.push(entry.pair.clone()),
+                None => groups.push((provider, vec![entry.pair.clone()])),
+            }
+        }
+        groups
+    }
+
+    /// Fetches every active pair, a request per provider they are fetched from, each
+    /// answering with a `Message::RatesFetched`. Like `fetch`, pairs with a fresh quote in
+    /// the cache are answered from it, unless the refresh is `Manual`.
+    pub(crate) fn fetch_active(&mut self, trigger: FetchTrigger) -> Command<Message> {
+        let now = self.clock.now_instant();
+        let mut cached = Vec::new();
+        let mut batches = Vec::new();
+        for (provider, group) in self.pairs_by_provider() {
+            let mut pairs = Vec::new();
+            for pair in group {
+                match self.rate_cache.fresh(&pair, now) {
+                    Some(response) if trigger != FetchTrigger::Manual => {
+                        let fetched_at = self.fetched_at.get(&pair).copied().unwrap_or(now);
+                        cached.push((pair, fetched_at, Ok(response.clone())));
+                    }
+                    _ => pairs.push(pair),
+                }
+            }
+            // A provider under maintenance doesn't hold back the pairs of the others.
+            if self.under_maintenance(provider.name(), self.clock.now_local()) {
+                tracing::debug!(
+                    "not fetching {} pairs from {} during the maintenance ({trigger:?})",
+                    pairs.len(),
+                    provider.name()
+                );
+            } else if !pairs.is_empty() {
+                batches.push((provider, pairs));
+            }
+        }
+        if cached.is_empty() && batches.is_empty() {
+            return Command::none();
+        }
+        let mut commands = Vec::new();
+        for (provider, pairs) in batches {
+            tracing::debug!(
+                "fetching {} pairs from {} ({trigger:?})",
+                pairs.len(),
+                provider.describe_request(&pairs)
+            );
+            let stats = self
+                .provider_stats
+                .entry(provider.name().to_string())
+                .or_default();
+            for pair in &pairs {
+                stats.record(FetchRecord {
+                    pair: pair.clone(),
+                    trigger,
+                    at: self.clock.now_local(),
+                });
+            }
+            commands.extend(self.fetch_fallbacks(&pairs, trigger));
+            // The cached outcomes go along with the first request, or alone without one.
+            commands.push(fetch_many_command(
+                provider,
+                pairs,
+                std::mem::take(&mut cached),
+                self.usage.clone(),
+                self.clock.clone(),
+            ));
+        }
+        if !cached.is_empty() {
+            tracing::debug!(
+                "answering {} pairs from the cache ({trigger:?})",
+                cached.len()
+            );
+            commands.push(fetch_many_command(
+                providers::provider(&self.config.provider),
+                Vec::new(),
+                cached,
+                self.usage.clone(),
+                self.clock.clone(),
+            ));
+        }
+        Command::batch(commands)
+    }
+
+    /// Counts what the applet holds in memory, for the status page. Requests are counted
+    /// from the live counters in `usage`, so those made since the state was last persisted
+    /// are included.
+    pub(crate) fn held_counts(&self) -> HeldCounts {
+        let today = self.clock.now_local().date_naive();
+        HeldCounts {
+            history_points: self
+                .pair_states
+                .values()
+                .map(|state| state.history.len())
+                .sum(),
+            cached_quotes: self.rate_cache.entries.len(),
+            raw_responses: self
+                .pair_states
+                .values()
+                .filter(|state| state.raw_response.is_some())
+                .count(),
+            requests_today: self
+                .usage
+                .lock()
+                .unwrap()
+                .providers
+                .values()
+                .map(|usage| usage.as_of(today).today)
+                .sum(),
+        }
+    }
+
+    /// Copies the counters in `usage`, which every request updates, into the persisted state.
+    pub(crate) fn save_api_usage(&mut self) {
+        let usage = self.usage.lock().unwrap().clone();
+        if usage == self.state.api_usage {
+            return;
+        }
+        self.state.api_usage = usage;
+        self.save_state();
+    }
+
+    /// Snapshots what is known of the current quote of `pair` into the persisted flagged
+    /// quotes, dropping the oldest beyond `MAX_FLAGGED_QUOTES`.
+    pub(crate) fn flag_quote(&mut self, pair: Pair) {
+        let now = self.clock.now_instant();
+        let flagged = FlaggedQuote {
+            flagged_at: self.clock.now_local(),
+            provider: self.config.provider_of(&pair).to_string(),
+            quoted_by: self
+                .pair_states
+                .get(&pair)
+                .and_then(|state| state.quoted_by)
+                .map(str::to_string),
+            quote: self.exchange_rates.get(&pair).cloned(),
+            fetched_secs_ago: self
+                .fetched_at
+                .get(&pair)
+                .map(|at| now.saturating_duration_since(*at).as_secs()),
+            response_excerpt: self
+                .pair_states
+                .get(&pair)
+                .and_then(|state| state.raw_response.clone()),
+            settings: redacted_settings(&self.config).to_string(),
+            pair,
+        };
+        let flagged_quotes = &mut self.state.flagged_quotes;
+        flagged_quotes.push(flagged);
+        let excess = flagged_quotes.len().saturating_sub(MAX_FLAGGED_QUOTES);
+        flagged_quotes.drain(..excess);
+        self.save_state();
+    }
+
+    /// Persists the state.
+    pub(crate) fn save_state(&mut self) {
+        if let Some(handler) = &self.state_handler {
+            let (state, handler) = (self.state.clone(), handler.clone());
+            self.persistence
+                .write("state", move || state.write_entry(&handler));
+        }
+    }
+
+    /// The decimals `pair` is displayed with, `None` to display it as the provider sent it.
+    pub(crate) fn display_precision(&self, pair: &Pair) -> Option<usize> {
+        self.config
+            .market_precision
+            .then(|| pair.market_convention())
+            .flatten()
+            .map(|convention| convention.decimals)
+            .or(self.config.precision)
+    }
+
+    /// Stores a quote of `pair` received at `at`, noting whether its value changed.
+    pub(crate) fn store_quote(&mut self, pair: Pair, quote: PairResponse, at: Instant) {
+        let precision = self
+            .config
+            .change_at_display_precision
+            .then(|| self.display_precision(&pair))
+            .flatten();
+        let changed = quote_changed(self.exchange_rates.get(&pair), &quote, precision);
+        let previous_bid = self.exchange_rates.get(&pair).map(|previous| previous.bid);
+        // Quiet pairs don't play the animation, however much they moved.
+        let quiet = self
+            .config
+            .pairs
+            .iter()
+            .any(|entry| entry.pair == pair && entry.quiet);
+        let animated = !quiet && self.animation_style() != AnimationStyle::None;
+        let state = self.pair_states.entry(pair.clone()).or_default();
+        state.record_quote(quote.bid, self.clock.now_local());
+        if changed {
+            state.changed_at = Some(at);
+            if let Some(previous_bid) = previous_bid.filter(|_| animated) {
+                state.start_animation(quote.bid > previous_bid);
+            }
+        }
+        self.exchange_rates.insert(pair, quote);
+    }
+
+    /// How the age of the current quote of `pair` reads, see `classify_age`. Quotes are
+    /// stale once `STALE_AFTER_CADENCES` times older than the pair gets new quotes, or is
+    /// fetched if that is less often. `None` without a quote, or a time the provider sent.
+    pub(crate) fn quote_age(&self, pair: &Pair) -> Option<QuoteAge> {
+        let quote = self.exchange_rates.get(pair)?;
+        if quote.timestamp <= 0 {
+            return None;
+        }
+        let quoted_at = Utc.timestamp_opt(quote.timestamp, 0).single()?;
+        let interval = self.pair_cadence(pair).secs().max(self.config.refresh_secs);
+        // `chrono::Duration::seconds` takes up to `i64::MAX` milliseconds.
+        let max_age = interval
+            .saturating_mul(STALE_AFTER_CADENCES)
+            .min(i64::MAX as u64 / 1000);
+        let max_age = chrono::Duration::seconds(max_age as i64);
+        Some(classify_age(
+            quoted_at,
+            self.clock.now_utc(),
+            max_age,
+            pair.trades_all_week(),
+        ))
+    }
+
+    /// Whether the panel shows `pair` dimmed: its rate is the bundled approximation, or a
+    /// stale quote.
+    pub(crate) fn panel_dims(&self, pair: &Pair) -> bool {
+        self.shows_bundled(pair) || self.quote_age(pair) == Some(QuoteAge::Stale)
+    }
+
+    /// How often `pair` gets new quotes from the provider it is fetched from or the
+    /// fallback.
+    pub(crate) fn pair_cadence(&self, pair: &Pair) -> Cadence {
+        let mut sources = vec![providers::provider(self.config.provider_of(pair))];
+        if !self.config.fallback_provider.is_empty() {
+            sources.push(providers::provider(&self.config.fallback_provider));
+        }
+        pair_cadence(&sources, pair, metadata())
+    }
+
+    /// The pairs listed in the quick menu, as indices into `config.pairs`: the active
+    /// ones, in the popup's sort order.
+    pub(crate) fn quick_menu_pairs(&self) -> Vec<usize> {
+        self.sorted_indices()
+            .into_iter()
+            .filter(|&index| self.config.pairs[index].is_active())
+            .collect()
+    }
+
+    /// The pair the calculator converts with.
+    pub(crate) fn converted_pair(&self) -> Option<&Pair> {
+        self.calculator_pair.as_ref().or_else(|| self.panel_pair())
+    }
+
+    /// Recomputes the calculator side the user did not type in last from the other one,
+    /// with the display precision. The edited side is left untouched so its text and
+    /// cursor never jump while typing.
+    pub(crate) fn recompute_calculator(&mut self) {
+        let Some((quote, markup)) = self.converted_pair().and_then(|pair| {
+            let markup = if self.config.calculator_uses_markup {
+                self.config.markup_for(pair)
+            } else {
+                0.0
+            };
+            Some((self.exchange_rates.get(pair)?.clone(), markup))
+        }) else {
+            return;
+        };
+        let decimals = self.config.precision.unwrap_or(CALCULATOR_DECIMALS);
+        let (source, target) = match self.calculator_last_edited {
+            CalculatorSide::Base => (
+                &self.calculator_base_input,
+                &mut self.calculator_quote_input,
+            ),
+            CalculatorSide::Quote => (
+                &self.calculator_quote_input,
+                &mut self.calculator_base_input,
+            ),
+        };
+        let style = self.config.decimal_groups;
+        *target = match parse_decimal(source) {
+            Some(amount) => quote
+                .convert(amount, self.calculator_last_edited, markup)
+                .map(|converted| format_decimal(converted, decimals as u32, style))
+                // A zero rate, which converts nothing, or a result too large to hold.
+                .unwrap_or_else(|| fl!("not-available")),
+            None => String::new(),
+        };
+    }
+}
+
+/// Resolves `DisplayMode::Auto` for an applet placed in `panel_type` with `active_pairs`
+/// pairs, rotating through them if `cycling`. Other modes are kept as configured.
+pub fn resolve_display_mode(
+    configured: DisplayMode,
+    panel_type: &PanelType,
+    active_pairs: usize,
+    cycling: bool,
+) -> DisplayMode {
+    match configured {
+        DisplayMode::Auto if *panel_type == PanelType::Dock => DisplayMode::Icon,
+        DisplayMode::Auto if active_pairs > 1 && !cycling => DisplayMode::Summary,
+        DisplayMode::Auto => DisplayMode::Rate,
+        mode => mode,
+    }
+}
+
+/// Whether `next` is a change from the `previous` quote of a pair. Only the bid
+/// counts: a new provider timestamp with the same bid is no change. With a `precision`
+/// the bids are compared as displayed with that many decimals, so a move below the
+/// displayed precision is no change either.
+pub fn quote_changed(
+    previous: Option<&PairResponse>,
+    next: &PairResponse,
+    precision: Option<usize>,
+) -> bool {
+    let Some(previous) = previous else {
+        return true;
+    };
+    match precision {
+        Some(precision) => {
+            format!("{:.*}", precision, previous.bid) != format!("{:.*}", precision, next.bid)
+        }
+        None => previous.bid != next.bid,
+    }
+}
+
+/// Which way the bid moved between two quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateDirection {
+    Up,
+    Down,
+    Unchanged,
+}
+
+/// How a pair's quote changed between two fetches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeSummary {
+    pub bid_delta: f64,
+    /// `bid_delta` in percent of the old bid.
+    pub bid_pct: f64,
+    pub direction: RateDirection,
+    /// The rate bounds of the `Above` conditions the bid crossed, amounts included.
+    pub crossed_above: Vec<f64>,
+    /// The rate bounds of the `Below` conditions the bid crossed, amounts included.
+    pub crossed_below: Vec<f64>,
+}
+
+impl ChangeSummary {
+    /// Whether `condition`, one of those the summary was made with, started holding.
+    pub fn fired(&self, condition: &AlertCondition, old: Option<Observed>, new: Observed) -> bool {
+        match condition.rate_bound() {
+            AlertCondition::Above(bound) => self.crossed_above.contains(&bound),
+            AlertCondition::Below(bound) => self.crossed_below.contains(&bound),
+            // Not a threshold of the rate, such as the change over the window.
+            _ => starts_holding(condition, old, new),
+        }
+    }
+}
+
+/// Compares the `new` quote of a pair to its `old` one, noting which of the pair's alert
+/// `conditions` it crossed. Without an `old` quote nothing moved, but every condition that
+/// holds counts as crossed.
+pub fn pair_change_summary(
+    old: Option<Observed>,
+    new: Observed,
+    conditions: &[AlertCondition],
+) -> ChangeSummary {
+    let bid_delta = old.map_or(0.0, |old| new.quote.bid - old.quote.bid);
+    let bid_pct = match old {
+        Some(old) if old.quote.bid != 0.0 => bid_delta / old.quote.bid * 100.0,
+        _ => 0.0,
+    };
+    let direction = match bid_delta.partial_cmp(&0.0) {
+        Some(Ordering::Greater) => RateDirection::Up,
+        Some(Ordering::Less) => RateDirection::Down,
+        _ => RateDirection::Unchanged,
+    };
+    let mut summary = ChangeSummary {
+        bid_delta,
+        bid_pct,
+        direction,
+        crossed_above: Vec::new(),
+        crossed_below: Vec::new(),
+    };
+    for condition in conditions {
+        if !starts_holding(condition, old, new) {
+            continue;
+        }
+        match condition.rate_bound() {
+            AlertCondition::Above(bound) => summary.crossed_above.push(bound),
+            AlertCondition::Below(bound) => summary.crossed_below.push(bound),
+            _ => {}
+        }
+    }
+    summary
+}
+
+/// Whether `condition` holds for `new` but didn't for `old`, so alerts fire once when
+/// their condition becomes true rather than on every fetch while it stays true.
+fn starts_holding(condition: &AlertCondition, old: Option<Observed>, new: Observed) -> bool {
+    new.satisfies(condition) && !old.is_some_and(|old| old.satisfies(condition))
+}
+
+/// A move of a pair's bid over its `ChangeWindow`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Change {
+    pub delta: f64,
+    /// `delta` in percent of the bid it is measured from.
+    pub percent: f64,
+}
+
+/// A quote with its change over the pair's window, what alert conditions are checked on.
+#[derive(Debug, Clone, Copy)]
+pub struct Observed<'a> {
+    pub quote: &'a PairResponse,
+    pub change: Option<Change>,
+}
+
+impl Observed<'_> {
+    fn satisfies(&self, condition: &AlertCondition) -> bool {
+        condition.holds(self.quote, self.change.map(|change| change.percent))
+    }
+}
+
+/// The change of `quote` over `window`, `earlier` being the bids received before it with
+/// when they arrived, newest first. `None` while there is nothing to measure from, and
+/// when that is zero or too close to it for a percentage, see `degenerate_baseline`.
+pub fn window_change<'a>(
+    window: ChangeWindow,
+    quote: &PairResponse,
+    earlier: impl IntoIterator<Item = &'a (DateTime<Local>, f64)>,
+    now: DateTime<Local>,
+) -> Option<Change> {
+    let from = window_baseline(window, quote, earlier, now)?;
+    if degenerate_baseline(from) {
+        return None;
+    }
+    let change = match window {
+        // As the provider sent it, which may have more digits than the close it implies.
+        ChangeWindow::PreviousClose => Change {
+            delta: quote.var_bid,
+            percent: quote.pct_change,
+        },
+        _ => {
+            let delta = quote.bid - from;
+            Change {
+                delta,
+                percent: delta / from * 100.0,
+            }
+        }
+    };
+    (change.delta.is_finite() && change.percent.is_finite()).then_some(change)
+}
+
+/// Whether a change measured from the bid `from` has no meaningful percentage: for zero,
+/// which a provider sends during an incident, subnormal and non-finite bids.
+pub fn degenerate_baseline(from: f64) -> bool {
+    !from.is_normal()
+}
+
+/// The bid the change of `quote` over `window` is measured from, see `window_change`.
+pub fn window_baseline<'a>(
+    window: ChangeWindow,
+    quote: &PairResponse,
+    earlier: impl IntoIterator<Item = &'a (DateTime<Local>, f64)>,
+    now: DateTime<Local>,
+) -> Option<f64> {
+    let from = match window {
+        ChangeWindow::PreviousClose => quote.bid - quote.var_bid,
+        ChangeWindow::LastRefresh => earlier.into_iter().next()?.1,
+        ChangeWindow::Rolling24h => bid_at(earlier, now - chrono::Duration::hours(24))?,
+        ChangeWindow::SinceTime(time) => {
+            let today = now
+                .date_naive()
+                .and_time(time)
+                .and_local_timezone(Local)
+                .earliest()?;
+            let start = if today > now {
+                today - chrono::Duration::days(1)
+            } else {
+                today
+            };
+            bid_at(earlier, start)?
+        }
+    };
+    Some(from)
+}
+
+/// The bid that was current at `at`: the newest of `earlier` received by then, or the
+/// oldest one if none is that old.
+fn bid_at<'a>(
+    earlier: impl IntoIterator<Item = &'a (DateTime<Local>, f64)>,
+    at: DateTime<Local>,
+) -> Option<f64> {
+    let mut oldest = None;
+    for &(received, bid) in earlier {
+        if received <= at {
+            return Some(bid);
+        }
+        oldest = Some(bid);
+    }
+    oldest
+}
+
+/// The change heat of the rows of the watchlist from their `percents` change: the change as
+/// a share of the largest one in size, from -1 to 1. `None` for rows without a change, and
+/// for every row when nothing moved.
+pub fn change_heat(percents: &[Option<f64>]) -> Vec<Option<f64>> {
+    let largest = percents
+        .iter()
+        .flatten()
+        .map(|percent| percent.abs())
+        .filter(|percent| percent.is_finite())
+        .fold(0.0, f64::max);
+    percents
+        .iter()
+        .map(|&percent| {
+            let percent = percent.filter(|percent| percent.is_finite())?;
+            (largest > 0.0).then(|| percent / largest)
+        })
+        .collect()
+}
+
+/// Compares two optional values with `cmp`, ordering missing values after present ones.
+fn missing_last<T>(a: Option<T>, b: Option<T>, cmp: impl Fn(T, T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => cmp(a, b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Discrepancy from which a quote is flagged as inconsistent with the rate derived from
+/// other quotes, in percent.
+pub const CONSISTENCY_TOLERANCE_PCT: f64 = 0.5;
+
+/// A quote that disagrees with the rate derived from other quotes of the watchlist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inconsistency {
+    pub pair: Pair,
+    pub quoted: f64,
+    pub derived: f64,
+    /// The pairs `derived` was computed from: the inverse pair, or the two legs of a cross.
+    pub from: Vec<Pair>,
+    pub discrepancy_pct: f64,
+}
+
+/// How far `derived` is from `quoted`, in percent of `quoted`, if that is more than
+/// `tolerance_pct`.
+pub fn exceeds_tolerance(quoted: f64, derived: f64, tolerance_pct: f64) -> Option<f64> {
+    if quoted <= 0.0 || !derived.is_finite() {
+        return None;
+    }
+    let discrepancy = (derived - quoted).abs() / quoted * 100.0;
+    (discrepancy > tolerance_pct).then_some(discrepancy)
+}
+
+/// Checks each of `quotes` against its inverse pair and the crosses through a third
+/// currency, using only the other `quotes`. A set of pairs that disagree is reported once,
+/// for the first of them. The derived rates are computed exactly, see `crate::exact`.
+pub fn inconsistencies(quotes: &[(&Pair, f64)]) -> Vec<Inconsistency> {
+    let bids: HashMap<&Pair, Decimal> = quotes
+        .iter()
+        .filter(|&&(_, bid)| bid > 0.0)
+        .filter_map(|&(pair, bid)| Some((pair, decimal(bid)?)))
+        .collect();
+    // The rate from one currency to another as quoted, or by inverting the opposite pair.
+    let rate = |from: &str, to: &str| -> Option<(Decimal, Pair)> {
+        let direct = Pair::new(from, to)?;
+        if let Some(&bid) = bids.get(&direct) {
+            return Some((bid, direct));
+        }
+        let inverse = Pair::new(to, from)?;
+        Some((exact::inverse(*bids.get(&inverse)?)?, inverse))
+    };
+    let mut currencies: Vec<&str> = quotes
+        .iter()
+        .flat_map(|(pair, _)| [pair.base(), pair.quote()])
+        .collect();
+    currencies.sort_unstable();
+    currencies.dedup();
+
+    let mut reported: Vec<Vec<String>> = Vec::new();
+    let mut found = Vec::new();
+    for &(pair, quoted) in quotes {
+        let inverse = rate(pair.quote(), pair.base())
+            .filter(|(_, from)| from != pair)
+            .and_then(|(bid, from)| Some((exact::inverse(bid)?, vec![from])));
+        let crosses = currencies
+            .iter()
+            .filter(|&&via| via != pair.base() && via != pair.quote())
+            .filter_map(|via| {
+                let (first, first_pair) = rate(pair.base(), via)?;
+                let (second, second_pair) = rate(via, pair.quote())?;
+                Some((first.checked_mul(second)?, vec![first_pair, second_pair]))
+            });
+        for (derived, from) in inverse.into_iter().chain(crosses) {
+            let derived = exact::to_f64(derived);
+            let Some(discrepancy_pct) =
+                exceeds_tolerance(quoted, derived, CONSISTENCY_TOLERANCE_PCT)
+            else {
+                continue;
+            };
+            let mut involved: Vec<String> = from.iter().map(Pair::code).collect();
+            involved.push(pair.code());
+            involved.sort_unstable();
+            if reported.contains(&involved) {
+                continue;
+            }
+            reported.push(involved);
+            found.push(Inconsistency {
+                pair: pair.clone(),
+                quoted,
+                derived,
+                from,
+                discrepancy_pct,
+            });
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::localization::LANGUAGE_LOADER;
+    use crate::providers::AWESOMEAPI;
+    use chrono::{NaiveTime, TimeZone};
+
+    const PRIMARY: &str = "Primary";
+    const FALLBACK: &str = "Fallback";
+    const AFTER: Duration = Duration::from_secs(600);
+
+    /// Who serves a pair failing for `failing_for`, or not failing for `None`.
+    fn served_by(fallback: &str, failing_for: Option<Duration>) -> &str {
+        let now = Instant::now() + Duration::from_secs(3600);
+        serving_provider(
+            PRIMARY,
+            fallback,
+            failing_for.map(|failing_for| now - failing_for),
+            now,
+            AFTER,
+        )
+    }
+
+    #[test]
+    fn a_healthy_pair_stays_on_the_primary() {
+        assert_eq!(served_by(FALLBACK, None), PRIMARY);
+        assert_eq!(served_by(FALLBACK, Some(Duration::ZERO)), PRIMARY);
+    }
+
+    #[test]
+    fn the_fallback_serves_from_the_threshold_on() {
+        assert_eq!(served_by(FALLBACK, Some(AFTER / 2)), PRIMARY);
+        assert_eq!(
+            served_by(FALLBACK, Some(AFTER - Duration::from_millis(1))),
+            PRIMARY
+        );
+        assert_eq!(served_by(FALLBACK, Some(AFTER)), FALLBACK);
+        assert_eq!(served_by(FALLBACK, Some(AFTER * 10)), FALLBACK);
+    }
+
+    #[test]
+    fn no_fallback_or_the_primary_itself_keeps_the_primary() {
+        assert_eq!(served_by("", Some(AFTER * 10)), PRIMARY);
+        assert_eq!(served_by(PRIMARY, Some(AFTER * 10)), PRIMARY);
+    }
+
+    #[test]
+    fn a_zero_threshold_falls_back_on_the_first_failure() {
+        let now = Instant::now();
+        assert_eq!(
+            serving_provider(PRIMARY, FALLBACK, Some(now), now, Duration::ZERO),
+            FALLBACK
+        );
+        assert_eq!(
+            serving_provider(PRIMARY, FALLBACK, None, now, Duration::ZERO),
+            PRIMARY
+        );
+    }
+
+    #[test]
+    fn failing_since_after_now_is_not_failing_long() {
+        // A failure recorded by a clock ahead of the one asking, e.g. a fake one in tests.
+        let now = Instant::now();
+        let since = now + Duration::from_secs(60);
+        assert_eq!(
+            serving_provider(PRIMARY, FALLBACK, Some(since), now, AFTER),
+            PRIMARY
+        );
+    }
+
+    #[test]
+    fn recovery_goes_back_to_the_primary() {
+        // The primary answering again clears `failing_since`, whatever the failure lasted.
+        let start = Instant::now();
+        let mut failing_since = None;
+        let mut served = Vec::new();
+        for (minute, primary_answers) in [(0, false), (5, false), (10, false), (15, true)] {
+            let now = start + Duration::from_secs(minute * 60);
+            if primary_answers {
+                failing_since = None;
+            } else {
+                failing_since.get_or_insert(now);
+            }
+            served.push(serving_provider(
+                PRIMARY,
+                FALLBACK,
+                failing_since,
+                now,
+                AFTER,
+            ));
+        }
+        assert_eq!(served, [PRIMARY, PRIMARY, FALLBACK, PRIMARY]);
+    }
+
+    #[test]
+    fn each_pair_is_decided_on_its_own_failures() {
+        let now = Instant::now() + Duration::from_secs(3600);
+        let pairs = [
+            ("USDBRL", None),
+            ("EURBRL", Some(now - AFTER * 2)),
+            ("BTCUSD", Some(now - AFTER / 2)),
+        ];
+        let served: Vec<(&str, &str)> = pairs
+            .iter()
+            .map(|&(pair, since)| (pair, serving_provider(PRIMARY, FALLBACK, since, now, AFTER)))
+            .collect();
+        assert_eq!(
+            served,
+            [
+                ("USDBRL", PRIMARY),
+                ("EURBRL", FALLBACK),
+                ("BTCUSD", PRIMARY)
+            ]
+        );
+    }
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    /// The events of a run of fetches, each `(minutes since the start, ok)`, with a digest
+    /// after an hour.
+    fn episode(fetches: &[(u64, bool)]) -> (Vec<Option<EpisodeEvent>>, FailureEpisode) {
+        let start = Instant::now();
+        let mut episode = FailureEpisode::default();
+        let events = fetches
+            .iter()
+            .map(|&(minute, ok)| episode.step(ok, start + Duration::from_secs(minute * 60), HOUR))
+            .collect();
+        (events, episode)
+    }
+
+    #[test]
+    fn successes_alone_announce_nothing() {
+        let (events, episode) = episode(&[(0, true), (60, true), (120, true)]);
+        assert_eq!(events, [None, None, None]);
+        assert_eq!(episode, FailureEpisode::None);
+    }
+
+    #[test]
+    fn fail_notify_keep_failing_recover() {
+        let (events, episode) = episode(&[
+            (0, false),
+            (30, false),
+            (60, false),
+            (90, false),
+            (180, false),
+            (181, true),
+            (182, true),
+        ]);
+        assert_eq!(
+            events,
+            [
+                None,
+                None,
+                Some(EpisodeEvent::Digest(HOUR)),
+                None,
+                None,
+                Some(EpisodeEvent::Recovered),
+                None,
+            ]
+        );
+        assert_eq!(episode, FailureEpisode::None);
+    }
+
+    #[test]
+    fn a_short_outage_is_not_announced_or_recovered() {
+        let (events, episode) = episode(&[(0, false), (59, false), (60, true), (200, true)]);
+        assert_eq!(events, [None, None, None, None]);
+        assert_eq!(episode, FailureEpisode::None);
+    }
+
+    #[test]
+    fn the_digest_waits_for_the_fetch_after_the_threshold() {
+        // Fetches backing off: the digest comes with the first failure past the hour, and
+        // tells how long it has really been.
+        let (events, _) = episode(&[(0, false), (45, false), (95, false)]);
+        assert_eq!(
+            events,
+            [
+                None,
+                None,
+                Some(EpisodeEvent::Digest(Duration::from_secs(95 * 60)))
+            ]
+        );
+    }
+
+    #[test]
+    fn a_success_resets_the_episode() {
+        // 50 minutes failing, one success, then 50 more: two short episodes, no digest.
+        let (events, episode) = episode(&[
+            (0, false),
+            (50, false),
+            (51, true),
+            (52, false),
+            (102, false),
+        ]);
+        assert_eq!(events, [None, None, None, None, None]);
+        assert!(matches!(episode, FailureEpisode::Failing { .. }));
+    }
+
+    #[test]
+    fn each_episode_is_announced_once() {
+        let (events, _) = episode(&[
+            (0, false),
+            (60, false),
+            (61, true),
+            (70, false),
+            (130, false),
+            (200, false),
+            (201, true),
+        ]);
+        let digests = events
+            .iter()
+            .filter(|event| matches!(event, Some(EpisodeEvent::Digest(_))))
+            .count();
+        let recoveries = events
+            .iter()
+            .filter(|event| **event == Some(EpisodeEvent::Recovered))
+            .count();
+        assert_eq!((digests, recoveries), (2, 2));
+        assert_eq!(events[4], Some(EpisodeEvent::Digest(HOUR)));
+    }
+
+    #[test]
+    fn a_zero_threshold_never_announces() {
+        let start = Instant::now();
+        let mut episode = FailureEpisode::default();
+        for minute in 0..300 {
+            let at = start + Duration::from_secs(minute * 60);
+            assert_eq!(episode.step(false, at, Duration::ZERO), None);
+        }
+        assert_eq!(episode.step(true, start + HOUR * 6, Duration::ZERO), None);
+    }
+
+    fn minutes(start: Instant, minutes: u64) -> Instant {
+        start + Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn a_burst_is_capped_and_counted() {
+        let start = Instant::now();
+        let mut budget = NotificationBudget::default();
+        // Ten alerts within a minute, five an hour allowed.
+        let shown: Vec<bool> = (0..10)
+            .map(|alert| budget.take(start + Duration::from_secs(alert * 6), 5))
+            .collect();
+        assert_eq!(
+            shown,
+            [true, true, true, true, true, false, false, false, false, false]
+        );
+        assert_eq!(budget.suppressed, 5);
+        assert_eq!(budget.next_room(), Some(start + NOTIFICATION_BUDGET_WINDOW));
+    }
+
+    #[test]
+    fn the_window_slides_one_notification_at_a_time() {
+        let start = Instant::now();
+        let mut budget = NotificationBudget::default();
+        for minute in [0, 10, 20] {
+            assert!(budget.take(minutes(start, minute), 3));
+        }
+        assert!(!budget.take(minutes(start, 59), 3));
+        // The first one left the window, making room for exactly one more.
+        assert!(budget.take(minutes(start, 60), 3));
+        assert!(!budget.take(minutes(start, 61), 3));
+        assert_eq!(budget.next_room(), Some(minutes(start, 70)));
+        assert!(budget.take(minutes(start, 70), 3));
+        assert_eq!(budget.suppressed, 2);
+    }
+
+    #[test]
+    fn the_summary_waits_for_room_and_counts_once() {
+        let start = Instant::now();
+        let mut budget = NotificationBudget::default();
+        for minute in 0..8 {
+            budget.take(minutes(start, minute), 3);
+        }
+        assert_eq!(budget.suppressed, 5);
+        // Still spent: no summary yet, and the count is kept.
+        assert_eq!(budget.reset(minutes(start, 30), 3), None);
+        assert_eq!(budget.suppressed, 5);
+        assert_eq!(budget.reset(minutes(start, 60), 3), Some(5));
+        assert_eq!(budget.suppressed, 0);
+        // Nothing more to summarize.
+        assert_eq!(budget.reset(minutes(start, 90), 3), None);
+    }
+
+    #[test]
+    fn the_summary_is_taken_out_of_the_budget() {
+        let start = Instant::now();
+        let mut budget = NotificationBudget::default();
+        assert!(budget.take(minutes(start, 0), 2));
+        assert!(budget.take(minutes(start, 1), 2));
+        assert!(!budget.take(minutes(start, 2), 2));
+        assert_eq!(budget.reset(minutes(start, 60), 2), Some(1));
+        // The summary at 60 and the alert at 1 fill the window again.
+        assert!(!budget.take(minutes(start, 60), 2));
+        assert_eq!(budget.suppressed, 1);
+        assert!(budget.take(minutes(start, 61), 2));
+    }
+
+    #[test]
+    fn no_limit_shows_everything() {
+        let start = Instant::now();
+        let mut budget = NotificationBudget::default();
+        assert!((0..1000).all(|second| budget.take(start + Duration::from_secs(second), 0)));
+        assert_eq!(budget.suppressed, 0);
+        assert_eq!(budget.reset(minutes(start, 20), 0), None);
+    }
+
+    #[test]
+    fn an_empty_budget_has_no_next_room() {
+        assert_eq!(NotificationBudget::default().next_room(), None);
+    }
+
+    #[test]
+    fn market_precision_goes_by_the_pair_and_falls_back_to_the_precision() {
+        let mut app = YourApp::default();
+        app.config.precision = Some(2);
+        let pair = |code: &str| Pair::new(&code[..3], &code[3..]).unwrap();
+        assert_eq!(app.display_precision(&pair("EURUSD")), Some(2));
+        app.config.market_precision = true;
+        assert_eq!(app.display_precision(&pair("EURUSD")), Some(5));
+        assert_eq!(app.display_precision(&pair("USDJPY")), Some(3));
+        assert_eq!(app.display_precision(&pair("USDTRY")), Some(2));
+        app.config.precision = None;
+        assert_eq!(app.display_precision(&pair("USDTRY")), None);
+    }
+
+    /// The placements an applet can be given: a panel, the dock and a custom one.
+    fn placements() -> [PanelType; 3] {
+        [
+            PanelType::Panel,
+            PanelType::Dock,
+            PanelType::Other("bar".to_string()),
+        ]
+    }
+
+    #[test]
+    fn auto_shows_the_icon_in_the_dock() {
+        for pairs in [0, 1, 2, 5] {
+            for cycling in [false, true] {
+                assert_eq!(
+                    resolve_display_mode(DisplayMode::Auto, &PanelType::Dock, pairs, cycling),
+                    DisplayMode::Icon
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn auto_shows_text_in_a_panel() {
+        for panel in [PanelType::Panel, PanelType::Other("bar".to_string())] {
+            let mode =
+                |pairs, cycling| resolve_display_mode(DisplayMode::Auto, &panel, pairs, cycling);
+            assert_eq!(mode(0, false), DisplayMode::Rate);
+            assert_eq!(mode(1, false), DisplayMode::Rate);
+            assert_eq!(mode(2, false), DisplayMode::Summary);
+            assert_eq!(mode(2, true), DisplayMode::Rate);
+        }
+    }
+
+    #[test]
+    fn a_configured_mode_is_kept_wherever_the_applet_is() {
+        for panel in placements() {
+            for mode in [DisplayMode::Rate, DisplayMode::Summary, DisplayMode::Icon] {
+                assert_eq!(resolve_display_mode(mode, &panel, 3, false), mode);
+            }
+        }
+    }
+
+    fn quote(bid: f64, timestamp: i64) -> PairResponse {
+        PairResponse {
+            bid,
+            timestamp,
+            ..PairResponse::default()
+        }
+    }
+
+    #[test]
+    fn only_a_new_bid_is_a_change() {
+        let first = quote(5.4321, 100);
+        assert!(quote_changed(None, &first, None));
+        assert!(!quote_changed(Some(&first), &quote(5.4321, 100), None));
+        // A newer timestamp confirms the same value.
+        assert!(!quote_changed(Some(&first), &quote(5.4321, 160), None));
+        assert!(quote_changed(Some(&first), &quote(5.43211, 100), None));
+    }
+
+    #[test]
+    fn moves_below_the_display_precision_are_no_change() {
+        let first = quote(5.4321, 100);
+        assert!(!quote_changed(Some(&first), &quote(5.43214, 160), Some(4)));
+        assert!(quote_changed(Some(&first), &quote(5.43216, 160), Some(4)));
+        assert!(!quote_changed(Some(&first), &quote(5.4349, 160), Some(2)));
+        assert!(quote_changed(None, &first, Some(2)));
+    }
+
+    #[test]
+    fn the_last_change_is_tracked_apart_from_the_last_fetch() {
+        let mut app = YourApp::default();
+        app.config.precision = Some(4);
+        let pair = Pair::new("USD", "BRL").unwrap();
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let changed_at = |app: &YourApp| app.pair_states[&pair].changed_at;
+        app.store_quote(pair.clone(), quote(5.4321, 100), at(0));
+        assert_eq!(changed_at(&app), Some(at(0)));
+        app.store_quote(pair.clone(), quote(5.4321, 160), at(60));
+        assert_eq!(changed_at(&app), Some(at(0)));
+        app.store_quote(pair.clone(), quote(5.43212, 220), at(120));
+        assert_eq!(changed_at(&app), Some(at(120)));
+        // Compared at the display precision, the same move is no change.
+        app.config.change_at_display_precision = true;
+        app.store_quote(pair.clone(), quote(5.43214, 280), at(180));
+        assert_eq!(changed_at(&app), Some(at(120)));
+        app.store_quote(pair.clone(), quote(5.4330, 340), at(240));
+        assert_eq!(changed_at(&app), Some(at(240)));
+    }
+
+    /// An app watching USDBRL, quiet if `quiet`, that just got a quote 5% up from 5.4.
+    fn moved_pair(quiet: bool) -> (YourApp, Pair) {
+        let mut app = YourApp::default();
+        let pair = Pair::new("USD", "BRL").unwrap();
+        let mut entry = PairEntry::new(pair.clone());
+        entry.quiet = quiet;
+        app.config.pairs = vec![entry];
+        app.config.rate_change_animation = AnimationStyle::Flash;
+        let start = Instant::now();
+        app.store_quote(pair.clone(), quote(5.4, 100), start);
+        let moved = PairResponse {
+            bid: 5.67,
+            var_bid: 0.27,
+            pct_change: 5.0,
+            timestamp: 160,
+            ..PairResponse::default()
+        };
+        app.store_quote(pair.clone(), moved, start + Duration::from_secs(60));
+        (app, pair)
+    }
+
+    #[test]
+    fn quiet_pairs_are_shown_without_change_styling() {
+        let (app, pair) = moved_pair(false);
+        assert!(app.pair_states[&pair].animation_progress > 0.0);
+        assert!(app.watchlist_heat()[0].is_some());
+        let (app, pair) = moved_pair(true);
+        assert_eq!(app.pair_states[&pair].animation_progress, 0.0);
+        assert_eq!(app.watchlist_heat(), vec![None]);
+        // The value is still shown and tracked as usual.
+        assert_eq!(app.exchange_rates[&pair].bid, 5.67);
+        assert!(app.pair_states[&pair].changed_at.is_some());
+    }
+
+    /// A quote of 5.5, 0.1 up from the previous close, and the bids received before it
+    /// (newest first) at 14:00, 10:00 and 16:00 and 14:00 yesterday, asked at 15:00.
+    fn windows() -> (PairResponse, Vec<(DateTime<Local>, f64)>, DateTime<Local>) {
+        let now = Local.with_ymd_and_hms(2026, 6, 10, 15, 0, 0).unwrap();
+        let hours = |hours| now - chrono::Duration::hours(hours);
+        let quote = PairResponse {
+            bid: 5.5,
+            var_bid: 0.1,
+            pct_change: 1.85,
+            ..PairResponse::default()
+        };
+        let earlier = vec![
+            (hours(1), 5.4),
+            (hours(5), 5.3),
+            (hours(23), 5.2),
+            (hours(25), 5.0),
+        ];
+        (quote, earlier, now)
+    }
+
+    #[test]
+    fn each_window_measures_from_its_own_baseline() {
+        let (quote, earlier, now) = windows();
+        let at = |hour| ChangeWindow::SinceTime(NaiveTime::from_hms_opt(hour, 0, 0).unwrap());
+        let cases = [
+            (ChangeWindow::LastRefresh, 5.4),
+            (ChangeWindow::Rolling24h, 5.0),
+            (at(12), 5.3),
+            (at(9), 5.2),
+            // Not reached yet today, so since yesterday's 16:00.
+            (at(16), 5.2),
+        ];
+        for (window, from) in cases {
+            let change = window_change(window, &quote, &earlier, now).unwrap();
+            assert!(
+                (change.delta - (5.5 - from)).abs() < 1e-9,
+                "{window:?}: {change:?}"
+            );
+            let percent = (5.5 - from) / from * 100.0;
+            assert!(
+                (change.percent - percent).abs() < 1e-9,
+                "{window:?}: {change:?}"
+            );
+        }
+        // The previous close as the provider sent it.
+        let change = window_change(ChangeWindow::PreviousClose, &quote, &earlier, now).unwrap();
+        assert_eq!((change.delta, change.percent), (0.1, 1.85));
+    }
+
+    #[test]
+    fn windows_without_enough_history_fall_back_or_have_no_change() {
+        let (quote, earlier, now) = windows();
+        for window in [ChangeWindow::LastRefresh, ChangeWindow::Rolling24h] {
+            assert_eq!(window_change(window, &quote, &[], now), None);
+        }
+        assert!(window_change(ChangeWindow::PreviousClose, &quote, &[], now).is_some());
+        // Younger than the window, the oldest bid there is stands in for it.
+        let change = window_change(ChangeWindow::Rolling24h, &quote, &earlier[..2], now).unwrap();
+        assert!((change.delta - 0.2).abs() < 1e-9);
+    }
+
+    /// A store whose writes can be made to fail, noting what it was handed to write.
+    #[derive(Debug, Default)]
+    struct TestStore {
+        unwritable: AtomicBool,
+        fail_writes: AtomicBool,
+        failed: AtomicBool,
+        writes: Mutex<Vec<&'static str>>,
+    }
+
+    impl SettingsStore for TestStore {
+        fn writable(&self) -> bool {
+            !self.unwritable.load(AtomicOrdering::Relaxed)
+        }
+
+        fn write(&self, what: &'static str, job: WriteJob) {
+            self.writes.lock().unwrap().push(what);
+            let result = if self.fail_writes.load(AtomicOrdering::Relaxed) {
+                Err("Read-only file system".to_string())
+            } else {
+                job()
+            };
+            if result.is_err() {
+                self.failed.store(true, AtomicOrdering::Relaxed);
+            }
+        }
+
+        fn take_failure(&self) -> bool {
+            self.failed.swap(false, AtomicOrdering::Relaxed)
+        }
+
+        fn has_failed(&self) -> bool {
+            self.failed.load(AtomicOrdering::Relaxed)
+        }
+    }
+
+    /// A store that looks writable, but every write to it fails.
+    fn failing_store() -> Arc<TestStore> {
+        let store = Arc::new(TestStore::default());
+        store.fail_writes.store(true, AtomicOrdering::Relaxed);
+        store
+    }
+
+    fn unwritable_store() -> Arc<TestStore> {
+        let store = Arc::new(TestStore::default());
+        store.unwritable.store(true, AtomicOrdering::Relaxed);
+        store
+    }
+
+    fn saved() -> Result<(), String> {
+        Ok(())
+    }
+
+    #[test]
+    fn the_first_failed_write_switches_to_memory_only() {
+        let mut persistence = Persistence::with_store(None, failing_store());
+        assert!(!persistence.is_memory_only());
+        assert!(!persistence.show_notice());
+        persistence.write("config", saved);
+        assert!(persistence.is_memory_only());
+        assert!(persistence.show_notice());
+    }
+
+    #[test]
+    fn nothing_is_written_once_in_memory_only() {
+        let store = failing_store();
+        let mut persistence = Persistence::with_store(None, store.clone());
+        for what in ["config", "state", "config"] {
+            persistence.write(what, saved);
+        }
+        assert_eq!(*store.writes.lock().unwrap(), ["config"]);
+        assert!(persistence.memory_only);
+    }
+
+    #[test]
+    fn the_notice_is_shown_once_per_session() {
+        let mut persistence = Persistence::with_store(None, failing_store());
+        persistence.write("config", saved);
+        assert!(persistence.show_notice());
+        persistence.notice_dismissed = true;
+        assert!(!persistence.show_notice());
+        // The store looks writable again, but writes fail again: no second notice.
+        persistence.reprobe();
+        assert!(!persistence.is_memory_only());
+        persistence.write("config", saved);
+        assert!(persistence.is_memory_only());
+        assert!(!persistence.show_notice());
+    }
+
+    #[test]
+    fn an_unwritable_store_starts_in_memory_only() {
+        let store = unwritable_store();
+        let mut persistence = Persistence::with_store(None, store.clone());
+        assert!(persistence.show_notice());
+        persistence.write("config", saved);
+        assert!(store.writes.lock().unwrap().is_empty());
+        // Still unwritable, probing again changes nothing.
+        persistence.reprobe();
+        assert!(persistence.is_memory_only());
+    }
+
+    #[test]
+    fn saving_the_config_probes_the_store_again() {
+        let store = unwritable_store();
+        let mut app = YourApp {
+            persistence: Persistence::with_store(None, store.clone()),
+            ..YourApp::default()
+        };
+        app.save_config();
+        assert!(app.persistence.is_memory_only());
+        store.unwritable.store(false, AtomicOrdering::Relaxed);
+        app.save_config();
+        assert!(!app.persistence.is_memory_only());
+    }
+
+    #[test]
+    fn discrepancies_within_the_tolerance_pass() {
+        assert_eq!(exceeds_tolerance(5.0, 5.0, 0.5), None);
+        assert_eq!(exceeds_tolerance(5.0, 5.02, 0.5), None);
+        assert_eq!(exceeds_tolerance(5.0, 4.98, 0.5), None);
+        let over = exceeds_tolerance(5.0, 5.1, 0.5).unwrap();
+        assert!((over - 2.0).abs() < 1e-9);
+        let under = exceeds_tolerance(5.0, 4.9, 0.5).unwrap();
+        assert!((under - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn quotes_that_cannot_be_compared_pass() {
+        assert_eq!(exceeds_tolerance(0.0, 5.0, 0.5), None);
+        assert_eq!(exceeds_tolerance(-5.0, 5.0, 0.5), None);
+        assert_eq!(exceeds_tolerance(5.0, f64::INFINITY, 0.5), None);
+        assert_eq!(exceeds_tolerance(5.0, f64::NAN, 0.5), None);
+    }
+
+    fn pairs(codes: &[&str]) -> Vec<Pair> {
+        codes
+            .iter()
+            .map(|code| Pair::parse(code).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn a_quote_is_checked_against_its_inverse() {
+        let watched = pairs(&["USDBRL", "BRLUSD"]);
+        let quotes = |inverse| vec![(&watched[0], 5.0), (&watched[1], inverse)];
+        assert!(inconsistencies(&quotes(0.2)).is_empty());
+        assert!(inconsistencies(&quotes(0.2005)).is_empty());
+        let found = inconsistencies(&quotes(0.21));
+        // Reported once, not again for the inverse.
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert_eq!(found[0].pair, watched[0]);
+        assert_eq!(found[0].from, [watched[1].clone()]);
+        assert!((found[0].derived - 1.0 / 0.21).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_quote_is_checked_against_the_crosses() {
+        let watched = pairs(&["USDBRL", "EURUSD", "EURBRL"]);
+        let quotes = |cross| vec![(&watched[0], 5.0), (&watched[1], 1.1), (&watched[2], cross)];
+        assert!(inconsistencies(&quotes(5.5)).is_empty());
+        let found = inconsistencies(&quotes(5.7));
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert_eq!(found[0].pair, watched[0]);
+        assert!((found[0].derived - 5.7 / 1.1).abs() < 1e-9);
+        assert!(found[0].from.contains(&watched[2]));
+    }
+
+    #[test]
+    fn quotes_without_a_counterpart_or_a_bid_are_not_checked() {
+        let watched = pairs(&["USDBRL", "BRLUSD", "EURJPY"]);
+        assert!(inconsistencies(&[(&watched[0], 5.0), (&watched[2], 160.0)]).is_empty());
+        assert!(inconsistencies(&[(&watched[0], 5.0), (&watched[1], 0.0)]).is_empty());
+    }
+
+    #[test]
+    fn only_pairs_slower_than_the_refresh_get_a_note_and_a_tag() {
+        let pair = Pair::parse("USDBOB").unwrap();
+        assert_eq!(cadence_note(&pair, Cadence::Every(30), 60), None);
+        assert_eq!(cadence_tag(Cadence::Every(60), 60), None);
+        assert_eq!(cadence_tag(Cadence::BusinessDay, 24 * 60 * 60), None);
+        assert!(cadence_note(&pair, Cadence::Every(61), 60).is_some());
+        assert!(cadence_tag(Cadence::BusinessDay, 60).is_some());
+    }
+
+    #[test]
+    fn the_note_and_tag_name_the_cadence() {
+        let pair = Pair::parse("USDBOB").unwrap();
+        assert_eq!(
+            cadence_note(&pair, Cadence::BusinessDay, 60),
+            Some(fl!("cadence-note-business-day", pair = pair.to_string()))
+        );
+        assert_eq!(
+            cadence_tag(Cadence::BusinessDay, 60),
+            Some(fl!("cadence-tag-business-day"))
+        );
+        // Minutes are rounded up, a pair updated every 90 seconds is not one of a minute.
+        assert_eq!(
+            cadence_note(&pair, Cadence::Every(90), 60),
+            Some(fl!(
+                "cadence-note-every",
+                pair = pair.to_string(),
+                minutes = 2
+            ))
+        );
+        assert_eq!(
+            cadence_tag(Cadence::Every(600), 60),
+            Some(fl!("cadence-tag-every", minutes = 10))
+        );
+    }
+
+    #[test]
+    fn a_flagged_quote_is_snapshot_with_its_response() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let mut app = YourApp::default();
+        app.config.pairs = vec![PairEntry::new(pair.clone())];
+        let quote = PairResponse {
+            bid: 61.0,
+            ..PairResponse::default()
+        };
+        app.exchange_rates.insert(pair.clone(), quote.clone());
+        app.fetched_at.insert(pair.clone(), Instant::now());
+        let state = app.pair_states.entry(pair.clone()).or_default();
+        state.quoted_by = Some(AWESOMEAPI);
+        state.raw_response = Some(r#"{"USDBRL": {"bid": "61.0"}}"#.to_string());
+        app.flag_quote(pair.clone());
+
+        let flagged = &app.state.flagged_quotes[0];
+        assert_eq!(flagged.pair, pair);
+        assert_eq!(flagged.quote, Some(quote));
+        assert_eq!(flagged.provider, app.config.provider);
+        assert_eq!(flagged.quoted_by.as_deref(), Some(AWESOMEAPI));
+        assert!(flagged.fetched_secs_ago.is_some());
+        assert_eq!(
+            flagged.response_excerpt.as_deref(),
+            Some(r#"{"USDBRL": {"bid": "61.0"}}"#)
+        );
+        assert_eq!(flagged.settings, redacted_settings(&app.config).to_string());
+        // What comes next doesn't change the evidence.
+        app.pair_states.get_mut(&pair).unwrap().raw_response = Some("{}".to_string());
+        assert_eq!(
+            app.state.flagged_quotes[0].response_excerpt.as_deref(),
+            Some(r#"{"USDBRL": {"bid": "61.0"}}"#)
+        );
+    }
+
+    #[test]
+    fn a_pair_never_fetched_is_flagged_without_a_quote() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let mut app = YourApp::default();
+        app.flag_quote(pair);
+        let flagged = &app.state.flagged_quotes[0];
+        assert_eq!(flagged.quote, None);
+        assert_eq!(flagged.response_excerpt, None);
+        assert_eq!(flagged.fetched_secs_ago, None);
+    }
+
+    #[test]
+    fn only_the_latest_flagged_quotes_are_kept() {
+        let pair = Pair::parse("USDBRL").unwrap();
+        let mut app = YourApp::default();
+        for bid in 0..MAX_FLAGGED_QUOTES + 2 {
+            let quote = PairResponse {
+                bid: bid as f64,
+                ..PairResponse::default()
+            };
+            app.exchange_rates.insert(pair.clone(), quote);
+            app.flag_quote(pair.clone());
+        }
+        let bids: Vec<f64> = app
+            .state
+            .flagged_quotes
+            .iter()
+            .map(|flagged| flagged.quote.as_ref().unwrap().bid)
+            .collect();
+        let latest: Vec<f64> = (2..MAX_FLAGGED_QUOTES + 2).map(|bid| bid as f64).collect();
+        assert_eq!(bids, latest);
+    }
+
+    /// Every reason, in their order of precedence.
+    const REASONS: [PauseReason; 7] = [
+        PauseReason::Frozen,
+        PauseReason::SessionLocked,
+        PauseReason::Maintenance,
+        PauseReason::Offline,
+        PauseReason::CircuitBreaker,
+        PauseReason::MarketClosed,
+        PauseReason::LowPower,
+    ];
+
+    #[test]
+    fn offline_over_the_weekend_reads_as_offline() {
+        use PauseReason::*;
+        assert_eq!(pause_reason([MarketClosed, Offline]), Some(Offline));
+        assert_eq!(pause_reason([Offline, MarketClosed]), Some(Offline));
+        // Failing through the retries is also what being offline leads to.
+        assert_eq!(pause_reason([CircuitBreaker, Offline]), Some(Offline));
+        assert_eq!(
+            pause_reason([MarketClosed, CircuitBreaker]),
+            Some(CircuitBreaker)
+        );
+        assert_eq!(pause_reason([LowPower, MarketClosed]), Some(MarketClosed));
+        assert_eq!(pause_reason([]), None);
+    }
+
+    #[test]
+    fn the_first_reason_declared_wins_whatever_else_applies() {
+        for (rank, &reason) in REASONS.iter().enumerate() {
+            // With every reason after it, and in any order.
+            let mut reasons = REASONS[rank..].to_vec();
+            assert_eq!(pause_reason(reasons.clone()), Some(reason));
+            reasons.reverse();
+            assert_eq!(pause_reason(reasons), Some(reason));
+        }
+        assert_eq!(pause_reason(REASONS), Some(PauseReason::Frozen));
+    }
+
+    #[test]
+    fn each_reason_has_an_icon_label_and_explanation_of_its_own() {
+        for (i, a) in REASONS.iter().enumerate() {
+            assert!(
+                !a.label().is_empty() && !a.explanation().is_empty(),
+                "{a:?}"
+            );
+            assert_ne!(a.label(), a.explanation(), "{a:?}");
+            for b in &REASONS[i + 1..] {
+                assert_ne!(a.id(), b.id());
+                assert_ne!(a.icon(), b.icon(), "{a:?} and {b:?}");
+                assert_ne!(a.label(), b.label(), "{a:?} and {b:?}");
+                assert_ne!(a.explanation(), b.explanation(), "{a:?} and {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn the_strings_of_a_reason_are_named_after_its_id() {
+        for reason in REASONS {
+            let label = format!("pause-{}", reason.id());
+            let explanation = format!("{label}-explanation");
+            assert!(LANGUAGE_LOADER.has(&label), "{label}");
+            assert!(LANGUAGE_LOADER.has(&explanation), "{explanation}");
+            assert_eq!(reason.label(), LANGUAGE_LOADER.get(&label));
+            assert_eq!(reason.explanation(), LANGUAGE_LOADER.get(&explanation));
+            assert!(reason.icon().ends_with("-symbolic"), "{reason:?}");
+        }
+    }
+
+    #[test]
+    fn live_quotes_come_first_then_cached_ones_then_the_bundled_table() {
+        let (live, cached) = (quote(5.5, 1_700_000_060), quote(5.4, 1_700_000_000));
+        let bundled = BundledRate {
+            bid: 5.0,
+            as_of: chrono::NaiveDate::from_ymd_opt(2025, 6, 30).unwrap(),
+        };
+        assert_eq!(
+            select_rate(Some(&live), Some(&cached), Some(bundled)),
+            Some(RateSource::Fetched(&live))
+        );
+        assert_eq!(
+            select_rate(None, Some(&cached), Some(bundled)),
+            Some(RateSource::Fetched(&cached))
+        );
+        assert_eq!(
+            select_rate(Some(&live), None, None),
+            Some(RateSource::Fetched(&live))
+        );
+        assert_eq!(
+            select_rate(None, None, Some(bundled)),
+            Some(RateSource::Bundled(bundled))
+        );
+        assert_eq!(select_rate(None, None, None), None);
+    }
+
+    #[test]
+    fn bundled_rates_are_told_apart_from_fetched_ones() {
+        let (bundled, fetched, cached) = (
+            Pair::parse("USDBRL").unwrap(),
+            Pair::parse("EURBRL").unwrap(),
+            Pair::parse("GBPBRL").unwrap(),
+        );
+        let mut app = YourApp::default();
+        app.config.pairs = [&bundled, &fetched, &cached, &Pair::parse("USDBOB").unwrap()]
+            .into_iter()
+            .map(|pair| PairEntry::new(pair.clone()))
+            .collect();
+        app.exchange_rates
+            .insert(fetched.clone(), quote(6.4, 1_700_000_000));
+        app.rate_cache.insert(
+            cached.clone(),
+            quote(7.3, 1_700_000_000),
+            Instant::now() + AFTER,
+        );
+
+        assert!(app.shows_bundled(&bundled));
+        assert!(!app.shows_bundled(&fetched) && !app.shows_bundled(&cached));
+        let sources = app.rate_sources();
+        let as_of = bundled_rate(&bundled).unwrap().as_of.to_string();
+        assert_eq!(sources["USDBRL"], ("bundled".to_string(), as_of));
+        assert_eq!(
+            sources["EURBRL"],
+            ("fetched".to_string(), "1700000000".to_string())
+        );
+        assert_eq!(
+            sources["GBPBRL"],
+            ("fetched".to_string(), "1700000000".to_string())
+        );
+        // Neither fetched nor bundled, it has nothing to show yet.
+        assert_eq!(sources.len(), 3);
+        // Shown only, the bundled rate never becomes a quote of the pair.
+        assert!(!app.exchange_rates.contains_key(&bundled));
+    }
+
+    #[test]
+    fn heat_is_the_change_as_a_share_of_the_largest_mover() {
+        let heat = change_heat(&[Some(2.0), Some(-4.0), None, Some(1.0), Some(f64::NAN)]);
+        assert_eq!(heat, [Some(0.5), Some(-1.0), None, Some(0.25), None]);
+        assert_eq!(
+            change_heat(&[Some(0.0), Some(-0.0), None]),
+            [None, None, None]
+        );
+        assert_eq!(change_heat(&[]), []);
+    }
+
+    /// A watchlist whose pairs moved by the percents of `changes` since the previous close,
+    /// `None` for a pair without a quote.
+    fn heat_watchlist(changes: &[(&str, Option<f64>)]) -> YourApp {
+        let mut app = YourApp::default();
+        app.config.pairs.clear();
+        for &(code, percent) in changes {
+            let pair = Pair::parse(code).unwrap();
+            if let Some(percent) = percent {
+                app.exchange_rates.insert(pair.clone(), moved_by(percent));
+            }
+            app.config.pairs.push(PairEntry::new(pair));
+        }
+        app
+    }
+
+    /// A quote of 5 that moved by `percent` since the previous close.
+    fn moved_by(percent: f64) -> PairResponse {
+        let close = 5.0 / (1.0 + percent / 100.0);
+        PairResponse {
+            bid: 5.0,
+            var_bid: 5.0 - close,
+            pct_change: percent,
+            ..PairResponse::default()
+        }
+    }
+
+    /// The heat of every row rounded to a thousandth, for comparisons.
+    fn rounded_heat(app: &YourApp) -> Vec<Option<f64>> {
+        app.watchlist_heat()
+            .into_iter()
+            .map(|heat| heat.map(|heat| (heat * 1000.0).round() / 1000.0))
+            .collect()
+    }
+
+    const FIXTURE: [(&str, Option<f64>); 6] = [
+        ("USDBRL", Some(2.0)),
+        ("EURBRL", Some(-1.0)),
+        ("GBPBRL", Some(0.5)),
+        ("BTCUSD", Some(-8.0)),
+        ("USDJPY", Some(16.0)),
+        ("USDBOB", None),
+    ];
+
+    #[test]
+    fn quiet_and_disabled_pairs_have_no_heat_and_set_no_scale() {
+        let mut app = heat_watchlist(&FIXTURE);
+        app.config.pairs[3].quiet = true;
+        app.config.pairs[4].enabled = false;
+        assert_eq!(
+            rounded_heat(&app),
+            [Some(1.0), Some(-0.5), Some(0.25), None, None, None]
+        );
+    }
+
+    #[test]
+    fn heat_follows_the_quotes_as_they_arrive() {
+        let mut app = heat_watchlist(&FIXTURE[..3]);
+        assert_eq!(rounded_heat(&app), [Some(1.0), Some(-0.5), Some(0.25)]);
+        app.exchange_rates
+            .insert(Pair::parse("EURBRL").unwrap(), moved_by(-4.0));
+        assert_eq!(rounded_heat(&app), [Some(0.5), Some(-1.0), Some(0.125)]);
+    }
+
+    #[test]
+    fn nothing_moving_draws_no_bars() {
+        let app = heat_watchlist(&[
+            ("USDBRL", Some(0.0)),
+            ("EURBRL", Some(0.0)),
+            ("GBPBRL", None),
+        ]);
+        assert_eq!(app.watchlist_heat(), [None, None, None]);
+    }
+
+    #[test]
+    fn sorting_by_change_ranks_rows_by_their_heat() {
+        let mut app = heat_watchlist(&FIXTURE);
+        app.config.popup_sort = SortOrder::ChangeDescending;
+        let order = app.sorted_indices();
+        assert_eq!(order, [4, 3, 0, 1, 2, 5]);
+        let heat = app.watchlist_heat();
+        let ranked: Vec<f64> = order
+            .iter()
+            .filter_map(|&index| heat[index])
+            .map(f64::abs)
+            .collect();
+        assert_eq!(ranked, [1.0, 0.5, 0.125, 0.0625, 0.03125]);
+        // Quiet, the largest mover keeps its place in the ranking but loses its bar.
+        app.config.pairs[4].quiet = true;
+        assert_eq!(app.sorted_indices(), order);
+        assert_eq!(app.watchlist_heat()[4], None);
+        assert_eq!(app.watchlist_heat()[3], Some(-1.0));
+    }
+
+    #[test]
+    fn what_the_applet_holds_is_counted_over_every_pair() {
+        let mut app = YourApp::default();
+        let now = app.clock.now_local();
+        let [usdbrl, eurbrl] = ["USDBRL", "EURBRL"].map(|code| Pair::parse(code).unwrap());
+        for (pair, quotes) in [(&usdbrl, 3), (&eurbrl, 2)] {
+            let state = app.pair_states.entry(pair.clone()).or_default();
+            for minute in (0..quotes).rev() {
+                state.record_quote(5.0, now - chrono::Duration::minutes(minute));
+            }
+        }
+        app.pair_states.get_mut(&eurbrl).unwrap().raw_response = Some("{}".to_string());
+        // Expired, but held until replaced.
+        app.rate_cache
+            .insert(usdbrl.clone(), quote(5.0, 0), Instant::now());
+        assert_eq!(
+            app.held_counts(),
+            HeldCounts {
+                history_points: 5,
+                cached_quotes: 1,
+                raw_responses: 1,
+                requests_today: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn requests_are_counted_today_whether_saved_or_not() {
+        let mut app = YourApp::default();
+        let today = app.clock.now_local().date_naive();
+        {
+            let mut usage = app.usage.lock().unwrap();
+            usage.record(AWESOMEAPI, today.pred_opt().unwrap());
+            usage.record(providers::FORWARD_RATES, today.pred_opt().unwrap());
+            usage.record(AWESOMEAPI, today);
+            usage.record(AWESOMEAPI, today);
+        }
+        // Yesterday's forward rates request isn't of today, nor is the first AwesomeAPI one.
+        assert_eq!(app.held_counts().requests_today, 2);
+        assert_ne!(app.state.api_usage, *app.usage.lock().unwrap());
+        app.save_api_usage();
+        assert_eq!(app.state.api_usage, *app.usage.lock().unwrap());
+        assert_eq!(app.held_counts().requests_today, 2);
+        app.usage
+            .lock()
+            .unwrap()
+            .record(providers::FORWARD_RATES, today);
+        assert_eq!(app.held_counts().requests_today, 3);
+    }
+
+    /// Three pairs fetched every 10 minutes and animating, turning to low power mode on a
+    /// battery discharging below 20%.
+    fn on_battery(battery: Option<f64>) -> YourApp {
+        let mut app = YourApp::default();
+        app.config.pairs = ["USDBRL", "BTCUSD", "EURUSD"]
+            .iter()
+            .map(|code| PairEntry::new(Pair::parse(code).unwrap()))
+            .collect();
+        app.config.refresh_secs = 600;
+        app.config.rate_change_animation = AnimationStyle::Slide;
+        app.config.low_power_below_pct = 20;
+        app.battery = battery;
+        app
+    }
+
+    #[test]
+    fn low_power_overlays_the_settings_without_changing_them() {
+        let settings = on_battery(Some(80.0)).config;
+        let app = on_battery(Some(15.0));
+        assert!(app.low_power());
+        assert_eq!(
+            app.refresh_interval(),
+            Duration::from_secs(600) * LOW_POWER_INTERVAL_FACTOR
+        );
+        assert_eq!(app.animation_style(), AnimationStyle::None);
+        assert_eq!(app.config, settings);
+    }
+
+    #[test]
+    fn leaving_low_power_restores_what_the_settings_say() {
+        for app in [on_battery(Some(80.0)), on_battery(None)] {
+            assert!(!app.low_power());
+            assert_eq!(app.refresh_interval(), Duration::from_secs(600));
+            assert_eq!(app.animation_style(), AnimationStyle::Slide);
+        }
+        let mut app = on_battery(Some(15.0));
+        app.config.low_power_below_pct = 0;
+        assert!(!app.low_power());
+        app.config.low_power = true;
+        app.battery = None;
+        assert!(app.low_power());
+    }
+
+    #[test]
+    fn low_power_holds_back_the_pairs_the_panel_doesnt_show() {
+        let mut app = on_battery(Some(15.0));
+        let pairs: Vec<Pair> = app.config.pairs.iter().map(|e| e.pair.clone()).collect();
+        let held_back = |app: &YourApp| -> Vec<bool> {
+            pairs
+                .iter()
+                .map(|pair| app.paused_for_low_power(pair))
+                .collect()
+        };
+        assert_eq!(held_back(&app), [false, true, true]);
+        // Bitcoin trades all week, so nothing else can pause it before low power mode.
+        assert_eq!(
+            app.pair_pause(&app.config.pairs[1]),
+            Some(PauseReason::LowPower)
+        );
+        app.config.panel_slots = vec![pairs[2].clone()];
+        assert_eq!(held_back(&app), [false, true, false]);
+        app.battery = Some(50.0);
+        assert_eq!(held_back(&app), [false, false, false]);
+        assert_eq!(app.pair_pause(&app.config.pairs[1]), None);
+    }
+}